@@ -0,0 +1,48 @@
+//! Matmul benchmark: the original triple loop vs the blocked GEMM with each available kernel.
+//! Run with `cargo run --release --example gemm_bench`.
+
+use dl_core::backend::gemm::{sgemm_naive, sgemm_with, GemmKernel};
+use std::time::Instant;
+
+fn gflops(m: usize, k: usize, n: usize, secs: f64) -> f64 {
+    2.0 * (m * k * n) as f64 / secs / 1e9
+}
+
+/// Best-of-`reps` seconds for one call of `f`.
+fn time(reps: usize, mut f: impl FnMut()) -> f64 {
+    (0..reps)
+        .map(|_| {
+            let t = Instant::now();
+            f();
+            t.elapsed().as_secs_f64()
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn main() {
+    println!("detected kernel: {}", GemmKernel::detect().name());
+    println!("{:>14} {:>10} {:>10}", "size", "kernel", "GFLOP/s");
+    for &s in &[64usize, 128, 256, 512, 1024] {
+        let (m, k, n) = (s, s, s);
+        let a: Vec<f32> = (0..m * k).map(|i| (i % 7) as f32 * 0.1).collect();
+        let b: Vec<f32> = (0..k * n).map(|i| (i % 5) as f32 * 0.1).collect();
+        let mut c = vec![0.0f32; m * n];
+        let reps = if s >= 512 { 2 } else { 5 };
+        let secs = time(reps, || sgemm_naive(m, k, n, &a, &b, &mut c));
+        println!(
+            "{:>14} {:>10} {:>10.2}",
+            format!("{}x{}x{}", m, k, n),
+            "naive",
+            gflops(m, k, n, secs)
+        );
+        for kernel in GemmKernel::available() {
+            let secs = time(reps, || sgemm_with(kernel, m, k, n, &a, &b, &mut c));
+            println!(
+                "{:>14} {:>10} {:>10.2}",
+                "",
+                kernel.name(),
+                gflops(m, k, n, secs)
+            );
+        }
+    }
+}
@@ -53,7 +53,7 @@ pub fn check_gradients(
         let autograd_grad = g
             .grad(*input_id)
             .map_err(|e| e.to_string())?
-            .and_then(|t| Some(t.data().to_vec()))
+            .map(|t| t.data().to_vec())
             .ok_or_else(|| format!("missing grad at input {}", idx))?;
 
         let idx_capture = idx;
@@ -94,3 +94,230 @@ pub fn check_gradients(
     }
     Ok(())
 }
+
+/// Tolerances and seed for the op-conformance harness ([check_registry], [check_op]).
+#[derive(Clone, Copy, Debug)]
+pub struct HarnessConfig {
+    /// Central-difference step.
+    pub eps: f32,
+    pub atol: f64,
+    pub rtol: f64,
+    /// Seed for input generation (independent of [crate::set_seed]).
+    pub seed: u64,
+}
+
+impl Default for HarnessConfig {
+    fn default() -> Self {
+        HarnessConfig {
+            eps: 1e-3,
+            atol: 1e-2,
+            rtol: 1e-2,
+            seed: 0,
+        }
+    }
+}
+
+/// Per-op result of the conformance harness.
+#[derive(Clone, Debug)]
+pub struct OpGradReport {
+    pub op: &'static str,
+    pub cases: usize,
+    /// Largest |analytic - numerical| over all checked elements.
+    pub max_abs_err: f64,
+    /// Largest |analytic - numerical| / max(|analytic|, |numerical|).
+    pub max_rel_err: f64,
+    /// One message per failing case; empty if the op passed.
+    pub failures: Vec<String>,
+    /// Set when the op opted out with [crate::ops::GradCheckSpec::skip]: the reason given.
+    pub skipped: Option<String>,
+}
+
+impl OpGradReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Gradient-check every op in `registry`, one report per op, sorted by op name. An op
+/// without an [crate::ops::GradCheckSpec] fails; opting out takes an explicit
+/// [crate::ops::GradCheckSpec::skip], which is reported in [OpGradReport::skipped].
+pub fn check_registry(
+    registry: &crate::ops::OpRegistry,
+    backend: std::sync::Arc<dyn crate::backend::Backend>,
+    config: &HarnessConfig,
+) -> Vec<OpGradReport> {
+    registry
+        .ops()
+        .iter()
+        .map(|op| {
+            check_op(op.as_ref(), backend.clone(), config).unwrap_or_else(|| OpGradReport {
+                op: op.name(),
+                cases: 0,
+                max_abs_err: 0.0,
+                max_rel_err: 0.0,
+                failures: vec![
+                    "no grad_check_spec; return GradCheckSpec::skip(reason) to opt out".into(),
+                ],
+                skipped: None,
+            })
+        })
+        .collect()
+}
+
+/// Each case with every dim of one size (the same size throughout, so inputs stay
+/// consistent, e.g. the shared K of a matmul) set to 1. Variants equal to a spec case are
+/// dropped.
+fn size_one_variants(cases: &[Vec<crate::shape::Shape>]) -> Vec<Vec<crate::shape::Shape>> {
+    let mut variants: Vec<Vec<crate::shape::Shape>> = Vec::new();
+    for case in cases {
+        let mut sizes: Vec<usize> = case
+            .iter()
+            .flat_map(|s| s.dims().to_vec())
+            .filter(|&d| d > 1)
+            .collect();
+        sizes.sort_unstable();
+        sizes.dedup();
+        for size in sizes {
+            let variant: Vec<crate::shape::Shape> = case
+                .iter()
+                .map(|s| {
+                    let dims = s.dims().iter().map(|&d| if d == size { 1 } else { d });
+                    crate::shape::Shape::new(dims.collect())
+                })
+                .collect();
+            let dims = |c: &[crate::shape::Shape]| {
+                c.iter().map(|s| s.dims().to_vec()).collect::<Vec<_>>()
+            };
+            let seen = cases.iter().chain(&variants).any(|c| dims(c) == dims(&variant));
+            if !seen {
+                variants.push(variant);
+            }
+        }
+    }
+    variants
+}
+
+/// Gradient-check one op over the cases of its spec (None if it has no spec), plus
+/// generated size-1 variants of each case (see [size_one_variants]). A variant the op
+/// rejects in forward (e.g. it breaks the op's geometry) is left out; the others count
+/// towards [OpGradReport::cases].
+/// Each case draws random inputs, projects the output onto random weights r, and compares
+/// `backward(r)` with central differences of sum(forward * r) accumulated in f64.
+pub fn check_op(
+    op: &dyn crate::ops::Op,
+    backend: std::sync::Arc<dyn crate::backend::Backend>,
+    config: &HarnessConfig,
+) -> Option<OpGradReport> {
+    use rand::SeedableRng;
+    let spec = op.grad_check_spec()?;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(config.seed);
+    let mut report = OpGradReport {
+        op: op.name(),
+        cases: 0,
+        max_abs_err: 0.0,
+        max_rel_err: 0.0,
+        failures: Vec::new(),
+        skipped: spec.skip.clone(),
+    };
+    let accepted = |shapes: &[crate::shape::Shape]| {
+        let inputs: Vec<Tensor> = shapes.iter().filter_map(|s| backend.ones(s).ok()).collect();
+        let refs: Vec<&Tensor> = inputs.iter().collect();
+        inputs.len() == shapes.len() && op.forward(&refs).is_ok()
+    };
+    let variants = size_one_variants(&spec.cases).into_iter().filter(|c| accepted(c));
+    let cases: Vec<Vec<crate::shape::Shape>> = spec.cases.iter().cloned().chain(variants).collect();
+    for (idx, shapes) in cases.iter().enumerate() {
+        report.cases += 1;
+        if let Err(msg) = check_case(op, &spec, shapes, &backend, config, &mut rng, &mut report) {
+            report.failures.push(format!("case {} {:?}: {}", idx, shapes, msg));
+        }
+    }
+    Some(report)
+}
+
+fn check_case(
+    op: &dyn crate::ops::Op,
+    spec: &crate::ops::GradCheckSpec,
+    shapes: &[crate::shape::Shape],
+    backend: &std::sync::Arc<dyn crate::backend::Backend>,
+    config: &HarnessConfig,
+    rng: &mut rand::rngs::StdRng,
+    report: &mut OpGradReport,
+) -> Result<(), String> {
+    use rand::Rng;
+    let mut sample = |n: usize, low: f32, high: f32, min_abs: f32| -> Vec<f32> {
+        (0..n)
+            .map(|_| {
+                let v: f32 = rng.gen_range(low..high);
+                if v.abs() < min_abs {
+                    min_abs.copysign(v)
+                } else {
+                    v
+                }
+            })
+            .collect()
+    };
+    let mut inputs = Vec::with_capacity(shapes.len());
+    for s in shapes {
+        let data = sample(s.numel(), spec.low, spec.high, spec.min_abs);
+        inputs.push(backend.from_vec(data, s.clone()).map_err(|e| e.to_string())?);
+    }
+    let refs: Vec<&Tensor> = inputs.iter().collect();
+    let out = op.forward(&refs).map_err(|e| e.to_string())?;
+    let weights = sample(out.numel(), -1.0, 1.0, 0.0);
+    let r = backend
+        .from_vec(weights.clone(), out.shape().clone())
+        .map_err(|e| e.to_string())?;
+    let grads = op.backward(&r, &refs, &out).map_err(|e| e.to_string())?;
+    if grads.len() != inputs.len() {
+        return Err(format!("backward returned {} grads for {} inputs", grads.len(), inputs.len()));
+    }
+
+    let objective = |xs: &[&Tensor]| -> Result<f64, String> {
+        let y = op.forward(xs).map_err(|e| e.to_string())?;
+        Ok(y.data()
+            .iter()
+            .zip(weights.iter())
+            .map(|(&a, &w)| a as f64 * w as f64)
+            .sum())
+    };
+    for (k, (input, grad)) in inputs.iter().zip(grads.iter()).enumerate() {
+        if !grad.shape().same_as(input.shape()) {
+            return Err(format!(
+                "grad {} shape {} != input shape {}",
+                k,
+                grad.shape(),
+                input.shape()
+            ));
+        }
+        for j in 0..input.numel() {
+            let eval = |delta: f32| -> Result<(f64, f32), String> {
+                let mut data = input.data().to_vec();
+                data[j] += delta;
+                let x = data[j];
+                let t = backend
+                    .from_vec(data, input.shape().clone())
+                    .map_err(|e| e.to_string())?;
+                let mut xs = refs.clone();
+                xs[k] = &t;
+                Ok((objective(&xs)?, x))
+            };
+            let (f_plus, x_plus) = eval(config.eps)?;
+            let (f_minus, x_minus) = eval(-config.eps)?;
+            let numerical = (f_plus - f_minus) / (x_plus as f64 - x_minus as f64);
+            let analytic = grad.data()[j] as f64;
+            let abs = (analytic - numerical).abs();
+            let scale = analytic.abs().max(numerical.abs());
+            let rel = if scale > 0.0 { abs / scale } else { 0.0 };
+            report.max_abs_err = report.max_abs_err.max(abs);
+            report.max_rel_err = report.max_rel_err.max(rel);
+            if abs > config.atol && rel > config.rtol {
+                return Err(format!(
+                    "input {} elem {}: analytic {} vs numerical {}",
+                    k, j, analytic, numerical
+                ));
+            }
+        }
+    }
+    Ok(())
+}
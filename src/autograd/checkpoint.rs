@@ -0,0 +1,108 @@
+//! Gradient checkpointing: compile a segment's sub-graph into a [Plan], keep only the
+//! segment inputs and output, and recompute the intermediates during backward.
+
+use super::graph::{Graph, GraphError, GraphResult, NodeId};
+use super::plan::Plan;
+use crate::ops::{Op, OpError, OpId, OpResult};
+use crate::tensor::Tensor;
+use std::sync::Arc;
+
+/// A checkpointed segment. Forward runs the plan, freeing intermediates as it goes;
+/// backward re-runs it and backpropagates `grad_out` to the segment inputs.
+pub struct Segment {
+    plan: Plan,
+}
+
+impl Op for Segment {
+    fn id(&self) -> OpId {
+        OpId::Checkpoint
+    }
+
+    fn name(&self) -> &'static str {
+        "Checkpoint"
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        let mut outs = self.plan.run_refs(inputs).map_err(|e| OpError(e.0))?;
+        outs.pop()
+            .ok_or_else(|| OpError("Checkpoint: segment has no output".into()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        let grads = self
+            .plan
+            .backward_refs(inputs, 0, Some(grad_out.clone()))
+            .map_err(|e| OpError(e.0))?;
+        grads
+            .input_grads
+            .into_iter()
+            .zip(inputs.iter())
+            .map(|(g, t)| match g {
+                Some(grad) => Ok(grad),
+                None => t
+                    .backend()
+                    .zeros(t.shape())
+                    .map_err(|e| OpError(e.to_string())),
+            })
+            .collect()
+    }
+}
+
+impl Graph {
+    /// Run `build` on the value of `x_id` in a scratch graph and record the result here as a
+    /// single checkpointed node. Only the segment inputs (x plus the leaves `build` creates,
+    /// e.g. parameters) and its output are kept; intermediates are recomputed in backward.
+    /// Returns (output_node_id, leaf ids returned by `build`, mapped into this graph).
+    pub fn checkpoint_with<F>(
+        &mut self,
+        x_id: NodeId,
+        build: F,
+    ) -> GraphResult<(NodeId, Vec<NodeId>)>
+    where
+        F: FnOnce(&mut Graph, NodeId) -> GraphResult<(NodeId, Vec<NodeId>)>,
+    {
+        let mut sub = Graph::with_registry(self.registry().clone());
+        let sub_x = sub.var(self.data(x_id)?.clone());
+        let (sub_out, sub_leaves) = build(&mut sub, sub_x)?;
+
+        // Every leaf of the segment becomes a segment input so parameters receive gradients.
+        // Parameter leaves are bound by id here too, so shared parameters accumulate.
+        let mut leaves = vec![sub_x];
+        let mut outer_inputs = vec![x_id];
+        for id in 0..sub.len() {
+            let node = sub.node(id)?;
+            if id != sub_x && node.op.is_none() {
+                leaves.push(id);
+                let outer = match (sub.param_id(id), sub.row_binding(id)) {
+                    (Some(pid), _) => self.bind_param(pid, &node.data),
+                    (None, Some((pid, rows))) => {
+                        self.bind_param_rows(pid, node.data.clone(), rows.to_vec())
+                    }
+                    (None, None) => self.var(node.data.clone()),
+                };
+                outer_inputs.push(outer);
+            }
+        }
+        let leaf_ids = sub_leaves
+            .iter()
+            .map(|l| {
+                leaves
+                    .iter()
+                    .position(|x| x == l)
+                    .map(|k| outer_inputs[k])
+                    .ok_or_else(|| GraphError(format!("checkpoint: node {} is not a leaf", l)))
+            })
+            .collect::<GraphResult<Vec<_>>>()?;
+
+        let plan = Plan::compile(&sub, &leaves, &[sub_out])?;
+        let out_data = sub.data(sub_out)?.clone();
+        drop(sub);
+        let out_id = self.push_op_node(Arc::new(Segment { plan }), &outer_inputs, out_data);
+        Ok((out_id, leaf_ids))
+    }
+}
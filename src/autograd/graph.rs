@@ -1,9 +1,17 @@
 //! Computation graph: nodes, dependency recording, topological sort, backward driver.
 //! Each node holds: op (if any), input node ids, data (Tensor), grad (Option<Tensor>).
 
-use crate::ops::{OpId, OpRegistry};
+use crate::backend::profile::{op_scope, Phase};
+use crate::backend::ConvGeometry;
+use crate::ops::batch_matmul::BatchMatMul;
+use crate::ops::conv::Conv;
+use crate::ops::view::{Concat, Narrow, Permute, Reshape};
+use crate::ops::{Op, OpId, OpRegistry};
+use crate::parameter::{ParamId, Parameter, SparseGrad};
+use crate::shape::Shape;
 use crate::tensor::Tensor;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -15,6 +23,8 @@ pub type GraphResult<T> = Result<T, GraphError>;
 /// A single node in the graph: either a leaf (variable) or an op output.
 pub struct Node {
     pub op_id: Option<OpId>,
+    /// Op instance used for forward; backward dispatches to the same instance.
+    pub op: Option<Arc<dyn Op>>,
     pub inputs: Vec<NodeId>,
     pub data: Tensor,
     pub grad: Option<Tensor>,
@@ -27,21 +37,59 @@ pub type NodeId = usize;
 pub struct Graph {
     nodes: Vec<Node>,
     registry: OpRegistry,
+    /// Parameter leaves bound with [Graph::param], by parameter identity.
+    params: HashMap<ParamId, NodeId>,
+    /// Leaves holding selected rows of a parameter ([Graph::param_rows]).
+    row_params: Vec<(ParamId, NodeId, Vec<usize>)>,
 }
 
 impl Graph {
     pub fn new() -> Self {
+        Self::with_registry(OpRegistry::new())
+    }
+
+    /// Graph dispatching [Self::apply] through a custom registry (e.g. with user ops registered).
+    pub fn with_registry(registry: OpRegistry) -> Self {
         Graph {
             nodes: Vec::new(),
-            registry: OpRegistry::new(),
+            registry,
+            params: HashMap::new(),
+            row_params: Vec::new(),
         }
     }
 
+    /// Registry used by [Self::apply].
+    pub fn registry(&self) -> &OpRegistry {
+        &self.registry
+    }
+
+    /// Mutable registry, for registering custom ops on this graph.
+    pub fn registry_mut(&mut self) -> &mut OpRegistry {
+        &mut self.registry
+    }
+
+    /// Number of nodes recorded so far.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Get node by id (op, inputs, data, grad) for inspection.
+    pub fn node(&self, id: NodeId) -> GraphResult<&Node> {
+        self.nodes
+            .get(id)
+            .ok_or_else(|| GraphError(format!("invalid node id {}", id)))
+    }
+
     /// Create a leaf node (variable). Returns NodeId.
     pub fn var(&mut self, data: Tensor) -> NodeId {
         let id = self.nodes.len();
         self.nodes.push(Node {
             op_id: None,
+            op: None,
             inputs: vec![],
             data,
             grad: None,
@@ -49,6 +97,154 @@ impl Graph {
         id
     }
 
+    /// Leaf node for a parameter, bound by [Parameter::id]. Using the same parameter twice
+    /// returns the same node, so its gradient accumulates over every use.
+    pub fn param(&mut self, p: &Parameter) -> NodeId {
+        self.bind_param(p.id(), p.data())
+    }
+
+    pub(crate) fn bind_param(&mut self, id: ParamId, data: &Tensor) -> NodeId {
+        if let Some(&node) = self.params.get(&id) {
+            return node;
+        }
+        let node = self.var(data.clone());
+        self.params.insert(id, node);
+        node
+    }
+
+    /// Leaf holding `rows` of a parameter viewed as [rows, row_len] (e.g. the rows an embedding
+    /// lookup touches), as [rows.len(), row_len]. Its gradient is written back as a
+    /// [SparseGrad], so only those rows are ever materialised. Every call makes a new leaf.
+    pub fn param_rows(&mut self, p: &Parameter, rows: &[usize]) -> GraphResult<NodeId> {
+        let data = p.data();
+        let total = data.shape().dims().first().copied().unwrap_or(0);
+        let row_len = data.numel() / total.max(1);
+        // Checked up front: with row_len 0 the copy loop below never runs.
+        if let Some(r) = rows.iter().find(|&&r| r >= total) {
+            return Err(GraphError(format!(
+                "param_rows: row {} out of range for {:?}",
+                r,
+                data.shape().dims()
+            )));
+        }
+        let mut values = data.backend().alloc(rows.len() * row_len);
+        for (dst, &r) in values.chunks_mut(row_len.max(1)).zip(rows) {
+            dst.copy_from_slice(&data.data()[r * row_len..(r + 1) * row_len]);
+        }
+        let leaf = Tensor::from_vec(
+            values,
+            Shape::new(vec![rows.len(), row_len]),
+            data.backend(),
+        )
+        .map_err(|e| GraphError(e.to_string()))?;
+        Ok(self.bind_param_rows(p.id(), leaf, rows.to_vec()))
+    }
+
+    pub(crate) fn bind_param_rows(
+        &mut self,
+        id: ParamId,
+        leaf: Tensor,
+        rows: Vec<usize>,
+    ) -> NodeId {
+        let node = self.var(leaf);
+        self.row_params.push((id, node, rows));
+        node
+    }
+
+    /// Parameter id and rows bound to a [Self::param_rows] leaf, if any.
+    pub fn row_binding(&self, node: NodeId) -> Option<(ParamId, &[usize])> {
+        self.row_params
+            .iter()
+            .find(|(_, n, _)| *n == node)
+            .map(|(id, _, rows)| (*id, rows.as_slice()))
+    }
+
+    /// Node bound to a parameter id, if the parameter was used in this graph.
+    pub fn param_node(&self, id: ParamId) -> Option<NodeId> {
+        self.params.get(&id).copied()
+    }
+
+    /// True if [Self::param_rows] bound rows of this parameter in this graph.
+    pub fn has_param_rows(&self, id: ParamId) -> bool {
+        self.row_params.iter().any(|(pid, _, _)| *pid == id)
+    }
+
+    /// Leaf carrying `p`: the node bound to its id, else `returned` (its node from
+    /// [crate::nn::Module::forward_graph], matched by position) if that is a leaf of `p`'s
+    /// shape not bound to any parameter. None for row-bound or unused parameters.
+    pub fn param_leaf(
+        &self,
+        p: &Parameter,
+        returned: Option<NodeId>,
+    ) -> GraphResult<Option<NodeId>> {
+        if let Some(node) = self.param_node(p.id()) {
+            return Ok(Some(node));
+        }
+        let node = match returned {
+            Some(node) if !self.has_param_rows(p.id()) => node,
+            _ => return Ok(None),
+        };
+        let bound = self.params.values().any(|&n| n == node)
+            || self.row_params.iter().any(|(_, n, _)| *n == node);
+        let leaf = self.node(node)?;
+        if leaf.op.is_some() || bound || !leaf.data.shape().same_as(p.data().shape()) {
+            return Ok(None);
+        }
+        Ok(Some(node))
+    }
+
+    /// Parameter id bound to a leaf node, if any.
+    pub fn param_id(&self, node: NodeId) -> Option<ParamId> {
+        self.params
+            .iter()
+            .find(|(_, &n)| n == node)
+            .map(|(&id, _)| id)
+    }
+
+    /// After backward: add each bound parameter's node gradient to the matching [Parameter]
+    /// (matched by id, not position). Row leaves from [Self::param_rows] are added as sparse
+    /// gradients. Parameters not used in this graph are left untouched.
+    pub fn write_grads(&self, params: &mut [&mut Parameter]) -> GraphResult<()> {
+        for p in params.iter_mut() {
+            if let Some(&node) = self.params.get(&p.id()) {
+                if let Some(grad) = self.grad(node)? {
+                    p.accumulate_grad(grad).map_err(|e| GraphError(e.to_string()))?;
+                }
+            }
+            let id = p.id();
+            for (_, node, rows) in self.row_params.iter().filter(|(pid, _, _)| *pid == id) {
+                if let Some(grad) = self.grad(*node)? {
+                    SparseGrad::new(rows.clone(), grad.clone())
+                        .and_then(|g| p.accumulate_sparse_grad(&g))
+                        .map_err(|e| GraphError(e.to_string()))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// [Self::write_grads], falling back to position for parameters bound neither by id nor
+    /// by rows: `params[i]` takes the gradient of `returned[i]` (see [Self::param_leaf]).
+    /// This keeps modules that create their weights with [Self::var] trainable.
+    pub fn write_grads_with(
+        &self,
+        params: &mut [&mut Parameter],
+        returned: &[NodeId],
+    ) -> GraphResult<()> {
+        self.write_grads(params)?;
+        for (p, &node) in params.iter_mut().zip(returned) {
+            if self.params.contains_key(&p.id()) {
+                continue;
+            }
+            if let Some(leaf) = self.param_leaf(p, Some(node))? {
+                if let Some(grad) = self.grad(leaf)? {
+                    p.accumulate_grad(grad).map_err(|e| GraphError(e.to_string()))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Get reference to node data.
     pub fn data(&self, id: NodeId) -> GraphResult<&Tensor> {
         self.nodes
@@ -75,37 +271,47 @@ impl Graph {
 
     /// Run backward from loss node. Fills grad for all nodes that contribute to loss.
     pub fn backward(&mut self, loss_id: NodeId) -> GraphResult<()> {
-        let order = self.reverse_topo(loss_id)?;
         let loss_data = self.data(loss_id)?;
         let backend = loss_data.backend();
         let one = backend
             .ones(loss_data.shape())
             .map_err(|e| GraphError(e.to_string()))?;
-        *self.grad_mut(loss_id)? = Some(one);
+        self.backward_from(loss_id, one)
+    }
+
+    /// Run backward from `out_id` seeded with `grad_out` (same shape as the node's data)
+    /// instead of ones. Used to backpropagate through a recomputed sub-graph.
+    pub fn backward_from(&mut self, out_id: NodeId, grad_out: Tensor) -> GraphResult<()> {
+        if !self.data(out_id)?.shape().same_as(grad_out.shape()) {
+            return Err(GraphError(format!(
+                "backward_from: grad shape {} != node shape {}",
+                grad_out.shape(),
+                self.data(out_id)?.shape()
+            )));
+        }
+        let order = self.reverse_topo(out_id)?;
+        *self.grad_mut(out_id)? = Some(grad_out);
 
         for node_id in order {
-            let (op_id, inputs, data) = {
+            let (op, inputs, data) = {
                 let n = &self.nodes[node_id];
-                let op_id = match n.op_id {
-                    Some(o) => o,
+                let op = match &n.op {
+                    Some(o) => Arc::clone(o),
                     None => continue,
                 };
-                (op_id, n.inputs.clone(), n.data.clone())
+                (op, n.inputs.clone(), n.data.clone())
             };
             let grad_out = self.grad(node_id)?.cloned().ok_or_else(|| {
                 GraphError(format!("missing grad at node {}", node_id))
             })?;
-            let op = self
-                .registry
-                .get(op_id)
-                .ok_or_else(|| GraphError(format!("unknown op {:?}", op_id)))?;
             let input_tensors: Vec<&Tensor> = inputs
                 .iter()
                 .map(|&i| self.data(i).unwrap())
                 .collect();
-            let grads = op
-                .backward(&grad_out, &input_tensors, &data)
-                .map_err(|e| GraphError(e.0))?;
+            let grads = op_scope(op.name(), Phase::Backward, || {
+                op.backward(&grad_out, &input_tensors, &data)
+            })
+            .map_err(|e| GraphError(e.0))?;
             for (i, g) in grads.into_iter().enumerate() {
                 let in_id = inputs[i];
                 self.accumulate_grad(in_id, g)?;
@@ -162,19 +368,37 @@ impl Graph {
             .registry
             .get(op_id)
             .ok_or_else(|| GraphError(format!("unknown op {:?}", op_id)))?;
+        self.apply_op(op, inputs)
+    }
+
+    /// Apply an op instance directly (bypassing the registry). Used for ops that carry
+    /// per-call state, e.g. a checkpointed segment. Returns new NodeId.
+    pub fn apply_op(&mut self, op: Arc<dyn Op>, inputs: &[NodeId]) -> GraphResult<NodeId> {
         let input_tensors: Vec<&Tensor> = inputs
             .iter()
             .map(|&i| self.data(i).map_err(|_| GraphError("invalid input id".into())))
             .collect::<GraphResult<Vec<_>>>()?;
-        let data = op.forward(&input_tensors).map_err(|e| GraphError(e.0))?;
+        let data = op_scope(op.name(), Phase::Forward, || op.forward(&input_tensors))
+            .map_err(|e| GraphError(e.0))?;
+        Ok(self.push_op_node(op, inputs, data))
+    }
+
+    /// Record an op node whose output has already been computed.
+    pub(crate) fn push_op_node(
+        &mut self,
+        op: Arc<dyn Op>,
+        inputs: &[NodeId],
+        data: Tensor,
+    ) -> NodeId {
         let id = self.nodes.len();
         self.nodes.push(Node {
-            op_id: Some(op_id),
+            op_id: Some(op.id()),
+            op: Some(op),
             inputs: inputs.to_vec(),
             data,
             grad: None,
         });
-        Ok(id)
+        id
     }
 
     /// Add: a + b (same shape)
@@ -226,6 +450,60 @@ impl Graph {
     pub fn log(&mut self, a: NodeId) -> GraphResult<NodeId> {
         self.apply(OpId::Log, &[a])
     }
+
+    pub fn tanh(&mut self, a: NodeId) -> GraphResult<NodeId> {
+        self.apply(OpId::Tanh, &[a])
+    }
+
+    /// Entries start..start + len of `dim` (see [crate::ops::view::Narrow]).
+    pub fn narrow(
+        &mut self,
+        a: NodeId,
+        dim: usize,
+        start: usize,
+        len: usize,
+    ) -> GraphResult<NodeId> {
+        self.apply_op(Arc::new(Narrow::new(dim, start, len)), &[a])
+    }
+
+    /// Inputs joined along `dim` (see [crate::ops::view::Concat]).
+    pub fn concat(&mut self, inputs: &[NodeId], dim: usize) -> GraphResult<NodeId> {
+        self.apply_op(Arc::new(Concat::new(dim)), inputs)
+    }
+
+    pub fn reshape(&mut self, a: NodeId, dims: &[usize]) -> GraphResult<NodeId> {
+        self.apply_op(Arc::new(Reshape::new(dims.to_vec())), &[a])
+    }
+
+    /// Output dim i is input dim `perm[i]`.
+    pub fn permute(&mut self, a: NodeId, perm: &[usize]) -> GraphResult<NodeId> {
+        self.apply_op(Arc::new(Permute::new(perm.to_vec())), &[a])
+    }
+
+    /// alpha * a @ b per batch for a [N, M, K] and b [N, K, P] (or [N, P, K] with
+    /// `transpose_b`); see [crate::ops::batch_matmul::BatchMatMul].
+    pub fn batch_matmul(
+        &mut self,
+        a: NodeId,
+        b: NodeId,
+        transpose_b: bool,
+        alpha: f32,
+    ) -> GraphResult<NodeId> {
+        self.apply_op(Arc::new(BatchMatMul::new(transpose_b, alpha)), &[a, b])
+    }
+
+    /// Convolution of x with weight w and optional bias b (see [crate::ops::conv::Conv]).
+    pub fn conv(
+        &mut self,
+        x: NodeId,
+        w: NodeId,
+        b: Option<NodeId>,
+        geom: ConvGeometry,
+        groups: usize,
+    ) -> GraphResult<NodeId> {
+        let inputs: Vec<NodeId> = [x, w].into_iter().chain(b).collect();
+        self.apply_op(Arc::new(Conv::new(geom, groups)), &inputs)
+    }
 }
 
 impl Default for Graph {
@@ -3,5 +3,8 @@
 
 pub mod graph;
 pub mod check;
+pub mod checkpoint;
+pub mod plan;
 
 pub use graph::{Graph, GraphError, GraphResult, Node, NodeId};
+pub use plan::{CompileOptions, Plan, PlanGrads, Slot};
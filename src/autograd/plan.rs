@@ -0,0 +1,547 @@
+//! Compiled execution plans: lower a recorded Graph into a flat instruction list and optimise it
+//! (dead-code elimination, common sub-expression elimination, linear fusion). A plan runs
+//! forward and backward without building Graph nodes, so it can be re-run across steps.
+
+use super::graph::{Graph, GraphError, GraphResult, NodeId};
+use crate::backend::profile::{op_scope, Phase};
+use crate::backend::Activation;
+use crate::ops::linear::FusedLinear;
+use crate::ops::{Op, OpId};
+use crate::tensor::Tensor;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Where an instruction reads a value from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Slot {
+    /// Runtime input, bound on each run (in compile order).
+    Input(usize),
+    /// Constant captured from a graph leaf at compile time.
+    Const(usize),
+    /// Output of an earlier instruction.
+    Instr(usize),
+}
+
+/// One op application in a plan.
+#[derive(Clone)]
+pub struct Instr {
+    pub op: Arc<dyn Op>,
+    pub inputs: Vec<Slot>,
+}
+
+/// Optional passes run by [Plan::compile_with]. Dead-code elimination always runs.
+#[derive(Clone, Copy, Debug)]
+pub struct CompileOptions {
+    /// Merge instructions with the same op and inputs, and constants with the same data.
+    pub cse: bool,
+    /// Fuse matmul -> add_broadcast [-> relu | sigmoid] into one [FusedLinear] kernel.
+    pub fuse: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            cse: true,
+            fuse: true,
+        }
+    }
+}
+
+/// Result of [Plan::backward]: forward outputs and one gradient per runtime input
+/// (None when the input does not affect the differentiated output).
+pub struct PlanGrads {
+    pub outputs: Vec<Tensor>,
+    pub input_grads: Vec<Option<Tensor>>,
+}
+
+/// Flat, optimised program: runtime inputs, captured constants, instructions in execution order.
+pub struct Plan {
+    num_inputs: usize,
+    constants: Vec<Tensor>,
+    instrs: Vec<Instr>,
+    outputs: Vec<Slot>,
+    /// For each instruction, index of the last instruction reading it (usize::MAX if an output).
+    last_use: Vec<usize>,
+}
+
+impl Plan {
+    /// Compile the part of `g` that `outputs` depend on, with all passes enabled.
+    /// `inputs` are leaf nodes bound at run time; every other leaf becomes a constant.
+    pub fn compile(g: &Graph, inputs: &[NodeId], outputs: &[NodeId]) -> GraphResult<Plan> {
+        Self::compile_with(g, inputs, outputs, CompileOptions::default())
+    }
+
+    /// Compile with explicit pass selection.
+    pub fn compile_with(
+        g: &Graph,
+        inputs: &[NodeId],
+        outputs: &[NodeId],
+        options: CompileOptions,
+    ) -> GraphResult<Plan> {
+        let mut plan = Self::lower(g, inputs, outputs)?;
+        if options.cse {
+            plan.eliminate_common_subexpressions();
+        }
+        if options.fuse {
+            plan.fuse_linear();
+        }
+        plan.compute_last_use();
+        Ok(plan)
+    }
+
+    /// Build a plan from parts (e.g. a deserialised program). Instructions must be in
+    /// execution order and only read earlier instructions.
+    pub fn from_parts(
+        num_inputs: usize,
+        constants: Vec<Tensor>,
+        instrs: Vec<Instr>,
+        outputs: Vec<Slot>,
+    ) -> GraphResult<Plan> {
+        let check = |s: Slot, limit: usize| match s {
+            Slot::Input(k) if k < num_inputs => Ok(()),
+            Slot::Const(c) if c < constants.len() => Ok(()),
+            Slot::Instr(j) if j < limit => Ok(()),
+            _ => Err(GraphError(format!("plan slot {:?} out of range", s))),
+        };
+        for (i, instr) in instrs.iter().enumerate() {
+            for &s in &instr.inputs {
+                check(s, i)?;
+            }
+        }
+        for &s in &outputs {
+            check(s, instrs.len())?;
+        }
+        let mut plan = Plan {
+            num_inputs,
+            constants,
+            instrs,
+            outputs,
+            last_use: Vec::new(),
+        };
+        plan.compute_last_use();
+        Ok(plan)
+    }
+
+    /// Number of runtime inputs.
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    /// Captured constants.
+    pub fn constants(&self) -> &[Tensor] {
+        &self.constants
+    }
+
+    /// Instructions in execution order.
+    pub fn instrs(&self) -> &[Instr] {
+        &self.instrs
+    }
+
+    /// Output slots, in the order given to compile.
+    pub fn outputs(&self) -> &[Slot] {
+        &self.outputs
+    }
+
+    /// Number of instructions.
+    pub fn len(&self) -> usize {
+        self.instrs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instrs.is_empty()
+    }
+
+    /// Op names in execution order (for debugging).
+    pub fn op_names(&self) -> Vec<&'static str> {
+        self.instrs.iter().map(|i| i.op.name()).collect()
+    }
+
+    /// Dead-code elimination happens here: only nodes reachable from `outputs` are lowered.
+    fn lower(g: &Graph, inputs: &[NodeId], outputs: &[NodeId]) -> GraphResult<Plan> {
+        let n = g.len();
+        let mut slots: Vec<Option<Slot>> = vec![None; n];
+        for (k, &id) in inputs.iter().enumerate() {
+            if g.node(id)?.op.is_some() {
+                return Err(GraphError(format!("plan input {} is not a leaf", id)));
+            }
+            if slots[id].is_none() {
+                slots[id] = Some(Slot::Input(k));
+            }
+        }
+        let mut live = vec![false; n];
+        for &o in outputs {
+            g.node(o)?;
+            live[o] = true;
+        }
+        for id in (0..n).rev() {
+            if live[id] {
+                for &i in &g.node(id)?.inputs {
+                    live[i] = true;
+                }
+            }
+        }
+        let mut constants = Vec::new();
+        let mut instrs = Vec::new();
+        for id in 0..n {
+            if !live[id] || slots[id].is_some() {
+                continue;
+            }
+            let node = g.node(id)?;
+            match &node.op {
+                None => {
+                    slots[id] = Some(Slot::Const(constants.len()));
+                    constants.push(node.data.clone());
+                }
+                Some(op) => {
+                    let ins = node
+                        .inputs
+                        .iter()
+                        .map(|&i| {
+                            slots[i].ok_or_else(|| {
+                                GraphError(format!("node {} used before defined", i))
+                            })
+                        })
+                        .collect::<GraphResult<Vec<_>>>()?;
+                    slots[id] = Some(Slot::Instr(instrs.len()));
+                    instrs.push(Instr {
+                        op: Arc::clone(op),
+                        inputs: ins,
+                    });
+                }
+            }
+        }
+        let outputs = outputs
+            .iter()
+            .map(|&o| slots[o].ok_or_else(|| GraphError(format!("output {} not lowered", o))))
+            .collect::<GraphResult<Vec<_>>>()?;
+        Ok(Plan {
+            num_inputs: inputs.len(),
+            constants,
+            instrs,
+            outputs,
+            last_use: Vec::new(),
+        })
+    }
+
+    /// Merge constants with identical shape and data, then instructions applying the same op
+    /// instance to the same slots. Ops carrying per-call state are distinct instances and
+    /// are never merged.
+    fn eliminate_common_subexpressions(&mut self) {
+        let mut const_keys: HashMap<(Vec<usize>, Vec<u32>), usize> = HashMap::new();
+        let mut const_map = Vec::with_capacity(self.constants.len());
+        let mut constants = Vec::new();
+        for c in self.constants.drain(..) {
+            let key = (
+                c.shape().dims().to_vec(),
+                c.data().iter().map(|v| v.to_bits()).collect::<Vec<_>>(),
+            );
+            let idx = *const_keys.entry(key).or_insert_with(|| {
+                constants.push(c);
+                constants.len() - 1
+            });
+            const_map.push(idx);
+        }
+        self.constants = constants;
+
+        let mut instr_keys: HashMap<(usize, Vec<Slot>), usize> = HashMap::new();
+        let mut instr_map = Vec::with_capacity(self.instrs.len());
+        let mut instrs: Vec<Instr> = Vec::new();
+        for mut instr in self.instrs.drain(..) {
+            for s in instr.inputs.iter_mut() {
+                *s = match *s {
+                    Slot::Const(c) => Slot::Const(const_map[c]),
+                    Slot::Instr(j) => Slot::Instr(instr_map[j]),
+                    other => other,
+                };
+            }
+            let key = (
+                Arc::as_ptr(&instr.op) as *const () as usize,
+                instr.inputs.clone(),
+            );
+            let idx = *instr_keys.entry(key).or_insert_with(|| {
+                instrs.push(instr);
+                instrs.len() - 1
+            });
+            instr_map.push(idx);
+        }
+        self.instrs = instrs;
+        for s in self.outputs.iter_mut() {
+            *s = match *s {
+                Slot::Const(c) => Slot::Const(const_map[c]),
+                Slot::Instr(j) => Slot::Instr(instr_map[j]),
+                other => other,
+            };
+        }
+    }
+
+    /// Fuse matmul -> add_broadcast [-> relu | sigmoid] chains whose intermediates have a
+    /// single consumer into one [FusedLinear] instruction.
+    fn fuse_linear(&mut self) {
+        let uses = self.use_counts();
+        // head instruction -> (activation, matmul index, add_broadcast index)
+        let mut groups: HashMap<usize, (Activation, usize, usize)> = HashMap::new();
+        let mut absorbed = vec![false; self.instrs.len()];
+        for (i, instr) in self.instrs.iter().enumerate() {
+            let single_use_input = |k: usize| match instr.inputs.get(k) {
+                Some(Slot::Instr(j)) if uses[*j] == 1 => Some(*j),
+                _ => None,
+            };
+            match instr.op.id() {
+                OpId::AddBroadcast => {
+                    if let Some(mm) = single_use_input(0) {
+                        if self.instrs[mm].op.id() == OpId::MatMul {
+                            groups.insert(i, (Activation::Identity, mm, i));
+                            absorbed[mm] = true;
+                        }
+                    }
+                }
+                OpId::ReLU | OpId::Sigmoid => {
+                    let act = if instr.op.id() == OpId::ReLU {
+                        Activation::ReLU
+                    } else {
+                        Activation::Sigmoid
+                    };
+                    if let Some(j) = single_use_input(0) {
+                        if let Some(&(Activation::Identity, mm, ab)) = groups.get(&j) {
+                            groups.remove(&j);
+                            groups.insert(i, (act, mm, ab));
+                            absorbed[j] = true;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if groups.is_empty() {
+            return;
+        }
+
+        let old = std::mem::take(&mut self.instrs);
+        let mut map = vec![usize::MAX; old.len()];
+        let remap = |s: Slot, map: &[usize]| match s {
+            Slot::Instr(j) => Slot::Instr(map[j]),
+            other => other,
+        };
+        for (i, instr) in old.iter().enumerate() {
+            if absorbed[i] {
+                continue;
+            }
+            let new_instr = match groups.get(&i) {
+                Some(&(act, mm, ab)) => Instr {
+                    op: Arc::new(FusedLinear::new(act)),
+                    inputs: vec![
+                        remap(old[mm].inputs[0], &map),
+                        remap(old[mm].inputs[1], &map),
+                        remap(old[ab].inputs[1], &map),
+                    ],
+                },
+                None => Instr {
+                    op: Arc::clone(&instr.op),
+                    inputs: instr.inputs.iter().map(|&s| remap(s, &map)).collect(),
+                },
+            };
+            map[i] = self.instrs.len();
+            self.instrs.push(new_instr);
+        }
+        for s in self.outputs.iter_mut() {
+            *s = remap(*s, &map);
+        }
+    }
+
+    fn use_counts(&self) -> Vec<usize> {
+        let mut uses = vec![0usize; self.instrs.len()];
+        for s in self
+            .instrs
+            .iter()
+            .flat_map(|i| i.inputs.iter())
+            .chain(self.outputs.iter())
+        {
+            if let Slot::Instr(j) = *s {
+                uses[j] += 1;
+            }
+        }
+        uses
+    }
+
+    fn compute_last_use(&mut self) {
+        let mut last_use = vec![0usize; self.instrs.len()];
+        for (i, instr) in self.instrs.iter().enumerate() {
+            for s in &instr.inputs {
+                if let Slot::Instr(j) = *s {
+                    last_use[j] = i;
+                }
+            }
+        }
+        for s in &self.outputs {
+            if let Slot::Instr(j) = *s {
+                last_use[j] = usize::MAX;
+            }
+        }
+        self.last_use = last_use;
+    }
+
+    fn check_inputs(&self, n: usize) -> GraphResult<()> {
+        if n != self.num_inputs {
+            return Err(GraphError(format!(
+                "plan expects {} inputs, got {}",
+                self.num_inputs, n
+            )));
+        }
+        Ok(())
+    }
+
+    fn value<'a>(
+        &'a self,
+        slot: Slot,
+        inputs: &[&'a Tensor],
+        values: &'a [Option<Tensor>],
+    ) -> GraphResult<&'a Tensor> {
+        match slot {
+            Slot::Input(k) => Ok(inputs[k]),
+            Slot::Const(c) => Ok(&self.constants[c]),
+            Slot::Instr(j) => values[j]
+                .as_ref()
+                .ok_or_else(|| GraphError(format!("plan value {} already freed", j))),
+        }
+    }
+
+    /// Forward: run all instructions, freeing each intermediate after its last use.
+    pub fn run(&self, inputs: &[Tensor]) -> GraphResult<Vec<Tensor>> {
+        let refs: Vec<&Tensor> = inputs.iter().collect();
+        self.run_refs(&refs)
+    }
+
+    pub(crate) fn run_refs(&self, inputs: &[&Tensor]) -> GraphResult<Vec<Tensor>> {
+        self.check_inputs(inputs.len())?;
+        let mut values: Vec<Option<Tensor>> = (0..self.instrs.len()).map(|_| None).collect();
+        for (i, instr) in self.instrs.iter().enumerate() {
+            let out = {
+                let args = instr
+                    .inputs
+                    .iter()
+                    .map(|&s| self.value(s, inputs, &values))
+                    .collect::<GraphResult<Vec<_>>>()?;
+                op_scope(instr.op.name(), Phase::Forward, || instr.op.forward(&args))
+                    .map_err(|e| GraphError(e.0))?
+            };
+            values[i] = Some(out);
+            for s in &instr.inputs {
+                if let Slot::Instr(j) = *s {
+                    if self.last_use[j] == i {
+                        values[j] = None;
+                    }
+                }
+            }
+        }
+        self.outputs
+            .iter()
+            .map(|&s| self.value(s, inputs, &values).cloned())
+            .collect()
+    }
+
+    /// Forward then backward from `outputs[output]`, seeded with `grad_out` (ones if None).
+    /// Returns all outputs and the gradient w.r.t. each runtime input.
+    pub fn backward(
+        &self,
+        inputs: &[Tensor],
+        output: usize,
+        grad_out: Option<Tensor>,
+    ) -> GraphResult<PlanGrads> {
+        let refs: Vec<&Tensor> = inputs.iter().collect();
+        self.backward_refs(&refs, output, grad_out)
+    }
+
+    pub(crate) fn backward_refs(
+        &self,
+        inputs: &[&Tensor],
+        output: usize,
+        grad_out: Option<Tensor>,
+    ) -> GraphResult<PlanGrads> {
+        self.check_inputs(inputs.len())?;
+        let out_slot = *self
+            .outputs
+            .get(output)
+            .ok_or_else(|| GraphError(format!("plan has no output {}", output)))?;
+        // Keep every intermediate: backward needs them all.
+        let mut values: Vec<Option<Tensor>> = Vec::with_capacity(self.instrs.len());
+        for instr in &self.instrs {
+            let out = {
+                let args = instr
+                    .inputs
+                    .iter()
+                    .map(|&s| self.value(s, inputs, &values))
+                    .collect::<GraphResult<Vec<_>>>()?;
+                op_scope(instr.op.name(), Phase::Forward, || instr.op.forward(&args))
+                    .map_err(|e| GraphError(e.0))?
+            };
+            values.push(Some(out));
+        }
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|&s| self.value(s, inputs, &values).cloned())
+            .collect::<GraphResult<Vec<_>>>()?;
+
+        let seed = match grad_out {
+            Some(g) => g,
+            None => {
+                let out = &outputs[output];
+                out.backend()
+                    .ones(out.shape())
+                    .map_err(|e| GraphError(e.to_string()))?
+            }
+        };
+        if !seed.shape().same_as(outputs[output].shape()) {
+            return Err(GraphError("plan backward: grad shape mismatch".into()));
+        }
+        let mut instr_grads: Vec<Option<Tensor>> = (0..self.instrs.len()).map(|_| None).collect();
+        let mut input_grads: Vec<Option<Tensor>> = (0..self.num_inputs).map(|_| None).collect();
+        accumulate(out_slot, seed, &mut instr_grads, &mut input_grads)?;
+
+        for i in (0..self.instrs.len()).rev() {
+            let grad = match instr_grads[i].take() {
+                Some(g) => g,
+                None => continue,
+            };
+            let instr = &self.instrs[i];
+            let grads = {
+                let args = instr
+                    .inputs
+                    .iter()
+                    .map(|&s| self.value(s, inputs, &values))
+                    .collect::<GraphResult<Vec<_>>>()?;
+                let out = self.value(Slot::Instr(i), inputs, &values)?;
+                op_scope(instr.op.name(), Phase::Backward, || {
+                    instr.op.backward(&grad, &args, out)
+                })
+                .map_err(|e| GraphError(e.0))?
+            };
+            for (&s, g) in instr.inputs.iter().zip(grads) {
+                accumulate(s, g, &mut instr_grads, &mut input_grads)?;
+            }
+        }
+        Ok(PlanGrads {
+            outputs,
+            input_grads,
+        })
+    }
+}
+
+fn accumulate(
+    slot: Slot,
+    g: Tensor,
+    instr_grads: &mut [Option<Tensor>],
+    input_grads: &mut [Option<Tensor>],
+) -> GraphResult<()> {
+    let target = match slot {
+        Slot::Input(k) => &mut input_grads[k],
+        Slot::Instr(j) => &mut instr_grads[j],
+        Slot::Const(_) => return Ok(()),
+    };
+    *target = Some(match target.take() {
+        None => g,
+        Some(existing) => existing.add(&g).map_err(|e| GraphError(e.to_string()))?,
+    });
+    Ok(())
+}
@@ -0,0 +1,145 @@
+//! Buffer allocators for host backends. Tensors hand their buffer back to their backend on
+//! drop ([super::Backend::release]); a [PoolAllocator] keeps those buffers and reuses them
+//! for later tensors of the same length, which is the common case across training steps.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Allocation counters. Bytes are counted as `len * size_of::<f32>()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Buffers handed out by `alloc`.
+    pub requests: u64,
+    /// Requests served from previously released buffers.
+    pub reused: u64,
+    /// Bytes of fresh (not reused) allocations.
+    pub bytes_allocated: u64,
+    /// Buffers given back through `release`.
+    pub released: u64,
+    /// Bytes currently held for reuse.
+    pub pooled_bytes: u64,
+    /// Largest value `pooled_bytes` has reached.
+    pub peak_pooled_bytes: u64,
+}
+
+/// Source of zeroed f32 buffers for tensors.
+pub trait Allocator: Send + Sync {
+    /// A buffer of `len` zeros.
+    fn alloc(&self, len: usize) -> Vec<f32>;
+
+    /// Take back a buffer that is no longer used.
+    fn release(&self, buf: Vec<f32>);
+
+    fn stats(&self) -> AllocStats;
+}
+
+fn bytes(len: usize) -> u64 {
+    (len * std::mem::size_of::<f32>()) as u64
+}
+
+/// Plain heap allocation; released buffers are freed. Only counts requests.
+#[derive(Default)]
+pub struct SystemAllocator {
+    requests: AtomicU64,
+    bytes_allocated: AtomicU64,
+    released: AtomicU64,
+}
+
+impl SystemAllocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Allocator for SystemAllocator {
+    fn alloc(&self, len: usize) -> Vec<f32> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes_allocated
+            .fetch_add(bytes(len), Ordering::Relaxed);
+        vec![0.0; len]
+    }
+
+    fn release(&self, _buf: Vec<f32>) {
+        self.released.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> AllocStats {
+        AllocStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            bytes_allocated: self.bytes_allocated.load(Ordering::Relaxed),
+            released: self.released.load(Ordering::Relaxed),
+            ..AllocStats::default()
+        }
+    }
+}
+
+#[derive(Default)]
+struct PoolState {
+    /// Free buffers keyed by length.
+    free: HashMap<usize, Vec<Vec<f32>>>,
+    stats: AllocStats,
+}
+
+/// Keeps released buffers (up to `max_pooled_bytes`) and reuses them for equal-length requests.
+pub struct PoolAllocator {
+    max_pooled_bytes: u64,
+    state: Mutex<PoolState>,
+}
+
+impl PoolAllocator {
+    /// Pool holding at most `max_pooled_bytes` of free buffers; extra releases are freed.
+    pub fn new(max_pooled_bytes: usize) -> Self {
+        PoolAllocator {
+            max_pooled_bytes: max_pooled_bytes as u64,
+            state: Mutex::new(PoolState::default()),
+        }
+    }
+
+    /// Free all pooled buffers.
+    pub fn clear(&self) {
+        let mut st = self.state.lock().unwrap();
+        st.free.clear();
+        st.stats.pooled_bytes = 0;
+    }
+}
+
+impl Default for PoolAllocator {
+    /// 256 MiB pool.
+    fn default() -> Self {
+        Self::new(256 << 20)
+    }
+}
+
+impl Allocator for PoolAllocator {
+    fn alloc(&self, len: usize) -> Vec<f32> {
+        let mut st = self.state.lock().unwrap();
+        st.stats.requests += 1;
+        if let Some(mut buf) = st.free.get_mut(&len).and_then(Vec::pop) {
+            st.stats.reused += 1;
+            st.stats.pooled_bytes -= bytes(len);
+            drop(st);
+            buf.fill(0.0);
+            return buf;
+        }
+        st.stats.bytes_allocated += bytes(len);
+        drop(st);
+        vec![0.0; len]
+    }
+
+    fn release(&self, buf: Vec<f32>) {
+        let mut st = self.state.lock().unwrap();
+        st.stats.released += 1;
+        let b = bytes(buf.len());
+        if buf.is_empty() || st.stats.pooled_bytes + b > self.max_pooled_bytes {
+            return;
+        }
+        st.stats.pooled_bytes += b;
+        st.stats.peak_pooled_bytes = st.stats.peak_pooled_bytes.max(st.stats.pooled_bytes);
+        st.free.entry(buf.len()).or_default().push(buf);
+    }
+
+    fn stats(&self) -> AllocStats {
+        self.state.lock().unwrap().stats
+    }
+}
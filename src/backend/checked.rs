@@ -0,0 +1,612 @@
+//! Cross-checking decorator: [CheckedBackend] runs every op on a candidate backend and on
+//! an f64 reference implementation of CpuBackend semantics, and records (or fails on) the
+//! first result that differs beyond tolerance. Use it to bring up optimised backends.
+
+use crate::backend::{
+    Activation, AllocStats, Backend, BackendError, BackendResult, Capabilities, ConvGeometry,
+    Device,
+};
+use crate::shape::Shape;
+use crate::tensor::Tensor;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Elements match when |got - reference| <= atol + rtol * |reference|.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    pub atol: f64,
+    pub rtol: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            atol: 1e-5,
+            rtol: 1e-4,
+        }
+    }
+}
+
+/// A candidate result that disagrees with the reference.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// Backend method, e.g. "matmul".
+    pub op: &'static str,
+    pub input_shapes: Vec<Vec<usize>>,
+    pub detail: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} on inputs {:?}: {}",
+            self.op, self.input_shapes, self.detail
+        )
+    }
+}
+
+#[derive(Default)]
+struct CheckState {
+    checked: u64,
+    divergences: Vec<Divergence>,
+}
+
+/// Backend decorator comparing every op against an f64 reference. Clones share the record.
+pub struct CheckedBackend<B: Backend> {
+    inner: Arc<B>,
+    tol: Tolerance,
+    fail_fast: bool,
+    state: Arc<Mutex<CheckState>>,
+}
+
+impl<B: Backend> Clone for CheckedBackend<B> {
+    fn clone(&self) -> Self {
+        CheckedBackend {
+            inner: Arc::clone(&self.inner),
+            tol: self.tol,
+            fail_fast: self.fail_fast,
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+/// Reference result: values and shape, or the reason the inputs are invalid.
+pub(crate) type Ref = Result<(Vec<f64>, Vec<usize>), String>;
+
+impl<B: Backend + 'static> CheckedBackend<B> {
+    /// Check `inner` with the default tolerance; a divergence fails the op.
+    pub fn new(inner: B) -> Self {
+        CheckedBackend {
+            inner: Arc::new(inner),
+            tol: Tolerance::default(),
+            fail_fast: true,
+            state: Arc::new(Mutex::new(CheckState::default())),
+        }
+    }
+
+    pub fn with_tolerance(mut self, atol: f64, rtol: f64) -> Self {
+        self.tol = Tolerance { atol, rtol };
+        self
+    }
+
+    /// If false, divergences are only recorded and the candidate's result is returned.
+    pub fn with_fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Number of ops compared so far.
+    pub fn checked_ops(&self) -> u64 {
+        self.state.lock().unwrap().checked
+    }
+
+    pub fn first_divergence(&self) -> Option<Divergence> {
+        self.state.lock().unwrap().divergences.first().cloned()
+    }
+
+    pub fn divergences(&self) -> Vec<Divergence> {
+        self.state.lock().unwrap().divergences.clone()
+    }
+
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = CheckState::default();
+    }
+
+    fn check(
+        &self,
+        op: &'static str,
+        inputs: &[&Tensor],
+        got: BackendResult<Tensor>,
+        reference: Ref,
+    ) -> BackendResult<Tensor> {
+        let detail = match (&got, &reference) {
+            (Ok(t), Ok((want, shape))) => compare(t, want, shape, self.tol),
+            (Ok(_), Err(why)) => Some(format!(
+                "candidate accepted inputs the reference rejects ({})",
+                why
+            )),
+            (Err(e), Ok(_)) => Some(format!("candidate failed: {}", e.0)),
+            (Err(_), Err(_)) => None,
+        };
+        let mut st = self.state.lock().unwrap();
+        st.checked += 1;
+        if let Some(detail) = detail {
+            let d = Divergence {
+                op,
+                input_shapes: inputs.iter().map(|t| t.shape().dims().to_vec()).collect(),
+                detail,
+            };
+            let msg = d.to_string();
+            st.divergences.push(d);
+            if self.fail_fast {
+                return Err(BackendError(format!("divergence from reference: {}", msg)));
+            }
+        }
+        got
+    }
+
+    fn bind(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor> {
+        Tensor::from_vec(data, shape, Arc::new(self.clone()))
+            .map_err(|e| BackendError(e.to_string()))
+    }
+}
+
+/// None if `got` matches the reference (rounded to f32) within `tol`, else what differs.
+/// NaN must match NaN and infinities must match exactly.
+pub(crate) fn compare(
+    got: &Tensor,
+    want: &[f64],
+    shape: &[usize],
+    tol: Tolerance,
+) -> Option<String> {
+    if got.shape().dims() != shape {
+        return Some(format!(
+            "output shape {:?}, reference {:?}",
+            got.shape().dims(),
+            shape
+        ));
+    }
+    for (i, (&g, &w)) in got.data().iter().zip(want).enumerate() {
+        let (g, w) = (g as f64, w as f32 as f64);
+        let ok = if w.is_nan() || g.is_nan() {
+            w.is_nan() && g.is_nan()
+        } else if w.is_infinite() || g.is_infinite() {
+            g == w
+        } else {
+            (g - w).abs() <= tol.atol + tol.rtol * w.abs()
+        };
+        if !ok {
+            return Some(format!("element {}: got {}, reference {}", i, g, w));
+        }
+    }
+    None
+}
+
+/// f64 kernels with CpuBackend semantics.
+pub(crate) mod reference {
+    use super::Ref;
+    use crate::backend::{Activation, ConvGeometry};
+    use crate::tensor::Tensor;
+
+    fn vals(t: &Tensor) -> Vec<f64> {
+        t.data().iter().map(|&v| v as f64).collect()
+    }
+
+    fn dims(t: &Tensor) -> Vec<usize> {
+        t.shape().dims().to_vec()
+    }
+
+    pub fn map(a: &Tensor, f: impl Fn(f64) -> f64) -> Ref {
+        Ok((vals(a).into_iter().map(f).collect(), dims(a)))
+    }
+
+    pub fn zip(a: &Tensor, b: &Tensor, f: impl Fn(f64, f64) -> f64) -> Ref {
+        if a.shape().dims() != b.shape().dims() {
+            return Err("shape mismatch".into());
+        }
+        Ok((
+            vals(a)
+                .into_iter()
+                .zip(vals(b))
+                .map(|(x, y)| f(x, y))
+                .collect(),
+            dims(a),
+        ))
+    }
+
+    pub fn matmul(a: &Tensor, b: &Tensor) -> Ref {
+        let (m, k, n) = match (a.shape().dims(), b.shape().dims()) {
+            ([m, k], [k2, n]) if k == k2 => (*m, *k, *n),
+            _ => return Err("expected [M,K] @ [K,N]".into()),
+        };
+        let (av, bv) = (vals(a), vals(b));
+        let mut out = vec![0.0; m * n];
+        for i in 0..m {
+            for j in 0..n {
+                out[i * n + j] = (0..k).map(|p| av[i * k + p] * bv[p * n + j]).sum();
+            }
+        }
+        Ok((out, vec![m, n]))
+    }
+
+    pub fn sum(a: &Tensor) -> Ref {
+        Ok((vec![vals(a).iter().sum()], vec![1]))
+    }
+
+    pub fn sum_dim(a: &Tensor, dim: usize) -> Ref {
+        let d = a.shape().dims();
+        if dim >= d.len() {
+            return Err("dim out of range".into());
+        }
+        let outer: usize = d[..dim].iter().product();
+        let inner: usize = d[dim + 1..].iter().product();
+        let av = vals(a);
+        let mut out = vec![0.0; outer * inner];
+        for o in 0..outer {
+            for r in 0..d[dim] {
+                for i in 0..inner {
+                    out[o * inner + i] += av[(o * d[dim] + r) * inner + i];
+                }
+            }
+        }
+        let mut out_dims = d.to_vec();
+        out_dims[dim] = 1;
+        Ok((out, out_dims))
+    }
+
+    pub fn add_broadcast(a: &Tensor, b: &Tensor) -> Ref {
+        let k = match (a.shape().dims(), b.shape().dims()) {
+            ([_, k], [k2]) if k == k2 => *k,
+            _ => return Err("expected [N,K] + [K]".into()),
+        };
+        let bv = vals(b);
+        let out = vals(a)
+            .iter()
+            .enumerate()
+            .map(|(i, x)| x + bv[i % k])
+            .collect();
+        Ok((out, dims(a)))
+    }
+
+    pub fn transpose(a: &Tensor) -> Ref {
+        let (m, n) = match a.shape().dims() {
+            [m, n] => (*m, *n),
+            _ => return Err("expected 2D".into()),
+        };
+        let av = vals(a);
+        let mut out = vec![0.0; m * n];
+        for i in 0..m {
+            for j in 0..n {
+                out[j * m + i] = av[i * n + j];
+            }
+        }
+        Ok((out, vec![n, m]))
+    }
+
+    pub fn sigmoid(x: f64) -> f64 {
+        1.0 / (1.0 + (-x).exp())
+    }
+
+    /// Apply `f(row_in, row_out)` over rows of the last dimension.
+    fn rows(a: &Tensor, f: impl Fn(&[f64], &mut [f64])) -> Ref {
+        let d = a.shape().dims();
+        let row = match d.last() {
+            Some(&r) => r.max(1),
+            None => return Err("need at least 1 dim".into()),
+        };
+        let av = vals(a);
+        let mut out = vec![0.0; av.len()];
+        for (x, o) in av.chunks(row).zip(out.chunks_mut(row)) {
+            f(x, o);
+        }
+        Ok((out, dims(a)))
+    }
+
+    pub fn softmax(a: &Tensor) -> Ref {
+        rows(a, |x, o| {
+            let max = x.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let total: f64 = x.iter().map(|v| (v - max).exp()).sum();
+            for (o, v) in o.iter_mut().zip(x) {
+                *o = (v - max).exp() / total;
+            }
+        })
+    }
+
+    pub fn softmax_backward(grad_out: &Tensor, y: &Tensor) -> Ref {
+        if grad_out.shape().dims() != y.shape().dims() {
+            return Err("shape mismatch".into());
+        }
+        let row = match y.shape().dims().last() {
+            Some(&r) => r.max(1),
+            None => return Err("need at least 1 dim".into()),
+        };
+        let (g, yv) = (vals(grad_out), vals(y));
+        let mut out = vec![0.0; yv.len()];
+        for ((gr, yr), or) in g.chunks(row).zip(yv.chunks(row)).zip(out.chunks_mut(row)) {
+            let dot: f64 = gr.iter().zip(yr).map(|(a, b)| a * b).sum();
+            for ((o, gi), yi) in or.iter_mut().zip(gr).zip(yr) {
+                *o = yi * (gi - dot);
+            }
+        }
+        Ok((out, dims(y)))
+    }
+
+    pub fn linear(x: &Tensor, w: &Tensor, b: &Tensor, act: Activation) -> Ref {
+        let (mut out, shape) = matmul(x, w)?;
+        let n = shape[1];
+        if b.shape().dims() != [n] {
+            return Err("expected bias [N]".into());
+        }
+        let bv = vals(b);
+        for (i, o) in out.iter_mut().enumerate() {
+            let v = *o + bv[i % n];
+            *o = match act {
+                Activation::Identity => v,
+                Activation::ReLU => v.max(0.0),
+                Activation::Sigmoid => sigmoid(v),
+            };
+        }
+        Ok((out, shape))
+    }
+
+    /// For every (row, column) of the im2col matrix, the input index it reads (None in
+    /// padding), with the matrix shape.
+    fn windows(
+        input: &[usize],
+        geom: &ConvGeometry,
+    ) -> Result<(Vec<Option<usize>>, Vec<usize>), String> {
+        let [n, c, h, w] = match input {
+            [n, c, h, w] => [*n, *c, *h, *w],
+            _ => return Err("expected [N, C, H, W]".into()),
+        };
+        let (oh, ow) = geom.output_size(h, w).map_err(|e| e.0)?;
+        let (kh, kw) = geom.kernel;
+        let at = |o: usize, k: usize, s: usize, d: usize, p: usize, size: usize| {
+            let i = (o * s + k * d) as isize - p as isize;
+            (i >= 0 && (i as usize) < size).then_some(i as usize)
+        };
+        let mut index = Vec::with_capacity(n * oh * ow * c * kh * kw);
+        for b in 0..n {
+            for oy in 0..oh {
+                for ox in 0..ow {
+                    for ch in 0..c {
+                        for ky in 0..kh {
+                            for kx in 0..kw {
+                                let iy =
+                                    at(oy, ky, geom.stride.0, geom.dilation.0, geom.padding.0, h);
+                                let ix =
+                                    at(ox, kx, geom.stride.1, geom.dilation.1, geom.padding.1, w);
+                                index.push(
+                                    iy.zip(ix).map(|(iy, ix)| ((b * c + ch) * h + iy) * w + ix),
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok((index, vec![n * oh * ow, c * kh * kw]))
+    }
+
+    pub fn im2col(x: &Tensor, geom: &ConvGeometry) -> Ref {
+        let (index, shape) = windows(x.shape().dims(), geom)?;
+        let xv = vals(x);
+        Ok((
+            index.iter().map(|i| i.map_or(0.0, |i| xv[i])).collect(),
+            shape,
+        ))
+    }
+
+    pub fn col2im(cols: &Tensor, input: &[usize], geom: &ConvGeometry) -> Ref {
+        let (index, shape) = windows(input, geom)?;
+        if cols.shape().dims() != shape {
+            return Err("cols shape does not match input and geometry".into());
+        }
+        let mut out = vec![0.0; input.iter().product()];
+        for (i, v) in index.iter().zip(vals(cols)) {
+            if let Some(i) = i {
+                out[*i] += v;
+            }
+        }
+        Ok((out, input.to_vec()))
+    }
+}
+
+impl<B: Backend + 'static> Backend for CheckedBackend<B> {
+    fn device(&self) -> Device {
+        self.inner.device()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn alloc(&self, len: usize) -> Vec<f32> {
+        self.inner.alloc(len)
+    }
+
+    fn release(&self, buf: Vec<f32>) {
+        self.inner.release(buf);
+    }
+
+    fn alloc_stats(&self) -> Option<AllocStats> {
+        self.inner.alloc_stats()
+    }
+
+    fn matmul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.check(
+            "matmul",
+            &[a, b],
+            self.inner.matmul(a, b),
+            reference::matmul(a, b),
+        )
+    }
+
+    fn add(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.check(
+            "add",
+            &[a, b],
+            self.inner.add(a, b),
+            reference::zip(a, b, |x, y| x + y),
+        )
+    }
+
+    fn mul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.check(
+            "mul",
+            &[a, b],
+            self.inner.mul(a, b),
+            reference::zip(a, b, |x, y| x * y),
+        )
+    }
+
+    fn sub(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.check(
+            "sub",
+            &[a, b],
+            self.inner.sub(a, b),
+            reference::zip(a, b, |x, y| x - y),
+        )
+    }
+
+    fn relu(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.check(
+            "relu",
+            &[a],
+            self.inner.relu(a),
+            reference::map(a, |x| if x > 0.0 { x } else { 0.0 }),
+        )
+    }
+
+    fn sum(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.check("sum", &[a], self.inner.sum(a), reference::sum(a))
+    }
+
+    fn sum_dim(&self, a: &Tensor, dim: usize) -> BackendResult<Tensor> {
+        self.check(
+            "sum_dim",
+            &[a],
+            self.inner.sum_dim(a, dim),
+            reference::sum_dim(a, dim),
+        )
+    }
+
+    fn from_vec(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor> {
+        self.bind(data, shape)
+    }
+
+    fn zeros(&self, shape: &Shape) -> BackendResult<Tensor> {
+        self.bind(self.inner.alloc(shape.numel()), shape.clone())
+    }
+
+    fn ones(&self, shape: &Shape) -> BackendResult<Tensor> {
+        let mut data = self.inner.alloc(shape.numel());
+        data.fill(1.0);
+        self.bind(data, shape.clone())
+    }
+
+    fn sigmoid(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.check(
+            "sigmoid",
+            &[a],
+            self.inner.sigmoid(a),
+            reference::map(a, reference::sigmoid),
+        )
+    }
+
+    fn exp(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.check("exp", &[a], self.inner.exp(a), reference::map(a, f64::exp))
+    }
+
+    fn log(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.check("log", &[a], self.inner.log(a), reference::map(a, f64::ln))
+    }
+
+    fn add_broadcast(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.check(
+            "add_broadcast",
+            &[a, b],
+            self.inner.add_broadcast(a, b),
+            reference::add_broadcast(a, b),
+        )
+    }
+
+    fn transpose(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.check(
+            "transpose",
+            &[a],
+            self.inner.transpose(a),
+            reference::transpose(a),
+        )
+    }
+
+    fn scale(&self, a: &Tensor, s: f32) -> BackendResult<Tensor> {
+        self.check(
+            "scale",
+            &[a],
+            self.inner.scale(a, s),
+            reference::map(a, |x| x * s as f64),
+        )
+    }
+
+    fn div(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.check(
+            "div",
+            &[a, b],
+            self.inner.div(a, b),
+            reference::zip(a, b, |x, y| x / y),
+        )
+    }
+
+    fn relu_backward(&self, grad_out: &Tensor, input: &Tensor) -> BackendResult<Tensor> {
+        let want = reference::zip(grad_out, input, |g, x| if x > 0.0 { g } else { 0.0 });
+        self.check(
+            "relu_backward",
+            &[grad_out, input],
+            self.inner.relu_backward(grad_out, input),
+            want,
+        )
+    }
+
+    fn sigmoid_backward(&self, grad_out: &Tensor, fwd_output: &Tensor) -> BackendResult<Tensor> {
+        let want = reference::zip(grad_out, fwd_output, |g, y| g * y * (1.0 - y));
+        let got = self.inner.sigmoid_backward(grad_out, fwd_output);
+        self.check("sigmoid_backward", &[grad_out, fwd_output], got, want)
+    }
+
+    fn softmax_last_dim(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.check(
+            "softmax",
+            &[a],
+            self.inner.softmax_last_dim(a),
+            reference::softmax(a),
+        )
+    }
+
+    fn softmax_backward(&self, grad_out: &Tensor, fwd_output: &Tensor) -> BackendResult<Tensor> {
+        let want = reference::softmax_backward(grad_out, fwd_output);
+        let got = self.inner.softmax_backward(grad_out, fwd_output);
+        self.check("softmax_backward", &[grad_out, fwd_output], got, want)
+    }
+
+    fn linear(&self, x: &Tensor, w: &Tensor, b: &Tensor, act: Activation) -> BackendResult<Tensor> {
+        let want = reference::linear(x, w, b, act);
+        self.check("linear", &[x, w, b], self.inner.linear(x, w, b, act), want)
+    }
+
+    fn im2col(&self, x: &Tensor, geom: &ConvGeometry) -> BackendResult<Tensor> {
+        let want = reference::im2col(x, geom);
+        self.check("im2col", &[x], self.inner.im2col(x, geom), want)
+    }
+
+    fn col2im(&self, cols: &Tensor, input: &Shape, geom: &ConvGeometry) -> BackendResult<Tensor> {
+        let want = reference::col2im(cols, input.dims(), geom);
+        let got = self.inner.col2im(cols, input, geom);
+        self.check("col2im", &[cols], got, want)
+    }
+}
@@ -0,0 +1,742 @@
+//! Backend conformance suite: [run_all] exercises every [Backend] method, including empty
+//! and size-1 dims, NaN/inf propagation, large values and invalid inputs, and compares the
+//! results with the f64 reference used by [super::checked::CheckedBackend]. Failures
+//! (including panics) are collected in a [ConformanceReport], so out-of-tree backends can
+//! run it from their own tests:
+//!
+//! ```no_run
+//! # use dl_core::CpuBackend;
+//! let report = dl_core::backend::conformance::run_all(&CpuBackend::new());
+//! assert!(report.passed(), "{}", report);
+//! ```
+
+use super::checked::{compare, reference, Ref, Tolerance};
+use super::{Activation, Backend, BackendResult, ConvGeometry};
+use crate::shape::Shape;
+use crate::tensor::Tensor;
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// One case whose result did not conform.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConformanceFailure {
+    /// Backend method, e.g. "sum_dim".
+    pub method: &'static str,
+    /// Short description of the inputs.
+    pub case: String,
+    pub detail: String,
+}
+
+impl fmt::Display for ConformanceFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]: {}", self.method, self.case, self.detail)
+    }
+}
+
+/// Outcome of [run_all].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConformanceReport {
+    /// Number of cases run.
+    pub cases: usize,
+    pub failures: Vec<ConformanceFailure>,
+}
+
+impl ConformanceReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} conformance cases passed",
+            self.cases - self.failures.len(),
+            self.cases
+        )?;
+        for fail in &self.failures {
+            write!(f, "\n  {}", fail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run every case against `backend` with the default [Tolerance].
+pub fn run_all(backend: &dyn Backend) -> ConformanceReport {
+    run_all_with(backend, Tolerance::default())
+}
+
+/// Run every case against `backend`, comparing values with `tol`.
+pub fn run_all_with(backend: &dyn Backend, tol: Tolerance) -> ConformanceReport {
+    let mut s = Suite {
+        backend,
+        tol,
+        report: ConformanceReport::default(),
+    };
+    constructors(&mut s);
+    matmul(&mut s);
+    elementwise(&mut s);
+    reductions(&mut s);
+    unary(&mut s);
+    shape_ops(&mut s);
+    backward(&mut s);
+    softmax(&mut s);
+    linear(&mut s);
+    conv(&mut s);
+    s.report
+}
+
+/// Candidate result and reference; the reference is Err when the inputs are invalid.
+type Outcome = Result<(BackendResult<Tensor>, Ref), String>;
+
+struct Suite<'a> {
+    backend: &'a dyn Backend,
+    tol: Tolerance,
+    report: ConformanceReport,
+}
+
+impl Suite<'_> {
+    fn case(
+        &mut self,
+        method: &'static str,
+        case: impl Into<String>,
+        f: impl FnOnce(&dyn Backend) -> Outcome,
+    ) {
+        self.report.cases += 1;
+        let b = self.backend;
+        let detail = match catch_unwind(AssertUnwindSafe(|| f(b))) {
+            Err(payload) => Some(format!("panicked: {}", panic_message(&*payload))),
+            Ok(Err(setup)) => Some(format!("could not build inputs: {}", setup)),
+            Ok(Ok((got, want))) => self.judge(got, want),
+        };
+        if let Some(detail) = detail {
+            self.report.failures.push(ConformanceFailure {
+                method,
+                case: case.into(),
+                detail,
+            });
+        }
+    }
+
+    fn judge(&self, got: BackendResult<Tensor>, want: Ref) -> Option<String> {
+        match (got, want) {
+            (Ok(t), Ok((values, shape))) => {
+                if t.device() != self.backend.device() {
+                    return Some(format!(
+                        "result on {}, backend is {}",
+                        t.device(),
+                        self.backend.device()
+                    ));
+                }
+                compare(&t, &values, &shape, self.tol)
+            }
+            (Ok(t), Err(why)) => Some(format!(
+                "accepted invalid inputs ({}), returned shape {:?}",
+                why,
+                t.shape().dims()
+            )),
+            (Err(e), Ok(_)) => Some(format!("failed: {}", e.0)),
+            (Err(_), Err(_)) => None,
+        }
+    }
+
+    fn unary(
+        &mut self,
+        method: &'static str,
+        case: &str,
+        x: (Vec<f32>, &[usize]),
+        op: impl FnOnce(&dyn Backend, &Tensor) -> BackendResult<Tensor>,
+        want: impl FnOnce(&Tensor) -> Ref,
+    ) {
+        self.case(method, case, |b| {
+            let x = input(b, x)?;
+            Ok((op(b, &x), want(&x)))
+        });
+    }
+
+    fn binary(
+        &mut self,
+        method: &'static str,
+        case: &str,
+        x: (Vec<f32>, &[usize]),
+        y: (Vec<f32>, &[usize]),
+        op: impl FnOnce(&dyn Backend, &Tensor, &Tensor) -> BackendResult<Tensor>,
+        want: impl FnOnce(&Tensor, &Tensor) -> Ref,
+    ) {
+        self.case(method, case, |b| {
+            let (x, y) = (input(b, x)?, input(b, y)?);
+            Ok((op(b, &x, &y), want(&x, &y)))
+        });
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "non-string payload".into()
+    }
+}
+
+fn input(b: &dyn Backend, (data, dims): (Vec<f32>, &[usize])) -> Result<Tensor, String> {
+    b.from_vec(data, Shape::new(dims.to_vec()))
+        .map_err(|e| e.to_string())
+}
+
+/// Deterministic values in [-1.5, 1.5] for a tensor of `dims`.
+fn ramp(dims: &[usize]) -> (Vec<f32>, &[usize]) {
+    let n: usize = dims.iter().product();
+    let data = (0..n).map(|i| ((i * 7 % 11) as f32 - 5.0) * 0.3).collect();
+    (data, dims)
+}
+
+/// Deterministic values in (0, 1), e.g. sigmoid or softmax outputs.
+fn probs(dims: &[usize]) -> (Vec<f32>, &[usize]) {
+    let n: usize = dims.iter().product();
+    let data = (0..n).map(|i| ((i * 5 % 9) as f32 + 0.5) / 10.0).collect();
+    (data, dims)
+}
+
+fn filled(dims: &[usize], v: f32) -> Ref {
+    Ok((vec![v as f64; dims.iter().product()], dims.to_vec()))
+}
+
+fn invalid(why: &str) -> Ref {
+    Err(why.into())
+}
+
+fn constructors(s: &mut Suite) {
+    for dims in [&[0][..], &[1], &[2, 3], &[0, 4], &[2, 1, 3]] {
+        let case = format!("{:?}", dims);
+        s.case("zeros", &case, |b| {
+            Ok((b.zeros(&Shape::new(dims.to_vec())), filled(dims, 0.0)))
+        });
+        s.case("ones", &case, |b| {
+            Ok((b.ones(&Shape::new(dims.to_vec())), filled(dims, 1.0)))
+        });
+        s.case("from_vec", &case, |b| {
+            let (data, _) = ramp(dims);
+            let want = data.iter().map(|&v| v as f64).collect();
+            Ok((
+                b.from_vec(data, Shape::new(dims.to_vec())),
+                Ok((want, dims.to_vec())),
+            ))
+        });
+    }
+    s.case("from_vec", "special values", |b| {
+        let data = vec![f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -0.0, f32::MAX];
+        let want = data.iter().map(|&v| v as f64).collect();
+        Ok((b.from_vec(data, Shape::new(vec![5])), Ok((want, vec![5]))))
+    });
+    s.case("from_vec", "length mismatch", |b| {
+        Ok((
+            b.from_vec(vec![1.0, 2.0, 3.0], Shape::new(vec![2, 2])),
+            invalid("3 values for [2, 2]"),
+        ))
+    });
+}
+
+fn matmul(s: &mut Suite) {
+    let mm = |b: &dyn Backend, x: &Tensor, y: &Tensor| b.matmul(x, y);
+    for (a, c) in [
+        (&[2, 3][..], &[3, 4][..]),
+        (&[1, 1], &[1, 1]),
+        (&[1, 5], &[5, 1]),
+        (&[7, 19], &[19, 33]),
+        (&[0, 3], &[3, 2]),
+        (&[2, 0], &[0, 3]),
+        (&[2, 3], &[3, 0]),
+    ] {
+        let case = format!("{:?} @ {:?}", a, c);
+        s.binary("matmul", &case, ramp(a), ramp(c), mm, reference::matmul);
+    }
+    let mut nan = ramp(&[2, 3]);
+    nan.0[4] = f32::NAN;
+    s.binary(
+        "matmul",
+        "NaN in lhs",
+        nan,
+        ramp(&[3, 2]),
+        mm,
+        reference::matmul,
+    );
+    s.binary(
+        "matmul",
+        "large values",
+        (vec![1e30, 1e30], &[1, 2]),
+        (vec![1e10, 1e10], &[2, 1]),
+        mm,
+        reference::matmul,
+    );
+    s.binary(
+        "matmul",
+        "inner mismatch",
+        ramp(&[2, 3]),
+        ramp(&[2, 3]),
+        mm,
+        reference::matmul,
+    );
+    s.binary(
+        "matmul",
+        "1D lhs",
+        ramp(&[3]),
+        ramp(&[3, 2]),
+        mm,
+        reference::matmul,
+    );
+}
+
+fn elementwise(s: &mut Suite) {
+    type Op = fn(&dyn Backend, &Tensor, &Tensor) -> BackendResult<Tensor>;
+    type Ref2 = fn(f64, f64) -> f64;
+    let ops: [(&'static str, Op, Ref2); 4] = [
+        ("add", |b, x, y| b.add(x, y), |x, y| x + y),
+        ("sub", |b, x, y| b.sub(x, y), |x, y| x - y),
+        ("mul", |b, x, y| b.mul(x, y), |x, y| x * y),
+        ("div", |b, x, y| b.div(x, y), |x, y| x / y),
+    ];
+    for (name, op, f) in ops {
+        let want = move |x: &Tensor, y: &Tensor| reference::zip(x, y, f);
+        for dims in [&[2, 3][..], &[1], &[0], &[0, 3], &[3, 1, 2]] {
+            let (x, mut y) = (ramp(dims), probs(dims));
+            // Keep divisors away from zero; div by zero is covered below.
+            y.0.iter_mut().for_each(|v| *v += 0.5);
+            s.binary(name, &format!("{:?}", dims), x, y, op, want);
+        }
+        s.binary(
+            name,
+            "NaN and inf",
+            (vec![f32::NAN, 1.0, f32::INFINITY, 0.0, -1.0], &[5]),
+            (vec![1.0, f32::NAN, 2.0, 0.0, f32::NEG_INFINITY], &[5]),
+            op,
+            want,
+        );
+        s.binary(
+            name,
+            "large values",
+            (vec![3e38, -3e38, 1e30], &[3]),
+            (vec![3e38, 3e38, 1e10], &[3]),
+            op,
+            want,
+        );
+        s.binary(
+            name,
+            "shape mismatch",
+            ramp(&[2, 3]),
+            ramp(&[3, 2]),
+            op,
+            want,
+        );
+    }
+}
+
+fn reductions(s: &mut Suite) {
+    let sum = |b: &dyn Backend, x: &Tensor| b.sum(x);
+    for dims in [&[2, 3][..], &[1], &[0], &[2, 0], &[2, 3, 4]] {
+        s.unary(
+            "sum",
+            &format!("{:?}", dims),
+            ramp(dims),
+            sum,
+            reference::sum,
+        );
+    }
+    s.unary(
+        "sum",
+        "NaN",
+        (vec![1.0, f32::NAN, 2.0], &[3]),
+        sum,
+        reference::sum,
+    );
+    s.unary(
+        "sum",
+        "overflow",
+        (vec![3e38, 3e38], &[2]),
+        sum,
+        reference::sum,
+    );
+
+    for (dims, dim) in [
+        (&[2, 3][..], 0),
+        (&[2, 3], 1),
+        (&[2, 3, 4], 0),
+        (&[2, 3, 4], 1),
+        (&[2, 3, 4], 2),
+        (&[5], 0),
+        (&[1, 1], 0),
+        (&[0, 3], 0),
+        (&[0, 3], 1),
+        (&[3, 0], 0),
+        (&[3, 0], 1),
+        (&[2, 3], 2),
+    ] {
+        s.unary(
+            "sum_dim",
+            &format!("{:?} dim {}", dims, dim),
+            ramp(dims),
+            |b, x| b.sum_dim(x, dim),
+            |x| reference::sum_dim(x, dim),
+        );
+    }
+    s.unary(
+        "sum_dim",
+        "NaN",
+        (vec![1.0, f32::NAN, 2.0, 3.0], &[2, 2]),
+        |b, x| b.sum_dim(x, 0),
+        |x| reference::sum_dim(x, 0),
+    );
+}
+
+fn unary(s: &mut Suite) {
+    type Op = fn(&dyn Backend, &Tensor) -> BackendResult<Tensor>;
+    type Ref1 = fn(f64) -> f64;
+    let ops: [(&'static str, Op, Ref1); 4] = [
+        ("relu", |b, x| b.relu(x), |x| if x > 0.0 { x } else { 0.0 }),
+        ("sigmoid", |b, x| b.sigmoid(x), reference::sigmoid),
+        ("exp", |b, x| b.exp(x), f64::exp),
+        ("scale", |b, x| b.scale(x, -2.5), |x| x * -2.5),
+    ];
+    for (name, op, f) in ops {
+        let want = move |x: &Tensor| reference::map(x, f);
+        for dims in [&[2, 3][..], &[1], &[0], &[1, 0, 2]] {
+            s.unary(name, &format!("{:?}", dims), ramp(dims), op, want);
+        }
+        s.unary(
+            name,
+            "special values",
+            (
+                vec![
+                    f32::NAN,
+                    f32::INFINITY,
+                    f32::NEG_INFINITY,
+                    -0.0,
+                    100.0,
+                    -100.0,
+                ],
+                &[6],
+            ),
+            op,
+            want,
+        );
+    }
+    let log = |b: &dyn Backend, x: &Tensor| b.log(x);
+    let ln = |x: &Tensor| reference::map(x, f64::ln);
+    s.unary("log", "[2, 3]", probs(&[2, 3]), log, ln);
+    s.unary("log", "[0]", probs(&[0]), log, ln);
+    s.unary(
+        "log",
+        "zero, negative, NaN, inf",
+        (vec![0.0, -1.0, f32::NAN, f32::INFINITY, 1e-30], &[5]),
+        log,
+        ln,
+    );
+    s.unary(
+        "scale",
+        "inf times zero",
+        (vec![f32::INFINITY, 3e38], &[2]),
+        |b, x| b.scale(x, 0.0),
+        |x| reference::map(x, |v| v * 0.0),
+    );
+    s.unary(
+        "scale",
+        "overflow",
+        (vec![3e38, -3e38], &[2]),
+        |b, x| b.scale(x, 2.0),
+        |x| reference::map(x, |v| v * 2.0),
+    );
+}
+
+fn shape_ops(s: &mut Suite) {
+    let ab = |b: &dyn Backend, x: &Tensor, y: &Tensor| b.add_broadcast(x, y);
+    for (a, c) in [
+        (&[2, 3][..], &[3][..]),
+        (&[1, 1], &[1]),
+        (&[0, 3], &[3]),
+        (&[2, 0], &[0]),
+    ] {
+        let case = format!("{:?} + {:?}", a, c);
+        s.binary(
+            "add_broadcast",
+            &case,
+            ramp(a),
+            ramp(c),
+            ab,
+            reference::add_broadcast,
+        );
+    }
+    s.binary(
+        "add_broadcast",
+        "NaN bias",
+        ramp(&[2, 2]),
+        (vec![f32::NAN, 1.0], &[2]),
+        ab,
+        reference::add_broadcast,
+    );
+    s.binary(
+        "add_broadcast",
+        "bias mismatch",
+        ramp(&[2, 3]),
+        ramp(&[2]),
+        ab,
+        reference::add_broadcast,
+    );
+
+    let tr = |b: &dyn Backend, x: &Tensor| b.transpose(x);
+    for dims in [&[2, 3][..], &[1, 4], &[4, 1], &[1, 1], &[0, 3], &[17, 33]] {
+        s.unary(
+            "transpose",
+            &format!("{:?}", dims),
+            ramp(dims),
+            tr,
+            reference::transpose,
+        );
+    }
+    s.unary(
+        "transpose",
+        "3D input",
+        ramp(&[2, 2, 2]),
+        tr,
+        reference::transpose,
+    );
+}
+
+fn backward(s: &mut Suite) {
+    let relu_bw = |b: &dyn Backend, g: &Tensor, x: &Tensor| b.relu_backward(g, x);
+    let relu_ref =
+        |g: &Tensor, x: &Tensor| reference::zip(g, x, |g, x| if x > 0.0 { g } else { 0.0 });
+    let sig_bw = |b: &dyn Backend, g: &Tensor, y: &Tensor| b.sigmoid_backward(g, y);
+    let sig_ref = |g: &Tensor, y: &Tensor| reference::zip(g, y, |g, y| g * y * (1.0 - y));
+    for dims in [&[2, 3][..], &[1], &[0], &[3, 0]] {
+        let case = format!("{:?}", dims);
+        s.binary(
+            "relu_backward",
+            &case,
+            probs(dims),
+            ramp(dims),
+            relu_bw,
+            relu_ref,
+        );
+        s.binary(
+            "sigmoid_backward",
+            &case,
+            ramp(dims),
+            probs(dims),
+            sig_bw,
+            sig_ref,
+        );
+    }
+    s.binary(
+        "relu_backward",
+        "NaN grad",
+        (vec![f32::NAN, f32::NAN], &[2]),
+        (vec![1.0, -1.0], &[2]),
+        relu_bw,
+        relu_ref,
+    );
+    s.binary(
+        "sigmoid_backward",
+        "NaN grad",
+        (vec![f32::NAN, 1.0], &[2]),
+        (vec![0.5, 0.5], &[2]),
+        sig_bw,
+        sig_ref,
+    );
+    s.binary(
+        "relu_backward",
+        "shape mismatch",
+        ramp(&[2, 3]),
+        ramp(&[3, 2]),
+        relu_bw,
+        relu_ref,
+    );
+    s.binary(
+        "sigmoid_backward",
+        "shape mismatch",
+        ramp(&[2, 3]),
+        ramp(&[6]),
+        sig_bw,
+        sig_ref,
+    );
+}
+
+fn softmax(s: &mut Suite) {
+    let sm = |b: &dyn Backend, x: &Tensor| b.softmax_last_dim(x);
+    for dims in [
+        &[2, 3][..],
+        &[1, 1],
+        &[3],
+        &[2, 2, 3],
+        &[0, 3],
+        &[2, 0],
+        &[4, 37],
+    ] {
+        s.unary(
+            "softmax_last_dim",
+            &format!("{:?}", dims),
+            ramp(dims),
+            sm,
+            reference::softmax,
+        );
+    }
+    s.unary(
+        "softmax_last_dim",
+        "large logits",
+        (vec![1000.0, 1000.0, -1000.0, -1e30, 0.0, 1e30], &[2, 3]),
+        sm,
+        reference::softmax,
+    );
+    s.unary(
+        "softmax_last_dim",
+        "NaN row",
+        (vec![1.0, f32::NAN, 2.0, 1.0, 2.0, 3.0], &[2, 3]),
+        sm,
+        reference::softmax,
+    );
+    s.unary(
+        "softmax_last_dim",
+        "scalar",
+        (vec![1.0], &[]),
+        sm,
+        reference::softmax,
+    );
+
+    let sm_bw = |b: &dyn Backend, g: &Tensor, y: &Tensor| b.softmax_backward(g, y);
+    for dims in [&[2, 3][..], &[1, 1], &[3], &[2, 2, 3], &[0, 3], &[2, 0]] {
+        let case = format!("{:?}", dims);
+        s.binary(
+            "softmax_backward",
+            &case,
+            ramp(dims),
+            probs(dims),
+            sm_bw,
+            reference::softmax_backward,
+        );
+    }
+    s.binary(
+        "softmax_backward",
+        "shape mismatch",
+        ramp(&[2, 3]),
+        probs(&[3, 2]),
+        sm_bw,
+        reference::softmax_backward,
+    );
+    s.binary(
+        "softmax_backward",
+        "scalar",
+        (vec![1.0], &[]),
+        (vec![1.0], &[]),
+        sm_bw,
+        reference::softmax_backward,
+    );
+}
+
+fn linear(s: &mut Suite) {
+    for act in [Activation::Identity, Activation::ReLU, Activation::Sigmoid] {
+        for (m, k, n) in [(3, 4, 5), (1, 1, 1), (0, 4, 5), (3, 0, 2), (13, 29, 21)] {
+            s.case(
+                "linear",
+                format!("{:?} [{}, {}] @ [{}, {}]", act, m, k, k, n),
+                |b| {
+                    let x = input(b, ramp(&[m, k]))?;
+                    let w = input(b, probs(&[k, n]))?;
+                    let bias = input(b, ramp(&[n]))?;
+                    Ok((
+                        b.linear(&x, &w, &bias, act),
+                        reference::linear(&x, &w, &bias, act),
+                    ))
+                },
+            );
+        }
+    }
+    s.case("linear", "bias mismatch", |b| {
+        let x = input(b, ramp(&[3, 4]))?;
+        let w = input(b, probs(&[4, 5]))?;
+        let bias = input(b, ramp(&[4]))?;
+        let act = Activation::Identity;
+        Ok((
+            b.linear(&x, &w, &bias, act),
+            reference::linear(&x, &w, &bias, act),
+        ))
+    });
+    s.case("linear", "weight mismatch", |b| {
+        let x = input(b, ramp(&[3, 4]))?;
+        let w = input(b, probs(&[3, 5]))?;
+        let bias = input(b, ramp(&[5]))?;
+        let act = Activation::ReLU;
+        Ok((
+            b.linear(&x, &w, &bias, act),
+            reference::linear(&x, &w, &bias, act),
+        ))
+    });
+}
+
+fn conv(s: &mut Suite) {
+    let k3 = ConvGeometry::new((3, 3));
+    let cases: [(&str, &[usize], ConvGeometry); 7] = [
+        ("3x3", &[2, 3, 5, 6], k3),
+        (
+            "3x3 stride 2 padding 1",
+            &[2, 3, 5, 6],
+            k3.with_stride((2, 2)).with_padding((1, 1)),
+        ),
+        ("3x3 dilation 2", &[1, 2, 6, 7], k3.with_dilation((2, 2))),
+        (
+            "2x3 mixed",
+            &[1, 2, 4, 7],
+            ConvGeometry::new((2, 3))
+                .with_stride((1, 2))
+                .with_padding((1, 0)),
+        ),
+        ("1d", &[2, 3, 1, 9], ConvGeometry::conv1d(3, 2, 1, 1)),
+        ("1x1", &[2, 4, 3, 3], ConvGeometry::new((1, 1))),
+        ("empty batch", &[0, 3, 5, 5], k3),
+    ];
+    for (case, dims, geom) in cases {
+        s.unary(
+            "im2col",
+            case,
+            ramp(dims),
+            |b, x| b.im2col(x, &geom),
+            |x| reference::im2col(x, &geom),
+        );
+        s.case("col2im", case, |b| {
+            let (oh, ow) = geom.output_size(dims[2], dims[3]).map_err(|e| e.0)?;
+            let rows = dims[0] * oh * ow;
+            let cols = input(b, ramp(&[rows, dims[1] * geom.kernel.0 * geom.kernel.1]))?;
+            let shape = Shape::new(dims.to_vec());
+            Ok((
+                b.col2im(&cols, &shape, &geom),
+                reference::col2im(&cols, dims, &geom),
+            ))
+        });
+    }
+    s.unary(
+        "im2col",
+        "window larger than input",
+        ramp(&[1, 1, 2, 2]),
+        |b, x| b.im2col(x, &k3),
+        |_| invalid("window does not fit"),
+    );
+    s.unary(
+        "im2col",
+        "zero stride",
+        ramp(&[1, 1, 4, 4]),
+        |b, x| b.im2col(x, &k3.with_stride((0, 1))),
+        |_| invalid("zero stride"),
+    );
+    s.unary(
+        "im2col",
+        "3D input",
+        ramp(&[1, 4, 4]),
+        |b, x| b.im2col(x, &k3),
+        |_| invalid("not 4D"),
+    );
+    s.case("col2im", "cols shape mismatch", |b| {
+        let cols = input(b, ramp(&[9, 8]))?;
+        let shape = Shape::new(vec![1, 1, 5, 5]);
+        Ok((b.col2im(&cols, &shape, &k3), invalid("cols shape")))
+    });
+}
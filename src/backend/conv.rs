@@ -0,0 +1,155 @@
+//! Convolution window geometry and the host im2col/col2im kernels behind the default
+//! [super::Backend::im2col] and [super::Backend::col2im].
+
+use super::{BackendError, BackendResult};
+
+/// Sliding-window geometry of a 2D convolution over [N, C, H, W], as (height, width) pairs.
+/// 1D convolutions use height 1 (see [Self::conv1d]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ConvGeometry {
+    pub kernel: (usize, usize),
+    pub stride: (usize, usize),
+    /// Zeros added on each side.
+    pub padding: (usize, usize),
+    /// Spacing between kernel taps.
+    pub dilation: (usize, usize),
+}
+
+impl ConvGeometry {
+    /// Stride 1, no padding, no dilation.
+    pub fn new(kernel: (usize, usize)) -> Self {
+        ConvGeometry {
+            kernel,
+            stride: (1, 1),
+            padding: (0, 0),
+            dilation: (1, 1),
+        }
+    }
+
+    /// Geometry of a 1D convolution: a (1, kernel) window over height 1.
+    pub fn conv1d(kernel: usize, stride: usize, padding: usize, dilation: usize) -> Self {
+        ConvGeometry {
+            kernel: (1, kernel),
+            stride: (1, stride),
+            padding: (0, padding),
+            dilation: (1, dilation),
+        }
+    }
+
+    pub fn with_stride(mut self, stride: (usize, usize)) -> Self {
+        self.stride = stride;
+        self
+    }
+
+    pub fn with_padding(mut self, padding: (usize, usize)) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_dilation(mut self, dilation: (usize, usize)) -> Self {
+        self.dilation = dilation;
+        self
+    }
+
+    /// True if this is a [Self::conv1d] geometry (height is left untouched).
+    pub fn is_1d(&self) -> bool {
+        self.kernel.0 == 1 && self.stride.0 == 1 && self.padding.0 == 0 && self.dilation.0 == 1
+    }
+
+    /// Output (OH, OW) for an input of (h, w). Errors on zero kernel, stride or dilation and
+    /// when the dilated window is larger than the padded input.
+    pub fn output_size(&self, h: usize, w: usize) -> BackendResult<(usize, usize)> {
+        let axis = |size: usize, k: usize, s: usize, p: usize, d: usize| {
+            if k == 0 || s == 0 || d == 0 {
+                return Err(BackendError(format!(
+                    "conv: kernel, stride and dilation must be positive, got {:?}",
+                    self
+                )));
+            }
+            let span = d * (k - 1) + 1;
+            let padded = size + 2 * p;
+            if padded < span {
+                return Err(BackendError(format!(
+                    "conv: window of {} does not fit padded input of {}",
+                    span, padded
+                )));
+            }
+            Ok((padded - span) / s + 1)
+        };
+        let (kh, kw) = self.kernel;
+        Ok((
+            axis(h, kh, self.stride.0, self.padding.0, self.dilation.0)?,
+            axis(w, kw, self.stride.1, self.padding.1, self.dilation.1)?,
+        ))
+    }
+}
+
+/// [N, C, H, W] of a 4D shape.
+pub(crate) fn dims4(method: &str, dims: &[usize]) -> BackendResult<[usize; 4]> {
+    match dims {
+        [n, c, h, w] => Ok([*n, *c, *h, *w]),
+        d => Err(BackendError(format!(
+            "{}: expected [N, C, H, W], got {:?}",
+            method, d
+        ))),
+    }
+}
+
+/// Calls `f(cols_index, input_index)` for every in-bounds tap; padding taps are skipped.
+fn for_each_tap(
+    [n, c, h, w]: [usize; 4],
+    geom: &ConvGeometry,
+    (oh, ow): (usize, usize),
+    mut f: impl FnMut(usize, usize),
+) {
+    let (kh, kw) = geom.kernel;
+    let cols = c * kh * kw;
+    for b in 0..n {
+        for oy in 0..oh {
+            for ox in 0..ow {
+                let row = (b * oh + oy) * ow + ox;
+                for ch in 0..c {
+                    for ky in 0..kh {
+                        let iy = (oy * geom.stride.0 + ky * geom.dilation.0)
+                            .wrapping_sub(geom.padding.0);
+                        if iy >= h {
+                            continue;
+                        }
+                        for kx in 0..kw {
+                            let ix = (ox * geom.stride.1 + kx * geom.dilation.1)
+                                .wrapping_sub(geom.padding.1);
+                            if ix < w {
+                                let col = (ch * kh + ky) * kw + kx;
+                                f(row * cols + col, ((b * c + ch) * h + iy) * w + ix);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Gather `x` [N, C, H, W] into `out` [N*OH*OW, C*KH*KW]; `out` must be zeroed.
+pub fn im2col_into(
+    x: &[f32],
+    dims: [usize; 4],
+    geom: &ConvGeometry,
+    out: &mut [f32],
+) -> BackendResult<()> {
+    let (oh, ow) = geom.output_size(dims[2], dims[3])?;
+    for_each_tap(dims, geom, (oh, ow), |o, i| out[o] = x[i]);
+    Ok(())
+}
+
+/// Scatter-add `cols` [N*OH*OW, C*KH*KW] into `out` [N, C, H, W]; `out` must be zeroed.
+pub fn col2im_into(
+    cols: &[f32],
+    dims: [usize; 4],
+    geom: &ConvGeometry,
+    out: &mut [f32],
+) -> BackendResult<()> {
+    let (oh, ow) = geom.output_size(dims[2], dims[3])?;
+    for_each_tap(dims, geom, (oh, ow), |o, i| out[i] += cols[o]);
+    Ok(())
+}
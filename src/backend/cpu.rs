@@ -1,17 +1,31 @@
-//! CPU (scalar) backend: reference implementation. Deterministic, single-threaded.
+//! CPU backend: reference implementation. Deterministic, single-threaded; matmul uses the
+//! packed SIMD kernel from [super::gemm].
 
-use crate::backend::{Backend, BackendError, BackendResult};
+use crate::backend::alloc::{AllocStats, Allocator, SystemAllocator};
+use crate::backend::{gemm, Activation, Backend, BackendError, BackendResult, Capabilities};
 use crate::shape::Shape;
 use crate::tensor::Tensor;
 use std::sync::Arc;
 
-/// CPU backend: plain loops, deterministic order.
+/// CPU backend: plain loops (blocked GEMM for matmul), deterministic order.
+/// Clones share the allocator.
 #[derive(Clone)]
-pub struct CpuBackend;
+pub struct CpuBackend {
+    allocator: Arc<dyn Allocator>,
+}
 
 impl CpuBackend {
     pub fn new() -> Self {
-        CpuBackend
+        Self::with_allocator(Arc::new(SystemAllocator::new()))
+    }
+
+    /// CPU backend drawing tensor buffers from `allocator` (e.g. a [super::alloc::PoolAllocator]).
+    pub fn with_allocator(allocator: Arc<dyn Allocator>) -> Self {
+        CpuBackend { allocator }
+    }
+
+    pub fn allocator(&self) -> &Arc<dyn Allocator> {
+        &self.allocator
     }
 }
 
@@ -36,19 +50,9 @@ impl Backend for CpuBackend {
                 k1, k2
             )));
         }
-        let adata = a.data();
-        let bdata = b.data();
-        let mut out = vec![0.0f32; m * n];
-        for i in 0..m {
-            for j in 0..n {
-                let mut s = 0.0;
-                for k in 0..k1 {
-                    s += adata[i * k1 + k] * bdata[k * n + j];
-                }
-                out[i * n + j] = s;
-            }
-        }
-        Tensor::from_vec(out, Shape::new(vec![m, n]), Arc::new(CpuBackend::new()))
+        let mut out = self.allocator.alloc(m * n);
+        gemm::sgemm(m, k1, n, a.data(), b.data(), &mut out);
+        Tensor::from_vec(out, Shape::new(vec![m, n]), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
@@ -57,13 +61,13 @@ impl Backend for CpuBackend {
             return Err(BackendError("add: shape mismatch".into()));
         }
         let n = a.numel();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         let ad = a.data();
         let bd = b.data();
         for i in 0..n {
             out[i] = ad[i] + bd[i];
         }
-        Tensor::from_vec(out, a.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, a.shape().clone(), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
@@ -72,13 +76,13 @@ impl Backend for CpuBackend {
             return Err(BackendError("mul: shape mismatch".into()));
         }
         let n = a.numel();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         let ad = a.data();
         let bd = b.data();
         for i in 0..n {
             out[i] = ad[i] * bd[i];
         }
-        Tensor::from_vec(out, a.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, a.shape().clone(), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
@@ -87,31 +91,32 @@ impl Backend for CpuBackend {
             return Err(BackendError("sub: shape mismatch".into()));
         }
         let n = a.numel();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         let ad = a.data();
         let bd = b.data();
         for i in 0..n {
             out[i] = ad[i] - bd[i];
         }
-        Tensor::from_vec(out, a.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, a.shape().clone(), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
     fn relu(&self, a: &Tensor) -> BackendResult<Tensor> {
         let n = a.numel();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         let ad = a.data();
         for i in 0..n {
             out[i] = if ad[i] > 0.0 { ad[i] } else { 0.0 };
         }
-        Tensor::from_vec(out, a.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, a.shape().clone(), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
     fn sum(&self, a: &Tensor) -> BackendResult<Tensor> {
         let s: f32 = a.data().iter().sum();
-        let out = vec![s];
-        Tensor::from_vec(out, Shape::new(vec![1]), Arc::new(CpuBackend::new()))
+        let mut out = self.allocator.alloc(1);
+        out[0] = s;
+        Tensor::from_vec(out, Shape::new(vec![1]), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
@@ -124,87 +129,71 @@ impl Backend for CpuBackend {
         let mut out_dims = dims.to_vec();
         out_dims[dim] = 1;
         let out_numel: usize = out_dims.iter().product();
-        let mut out = vec![0.0f32; out_numel];
+        let mut out = self.allocator.alloc(out_numel);
         let ad = a.data();
-        // Strides for input: row-major.
-        let in_strides: Vec<usize> = dims
-            .iter()
-            .rev()
-            .scan(1, |s, &d| {
-                let r = *s;
-                *s *= d;
-                Some(r)
-            })
-            .collect::<Vec<_>>()
-            .into_iter()
-            .rev()
-            .collect();
-        for out_linear in 0..out_numel {
+        // Row-major [outer, reduced, inner]; summed in order of the reduced index.
+        let inner: usize = dims[dim + 1..].iter().product();
+        for (j, o) in out.iter_mut().enumerate() {
+            let (outer, i) = (j / inner, j % inner);
             let mut sum = 0.0;
-            for k in 0..reduced_len {
-                let mut in_linear = 0;
-                let mut rem = out_linear;
-                for (d, &out_sz) in out_dims.iter().enumerate() {
-                    let coord = if d == dim { k } else { rem % out_sz };
-                    in_linear += coord * in_strides[d];
-                    if d != dim {
-                        rem /= out_sz;
-                    }
-                }
-                sum += ad[in_linear];
+            for r in 0..reduced_len {
+                sum += ad[(outer * reduced_len + r) * inner + i];
             }
-            out[out_linear] = sum;
+            *o = sum;
         }
-        Tensor::from_vec(out, Shape::new(out_dims), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, Shape::new(out_dims), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
     fn from_vec(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor> {
-        Tensor::from_vec(data, shape, Arc::new(CpuBackend::new())).map_err(|e| BackendError(e.to_string()))
+        Tensor::from_vec(data, shape, Arc::new(self.clone()))
+            .map_err(|e| BackendError(e.to_string()))
     }
 
     fn zeros(&self, shape: &Shape) -> BackendResult<Tensor> {
         let n = shape.numel();
-        Tensor::from_vec(vec![0.0; n], shape.clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(self.allocator.alloc(n), shape.clone(), Arc::new(self.clone()))
             .map_err(|e| BackendError(e.to_string()))
     }
 
     fn ones(&self, shape: &Shape) -> BackendResult<Tensor> {
         let n = shape.numel();
-        Tensor::from_vec(vec![1.0; n], shape.clone(), Arc::new(CpuBackend::new()))
+        let mut out = self.allocator.alloc(n);
+        out.fill(1.0);
+        Tensor::from_vec(out, shape.clone(), Arc::new(self.clone()))
             .map_err(|e| BackendError(e.to_string()))
     }
 
     fn sigmoid(&self, a: &Tensor) -> BackendResult<Tensor> {
         let n = a.numel();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         let ad = a.data();
         for i in 0..n {
             out[i] = 1.0 / (1.0 + (-ad[i]).exp());
         }
-        Tensor::from_vec(out, a.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, a.shape().clone(), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
     fn exp(&self, a: &Tensor) -> BackendResult<Tensor> {
         let n = a.numel();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         let ad = a.data();
         for i in 0..n {
             out[i] = ad[i].exp();
         }
-        Tensor::from_vec(out, a.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, a.shape().clone(), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
     fn log(&self, a: &Tensor) -> BackendResult<Tensor> {
         let n = a.numel();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         let ad = a.data();
         for i in 0..n {
             out[i] = ad[i].ln();
         }
-        Tensor::from_vec(out, a.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, a.shape().clone(), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
@@ -222,13 +211,13 @@ impl Backend for CpuBackend {
         }
         let adata = a.data();
         let bdata = b.data();
-        let mut out = vec![0.0f32; n * k];
+        let mut out = self.allocator.alloc(n * k);
         for i in 0..n {
             for j in 0..k {
                 out[i * k + j] = adata[i * k + j] + bdata[j];
             }
         }
-        Tensor::from_vec(out, Shape::new(vec![n, k]), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, Shape::new(vec![n, k]), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
@@ -239,24 +228,24 @@ impl Backend for CpuBackend {
         }
         let (m, n) = (d[0], d[1]);
         let ad = a.data();
-        let mut out = vec![0.0f32; m * n];
+        let mut out = self.allocator.alloc(m * n);
         for i in 0..m {
             for j in 0..n {
                 out[j * m + i] = ad[i * n + j];
             }
         }
-        Tensor::from_vec(out, Shape::new(vec![n, m]), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, Shape::new(vec![n, m]), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
     fn scale(&self, a: &Tensor, s: f32) -> BackendResult<Tensor> {
         let n = a.numel();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         let ad = a.data();
         for i in 0..n {
             out[i] = ad[i] * s;
         }
-        Tensor::from_vec(out, a.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, a.shape().clone(), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
@@ -265,13 +254,13 @@ impl Backend for CpuBackend {
             return Err(BackendError("div: shape mismatch".into()));
         }
         let n = a.numel();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         let ad = a.data();
         let bd = b.data();
         for i in 0..n {
             out[i] = ad[i] / bd[i];
         }
-        Tensor::from_vec(out, a.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, a.shape().clone(), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
@@ -280,13 +269,13 @@ impl Backend for CpuBackend {
             return Err(BackendError("relu_backward: shape mismatch".into()));
         }
         let n = input.numel();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         let gd = grad_out.data();
         let id = input.data();
         for i in 0..n {
             out[i] = if id[i] > 0.0 { gd[i] } else { 0.0 };
         }
-        Tensor::from_vec(out, input.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, input.shape().clone(), input.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
@@ -295,13 +284,13 @@ impl Backend for CpuBackend {
             return Err(BackendError("sigmoid_backward: shape mismatch".into()));
         }
         let n = fwd_output.numel();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         let gd = grad_out.data();
         let fd = fwd_output.data();
         for i in 0..n {
             out[i] = gd[i] * fd[i] * (1.0 - fd[i]);
         }
-        Tensor::from_vec(out, fwd_output.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, fwd_output.shape().clone(), fwd_output.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
@@ -313,9 +302,9 @@ impl Backend for CpuBackend {
         let last_dim = dims[dims.len() - 1];
         let n = a.numel();
         let ad = a.data();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         let row_size = last_dim;
-        let num_rows = n / row_size;
+        let num_rows = n.checked_div(row_size).unwrap_or(0);
         for row in 0..num_rows {
             let base = row * row_size;
             let row_max = ad[base..base + row_size]
@@ -331,7 +320,7 @@ impl Backend for CpuBackend {
                 out[base + j] /= sum;
             }
         }
-        Tensor::from_vec(out, a.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, a.shape().clone(), a.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
 
@@ -340,12 +329,15 @@ impl Backend for CpuBackend {
             return Err(BackendError("softmax_backward: shape mismatch".into()));
         }
         let dims = fwd_output.shape().dims();
+        if dims.is_empty() {
+            return Err(BackendError("softmax_backward: need at least 1 dim".into()));
+        }
         let last_dim = dims[dims.len() - 1];
         let n = fwd_output.numel();
-        let num_rows = n / last_dim;
+        let num_rows = n.checked_div(last_dim).unwrap_or(0);
         let gd = grad_out.data();
         let yd = fwd_output.data();
-        let mut out = vec![0.0f32; n];
+        let mut out = self.allocator.alloc(n);
         for row in 0..num_rows {
             let base = row * last_dim;
             let mut sum_gy = 0.0;
@@ -356,7 +348,65 @@ impl Backend for CpuBackend {
                 out[base + j] = yd[base + j] * (gd[base + j] - sum_gy);
             }
         }
-        Tensor::from_vec(out, fwd_output.shape().clone(), Arc::new(CpuBackend::new()))
+        Tensor::from_vec(out, fwd_output.shape().clone(), fwd_output.backend())
+            .map_err(|e| BackendError(e.to_string()))
+    }
+
+    fn linear(&self, x: &Tensor, w: &Tensor, b: &Tensor, act: Activation) -> BackendResult<Tensor> {
+        let xd = x.shape().dims();
+        let wd = w.shape().dims();
+        let bd = b.shape().dims();
+        if xd.len() != 2 || wd.len() != 2 || bd.len() != 1 {
+            return Err(BackendError("linear: expect x [M,K], w [K,N], b [N]".into()));
+        }
+        let (m, k) = (xd[0], xd[1]);
+        let n = wd[1];
+        if wd[0] != k || bd[0] != n {
+            return Err(BackendError(format!(
+                "linear dim mismatch: x {:?}, w {:?}, b {:?}",
+                xd, wd, bd
+            )));
+        }
+        let bdata = b.data();
+        let mut out = self.allocator.alloc(m * n);
+        gemm::sgemm(m, k, n, x.data(), w.data(), &mut out);
+        for row in out.chunks_mut(n.max(1)) {
+            for (o, &bj) in row.iter_mut().zip(bdata) {
+                let v = *o + bj;
+                *o = match act {
+                    Activation::Identity => v,
+                    Activation::ReLU => {
+                        if v > 0.0 {
+                            v
+                        } else {
+                            0.0
+                        }
+                    }
+                    Activation::Sigmoid => 1.0 / (1.0 + (-v).exp()),
+                };
+            }
+        }
+        Tensor::from_vec(out, Shape::new(vec![m, n]), x.backend())
             .map_err(|e| BackendError(e.to_string()))
     }
+
+    fn alloc(&self, len: usize) -> Vec<f32> {
+        self.allocator.alloc(len)
+    }
+
+    fn release(&self, buf: Vec<f32>) {
+        self.allocator.release(buf);
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            fused_linear: true,
+            fused_sigmoid: true,
+            fused_softmax_backward: true,
+        }
+    }
+
+    fn alloc_stats(&self) -> Option<AllocStats> {
+        Some(self.allocator.stats())
+    }
 }
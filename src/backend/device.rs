@@ -0,0 +1,63 @@
+//! Device identity: which memory space a backend's tensors live in. Backends that share a
+//! memory space (e.g. CpuBackend and ParallelCpuBackend) report the same device, so their
+//! tensors can be mixed; anything else must be moved with [crate::Tensor::to] first.
+
+use std::fmt;
+
+/// Kind of device. Accelerators are identified by a short name (e.g. "cuda", "metal").
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceKind {
+    Cpu,
+    Accelerator(&'static str),
+}
+
+/// Device descriptor: kind plus ordinal (for machines with several devices of one kind).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Device {
+    kind: DeviceKind,
+    index: usize,
+}
+
+impl Device {
+    /// Host memory.
+    pub const CPU: Device = Device {
+        kind: DeviceKind::Cpu,
+        index: 0,
+    };
+
+    pub fn new(kind: DeviceKind, index: usize) -> Self {
+        Device { kind, index }
+    }
+
+    /// Accelerator `name` with ordinal `index`, e.g. `Device::accelerator("cuda", 1)`.
+    pub fn accelerator(name: &'static str, index: usize) -> Self {
+        Device::new(DeviceKind::Accelerator(name), index)
+    }
+
+    pub fn kind(&self) -> DeviceKind {
+        self.kind
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn is_cpu(&self) -> bool {
+        self.kind == DeviceKind::Cpu
+    }
+}
+
+impl Default for Device {
+    fn default() -> Self {
+        Device::CPU
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            DeviceKind::Cpu => write!(f, "cpu"),
+            DeviceKind::Accelerator(name) => write!(f, "{}:{}", name, self.index),
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! Packed, cache-blocked single-precision GEMM used by the CPU backends.
+//! B is packed into KC x NR column panels and A into MR x KC row strips so the micro-kernel
+//! streams both contiguously; the micro-kernel is picked at runtime (AVX-512, AVX2+FMA, or
+//! a portable loop the compiler auto-vectorises, e.g. to NEON on aarch64).
+//! For a given kernel, each output element is accumulated in the same k order regardless of
+//! which rows are computed together, so splitting rows across threads gives identical results.
+
+use std::sync::OnceLock;
+
+/// Rows of A per micro-tile.
+const MR: usize = 6;
+/// Columns of B per micro-tile (one AVX-512 or two AVX2 registers).
+const NR: usize = 16;
+/// Depth of a packed block (A strip + B panel stay in L1/L2).
+const KC: usize = 256;
+/// Rows of A packed at once (multiple of MR).
+const MC: usize = 96;
+/// Columns of B packed at once (multiple of NR).
+const NC: usize = 2048;
+
+/// Micro-kernel implementation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GemmKernel {
+    Portable,
+    Avx2,
+    Avx512,
+}
+
+impl GemmKernel {
+    /// Fastest kernel supported by this CPU (detected once).
+    pub fn detect() -> Self {
+        static KERNEL: OnceLock<GemmKernel> = OnceLock::new();
+        *KERNEL.get_or_init(|| {
+            [GemmKernel::Avx512, GemmKernel::Avx2]
+                .into_iter()
+                .find(|k| k.is_supported())
+                .unwrap_or(GemmKernel::Portable)
+        })
+    }
+
+    /// Whether this kernel can run on the current CPU.
+    pub fn is_supported(self) -> bool {
+        match self {
+            GemmKernel::Portable => true,
+            #[cfg(target_arch = "x86_64")]
+            GemmKernel::Avx2 => is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma"),
+            #[cfg(target_arch = "x86_64")]
+            GemmKernel::Avx512 => is_x86_feature_detected!("avx512f"),
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => false,
+        }
+    }
+
+    /// All kernels supported by the current CPU, slowest first.
+    pub fn available() -> Vec<GemmKernel> {
+        [GemmKernel::Portable, GemmKernel::Avx2, GemmKernel::Avx512]
+            .into_iter()
+            .filter(|k| k.is_supported())
+            .collect()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GemmKernel::Portable => "portable",
+            GemmKernel::Avx2 => "avx2",
+            GemmKernel::Avx512 => "avx512",
+        }
+    }
+}
+
+/// c = a @ b for row-major a [m, k], b [k, n], c [m, n], using the detected kernel.
+pub fn sgemm(m: usize, k: usize, n: usize, a: &[f32], b: &[f32], c: &mut [f32]) {
+    sgemm_with(GemmKernel::detect(), m, k, n, a, b, c);
+}
+
+/// [sgemm] with an explicit kernel. Unsupported kernels fall back to [GemmKernel::Portable].
+pub fn sgemm_with(
+    kernel: GemmKernel,
+    m: usize,
+    k: usize,
+    n: usize,
+    a: &[f32],
+    b: &[f32],
+    c: &mut [f32],
+) {
+    assert!(
+        a.len() >= m * k && b.len() >= k * n && c.len() >= m * n,
+        "sgemm: buffer too small"
+    );
+    let kernel = if kernel.is_supported() {
+        kernel
+    } else {
+        GemmKernel::Portable
+    };
+    c[..m * n].fill(0.0);
+    if m == 0 || n == 0 || k == 0 {
+        return;
+    }
+    let mut bpack = vec![0.0f32; KC.min(k) * NC.min(n).div_ceil(NR) * NR];
+    let mut apack = vec![0.0f32; KC.min(k) * MC.min(m).div_ceil(MR) * MR];
+    let mut tile = [0.0f32; MR * NR];
+    for jc in (0..n).step_by(NC) {
+        let nc = NC.min(n - jc);
+        for pc in (0..k).step_by(KC) {
+            let kc = KC.min(k - pc);
+            pack_b(b, n, pc, jc, kc, nc, &mut bpack);
+            for ic in (0..m).step_by(MC) {
+                let mc = MC.min(m - ic);
+                pack_a(a, k, ic, pc, mc, kc, &mut apack);
+                for jr in (0..nc).step_by(NR) {
+                    let bp = &bpack[jr * kc..(jr + NR) * kc];
+                    for ir in (0..mc).step_by(MR) {
+                        let ap = &apack[ir * kc..(ir + MR) * kc];
+                        micro_kernel(kernel, kc, ap, bp, &mut tile);
+                        for i in 0..MR.min(mc - ir) {
+                            let row = (ic + ir + i) * n + jc + jr;
+                            let cols = NR.min(nc - jr);
+                            for (o, t) in c[row..row + cols].iter_mut().zip(&tile[i * NR..]) {
+                                *o += t;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reference triple loop (the original CpuBackend kernel); used by benchmarks and tests.
+pub fn sgemm_naive(m: usize, k: usize, n: usize, a: &[f32], b: &[f32], c: &mut [f32]) {
+    for i in 0..m {
+        for j in 0..n {
+            let mut s = 0.0;
+            for p in 0..k {
+                s += a[i * k + p] * b[p * n + j];
+            }
+            c[i * n + j] = s;
+        }
+    }
+}
+
+/// Largest k for which [igemm] cannot overflow: k * 128 * 128 must fit in i32.
+pub const IGEMM_MAX_K: usize = i32::MAX as usize / (128 * 128);
+
+/// Int8 GEMM with i32 accumulation: c = a @ b for row-major a [m, k] and b given
+/// transposed as `b_t` [n, k], so both operands are read along k. Exact for
+/// k <= [IGEMM_MAX_K]; panics for larger k rather than wrapping.
+pub fn igemm(m: usize, k: usize, n: usize, a: &[i8], b_t: &[i8], c: &mut [i32]) {
+    assert!(
+        k <= IGEMM_MAX_K,
+        "igemm: k = {} exceeds IGEMM_MAX_K = {}",
+        k,
+        IGEMM_MAX_K
+    );
+    // Columns of B per block, so the block stays in L1/L2 while all rows of A pass over it.
+    let nb = (32 * 1024 / k.max(1)).clamp(1, 256);
+    for jc in (0..n).step_by(nb) {
+        let cols = nb.min(n - jc);
+        for i in 0..m {
+            let a_row = &a[i * k..(i + 1) * k];
+            for j in jc..jc + cols {
+                let b_row = &b_t[j * k..(j + 1) * k];
+                c[i * n + j] = a_row
+                    .iter()
+                    .zip(b_row)
+                    .map(|(&x, &y)| x as i32 * y as i32)
+                    .sum();
+            }
+        }
+    }
+}
+
+/// Pack b[pc..pc+kc, jc..jc+nc] into NR-wide panels, each laid out p-major, zero-padded.
+fn pack_b(b: &[f32], n: usize, pc: usize, jc: usize, kc: usize, nc: usize, out: &mut [f32]) {
+    for jr in (0..nc).step_by(NR) {
+        let cols = NR.min(nc - jr);
+        let panel = &mut out[jr * kc..(jr + NR) * kc];
+        for p in 0..kc {
+            let src = (pc + p) * n + jc + jr;
+            let dst = &mut panel[p * NR..(p + 1) * NR];
+            dst[..cols].copy_from_slice(&b[src..src + cols]);
+            dst[cols..].fill(0.0);
+        }
+    }
+}
+
+/// Pack a[ic..ic+mc, pc..pc+kc] into MR-tall strips, each laid out p-major, zero-padded.
+fn pack_a(a: &[f32], k: usize, ic: usize, pc: usize, mc: usize, kc: usize, out: &mut [f32]) {
+    for ir in (0..mc).step_by(MR) {
+        let rows = MR.min(mc - ir);
+        let strip = &mut out[ir * kc..(ir + MR) * kc];
+        for p in 0..kc {
+            let dst = &mut strip[p * MR..(p + 1) * MR];
+            for (i, d) in dst.iter_mut().enumerate() {
+                *d = if i < rows {
+                    a[(ic + ir + i) * k + pc + p]
+                } else {
+                    0.0
+                };
+            }
+        }
+    }
+}
+
+/// tile[MR x NR] = packed A strip @ packed B panel over kc.
+fn micro_kernel(kernel: GemmKernel, kc: usize, a: &[f32], b: &[f32], tile: &mut [f32; MR * NR]) {
+    debug_assert!(a.len() >= kc * MR && b.len() >= kc * NR);
+    match kernel {
+        // SAFETY: the kernel was checked with is_supported(), and the slices hold kc * MR
+        // and kc * NR packed values.
+        #[cfg(target_arch = "x86_64")]
+        GemmKernel::Avx512 => unsafe { x86::kernel_avx512(kc, a.as_ptr(), b.as_ptr(), tile) },
+        #[cfg(target_arch = "x86_64")]
+        GemmKernel::Avx2 => unsafe { x86::kernel_avx2(kc, a.as_ptr(), b.as_ptr(), tile) },
+        _ => kernel_portable(kc, a, b, tile),
+    }
+}
+
+fn kernel_portable(kc: usize, a: &[f32], b: &[f32], tile: &mut [f32; MR * NR]) {
+    let mut acc = [[0.0f32; NR]; MR];
+    for p in 0..kc {
+        let ap = &a[p * MR..(p + 1) * MR];
+        let bp = &b[p * NR..(p + 1) * NR];
+        for (row, &ai) in acc.iter_mut().zip(ap) {
+            for (o, &bj) in row.iter_mut().zip(bp) {
+                *o += ai * bj;
+            }
+        }
+    }
+    for (dst, row) in tile.chunks_exact_mut(NR).zip(acc.iter()) {
+        dst.copy_from_slice(row);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::{MR, NR};
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2,fma")]
+    pub(super) unsafe fn kernel_avx2(
+        kc: usize,
+        a: *const f32,
+        b: *const f32,
+        tile: &mut [f32; MR * NR],
+    ) {
+        let mut acc = [[_mm256_setzero_ps(); 2]; MR];
+        for p in 0..kc {
+            let b0 = _mm256_loadu_ps(b.add(p * NR));
+            let b1 = _mm256_loadu_ps(b.add(p * NR + 8));
+            for (i, row) in acc.iter_mut().enumerate() {
+                let ai = _mm256_set1_ps(*a.add(p * MR + i));
+                row[0] = _mm256_fmadd_ps(ai, b0, row[0]);
+                row[1] = _mm256_fmadd_ps(ai, b1, row[1]);
+            }
+        }
+        for (i, row) in acc.iter().enumerate() {
+            _mm256_storeu_ps(tile.as_mut_ptr().add(i * NR), row[0]);
+            _mm256_storeu_ps(tile.as_mut_ptr().add(i * NR + 8), row[1]);
+        }
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub(super) unsafe fn kernel_avx512(
+        kc: usize,
+        a: *const f32,
+        b: *const f32,
+        tile: &mut [f32; MR * NR],
+    ) {
+        let mut acc = [_mm512_setzero_ps(); MR];
+        for p in 0..kc {
+            let bp = _mm512_loadu_ps(b.add(p * NR));
+            for (i, row) in acc.iter_mut().enumerate() {
+                *row = _mm512_fmadd_ps(_mm512_set1_ps(*a.add(p * MR + i)), bp, *row);
+            }
+        }
+        for (i, row) in acc.iter().enumerate() {
+            _mm512_storeu_ps(tile.as_mut_ptr().add(i * NR), *row);
+        }
+    }
+}
@@ -0,0 +1,509 @@
+//! Lazy evaluation: [LazyBackend] records element-wise ops (and row broadcasts) as an
+//! expression DAG instead of computing them. A chain is evaluated as one fused loop when its
+//! data is needed: [Tensor::data], [Tensor::realize], a `sum`, or any op that is not
+//! element-wise (those run on the inner backend). `mse` (scale, add, mul, sum) becomes a
+//! single pass over the data, and a bias add followed by an activation another.
+
+use crate::backend::{
+    Activation, AllocStats, Backend, BackendError, BackendResult, Capabilities, ConvGeometry,
+    Device,
+};
+use crate::shape::Shape;
+use crate::tensor::Tensor;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// Elements processed per step of a fused loop; intermediates stay in these small blocks.
+const BLOCK: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Unary {
+    Relu,
+    Sigmoid,
+    Exp,
+    Log,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Binary {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// (grad_out, input) -> grad_out where input > 0.
+    ReluBackward,
+    /// (grad_out, y) -> grad_out * y * (1 - y).
+    SigmoidBackward,
+}
+
+/// Element-wise expression over realized buffers, all with the output's element count
+/// (except [Node::Row], a row repeated over the output).
+pub(crate) enum Node {
+    Input(Arc<Vec<f32>>),
+    /// Element i reads `row[i % row.len()]`.
+    Row(Arc<Vec<f32>>),
+    Unary(Unary, Arc<Expr>),
+    Scale(Arc<Expr>, f32),
+    Binary(Binary, Arc<Expr>, Arc<Expr>),
+}
+
+pub(crate) struct Expr {
+    node: Node,
+    /// Ops evaluated per element, counting shared sub-expressions once per use.
+    ops: usize,
+}
+
+impl Expr {
+    fn new(node: Node) -> Arc<Expr> {
+        let ops = match &node {
+            Node::Input(_) | Node::Row(_) => 0,
+            Node::Unary(_, a) | Node::Scale(a, _) => 1 + a.ops,
+            Node::Binary(_, a, b) => 1 + a.ops + b.ops,
+        };
+        Arc::new(Expr { node, ops })
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    deferred: AtomicU64,
+    kernels: AtomicU64,
+    fused_ops: AtomicU64,
+}
+
+/// Lazy evaluation counters, shared by clones of a [LazyBackend].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LazyStats {
+    /// Element-wise ops recorded instead of executed.
+    pub deferred: u64,
+    /// Fused loops run (materialisations and fused reductions).
+    pub kernels: u64,
+    /// Ops executed inside those loops.
+    pub fused_ops: u64,
+}
+
+/// Pending data of a lazy tensor: the expression and the counters to charge when it runs.
+#[derive(Clone)]
+pub(crate) struct Deferred {
+    expr: Arc<Expr>,
+    counters: Arc<Counters>,
+}
+
+impl Deferred {
+    fn expr(&self) -> Arc<Expr> {
+        Arc::clone(&self.expr)
+    }
+
+    /// Run the fused loop into a buffer from `backend`.
+    fn evaluate(&self, len: usize, backend: &dyn Backend) -> Vec<f32> {
+        let mut out = backend.alloc(len);
+        self.run(len, |start, block| {
+            out[start..start + block.len()].copy_from_slice(block)
+        });
+        out
+    }
+
+    fn run(&self, len: usize, sink: impl FnMut(usize, &[f32])) {
+        self.counters.kernels.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .fused_ops
+            .fetch_add(self.expr.ops as u64, Ordering::Relaxed);
+        Program::compile(&self.expr).run(len, sink);
+    }
+}
+
+/// Data of a lazy tensor: computed from its expression on first access, then cached. The
+/// expression (and the buffers it reads) is dropped with the tensor or when
+/// [Tensor::data_mut] turns it into an eager one.
+pub(crate) struct LazyData {
+    deferred: Deferred,
+    data: OnceLock<Arc<Vec<f32>>>,
+}
+
+impl LazyData {
+    pub(crate) fn new(deferred: Deferred) -> Self {
+        LazyData {
+            deferred,
+            data: OnceLock::new(),
+        }
+    }
+
+    /// The data, evaluating the expression (`len` elements) into a `backend` buffer first.
+    pub(crate) fn get(&self, len: usize, backend: &dyn Backend) -> &Arc<Vec<f32>> {
+        self.data
+            .get_or_init(|| Arc::new(self.deferred.evaluate(len, backend)))
+    }
+
+    /// The data if it has been computed.
+    pub(crate) fn realized(&self) -> Option<&Arc<Vec<f32>>> {
+        self.data.get()
+    }
+
+    pub(crate) fn realized_mut(&mut self) -> Option<&mut Arc<Vec<f32>>> {
+        self.data.get_mut()
+    }
+
+    /// The expression while the data is still pending.
+    pub(crate) fn pending(&self) -> Option<&Deferred> {
+        match self.data.get() {
+            Some(_) => None,
+            None => Some(&self.deferred),
+        }
+    }
+
+    pub(crate) fn pending_expr(&self) -> Option<Arc<Expr>> {
+        self.pending().map(Deferred::expr)
+    }
+}
+
+enum Step {
+    Load(usize),
+    LoadRow(usize),
+    Unary(Unary),
+    Scale(f32),
+    Binary(Binary),
+}
+
+/// Postfix form of an expression, evaluated block by block on a small stack.
+struct Program {
+    inputs: Vec<Arc<Vec<f32>>>,
+    steps: Vec<Step>,
+    depth: usize,
+}
+
+impl Program {
+    fn compile(expr: &Expr) -> Program {
+        let mut p = Program {
+            inputs: Vec::new(),
+            steps: Vec::new(),
+            depth: 0,
+        };
+        p.emit(expr, 0);
+        p
+    }
+
+    /// Emit `expr` with `sp` values already on the stack.
+    fn emit(&mut self, expr: &Expr, sp: usize) {
+        self.depth = self.depth.max(sp + 1);
+        match &expr.node {
+            Node::Input(data) => {
+                let k = self.input(data);
+                self.steps.push(Step::Load(k));
+            }
+            Node::Row(data) => {
+                let k = self.input(data);
+                self.steps.push(Step::LoadRow(k));
+            }
+            Node::Unary(op, a) => {
+                self.emit(a, sp);
+                self.steps.push(Step::Unary(*op));
+            }
+            Node::Scale(a, s) => {
+                self.emit(a, sp);
+                self.steps.push(Step::Scale(*s));
+            }
+            Node::Binary(op, a, b) => {
+                self.emit(a, sp);
+                self.emit(b, sp + 1);
+                self.steps.push(Step::Binary(*op));
+            }
+        }
+    }
+
+    fn input(&mut self, data: &Arc<Vec<f32>>) -> usize {
+        match self.inputs.iter().position(|d| Arc::ptr_eq(d, data)) {
+            Some(k) => k,
+            None => {
+                self.inputs.push(Arc::clone(data));
+                self.inputs.len() - 1
+            }
+        }
+    }
+
+    /// Evaluate elements `0..len`, passing each finished block to `sink(start, values)`.
+    fn run(&self, len: usize, mut sink: impl FnMut(usize, &[f32])) {
+        let mut stack = vec![[0.0f32; BLOCK]; self.depth];
+        for start in (0..len).step_by(BLOCK) {
+            let n = BLOCK.min(len - start);
+            let mut sp = 0;
+            for step in &self.steps {
+                match *step {
+                    Step::Load(k) => {
+                        stack[sp][..n].copy_from_slice(&self.inputs[k][start..start + n]);
+                        sp += 1;
+                    }
+                    Step::LoadRow(k) => {
+                        let row = &self.inputs[k];
+                        for (j, v) in stack[sp][..n].iter_mut().enumerate() {
+                            *v = row[(start + j) % row.len()];
+                        }
+                        sp += 1;
+                    }
+                    Step::Unary(op) => {
+                        let x = &mut stack[sp - 1][..n];
+                        match op {
+                            Unary::Relu => x
+                                .iter_mut()
+                                .for_each(|v| *v = if *v > 0.0 { *v } else { 0.0 }),
+                            Unary::Sigmoid => {
+                                x.iter_mut().for_each(|v| *v = 1.0 / (1.0 + (-*v).exp()))
+                            }
+                            Unary::Exp => x.iter_mut().for_each(|v| *v = v.exp()),
+                            Unary::Log => x.iter_mut().for_each(|v| *v = v.ln()),
+                        }
+                    }
+                    Step::Scale(s) => stack[sp - 1][..n].iter_mut().for_each(|v| *v *= s),
+                    Step::Binary(op) => {
+                        let (lo, hi) = stack.split_at_mut(sp - 1);
+                        let (a, b) = (&mut lo[sp - 2][..n], &hi[0][..n]);
+                        let f: fn(f32, f32) -> f32 = match op {
+                            Binary::Add => |a, b| a + b,
+                            Binary::Sub => |a, b| a - b,
+                            Binary::Mul => |a, b| a * b,
+                            Binary::Div => |a, b| a / b,
+                            Binary::ReluBackward => |g, x| if x > 0.0 { g } else { 0.0 },
+                            Binary::SigmoidBackward => |g, y| g * y * (1.0 - y),
+                        };
+                        for (a, &b) in a.iter_mut().zip(b) {
+                            *a = f(*a, b);
+                        }
+                        sp -= 1;
+                    }
+                }
+            }
+            sink(start, &stack[0][..n]);
+        }
+    }
+}
+
+/// Backend decorator that defers element-wise ops and fuses them on evaluation; other ops
+/// run on `inner`. Results match `inner`'s element-wise kernels (same f32 formulas).
+pub struct LazyBackend<B: Backend> {
+    inner: Arc<B>,
+    max_fused_ops: usize,
+    counters: Arc<Counters>,
+}
+
+impl<B: Backend> Clone for LazyBackend<B> {
+    fn clone(&self) -> Self {
+        LazyBackend {
+            inner: Arc::clone(&self.inner),
+            max_fused_ops: self.max_fused_ops,
+            counters: Arc::clone(&self.counters),
+        }
+    }
+}
+
+impl<B: Backend + 'static> LazyBackend<B> {
+    pub fn new(inner: B) -> Self {
+        LazyBackend {
+            inner: Arc::new(inner),
+            max_fused_ops: 32,
+            counters: Arc::new(Counters::default()),
+        }
+    }
+
+    /// Largest expression (in ops per element) kept pending; an input whose expression
+    /// would exceed it is evaluated first. Bounds recomputation of shared sub-expressions.
+    pub fn with_max_fused_ops(mut self, max_fused_ops: usize) -> Self {
+        self.max_fused_ops = max_fused_ops.max(1);
+        self
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn stats(&self) -> LazyStats {
+        LazyStats {
+            deferred: self.counters.deferred.load(Ordering::Relaxed),
+            kernels: self.counters.kernels.load(Ordering::Relaxed),
+            fused_ops: self.counters.fused_ops.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset_stats(&self) {
+        self.counters.deferred.store(0, Ordering::Relaxed);
+        self.counters.kernels.store(0, Ordering::Relaxed);
+        self.counters.fused_ops.store(0, Ordering::Relaxed);
+    }
+
+    /// `t` as an expression operand: its pending expression if small enough, else its data.
+    fn operand(&self, t: &Tensor) -> Arc<Expr> {
+        match t.pending_expr() {
+            Some(e) if e.ops < self.max_fused_ops => e,
+            _ => Expr::new(Node::Input(t.shared_data())),
+        }
+    }
+
+    /// Pending tensor shaped and bound like `like`.
+    fn defer(&self, node: Node, like: &Tensor) -> BackendResult<Tensor> {
+        self.counters.deferred.fetch_add(1, Ordering::Relaxed);
+        let deferred = Deferred {
+            expr: Expr::new(node),
+            counters: Arc::clone(&self.counters),
+        };
+        Ok(Tensor::deferred(
+            deferred,
+            like.shape().clone(),
+            like.backend(),
+        ))
+    }
+
+    fn unary(&self, op: Unary, a: &Tensor) -> BackendResult<Tensor> {
+        self.defer(Node::Unary(op, self.operand(a)), a)
+    }
+
+    /// Element-wise `op(a, b)`, bound like `like` (one of `a` and `b`).
+    fn binary(
+        &self,
+        name: &str,
+        op: Binary,
+        a: &Tensor,
+        b: &Tensor,
+        like: &Tensor,
+    ) -> BackendResult<Tensor> {
+        if !a.shape().same_as(b.shape()) {
+            return Err(BackendError(format!("{}: shape mismatch", name)));
+        }
+        self.defer(Node::Binary(op, self.operand(a), self.operand(b)), like)
+    }
+}
+
+impl<B: Backend + 'static> Backend for LazyBackend<B> {
+    fn device(&self) -> Device {
+        self.inner.device()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn alloc(&self, len: usize) -> Vec<f32> {
+        self.inner.alloc(len)
+    }
+
+    fn release(&self, buf: Vec<f32>) {
+        self.inner.release(buf);
+    }
+
+    fn alloc_stats(&self) -> Option<AllocStats> {
+        self.inner.alloc_stats()
+    }
+
+    fn from_vec(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor> {
+        Tensor::from_vec(data, shape, Arc::new(self.clone()))
+            .map_err(|e| BackendError(e.to_string()))
+    }
+
+    fn matmul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.inner.matmul(a, b)
+    }
+
+    fn add(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.binary("add", Binary::Add, a, b, a)
+    }
+
+    fn mul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.binary("mul", Binary::Mul, a, b, a)
+    }
+
+    fn sub(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.binary("sub", Binary::Sub, a, b, a)
+    }
+
+    fn div(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.binary("div", Binary::Div, a, b, a)
+    }
+
+    fn relu(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.unary(Unary::Relu, a)
+    }
+
+    fn sigmoid(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.unary(Unary::Sigmoid, a)
+    }
+
+    fn exp(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.unary(Unary::Exp, a)
+    }
+
+    fn log(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.unary(Unary::Log, a)
+    }
+
+    fn scale(&self, a: &Tensor, s: f32) -> BackendResult<Tensor> {
+        self.defer(Node::Scale(self.operand(a), s), a)
+    }
+
+    fn relu_backward(&self, grad_out: &Tensor, input: &Tensor) -> BackendResult<Tensor> {
+        self.binary(
+            "relu_backward",
+            Binary::ReluBackward,
+            grad_out,
+            input,
+            input,
+        )
+    }
+
+    fn sigmoid_backward(&self, grad_out: &Tensor, fwd_output: &Tensor) -> BackendResult<Tensor> {
+        let op = Binary::SigmoidBackward;
+        self.binary("sigmoid_backward", op, grad_out, fwd_output, fwd_output)
+    }
+
+    fn add_broadcast(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        match (a.shape().dims(), b.shape().dims()) {
+            ([_, k], [k2]) if k == k2 => {}
+            _ => return Err(BackendError("add_broadcast: expected [N,K] + [K]".into())),
+        }
+        let row = Expr::new(Node::Row(b.shared_data()));
+        self.defer(Node::Binary(Binary::Add, self.operand(a), row), a)
+    }
+
+    /// A pending input is reduced inside its fused loop, never materialised.
+    fn sum(&self, a: &Tensor) -> BackendResult<Tensor> {
+        let Some(expr) = a.pending_expr() else {
+            return self.inner.sum(a);
+        };
+        let deferred = Deferred {
+            expr,
+            counters: Arc::clone(&self.counters),
+        };
+        let mut total = 0.0f32;
+        deferred.run(a.numel(), |_, block| {
+            total = block.iter().fold(total, |s, &v| s + v)
+        });
+        let mut out = self.inner.alloc(1);
+        out[0] = total;
+        Tensor::from_vec(out, Shape::new(vec![1]), a.backend())
+            .map_err(|e| BackendError(e.to_string()))
+    }
+
+    fn sum_dim(&self, a: &Tensor, dim: usize) -> BackendResult<Tensor> {
+        self.inner.sum_dim(a, dim)
+    }
+
+    fn transpose(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.inner.transpose(a)
+    }
+
+    fn softmax_last_dim(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.inner.softmax_last_dim(a)
+    }
+
+    fn softmax_backward(&self, grad_out: &Tensor, fwd_output: &Tensor) -> BackendResult<Tensor> {
+        self.inner.softmax_backward(grad_out, fwd_output)
+    }
+
+    fn linear(&self, x: &Tensor, w: &Tensor, b: &Tensor, act: Activation) -> BackendResult<Tensor> {
+        self.inner.linear(x, w, b, act)
+    }
+
+    fn im2col(&self, x: &Tensor, geom: &ConvGeometry) -> BackendResult<Tensor> {
+        self.inner.im2col(x, geom)
+    }
+
+    fn col2im(&self, cols: &Tensor, input: &Shape, geom: &ConvGeometry) -> BackendResult<Tensor> {
+        self.inner.col2im(cols, input, geom)
+    }
+}
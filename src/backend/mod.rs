@@ -6,39 +6,237 @@ use crate::tensor::Tensor;
 use crate::Shape;
 use thiserror::Error;
 
+pub use alloc::{AllocStats, Allocator, PoolAllocator, SystemAllocator};
+pub use conv::ConvGeometry;
+pub use device::{Device, DeviceKind};
+
 #[derive(Error, Debug)]
 #[error("backend error: {0}")]
 pub struct BackendError(pub String);
 
 pub type BackendResult<T> = Result<T, BackendError>;
 
+/// Activation applied by fused kernels such as [Backend::linear].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Activation {
+    Identity,
+    ReLU,
+    Sigmoid,
+}
+
+/// Optional fused kernels a backend provides natively. Methods without one fall back to the
+/// trait's default composition of primitives.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// [Backend::linear] runs as one kernel (bias and activation in the matmul epilogue).
+    pub fused_linear: bool,
+    /// [Backend::sigmoid] and [Backend::sigmoid_backward] are single element-wise passes.
+    pub fused_sigmoid: bool,
+    /// [Backend::softmax_backward] runs in one pass per row.
+    pub fused_softmax_backward: bool,
+}
+
 /// Device-agnostic backend for tensor operations.
-/// Tensor holds an Arc<dyn Backend> and delegates all ops here.
+/// Tensor holds an Arc<dyn Backend> and delegates all ops here. Op results are bound to the
+/// backend of the tensor the op was dispatched through (e.g. `a` for `a.add(b)`), so
+/// stateful and wrapping backends keep their identity; constructors bind to a clone of self,
+/// so backends with state keep it behind an Arc shared by clones.
+///
+/// Only a small primitive set is required; composite ops (zeros, ones, sub, scale, sum,
+/// sigmoid, add_broadcast, the sigmoid/softmax backward ops and linear) have default
+/// implementations in terms of it. Override them to accelerate, and report fused kernels
+/// in [Backend::capabilities]. The convolution data-movement ops (im2col, col2im) default to
+/// host kernels.
 pub trait Backend: Send + Sync {
+    /// Device this backend's tensors live on. Ops on tensors from different devices fail.
+    fn device(&self) -> Device {
+        Device::CPU
+    }
+
+    /// Fused kernels this backend implements natively.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Zeroed buffer for a new tensor. Pooling backends reuse released buffers.
+    fn alloc(&self, len: usize) -> Vec<f32> {
+        vec![0.0; len]
+    }
+
+    /// Called with the buffer of a dropped tensor bound to this backend.
+    fn release(&self, _buf: Vec<f32>) {}
+
+    /// Allocation statistics, if this backend tracks them.
+    fn alloc_stats(&self) -> Option<AllocStats> {
+        None
+    }
+
+    // Primitives.
+
+    #[allow(clippy::wrong_self_convention)]
+    fn from_vec(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor>;
     fn matmul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor>;
     fn add(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor>;
     fn mul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor>;
-    fn sub(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor>;
+    /// Element-wise a / b (same shape).
+    fn div(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor>;
     fn relu(&self, a: &Tensor) -> BackendResult<Tensor>;
-    fn sum(&self, a: &Tensor) -> BackendResult<Tensor>;
-    fn sum_dim(&self, a: &Tensor, dim: usize) -> BackendResult<Tensor>;
-    fn from_vec(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor>;
-    fn zeros(&self, shape: &Shape) -> BackendResult<Tensor>;
-    fn ones(&self, shape: &Shape) -> BackendResult<Tensor>;
-    fn sigmoid(&self, a: &Tensor) -> BackendResult<Tensor>;
+    fn relu_backward(&self, grad_out: &Tensor, input: &Tensor) -> BackendResult<Tensor>;
     fn exp(&self, a: &Tensor) -> BackendResult<Tensor>;
     fn log(&self, a: &Tensor) -> BackendResult<Tensor>;
-    fn add_broadcast(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor>;
+    fn sum_dim(&self, a: &Tensor, dim: usize) -> BackendResult<Tensor>;
     fn transpose(&self, a: &Tensor) -> BackendResult<Tensor>;
-    fn scale(&self, a: &Tensor, s: f32) -> BackendResult<Tensor>;
-    /// Element-wise a / b (same shape).
-    fn div(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor>;
-    fn relu_backward(&self, grad_out: &Tensor, input: &Tensor) -> BackendResult<Tensor>;
-    fn sigmoid_backward(&self, grad_out: &Tensor, fwd_output: &Tensor) -> BackendResult<Tensor>;
     /// Softmax along last dimension. For 2D [B, C], each row sums to 1.
     fn softmax_last_dim(&self, a: &Tensor) -> BackendResult<Tensor>;
+
+    // Composites.
+
+    fn zeros(&self, shape: &Shape) -> BackendResult<Tensor> {
+        self.from_vec(self.alloc(shape.numel()), shape.clone())
+    }
+
+    fn ones(&self, shape: &Shape) -> BackendResult<Tensor> {
+        let mut data = self.alloc(shape.numel());
+        data.fill(1.0);
+        self.from_vec(data, shape.clone())
+    }
+
+    /// a + b * -1.
+    fn sub(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.add(a, &self.scale(b, -1.0)?)
+    }
+
+    /// a * s, as a product with a constant tensor.
+    fn scale(&self, a: &Tensor, s: f32) -> BackendResult<Tensor> {
+        self.mul(a, &filled(a, a.shape().dims(), s)?)
+    }
+
+    /// Sum of all elements, shape [1].
+    fn sum(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.sum_dim(&reshaped(a, &[a.numel()])?, 0)
+    }
+
+    /// 1 / (1 + exp(-a)).
+    fn sigmoid(&self, a: &Tensor) -> BackendResult<Tensor> {
+        let one = filled(a, a.shape().dims(), 1.0)?;
+        let denom = self.add(&self.exp(&self.scale(a, -1.0)?)?, &one)?;
+        self.div(&one, &denom)
+    }
+
+    /// a [N,K] + b [K] per row; b is tiled by a [N,1] @ [1,K] matmul with ones.
+    fn add_broadcast(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        let (n, k) = match (a.shape().dims(), b.shape().dims()) {
+            ([n, k], [k2]) if k == k2 => (*n, *k),
+            (ad, bd) => {
+                return Err(BackendError(format!(
+                    "add_broadcast: expected [N,K] + [K], got {:?} + {:?}",
+                    ad, bd
+                )))
+            }
+        };
+        let tiled = self.matmul(&filled(a, &[n, 1], 1.0)?, &reshaped(b, &[1, k])?)?;
+        self.add(a, &tiled)
+    }
+
+    /// grad_out * y * (1 - y).
+    fn sigmoid_backward(&self, grad_out: &Tensor, fwd_output: &Tensor) -> BackendResult<Tensor> {
+        let y = fwd_output;
+        let one_minus_y = self.add(&self.scale(y, -1.0)?, &filled(y, y.shape().dims(), 1.0)?)?;
+        self.mul(y, &self.mul(grad_out, &one_minus_y)?)
+    }
+
     /// Backward for softmax: grad_in = y * (grad_out - sum(grad_out * y, last_dim)).
-    fn softmax_backward(&self, grad_out: &Tensor, fwd_output: &Tensor) -> BackendResult<Tensor>;
+    fn softmax_backward(&self, grad_out: &Tensor, fwd_output: &Tensor) -> BackendResult<Tensor> {
+        let dims = fwd_output.shape().dims();
+        if grad_out.shape().dims() != dims {
+            return Err(BackendError("softmax_backward: shape mismatch".into()));
+        }
+        let Some((&c, outer)) = dims.split_last() else {
+            return Err(BackendError("softmax_backward: need at least 1 dim".into()));
+        };
+        let rows: usize = outer.iter().product();
+        let g = reshaped(grad_out, &[rows, c])?;
+        let y = reshaped(fwd_output, &[rows, c])?;
+        let dot = self.sum_dim(&self.mul(&g, &y)?, 1)?;
+        let dot = self.matmul(&dot, &filled(&y, &[1, c], 1.0)?)?;
+        let out = self.mul(&y, &self.sub(&g, &dot)?)?;
+        reshaped(&out, dims)
+    }
+
+    /// Fused act(x @ w + b) for x [M,K], w [K,N], b [N]. Default composes matmul,
+    /// add_broadcast and the activation; backends override with a single-pass kernel.
+    fn linear(&self, x: &Tensor, w: &Tensor, b: &Tensor, act: Activation) -> BackendResult<Tensor> {
+        let out = self.add_broadcast(&self.matmul(x, w)?, b)?;
+        match act {
+            Activation::Identity => Ok(out),
+            Activation::ReLU => self.relu(&out),
+            Activation::Sigmoid => self.sigmoid(&out),
+        }
+    }
+
+    /// Unfold the windows of x [N, C, H, W] into a [N*OH*OW, C*KH*KW] matrix: row (n, oy, ox),
+    /// column (c, ky, kx), zero where a window covers padding. A convolution is then a matmul
+    /// with the flattened weight.
+    fn im2col(&self, x: &Tensor, geom: &ConvGeometry) -> BackendResult<Tensor> {
+        let dims = conv::dims4("im2col", x.shape().dims())?;
+        let [n, c, h, w] = dims;
+        let (oh, ow) = geom.output_size(h, w)?;
+        let (kh, kw) = geom.kernel;
+        let backend = x.backend();
+        let mut out = backend.alloc(n * oh * ow * c * kh * kw);
+        conv::im2col_into(x.data(), dims, geom, &mut out)?;
+        Tensor::from_vec(out, Shape::new(vec![n * oh * ow, c * kh * kw]), backend)
+            .map_err(|e| BackendError(e.to_string()))
+    }
+
+    /// Adjoint of [Backend::im2col]: scatter-add the rows of `cols` back into a tensor of
+    /// `input` shape [N, C, H, W]; overlapping windows sum.
+    fn col2im(&self, cols: &Tensor, input: &Shape, geom: &ConvGeometry) -> BackendResult<Tensor> {
+        let dims = conv::dims4("col2im", input.dims())?;
+        let [n, c, h, w] = dims;
+        let (oh, ow) = geom.output_size(h, w)?;
+        let (kh, kw) = geom.kernel;
+        let want = [n * oh * ow, c * kh * kw];
+        if cols.shape().dims() != want {
+            return Err(BackendError(format!(
+                "col2im: expected cols {:?} for input {:?}, got {:?}",
+                want,
+                input.dims(),
+                cols.shape().dims()
+            )));
+        }
+        let backend = cols.backend();
+        let mut out = backend.alloc(input.numel());
+        conv::col2im_into(cols.data(), dims, geom, &mut out)?;
+        Tensor::from_vec(out, input.clone(), backend).map_err(|e| BackendError(e.to_string()))
+    }
+}
+
+/// Tensor of `dims` filled with `value`, bound to the backend of `like`.
+fn filled(like: &Tensor, dims: &[usize], value: f32) -> BackendResult<Tensor> {
+    let backend = like.backend();
+    let mut data = backend.alloc(dims.iter().product());
+    data.fill(value);
+    Tensor::from_vec(data, Shape::new(dims.to_vec()), backend)
+        .map_err(|e| BackendError(e.to_string()))
+}
+
+/// Copy of `t` with shape `dims` (same element count), bound to the backend of `t`.
+fn reshaped(t: &Tensor, dims: &[usize]) -> BackendResult<Tensor> {
+    let backend = t.backend();
+    let mut data = backend.alloc(t.numel());
+    data.copy_from_slice(t.data());
+    Tensor::from_vec(data, Shape::new(dims.to_vec()), backend)
+        .map_err(|e| BackendError(e.to_string()))
 }
 
+pub mod alloc;
+pub mod checked;
+pub mod conformance;
+pub mod conv;
 pub mod cpu;
+pub mod device;
+pub mod gemm;
+pub mod lazy;
+pub mod parallel;
+pub mod profile;
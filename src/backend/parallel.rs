@@ -0,0 +1,536 @@
+//! Multithreaded CPU backend. Work is split into fixed contiguous chunks (one per thread)
+//! and run on a pool of worker threads owned by the backend (the calling thread takes the
+//! first chunk, worker `i` always the chunk after it). Each output element is computed by
+//! exactly one thread in the same order as [CpuBackend], and reductions combine per-chunk
+//! partials in chunk order, so results are deterministic for a fixed thread count.
+
+use crate::backend::alloc::{AllocStats, Allocator};
+use crate::backend::cpu::CpuBackend;
+use crate::backend::{gemm, Activation, Backend, BackendError, BackendResult, Capabilities};
+use crate::shape::Shape;
+use crate::tensor::Tensor;
+use std::cell::Cell;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+thread_local! {
+    /// Set on pool workers: a chunk that calls back into the backend runs serially instead
+    /// of queueing behind itself.
+    static IN_WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Persistent worker threads, shared by clones of a [ParallelCpuBackend] and joined when
+/// the last clone is dropped.
+struct WorkerPool {
+    senders: Vec<mpsc::Sender<Job>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+/// Counts outstanding chunks of one call; the caller waits on it before returning.
+struct Latch {
+    state: Mutex<(usize, bool)>,
+    done: Condvar,
+}
+
+impl Latch {
+    fn finish(&self, panicked: bool) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.0 -= 1;
+        state.1 |= panicked;
+        if state.0 == 0 {
+            self.done.notify_all();
+        }
+    }
+
+    /// Block until every chunk finished; true if any of them panicked.
+    fn wait(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        while state.0 > 0 {
+            state = self.done.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        state.1
+    }
+}
+
+impl WorkerPool {
+    fn new(workers: usize) -> Self {
+        let mut senders = Vec::with_capacity(workers);
+        let mut handles = Vec::with_capacity(workers);
+        for i in 0..workers {
+            let (tx, rx) = mpsc::channel::<Job>();
+            let handle = std::thread::Builder::new()
+                .name(format!("dl_core-parallel-{}", i))
+                .spawn(move || {
+                    IN_WORKER.with(|w| w.set(true));
+                    while let Ok(job) = rx.recv() {
+                        job();
+                    }
+                })
+                .expect("failed to spawn parallel backend worker");
+            senders.push(tx);
+            handles.push(handle);
+        }
+        WorkerPool { senders, handles }
+    }
+
+    /// Run `f(i, chunk)` for every chunk: chunk 0 on the calling thread, chunk `i` on worker
+    /// `i - 1`. Returns once all chunks are done; a panic in any chunk is re-raised here.
+    fn run<'a, T: Send + 'a, F>(&self, chunks: Vec<T>, f: &'a F)
+    where
+        F: Fn(usize, T) + Sync,
+    {
+        let latch = Arc::new(Latch {
+            state: Mutex::new((chunks.len().saturating_sub(1), false)),
+            done: Condvar::new(),
+        });
+        let mut chunks = chunks.into_iter().enumerate();
+        let first = chunks.next();
+        for ((i, chunk), tx) in chunks.zip(&self.senders) {
+            let latch = latch.clone();
+            let job: Box<dyn FnOnce() + Send + 'a> = Box::new(move || {
+                let panicked = catch_unwind(AssertUnwindSafe(|| f(i, chunk))).is_err();
+                latch.finish(panicked);
+            });
+            // SAFETY: the job only borrows data that outlives this call, and this function
+            // does not return (or unwind) before the latch has counted every job as finished.
+            let job: Job =
+                unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'a>, Job>(job) };
+            if let Err(mpsc::SendError(job)) = tx.send(job) {
+                job();
+            }
+        }
+        let caller = first.map(|(i, chunk)| catch_unwind(AssertUnwindSafe(|| f(i, chunk))));
+        let worker_panicked = latch.wait();
+        if let Some(Err(payload)) = caller {
+            resume_unwind(payload);
+        }
+        if worker_panicked {
+            panic!("parallel backend: a worker chunk panicked");
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.senders.clear();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Parallel CPU backend with a fixed thread count.
+#[derive(Clone)]
+pub struct ParallelCpuBackend {
+    threads: usize,
+    /// Ops with less work than this (in multiply-adds or elements) run on the calling thread.
+    min_parallel_work: usize,
+    cpu: CpuBackend,
+    /// `threads - 1` workers; the calling thread runs the first chunk itself.
+    pool: Arc<WorkerPool>,
+}
+
+impl ParallelCpuBackend {
+    /// Backend using `threads` threads (at least 1): the caller plus `threads - 1` workers
+    /// spawned here and kept for the backend's lifetime.
+    pub fn new(threads: usize) -> Self {
+        let threads = threads.max(1);
+        ParallelCpuBackend {
+            threads,
+            min_parallel_work: 1 << 14,
+            cpu: CpuBackend::new(),
+            pool: Arc::new(WorkerPool::new(threads - 1)),
+        }
+    }
+
+    /// Minimum work per op before it is split across threads. Does not affect results.
+    pub fn with_min_parallel_work(mut self, work: usize) -> Self {
+        self.min_parallel_work = work;
+        self
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Parallel backend drawing tensor buffers from `allocator`.
+    pub fn with_allocator(threads: usize, allocator: Arc<dyn Allocator>) -> Self {
+        ParallelCpuBackend {
+            cpu: CpuBackend::with_allocator(allocator),
+            ..Self::new(threads)
+        }
+    }
+
+    /// Bind an op result to the backend of `like` (the tensor the op was dispatched through).
+    fn wrap(&self, like: &Tensor, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor> {
+        Tensor::from_vec(data, shape, like.backend()).map_err(|e| BackendError(e.to_string()))
+    }
+
+    fn wrap_new(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor> {
+        Tensor::from_vec(data, shape, Arc::new(self.clone()))
+            .map_err(|e| BackendError(e.to_string()))
+    }
+
+    /// Split `out` into at most `threads` chunks of whole `unit`-sized rows and call
+    /// `f(first_row, chunk)` for each, in parallel when `work` is large enough.
+    fn for_each_chunk<F>(&self, out: &mut [f32], unit: usize, work: usize, f: F)
+    where
+        F: Fn(usize, &mut [f32]) + Sync,
+    {
+        let unit = unit.max(1);
+        let rows = out.len() / unit;
+        let nested = IN_WORKER.with(|w| w.get());
+        if self.threads == 1 || rows < 2 || work < self.min_parallel_work || nested {
+            f(0, out);
+            return;
+        }
+        let rows_per = rows.div_ceil(self.threads);
+        let chunks: Vec<&mut [f32]> = out.chunks_mut(rows_per * unit).collect();
+        self.pool
+            .run(chunks, &|i, chunk: &mut [f32]| f(i * rows_per, chunk));
+    }
+
+    /// a [m, k] @ b [k, n] with rows of the output split across threads, then `epilogue` on
+    /// each thread's finished rows. Each row block runs the same GEMM kernel, so results match
+    /// CpuBackend.
+    fn gemm_rows<E>(
+        &self,
+        a: &[f32],
+        b: &[f32],
+        m: usize,
+        k: usize,
+        n: usize,
+        epilogue: E,
+    ) -> Vec<f32>
+    where
+        E: Fn(&mut [f32]) + Sync,
+    {
+        let mut out = self.cpu.alloc(m * n);
+        self.for_each_chunk(&mut out, n, m * n * k, |row0, chunk| {
+            let rows = chunk.len() / n.max(1);
+            gemm::sgemm(rows, k, n, &a[row0 * k..(row0 + rows) * k], b, chunk);
+            epilogue(chunk);
+        });
+        out
+    }
+
+    fn unary<F>(&self, a: &Tensor, f: F) -> BackendResult<Tensor>
+    where
+        F: Fn(f32) -> f32 + Sync,
+    {
+        let ad = a.data();
+        let mut out = self.cpu.alloc(ad.len());
+        self.for_each_chunk(&mut out, 1, ad.len(), |start, chunk| {
+            for (i, o) in chunk.iter_mut().enumerate() {
+                *o = f(ad[start + i]);
+            }
+        });
+        self.wrap(a, out, a.shape().clone())
+    }
+
+    fn binary<F>(&self, name: &str, a: &Tensor, b: &Tensor, f: F) -> BackendResult<Tensor>
+    where
+        F: Fn(f32, f32) -> f32 + Sync,
+    {
+        if !a.shape().same_as(b.shape()) {
+            return Err(BackendError(format!("{}: shape mismatch", name)));
+        }
+        let ad = a.data();
+        let bd = b.data();
+        let mut out = self.cpu.alloc(ad.len());
+        self.for_each_chunk(&mut out, 1, ad.len(), |start, chunk| {
+            for (i, o) in chunk.iter_mut().enumerate() {
+                *o = f(ad[start + i], bd[start + i]);
+            }
+        });
+        self.wrap(a, out, a.shape().clone())
+    }
+
+    /// Row-wise kernel over the last dimension: `f(row_in, row_out)` per row.
+    fn rows<F>(&self, a: &Tensor, f: F) -> BackendResult<Tensor>
+    where
+        F: Fn(usize, &mut [f32]) + Sync,
+    {
+        let dims = a.shape().dims();
+        if dims.is_empty() {
+            return Err(BackendError("row op: need at least 1 dim".into()));
+        }
+        let row = dims[dims.len() - 1];
+        let mut out = self.cpu.alloc(a.numel());
+        self.for_each_chunk(&mut out, row, a.numel(), |start, chunk| {
+            for (r, o) in chunk.chunks_mut(row.max(1)).enumerate() {
+                f(start + r, o);
+            }
+        });
+        self.wrap(a, out, a.shape().clone())
+    }
+}
+
+impl Default for ParallelCpuBackend {
+    fn default() -> Self {
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        Self::new(threads)
+    }
+}
+
+impl Backend for ParallelCpuBackend {
+    fn matmul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        let ad = a.shape().dims();
+        let bd = b.shape().dims();
+        if ad.len() != 2 || bd.len() != 2 {
+            return Err(BackendError("matmul requires 2D tensors".into()));
+        }
+        let (m, k) = (ad[0], ad[1]);
+        let n = bd[1];
+        if bd[0] != k {
+            return Err(BackendError(format!(
+                "matmul dim mismatch: {} != {}",
+                k, bd[0]
+            )));
+        }
+        let out = self.gemm_rows(a.data(), b.data(), m, k, n, |_| {});
+        self.wrap(a, out, Shape::new(vec![m, n]))
+    }
+
+    fn add(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.binary("add", a, b, |x, y| x + y)
+    }
+
+    fn mul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.binary("mul", a, b, |x, y| x * y)
+    }
+
+    fn sub(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.binary("sub", a, b, |x, y| x - y)
+    }
+
+    fn relu(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.unary(a, |x| if x > 0.0 { x } else { 0.0 })
+    }
+
+    /// Fixed partition: `threads` contiguous chunks; partials are added in chunk order.
+    fn sum(&self, a: &Tensor) -> BackendResult<Tensor> {
+        let ad = a.data();
+        let per = ad.len().div_ceil(self.threads).max(1);
+        let mut partials = vec![0.0f32; ad.len().div_ceil(per)];
+        let work = ad.len();
+        self.for_each_chunk(&mut partials, 1, work, |start, chunk| {
+            for (i, p) in chunk.iter_mut().enumerate() {
+                let lo = (start + i) * per;
+                let hi = (lo + per).min(ad.len());
+                *p = ad[lo..hi].iter().sum();
+            }
+        });
+        let s: f32 = partials.iter().sum();
+        let mut out = self.cpu.alloc(1);
+        out[0] = s;
+        self.wrap(a, out, Shape::new(vec![1]))
+    }
+
+    fn sum_dim(&self, a: &Tensor, dim: usize) -> BackendResult<Tensor> {
+        let dims = a.shape().dims();
+        if dim >= dims.len() {
+            return Err(BackendError("sum_dim: dim out of range".into()));
+        }
+        let outer: usize = dims[..dim].iter().product();
+        let reduced = dims[dim];
+        let inner: usize = dims[dim + 1..].iter().product();
+        let ad = a.data();
+        let mut out_dims = dims.to_vec();
+        out_dims[dim] = 1;
+        let mut out = self.cpu.alloc(outer * inner);
+        // Each output element sums its fibre in index order, as CpuBackend does.
+        self.for_each_chunk(&mut out, 1, a.numel(), |start, chunk| {
+            for (i, o) in chunk.iter_mut().enumerate() {
+                let idx = start + i;
+                let (po, pi) = (idx / inner.max(1), idx % inner.max(1));
+                let mut s = 0.0;
+                for r in 0..reduced {
+                    s += ad[(po * reduced + r) * inner + pi];
+                }
+                *o = s;
+            }
+        });
+        self.wrap(a, out, Shape::new(out_dims))
+    }
+
+    fn from_vec(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor> {
+        self.wrap_new(data, shape)
+    }
+
+    fn zeros(&self, shape: &Shape) -> BackendResult<Tensor> {
+        self.wrap_new(self.cpu.alloc(shape.numel()), shape.clone())
+    }
+
+    fn ones(&self, shape: &Shape) -> BackendResult<Tensor> {
+        let mut out = self.cpu.alloc(shape.numel());
+        out.fill(1.0);
+        self.wrap_new(out, shape.clone())
+    }
+
+    fn sigmoid(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.unary(a, |x| 1.0 / (1.0 + (-x).exp()))
+    }
+
+    fn exp(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.unary(a, f32::exp)
+    }
+
+    fn log(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.unary(a, f32::ln)
+    }
+
+    fn add_broadcast(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        let ad = a.shape().dims();
+        let bd = b.shape().dims();
+        if ad.len() != 2 || bd.len() != 1 || bd[0] != ad[1] {
+            return self.cpu.add_broadcast(a, b);
+        }
+        let adata = a.data();
+        let bdata = b.data();
+        let k = ad[1];
+        self.rows(a, |r, o| {
+            for (j, v) in o.iter_mut().enumerate() {
+                *v = adata[r * k + j] + bdata[j];
+            }
+        })
+    }
+
+    fn transpose(&self, a: &Tensor) -> BackendResult<Tensor> {
+        let d = a.shape().dims();
+        if d.len() != 2 {
+            return Err(BackendError("transpose: requires 2D tensor".into()));
+        }
+        let (m, n) = (d[0], d[1]);
+        let ad = a.data();
+        let mut out = self.cpu.alloc(m * n);
+        self.for_each_chunk(&mut out, m, m * n, |row0, chunk| {
+            for (r, orow) in chunk.chunks_mut(m.max(1)).enumerate() {
+                let j = row0 + r;
+                for (i, o) in orow.iter_mut().enumerate() {
+                    *o = ad[i * n + j];
+                }
+            }
+        });
+        self.wrap(a, out, Shape::new(vec![n, m]))
+    }
+
+    fn scale(&self, a: &Tensor, s: f32) -> BackendResult<Tensor> {
+        self.unary(a, |x| x * s)
+    }
+
+    fn div(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.binary("div", a, b, |x, y| x / y)
+    }
+
+    fn relu_backward(&self, grad_out: &Tensor, input: &Tensor) -> BackendResult<Tensor> {
+        self.binary("relu_backward", input, grad_out, |x, g| {
+            if x > 0.0 {
+                g
+            } else {
+                0.0
+            }
+        })
+    }
+
+    fn sigmoid_backward(&self, grad_out: &Tensor, fwd_output: &Tensor) -> BackendResult<Tensor> {
+        self.binary("sigmoid_backward", fwd_output, grad_out, |y, g| {
+            g * y * (1.0 - y)
+        })
+    }
+
+    fn softmax_last_dim(&self, a: &Tensor) -> BackendResult<Tensor> {
+        let dims = a.shape().dims();
+        if dims.is_empty() {
+            return Err(BackendError("softmax: need at least 1 dim".into()));
+        }
+        let row = dims[dims.len() - 1];
+        let ad = a.data();
+        self.rows(a, |r, o| {
+            let x = &ad[r * row..(r + 1) * row];
+            let row_max = x.iter().fold(f32::NEG_INFINITY, |m, &v| m.max(v));
+            let mut sum = 0.0;
+            for (v, &xi) in o.iter_mut().zip(x.iter()) {
+                *v = (xi - row_max).exp();
+                sum += *v;
+            }
+            for v in o.iter_mut() {
+                *v /= sum;
+            }
+        })
+    }
+
+    fn softmax_backward(&self, grad_out: &Tensor, fwd_output: &Tensor) -> BackendResult<Tensor> {
+        if !grad_out.shape().same_as(fwd_output.shape()) {
+            return Err(BackendError("softmax_backward: shape mismatch".into()));
+        }
+        let dims = fwd_output.shape().dims();
+        if dims.is_empty() {
+            return Err(BackendError("softmax_backward: need at least 1 dim".into()));
+        }
+        let row = dims[dims.len() - 1];
+        let gd = grad_out.data();
+        let yd = fwd_output.data();
+        self.rows(fwd_output, |r, o| {
+            let base = r * row;
+            let mut sum_gy = 0.0;
+            for j in 0..row {
+                sum_gy += gd[base + j] * yd[base + j];
+            }
+            for (j, v) in o.iter_mut().enumerate() {
+                *v = yd[base + j] * (gd[base + j] - sum_gy);
+            }
+        })
+    }
+
+    fn linear(&self, x: &Tensor, w: &Tensor, b: &Tensor, act: Activation) -> BackendResult<Tensor> {
+        let xd = x.shape().dims();
+        let wd = w.shape().dims();
+        let bd = b.shape().dims();
+        if xd.len() != 2 || wd.len() != 2 || bd.len() != 1 || wd[0] != xd[1] || bd[0] != wd[1] {
+            return self.cpu.linear(x, w, b, act);
+        }
+        let (m, k, n) = (xd[0], xd[1], wd[1]);
+        let bdata = b.data();
+        let out = self.gemm_rows(x.data(), w.data(), m, k, n, |rows| {
+            for row in rows.chunks_mut(n.max(1)) {
+                for (o, &bj) in row.iter_mut().zip(bdata) {
+                    let v = *o + bj;
+                    *o = match act {
+                        Activation::Identity => v,
+                        Activation::ReLU => {
+                            if v > 0.0 {
+                                v
+                            } else {
+                                0.0
+                            }
+                        }
+                        Activation::Sigmoid => 1.0 / (1.0 + (-v).exp()),
+                    };
+                }
+            }
+        });
+        self.wrap(x, out, Shape::new(vec![m, n]))
+    }
+
+    fn alloc(&self, len: usize) -> Vec<f32> {
+        self.cpu.alloc(len)
+    }
+
+    fn release(&self, buf: Vec<f32>) {
+        self.cpu.release(buf);
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.cpu.capabilities()
+    }
+
+    fn alloc_stats(&self) -> Option<AllocStats> {
+        self.cpu.alloc_stats()
+    }
+}
@@ -0,0 +1,420 @@
+//! Profiling decorator: [ProfilingBackend] forwards every call to an inner backend and records
+//! call counts, wall time, estimated FLOPs and bytes of the returned tensor. Graph and Plan
+//! run ops inside [op_scope], so records are also attributed to the graph op (and phase)
+//! that issued them. Results render as a summary table or a Chrome trace-event JSON file
+//! (load in chrome://tracing or Perfetto).
+
+use crate::backend::{
+    Activation, AllocStats, Backend, BackendError, BackendResult, Capabilities, ConvGeometry,
+    Device,
+};
+use crate::shape::Shape;
+use crate::tensor::Tensor;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Whether a graph op is running forward or backward.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Forward,
+    Backward,
+}
+
+thread_local! {
+    static SCOPE: Cell<Scope> = const { Cell::new(None) };
+    static THREAD_ID: u64 = {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    };
+}
+
+type Scope = Option<(&'static str, Phase)>;
+
+/// Restores the enclosing scope on drop, so a panicking op doesn't leak its attribution.
+struct ScopeGuard(Scope);
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        SCOPE.with(|s| s.set(self.0));
+    }
+}
+
+/// Run `f` with backend calls attributed to graph op `op` in `phase`. Scopes nest; the
+/// innermost wins.
+pub fn op_scope<R>(op: &'static str, phase: Phase, f: impl FnOnce() -> R) -> R {
+    let _restore = ScopeGuard(SCOPE.with(|s| s.replace(Some((op, phase)))));
+    f()
+}
+
+/// Aggregated counters for one method or graph op.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProfileEntry {
+    pub name: String,
+    pub calls: u64,
+    pub total: Duration,
+    pub flops: u64,
+    pub bytes: u64,
+}
+
+impl ProfileEntry {
+    fn add(&mut self, e: &Event) {
+        self.calls += 1;
+        self.total += e.dur;
+        self.flops += e.flops;
+        self.bytes += e.bytes;
+    }
+}
+
+struct Event {
+    method: &'static str,
+    scope: Scope,
+    start: Duration,
+    dur: Duration,
+    flops: u64,
+    bytes: u64,
+    tid: u64,
+}
+
+fn scope_name(scope: Scope) -> String {
+    match scope {
+        Some((op, Phase::Forward)) => op.to_string(),
+        Some((op, Phase::Backward)) => format!("{}.backward", op),
+        None => "(outside graph)".to_string(),
+    }
+}
+
+/// Events kept for [ProfilingBackend::chrome_trace] by default (see
+/// [ProfilingBackend::with_max_events]).
+pub const DEFAULT_MAX_EVENTS: usize = 1 << 20;
+
+/// Recorded profile: running totals plus the first `max_events` events.
+#[derive(Default)]
+struct Profile {
+    by_method: HashMap<&'static str, ProfileEntry>,
+    by_op: HashMap<Scope, ProfileEntry>,
+    events: Vec<Event>,
+    dropped: u64,
+}
+
+fn sorted(entries: impl Iterator<Item = ProfileEntry>) -> Vec<ProfileEntry> {
+    let mut out: Vec<ProfileEntry> = entries.collect();
+    out.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.name.cmp(&b.name)));
+    out
+}
+
+/// Backend decorator recording per-call statistics. Clones share the recorded profile.
+pub struct ProfilingBackend<B: Backend> {
+    inner: Arc<B>,
+    epoch: Instant,
+    max_events: usize,
+    profile: Arc<Mutex<Profile>>,
+}
+
+impl<B: Backend> Clone for ProfilingBackend<B> {
+    fn clone(&self) -> Self {
+        ProfilingBackend {
+            inner: Arc::clone(&self.inner),
+            epoch: self.epoch,
+            max_events: self.max_events,
+            profile: Arc::clone(&self.profile),
+        }
+    }
+}
+
+impl<B: Backend + 'static> ProfilingBackend<B> {
+    pub fn new(inner: B) -> Self {
+        ProfilingBackend {
+            inner: Arc::new(inner),
+            epoch: Instant::now(),
+            max_events: DEFAULT_MAX_EVENTS,
+            profile: Arc::new(Mutex::new(Profile::default())),
+        }
+    }
+
+    /// Keep at most `max` individual events for the Chrome trace; later calls only update
+    /// the per-method and per-op totals. 0 records totals only, for long runs.
+    pub fn with_max_events(mut self, max: usize) -> Self {
+        self.max_events = max;
+        self
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Drop everything recorded so far.
+    pub fn reset(&self) {
+        *self.profile.lock().unwrap() = Profile::default();
+    }
+
+    /// Calls counted in the totals but left out of the Chrome trace by the event cap.
+    pub fn dropped_events(&self) -> u64 {
+        self.profile.lock().unwrap().dropped
+    }
+
+    /// Per backend method, sorted by total time (descending).
+    pub fn by_method(&self) -> Vec<ProfileEntry> {
+        sorted(self.profile.lock().unwrap().by_method.values().cloned())
+    }
+
+    /// Per graph op and phase (e.g. "MatMul", "MatMul.backward"), sorted by total time.
+    pub fn by_op(&self) -> Vec<ProfileEntry> {
+        sorted(self.profile.lock().unwrap().by_op.values().cloned())
+    }
+
+    /// Summary tables by backend method and by graph op.
+    pub fn summary(&self) -> String {
+        let mut s = String::new();
+        for (title, rows) in [("method", self.by_method()), ("graph op", self.by_op())] {
+            let _ = writeln!(
+                s,
+                "{:<24} {:>8} {:>12} {:>10} {:>10} {:>10}",
+                title, "calls", "total ms", "mean us", "GFLOP", "MB"
+            );
+            for r in rows {
+                let ms = r.total.as_secs_f64() * 1e3;
+                let _ = writeln!(
+                    s,
+                    "{:<24} {:>8} {:>12.3} {:>10.1} {:>10.4} {:>10.3}",
+                    r.name,
+                    r.calls,
+                    ms,
+                    ms * 1e3 / r.calls.max(1) as f64,
+                    r.flops as f64 / 1e9,
+                    r.bytes as f64 / (1 << 20) as f64
+                );
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Chrome trace-event JSON ("X" complete events, microsecond timestamps).
+    pub fn chrome_trace(&self) -> String {
+        let events: Vec<serde_json::Value> = self
+            .profile
+            .lock()
+            .unwrap()
+            .events
+            .iter()
+            .map(|e| {
+                serde_json::json!({
+                    "name": e.method,
+                    "cat": scope_name(e.scope),
+                    "ph": "X",
+                    "ts": e.start.as_secs_f64() * 1e6,
+                    "dur": e.dur.as_secs_f64() * 1e6,
+                    "pid": 0,
+                    "tid": e.tid,
+                    "args": { "flops": e.flops, "bytes": e.bytes },
+                })
+            })
+            .collect();
+        serde_json::json!({ "traceEvents": events }).to_string()
+    }
+
+    pub fn write_chrome_trace(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.chrome_trace())
+    }
+
+    fn record(
+        &self,
+        method: &'static str,
+        flops: u64,
+        f: impl FnOnce() -> BackendResult<Tensor>,
+    ) -> BackendResult<Tensor> {
+        let start = Instant::now();
+        let out = f();
+        let dur = start.elapsed();
+        let bytes = out
+            .as_ref()
+            .map(|t| (t.numel() * std::mem::size_of::<f32>()) as u64)
+            .unwrap_or(0);
+        let event = Event {
+            method,
+            scope: SCOPE.with(|s| s.get()),
+            start: start - self.epoch,
+            dur,
+            flops,
+            bytes,
+            tid: THREAD_ID.with(|t| *t),
+        };
+        let mut profile = self.profile.lock().unwrap();
+        let entry = |name: String| ProfileEntry {
+            name,
+            ..ProfileEntry::default()
+        };
+        profile
+            .by_method
+            .entry(method)
+            .or_insert_with(|| entry(method.to_string()))
+            .add(&event);
+        profile
+            .by_op
+            .entry(event.scope)
+            .or_insert_with(|| entry(scope_name(event.scope)))
+            .add(&event);
+        if profile.events.len() < self.max_events {
+            profile.events.push(event);
+        } else {
+            profile.dropped += 1;
+        }
+        out
+    }
+
+    fn bind(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor> {
+        Tensor::from_vec(data, shape, Arc::new(self.clone()))
+            .map_err(|e| BackendError(e.to_string()))
+    }
+}
+
+fn n(t: &Tensor) -> u64 {
+    t.numel() as u64
+}
+
+/// 2 * M * K * N for [M, K] @ [K, N] (0 if not 2D).
+fn matmul_flops(a: &Tensor, b: &Tensor) -> u64 {
+    match (a.shape().dims(), b.shape().dims()) {
+        ([m, k], [_, n]) => 2 * (m * k * n) as u64,
+        _ => 0,
+    }
+}
+
+// FLOP estimates: 1 per element for element-wise ops and reductions, a few per element for
+// softmax and activation backward, 2MKN for matmul.
+impl<B: Backend + 'static> Backend for ProfilingBackend<B> {
+    fn device(&self) -> Device {
+        self.inner.device()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn alloc(&self, len: usize) -> Vec<f32> {
+        self.inner.alloc(len)
+    }
+
+    fn release(&self, buf: Vec<f32>) {
+        self.inner.release(buf);
+    }
+
+    fn alloc_stats(&self) -> Option<AllocStats> {
+        self.inner.alloc_stats()
+    }
+
+    fn matmul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.record("matmul", matmul_flops(a, b), || self.inner.matmul(a, b))
+    }
+
+    fn add(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.record("add", n(a), || self.inner.add(a, b))
+    }
+
+    fn mul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.record("mul", n(a), || self.inner.mul(a, b))
+    }
+
+    fn sub(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.record("sub", n(a), || self.inner.sub(a, b))
+    }
+
+    fn relu(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.record("relu", n(a), || self.inner.relu(a))
+    }
+
+    fn sum(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.record("sum", n(a), || self.inner.sum(a))
+    }
+
+    fn sum_dim(&self, a: &Tensor, dim: usize) -> BackendResult<Tensor> {
+        self.record("sum_dim", n(a), || self.inner.sum_dim(a, dim))
+    }
+
+    fn from_vec(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor> {
+        self.record("from_vec", 0, || self.bind(data, shape))
+    }
+
+    fn zeros(&self, shape: &Shape) -> BackendResult<Tensor> {
+        self.record("zeros", 0, || {
+            self.bind(self.inner.alloc(shape.numel()), shape.clone())
+        })
+    }
+
+    fn ones(&self, shape: &Shape) -> BackendResult<Tensor> {
+        self.record("ones", 0, || {
+            let mut data = self.inner.alloc(shape.numel());
+            data.fill(1.0);
+            self.bind(data, shape.clone())
+        })
+    }
+
+    fn sigmoid(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.record("sigmoid", n(a), || self.inner.sigmoid(a))
+    }
+
+    fn exp(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.record("exp", n(a), || self.inner.exp(a))
+    }
+
+    fn log(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.record("log", n(a), || self.inner.log(a))
+    }
+
+    fn add_broadcast(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.record("add_broadcast", n(a), || self.inner.add_broadcast(a, b))
+    }
+
+    fn transpose(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.record("transpose", 0, || self.inner.transpose(a))
+    }
+
+    fn scale(&self, a: &Tensor, s: f32) -> BackendResult<Tensor> {
+        self.record("scale", n(a), || self.inner.scale(a, s))
+    }
+
+    fn div(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.record("div", n(a), || self.inner.div(a, b))
+    }
+
+    fn relu_backward(&self, grad_out: &Tensor, input: &Tensor) -> BackendResult<Tensor> {
+        self.record("relu_backward", n(input), || {
+            self.inner.relu_backward(grad_out, input)
+        })
+    }
+
+    fn sigmoid_backward(&self, grad_out: &Tensor, fwd_output: &Tensor) -> BackendResult<Tensor> {
+        self.record("sigmoid_backward", 3 * n(fwd_output), || {
+            self.inner.sigmoid_backward(grad_out, fwd_output)
+        })
+    }
+
+    fn softmax_last_dim(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.record("softmax", 4 * n(a), || self.inner.softmax_last_dim(a))
+    }
+
+    fn softmax_backward(&self, grad_out: &Tensor, fwd_output: &Tensor) -> BackendResult<Tensor> {
+        self.record("softmax_backward", 4 * n(fwd_output), || {
+            self.inner.softmax_backward(grad_out, fwd_output)
+        })
+    }
+
+    fn linear(&self, x: &Tensor, w: &Tensor, b: &Tensor, act: Activation) -> BackendResult<Tensor> {
+        let rows = x.shape().dims().first().copied().unwrap_or(0) as u64;
+        let flops = matmul_flops(x, w) + 2 * rows * n(b);
+        self.record("linear", flops, || self.inner.linear(x, w, b, act))
+    }
+
+    fn im2col(&self, x: &Tensor, geom: &ConvGeometry) -> BackendResult<Tensor> {
+        self.record("im2col", 0, || self.inner.im2col(x, geom))
+    }
+
+    fn col2im(&self, cols: &Tensor, input: &Shape, geom: &ConvGeometry) -> BackendResult<Tensor> {
+        self.record("col2im", n(cols), || self.inner.col2im(cols, input, geom))
+    }
+}
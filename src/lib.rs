@@ -13,22 +13,33 @@ pub mod nn;
 pub mod ops;
 pub mod optimizer;
 pub mod parameter;
+pub mod quant;
 pub mod runtime;
 pub mod shape;
 pub mod state_io;
 pub mod tensor;
+pub mod trace;
 pub mod train;
 
-pub use autograd::{Graph, GraphError, GraphResult, NodeId};
-pub use backend::{cpu::CpuBackend, Backend, BackendError, BackendResult};
+pub use autograd::{CompileOptions, Graph, GraphError, GraphResult, NodeId, Plan};
+pub use backend::{
+    checked::CheckedBackend, cpu::CpuBackend, lazy::LazyBackend, parallel::ParallelCpuBackend,
+    profile::ProfilingBackend, AllocStats, Allocator, Backend, BackendError,
+    BackendResult, Capabilities, ConvGeometry, Device, DeviceKind, PoolAllocator, SystemAllocator,
+};
 pub use data::{DataLoader, Dataset, InMemoryDataset};
 pub use init::{he_uniform, xavier_uniform};
-pub use nn::{Linear, MLP2, Module, ReLU, Sigmoid, ce_graph, mse, mse_graph};
+pub use nn::{
+    ce_graph, checkpoint, mse, mse_graph, Checkpointed, Conv1d, Conv2d, Linear, LoadReport,
+    MLPBuilder, Module, ReLU, Sequential, Sigmoid, StateDict, MLP2,
+};
 pub use runtime::{set_seed, with_rng};
 pub use ops::{Op, OpId, OpRegistry, OpResult};
 pub use optimizer::{Adam, Optimizer, OptimizerError, SGD};
-pub use parameter::{Parameter, ParameterState};
+pub use parameter::{ParamId, Parameter, ParameterState, SparseGrad};
+pub use quant::{QuantError, QuantResult, QuantizedLinear, QuantizedMLP2};
 pub use shape::{Shape, ShapeError};
-pub use state_io::{load_state_dict, save_state_dict};
+pub use state_io::{load_named_state_dict, load_state_dict, save_named_state_dict, save_state_dict};
 pub use tensor::{Tensor, TensorError, TensorResult};
+pub use trace::{trace, TraceError, TraceResult, TracedProgram};
 pub use train::{Trainer, TrainError, TrainResult, TrainStepResult};
@@ -0,0 +1,320 @@
+//! Multi-head scaled dot-product attention on batch-first [B, T, E] inputs. Heads are split
+//! with reshape + permute into [B * heads, T, E / heads] and scored with
+//! [Graph::batch_matmul], so the whole computation is ordinary graph ops. Masks are added to
+//! the scores as a constant before the softmax.
+
+use super::module::{prefixed, Module};
+use super::{Dropout, Layer, Linear};
+use crate::autograd::{Graph, GraphError, GraphResult, NodeId};
+use crate::parameter::Parameter;
+use crate::shape::{Shape, ShapeError};
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use std::sync::Arc;
+
+/// Added to masked scores. Finite, so a fully masked row gives a uniform softmax, not NaN.
+const MASKED: f32 = -1e9;
+
+/// Which keys each query may attend to.
+#[derive(Clone, Default)]
+pub struct AttentionMask {
+    /// Query i does not see keys j > i (autoregressive decoding).
+    pub causal: bool,
+    /// [B, S]; a nonzero entry marks key s of batch b as padding.
+    pub key_padding: Option<Tensor>,
+}
+
+impl AttentionMask {
+    pub fn causal() -> Self {
+        AttentionMask {
+            causal: true,
+            key_padding: None,
+        }
+    }
+
+    pub fn with_key_padding(mut self, key_padding: Tensor) -> Self {
+        self.key_padding = Some(key_padding);
+        self
+    }
+
+    /// Additive [B * heads, T, S] scores mask, or None when nothing is masked.
+    fn scores(
+        &self,
+        (b, heads, t, s): (usize, usize, usize, usize),
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> GraphResult<Option<Tensor>> {
+        if let Some(pad) = &self.key_padding {
+            if pad.shape().dims() != [b, s] {
+                return Err(GraphError(format!(
+                    "attention: key padding mask {:?}, expected [{}, {}]",
+                    pad.shape().dims(),
+                    b,
+                    s
+                )));
+            }
+        }
+        if !self.causal && self.key_padding.is_none() {
+            return Ok(None);
+        }
+        let mut out = backend.alloc(b * heads * t * s);
+        for (n, rows) in out.chunks_mut(t * s).enumerate() {
+            let batch = n / heads;
+            for (i, row) in rows.chunks_mut(s).enumerate() {
+                for (j, v) in row.iter_mut().enumerate() {
+                    let padded = self
+                        .key_padding
+                        .as_ref()
+                        .is_some_and(|p| p.data()[batch * s + j] != 0.0);
+                    *v = if padded || (self.causal && j > i) {
+                        MASKED
+                    } else {
+                        0.0
+                    };
+                }
+            }
+        }
+        Tensor::from_vec(out, Shape::new(vec![b * heads, t, s]), backend)
+            .map(Some)
+            .map_err(|e| GraphError(e.to_string()))
+    }
+}
+
+/// Runs `build` on a fresh graph with `inputs` as vars and returns the output's value.
+pub(crate) fn evaluate(
+    inputs: &[&Tensor],
+    build: impl FnOnce(&mut Graph, &[NodeId]) -> GraphResult<NodeId>,
+) -> TensorResult<Tensor> {
+    let graph_error = |e: GraphError| TensorError::Shape(ShapeError(e.0));
+    let mut g = Graph::new();
+    let ids: Vec<NodeId> = inputs.iter().map(|&x| g.var(x.clone())).collect();
+    let out = build(&mut g, &ids).map_err(graph_error)?;
+    Ok(g.data(out).map_err(graph_error)?.clone())
+}
+
+/// Multi-head attention: softmax(q k^T / sqrt(E / heads)) v per head, with q, k, v and the
+/// merged heads each passed through a [E, E] projection.
+pub struct MultiheadAttention {
+    pub q_proj: Linear,
+    pub k_proj: Linear,
+    pub v_proj: Linear,
+    pub out_proj: Linear,
+    num_heads: usize,
+    causal: bool,
+    dropout: Dropout,
+}
+
+impl MultiheadAttention {
+    /// Errors unless `embed_dim` is a nonzero multiple of `num_heads`. No dropout on the
+    /// attention weights and no causal mask; projections are zeros, so call
+    /// [Self::init_xavier].
+    pub fn new(
+        embed_dim: usize,
+        num_heads: usize,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        if num_heads == 0 || embed_dim == 0 || !embed_dim.is_multiple_of(num_heads) {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "MultiheadAttention: embed_dim {} is not a multiple of {} heads",
+                embed_dim, num_heads
+            ))));
+        }
+        let proj = || Linear::new(embed_dim, embed_dim, backend.clone());
+        Ok(MultiheadAttention {
+            q_proj: proj()?,
+            k_proj: proj()?,
+            v_proj: proj()?,
+            out_proj: proj()?,
+            num_heads,
+            causal: false,
+            dropout: Dropout::new(0.0)?,
+        })
+    }
+
+    /// Dropout with probability `p` on the attention weights (training only).
+    pub fn with_dropout(mut self, p: f32) -> TensorResult<Self> {
+        self.dropout = Dropout::new(p)?;
+        Ok(self)
+    }
+
+    /// Always apply a causal mask, on top of any mask passed per call.
+    pub fn with_causal(mut self, causal: bool) -> Self {
+        self.causal = causal;
+        self
+    }
+
+    pub fn init_xavier(&mut self) -> TensorResult<()> {
+        for proj in self.projections_mut() {
+            proj.init_xavier()?;
+        }
+        Ok(())
+    }
+
+    pub fn embed_dim(&self) -> usize {
+        self.q_proj.weight.data().shape().dims()[0]
+    }
+
+    pub fn num_heads(&self) -> usize {
+        self.num_heads
+    }
+
+    pub fn is_causal(&self) -> bool {
+        self.causal
+    }
+
+    fn projections(&self) -> [(&'static str, &Linear); 4] {
+        [
+            ("q_proj", &self.q_proj),
+            ("k_proj", &self.k_proj),
+            ("v_proj", &self.v_proj),
+            ("out_proj", &self.out_proj),
+        ]
+    }
+
+    fn projections_mut(&mut self) -> [&mut Linear; 4] {
+        [
+            &mut self.q_proj,
+            &mut self.k_proj,
+            &mut self.v_proj,
+            &mut self.out_proj,
+        ]
+    }
+
+    /// [B, T, E] -> [B * heads, T, E / heads].
+    fn split_heads(&self, g: &mut Graph, x: NodeId, b: usize, t: usize) -> GraphResult<NodeId> {
+        let (h, e) = (self.num_heads, self.embed_dim());
+        let x = g.reshape(x, &[b, t, h, e / h])?;
+        let x = g.permute(x, &[0, 2, 1, 3])?;
+        g.reshape(x, &[b * h, t, e / h])
+    }
+
+    /// [B * heads, T, E / heads] -> [B, T, E].
+    fn merge_heads(&self, g: &mut Graph, x: NodeId, b: usize, t: usize) -> GraphResult<NodeId> {
+        let (h, e) = (self.num_heads, self.embed_dim());
+        let x = g.reshape(x, &[b, h, t, e / h])?;
+        let x = g.permute(x, &[0, 2, 1, 3])?;
+        g.reshape(x, &[b, t, e])
+    }
+
+    /// Attention of `query` [B, T, E] over `key` / `value` [B, S, E], giving [B, T, E].
+    /// Returns the output and the parameter nodes, as [Module::forward_graph] does.
+    pub fn attend_graph(
+        &self,
+        g: &mut Graph,
+        query: NodeId,
+        key: NodeId,
+        value: NodeId,
+        mask: &AttentionMask,
+    ) -> GraphResult<(NodeId, Vec<NodeId>)> {
+        let e = self.embed_dim();
+        let (qd, kd, vd) = (
+            g.data(query)?.shape().dims().to_vec(),
+            g.data(key)?.shape().dims().to_vec(),
+            g.data(value)?.shape().dims().to_vec(),
+        );
+        let valid = qd.len() == 3 && qd[2] == e && kd == vd && kd.len() == 3;
+        if !valid || kd[0] != qd[0] || kd[2] != e {
+            return Err(GraphError(format!(
+                "attention: query {:?}, key {:?}, value {:?}; expected [B, T, {}] and [B, S, {}]",
+                qd, kd, vd, e, e
+            )));
+        }
+        let (b, t, s) = (qd[0], qd[1], kd[1]);
+        let mut params = Vec::new();
+        let mut project = |g: &mut Graph, proj: &Linear, x: NodeId| {
+            let (y, p) = proj.forward_graph(g, x)?;
+            params.extend(p);
+            Ok::<_, GraphError>(y)
+        };
+        let q = project(g, &self.q_proj, query)?;
+        let k = project(g, &self.k_proj, key)?;
+        let v = project(g, &self.v_proj, value)?;
+        let q = self.split_heads(g, q, b, t)?;
+        let k = self.split_heads(g, k, b, s)?;
+        let v = self.split_heads(g, v, b, s)?;
+
+        let scale = 1.0 / ((e / self.num_heads) as f32).sqrt();
+        let mut scores = g.batch_matmul(q, k, true, scale)?;
+        let mask = AttentionMask {
+            causal: mask.causal || self.causal,
+            key_padding: mask.key_padding.clone(),
+        };
+        let backend = g.data(query)?.backend();
+        if let Some(m) = mask.scores((b, self.num_heads, t, s), backend)? {
+            let m = g.var(m);
+            scores = g.add(scores, m)?;
+        }
+        let weights = g.softmax(scores)?;
+        let (weights, _) = self.dropout.forward_graph(g, weights)?;
+        let context = g.batch_matmul(weights, v, false, 1.0)?;
+        let context = self.merge_heads(g, context, b, t)?;
+        let out = project(g, &self.out_proj, context)?;
+        Ok((out, params))
+    }
+
+    /// Tensor counterpart of [Self::attend_graph].
+    pub fn attend(
+        &self,
+        query: &Tensor,
+        key: &Tensor,
+        value: &Tensor,
+        mask: &AttentionMask,
+    ) -> TensorResult<Tensor> {
+        evaluate(&[query, key, value], |g, ids| {
+            Ok(self.attend_graph(g, ids[0], ids[1], ids[2], mask)?.0)
+        })
+    }
+}
+
+/// Self-attention: query, key and value are all the input.
+impl Module for MultiheadAttention {
+    fn parameters(&self) -> Vec<&Parameter> {
+        self.projections()
+            .into_iter()
+            .flat_map(|(_, p)| p.parameters())
+            .collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        self.projections_mut()
+            .into_iter()
+            .flat_map(|p| p.parameters_mut())
+            .collect()
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        self.projections()
+            .into_iter()
+            .flat_map(|(name, p)| prefixed(name, p.named_parameters()))
+            .collect()
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        let names = ["q_proj", "k_proj", "v_proj", "out_proj"];
+        self.projections_mut()
+            .into_iter()
+            .zip(names)
+            .flat_map(|(p, name)| prefixed(name, p.named_parameters_mut()))
+            .collect()
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.dropout.set_training(training);
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.dropout.is_deterministic()
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        self.attend(x, x, x, &AttentionMask::default())
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        self.attend_graph(g, x_id, x_id, x_id, &AttentionMask::default())
+    }
+}
+
+impl Layer for MultiheadAttention {}
@@ -0,0 +1,359 @@
+//! BatchNorm1d/2d: per-channel normalisation with learnable affine weight/bias and running
+//! mean/variance buffers. In training mode each batch is normalised with its own statistics
+//! and the running ones are updated; in eval mode the running statistics are used.
+
+use super::module::Module;
+use super::Layer;
+use crate::autograd::{Graph, NodeId};
+use crate::ops::batch_norm::{channel_stats, BatchNorm};
+use crate::parameter::Parameter;
+use crate::shape::{Shape, ShapeError};
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use std::sync::{Arc, Mutex};
+
+const DEFAULT_MOMENTUM: f32 = 0.1;
+const DEFAULT_EPS: f32 = 1e-5;
+
+/// Running statistics plus settings shared by the BatchNorm layers. The buffers sit behind a
+/// mutex because forward takes `&self` but updates them in training mode.
+struct Stats {
+    running: Mutex<(Tensor, Tensor)>,
+    momentum: f32,
+    eps: f32,
+    training: bool,
+}
+
+impl Stats {
+    fn new(num_features: usize, backend: &Arc<dyn crate::backend::Backend>) -> TensorResult<Self> {
+        let shape = Shape::new(vec![num_features]);
+        Ok(Stats {
+            running: Mutex::new((backend.zeros(&shape)?, backend.ones(&shape)?)),
+            momentum: DEFAULT_MOMENTUM,
+            eps: DEFAULT_EPS,
+            training: true,
+        })
+    }
+
+    /// The op for this call. In training mode also folds the batch statistics into the
+    /// running ones: running = (1 - momentum) * running + momentum * batch, with the
+    /// unbiased batch variance.
+    fn op(&self, x: &Tensor) -> TensorResult<BatchNorm> {
+        let mut running = self.running.lock().unwrap();
+        let channels = running.0.numel();
+        if x.shape().dims().get(1) != Some(&channels) {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "batch_norm: expected [N, {}, ...], got {:?}",
+                channels,
+                x.shape().dims()
+            ))));
+        }
+        if !self.training {
+            let (mean, var) = &*running;
+            return Ok(BatchNorm::with_stats(
+                self.eps,
+                mean.data().to_vec(),
+                var.data().to_vec(),
+            ));
+        }
+        let m = x.numel() / channels;
+        if m < 2 {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "batch_norm: need more than one value per channel in training mode, got {:?}",
+                x.shape().dims()
+            ))));
+        }
+        let (mean, var) = channel_stats(x)?;
+        let unbias = m as f32 / (m - 1) as f32;
+        let blend = |old: &Tensor, batch: &[f32], scale: f32| {
+            let data = old
+                .data()
+                .iter()
+                .zip(batch)
+                .map(|(o, b)| (1.0 - self.momentum) * o + self.momentum * b * scale)
+                .collect();
+            Tensor::from_vec(data, old.shape().clone(), old.backend())
+        };
+        *running = (
+            blend(&running.0, &mean, 1.0)?,
+            blend(&running.1, &var, unbias)?,
+        );
+        Ok(BatchNorm::training(self.eps))
+    }
+
+    fn buffers(&self) -> Vec<(String, Tensor)> {
+        let running = self.running.lock().unwrap();
+        vec![
+            ("running_mean".into(), running.0.clone()),
+            ("running_var".into(), running.1.clone()),
+        ]
+    }
+
+    fn set_buffer(&mut self, name: &str, value: Tensor) -> TensorResult<()> {
+        let running = self.running.get_mut().unwrap();
+        let slot = match name {
+            "running_mean" => &mut running.0,
+            "running_var" => &mut running.1,
+            _ => {
+                return Err(TensorError::Shape(ShapeError(format!(
+                    "no buffer named {:?}",
+                    name
+                ))))
+            }
+        };
+        if value.shape().dims() != slot.shape().dims() {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "buffer {}: expected {:?}, got {:?}",
+                name,
+                slot.shape().dims(),
+                value.shape().dims()
+            ))));
+        }
+        *slot = value;
+        Ok(())
+    }
+}
+
+/// Affine parameters: weight (scale) ones, bias zeros.
+fn affine(
+    num_features: usize,
+    backend: &Arc<dyn crate::backend::Backend>,
+) -> TensorResult<(Parameter, Parameter)> {
+    let shape = Shape::new(vec![num_features]);
+    Ok((
+        Parameter::new(backend.ones(&shape)?),
+        Parameter::new(backend.zeros(&shape)?),
+    ))
+}
+
+fn check_rank(name: &str, x: &Tensor, ranks: &[usize]) -> TensorResult<()> {
+    if ranks.contains(&x.shape().rank()) {
+        return Ok(());
+    }
+    Err(TensorError::Shape(ShapeError(format!(
+        "{}: unsupported input {:?}",
+        name,
+        x.shape().dims()
+    ))))
+}
+
+fn bn_forward(
+    stats: &Stats,
+    weight: &Parameter,
+    bias: &Parameter,
+    x: &Tensor,
+) -> TensorResult<Tensor> {
+    stats.op(x)?.run(x, weight.data(), bias.data())
+}
+
+fn bn_forward_graph(
+    stats: &Stats,
+    weight: &Parameter,
+    bias: &Parameter,
+    g: &mut Graph,
+    x_id: NodeId,
+) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+    let op = stats
+        .op(g.data(x_id)?)
+        .map_err(|e| crate::GraphError(e.to_string()))?;
+    let w_id = g.param(weight);
+    let b_id = g.param(bias);
+    let out_id = g.apply_op(Arc::new(op), &[x_id, w_id, b_id])?;
+    Ok((out_id, vec![w_id, b_id]))
+}
+
+/// Batch normalisation over [N, C] or [N, C, L], per channel C.
+pub struct BatchNorm1d {
+    pub weight: Parameter,
+    pub bias: Parameter,
+    stats: Stats,
+}
+
+impl BatchNorm1d {
+    /// Momentum 0.1 and eps 1e-5; starts in training mode with running mean 0 and var 1.
+    pub fn new(
+        num_features: usize,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        let (weight, bias) = affine(num_features, &backend)?;
+        Ok(BatchNorm1d {
+            weight,
+            bias,
+            stats: Stats::new(num_features, &backend)?,
+        })
+    }
+
+    /// Weight of each new batch in the running statistics.
+    pub fn with_momentum(mut self, momentum: f32) -> Self {
+        self.stats.momentum = momentum;
+        self
+    }
+
+    pub fn with_eps(mut self, eps: f32) -> Self {
+        self.stats.eps = eps;
+        self
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.stats.training
+    }
+
+    pub fn running_mean(&self) -> Tensor {
+        self.stats.running.lock().unwrap().0.clone()
+    }
+
+    pub fn running_var(&self) -> Tensor {
+        self.stats.running.lock().unwrap().1.clone()
+    }
+}
+
+/// Batch normalisation over [N, C, H, W], per channel C.
+pub struct BatchNorm2d {
+    pub weight: Parameter,
+    pub bias: Parameter,
+    stats: Stats,
+}
+
+impl BatchNorm2d {
+    /// Momentum 0.1 and eps 1e-5; starts in training mode with running mean 0 and var 1.
+    pub fn new(
+        num_features: usize,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        let (weight, bias) = affine(num_features, &backend)?;
+        Ok(BatchNorm2d {
+            weight,
+            bias,
+            stats: Stats::new(num_features, &backend)?,
+        })
+    }
+
+    /// Weight of each new batch in the running statistics.
+    pub fn with_momentum(mut self, momentum: f32) -> Self {
+        self.stats.momentum = momentum;
+        self
+    }
+
+    pub fn with_eps(mut self, eps: f32) -> Self {
+        self.stats.eps = eps;
+        self
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.stats.training
+    }
+
+    pub fn running_mean(&self) -> Tensor {
+        self.stats.running.lock().unwrap().0.clone()
+    }
+
+    pub fn running_var(&self) -> Tensor {
+        self.stats.running.lock().unwrap().1.clone()
+    }
+}
+
+impl Module for BatchNorm1d {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![&self.weight, &self.bias]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![&mut self.weight, &mut self.bias]
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        vec![("weight".into(), &self.weight), ("bias".into(), &self.bias)]
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        vec![
+            ("weight".into(), &mut self.weight),
+            ("bias".into(), &mut self.bias),
+        ]
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.stats.training = training;
+    }
+
+    fn is_deterministic(&self) -> bool {
+        !self.stats.training
+    }
+
+    fn named_buffers(&self) -> Vec<(String, Tensor)> {
+        self.stats.buffers()
+    }
+
+    fn set_buffer(&mut self, name: &str, value: Tensor) -> TensorResult<()> {
+        self.stats.set_buffer(name, value)
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        check_rank("BatchNorm1d", x, &[2, 3])?;
+        bn_forward(&self.stats, &self.weight, &self.bias, x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        check_rank("BatchNorm1d", g.data(x_id)?, &[2, 3])
+            .map_err(|e| crate::GraphError(e.to_string()))?;
+        bn_forward_graph(&self.stats, &self.weight, &self.bias, g, x_id)
+    }
+}
+
+impl Module for BatchNorm2d {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![&self.weight, &self.bias]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![&mut self.weight, &mut self.bias]
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        vec![("weight".into(), &self.weight), ("bias".into(), &self.bias)]
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        vec![
+            ("weight".into(), &mut self.weight),
+            ("bias".into(), &mut self.bias),
+        ]
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.stats.training = training;
+    }
+
+    fn is_deterministic(&self) -> bool {
+        !self.stats.training
+    }
+
+    fn named_buffers(&self) -> Vec<(String, Tensor)> {
+        self.stats.buffers()
+    }
+
+    fn set_buffer(&mut self, name: &str, value: Tensor) -> TensorResult<()> {
+        self.stats.set_buffer(name, value)
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        check_rank("BatchNorm2d", x, &[4])?;
+        bn_forward(&self.stats, &self.weight, &self.bias, x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        check_rank("BatchNorm2d", g.data(x_id)?, &[4])
+            .map_err(|e| crate::GraphError(e.to_string()))?;
+        bn_forward_graph(&self.stats, &self.weight, &self.bias, g, x_id)
+    }
+}
+
+impl Layer for BatchNorm1d {}
+impl Layer for BatchNorm2d {}
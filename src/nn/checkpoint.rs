@@ -0,0 +1,77 @@
+//! Activation checkpointing for modules: keep only segment inputs during forward and
+//! recompute the segment in backward. Trades compute for activation memory.
+
+use super::module::Module;
+use super::Layer;
+use crate::autograd::{Graph, NodeId};
+use crate::parameter::Parameter;
+use crate::tensor::Tensor;
+
+/// Run `module.forward_graph` as a checkpointed segment of `g`.
+/// Returns (output_node_id, param_node_ids) like [Module::forward_graph].
+pub fn checkpoint<M: Module + ?Sized>(
+    g: &mut Graph,
+    module: &M,
+    x_id: NodeId,
+) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+    g.checkpoint_with(x_id, |sub, sub_x| module.forward_graph(sub, sub_x))
+}
+
+/// Wraps a module so its `forward_graph` is always checkpointed.
+pub struct Checkpointed<M> {
+    pub inner: M,
+}
+
+impl<M: Module> Checkpointed<M> {
+    pub fn new(inner: M) -> Self {
+        Checkpointed { inner }
+    }
+}
+
+impl<M: Module> Module for Checkpointed<M> {
+    fn parameters(&self) -> Vec<&Parameter> {
+        self.inner.parameters()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        self.inner.parameters_mut()
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        self.inner.named_parameters()
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        self.inner.named_parameters_mut()
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.inner.set_training(training)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.inner.is_deterministic()
+    }
+
+    fn named_buffers(&self) -> Vec<(String, Tensor)> {
+        self.inner.named_buffers()
+    }
+
+    fn set_buffer(&mut self, name: &str, value: Tensor) -> crate::TensorResult<()> {
+        self.inner.set_buffer(name, value)
+    }
+
+    fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
+        self.inner.forward(x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        checkpoint(g, &self.inner, x_id)
+    }
+}
+
+impl<M: Module> Layer for Checkpointed<M> {}
@@ -0,0 +1,329 @@
+//! Conv1d / Conv2d: grouped convolutions over [N, C, L] / [N, C, H, W], computed with
+//! im2col + matmul ([crate::ops::conv::Conv]).
+
+use super::module::Module;
+use super::Layer;
+use crate::autograd::{Graph, NodeId};
+use crate::backend::{Backend, ConvGeometry};
+use crate::ops::conv::Conv;
+use crate::parameter::Parameter;
+use crate::shape::Shape;
+use crate::tensor::{Tensor, TensorError};
+use std::sync::Arc;
+
+/// Stride, padding, dilation, groups and bias of a [Conv2d], as (height, width) pairs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conv2dOptions {
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
+    pub dilation: (usize, usize),
+    /// Input and output channels are split into this many independent groups.
+    pub groups: usize,
+    pub bias: bool,
+}
+
+impl Default for Conv2dOptions {
+    fn default() -> Self {
+        Conv2dOptions {
+            stride: (1, 1),
+            padding: (0, 0),
+            dilation: (1, 1),
+            groups: 1,
+            bias: true,
+        }
+    }
+}
+
+/// Stride, padding, dilation, groups and bias of a [Conv1d].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conv1dOptions {
+    pub stride: usize,
+    pub padding: usize,
+    pub dilation: usize,
+    pub groups: usize,
+    pub bias: bool,
+}
+
+impl Default for Conv1dOptions {
+    fn default() -> Self {
+        Conv1dOptions {
+            stride: 1,
+            padding: 0,
+            dilation: 1,
+            groups: 1,
+            bias: true,
+        }
+    }
+}
+
+/// 2D convolution: [N, C_in, H, W] -> [N, C_out, OH, OW]. Weight [C_out, C_in/groups, KH, KW].
+pub struct Conv2d {
+    pub weight: Parameter,
+    pub bias: Option<Parameter>,
+    op: Arc<Conv>,
+}
+
+/// 1D convolution: [N, C_in, L] -> [N, C_out, OL]. Weight [C_out, C_in/groups, K].
+pub struct Conv1d {
+    pub weight: Parameter,
+    pub bias: Option<Parameter>,
+    op: Arc<Conv>,
+}
+
+/// Zero weight and bias for a convolution; weight dims after [C_out, C_in/groups].
+fn conv_params(
+    in_channels: usize,
+    out_channels: usize,
+    kernel: &[usize],
+    groups: usize,
+    bias: bool,
+    backend: &Arc<dyn Backend>,
+) -> crate::TensorResult<(Parameter, Option<Parameter>)> {
+    if groups == 0 || !in_channels.is_multiple_of(groups) || !out_channels.is_multiple_of(groups) {
+        return Err(TensorError::Shape(crate::ShapeError(format!(
+            "conv: {} in / {} out channels are not divisible into {} groups",
+            in_channels, out_channels, groups
+        ))));
+    }
+    let mut dims = vec![out_channels, in_channels / groups];
+    dims.extend(kernel);
+    let weight = backend.zeros(&Shape::new(dims))?;
+    let bias = if bias {
+        Some(Parameter::new(
+            backend.zeros(&Shape::new(vec![out_channels]))?,
+        ))
+    } else {
+        None
+    };
+    Ok((Parameter::new(weight), bias))
+}
+
+/// He uniform over fan_in = C_in/groups * kernel size (the per-output-channel dot length).
+fn init_he(weight: &mut Parameter) -> crate::TensorResult<()> {
+    let w = weight.data();
+    let out_channels = w.shape().dims()[0];
+    let fan_in = w.numel() / out_channels.max(1);
+    let drawn = crate::he_uniform(&Shape::new(vec![fan_in, out_channels]), w.backend())?;
+    let init = Tensor::from_vec(drawn.data().to_vec(), w.shape().clone(), w.backend())?;
+    *weight.data_mut() = init;
+    Ok(())
+}
+
+fn conv_parameters<'a>(weight: &'a Parameter, bias: &'a Option<Parameter>) -> Vec<&'a Parameter> {
+    std::iter::once(weight).chain(bias.as_ref()).collect()
+}
+
+fn conv_parameters_mut<'a>(
+    weight: &'a mut Parameter,
+    bias: &'a mut Option<Parameter>,
+) -> Vec<&'a mut Parameter> {
+    std::iter::once(weight).chain(bias.as_mut()).collect()
+}
+
+fn conv_named<'a>(
+    weight: &'a Parameter,
+    bias: &'a Option<Parameter>,
+) -> Vec<(String, &'a Parameter)> {
+    std::iter::once(("weight".to_string(), weight))
+        .chain(bias.as_ref().map(|b| ("bias".to_string(), b)))
+        .collect()
+}
+
+fn conv_named_mut<'a>(
+    weight: &'a mut Parameter,
+    bias: &'a mut Option<Parameter>,
+) -> Vec<(String, &'a mut Parameter)> {
+    std::iter::once(("weight".to_string(), weight))
+        .chain(bias.as_mut().map(|b| ("bias".to_string(), b)))
+        .collect()
+}
+
+fn conv_forward_graph(
+    op: &Arc<Conv>,
+    weight: &Parameter,
+    bias: &Option<Parameter>,
+    g: &mut Graph,
+    x_id: NodeId,
+) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+    let w_id = g.param(weight);
+    let b_id = bias.as_ref().map(|b| g.param(b));
+    let inputs: Vec<NodeId> = [x_id, w_id].into_iter().chain(b_id).collect();
+    let out_id = g.apply_op(op.clone(), &inputs)?;
+    Ok((out_id, inputs[1..].to_vec()))
+}
+
+impl Conv2d {
+    /// Stride 1, no padding, one group, with bias. Weights are zeros; see [Self::init_he].
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        kernel: (usize, usize),
+        backend: Arc<dyn Backend>,
+    ) -> crate::TensorResult<Self> {
+        Self::with_options(
+            in_channels,
+            out_channels,
+            kernel,
+            Conv2dOptions::default(),
+            backend,
+        )
+    }
+
+    pub fn with_options(
+        in_channels: usize,
+        out_channels: usize,
+        kernel: (usize, usize),
+        options: Conv2dOptions,
+        backend: Arc<dyn Backend>,
+    ) -> crate::TensorResult<Self> {
+        let (weight, bias) = conv_params(
+            in_channels,
+            out_channels,
+            &[kernel.0, kernel.1],
+            options.groups,
+            options.bias,
+            &backend,
+        )?;
+        let geom = ConvGeometry::new(kernel)
+            .with_stride(options.stride)
+            .with_padding(options.padding)
+            .with_dilation(options.dilation);
+        Ok(Conv2d {
+            weight,
+            bias,
+            op: Arc::new(Conv::new(geom, options.groups)),
+        })
+    }
+
+    /// He uniform weights (for ReLU networks); bias stays zero.
+    pub fn init_he(&mut self) -> crate::TensorResult<()> {
+        init_he(&mut self.weight)
+    }
+
+    pub fn geometry(&self) -> ConvGeometry {
+        self.op.geometry()
+    }
+
+    pub fn groups(&self) -> usize {
+        self.op.groups()
+    }
+}
+
+impl Conv1d {
+    /// Stride 1, no padding, one group, with bias. Weights are zeros; see [Self::init_he].
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        kernel: usize,
+        backend: Arc<dyn Backend>,
+    ) -> crate::TensorResult<Self> {
+        Self::with_options(
+            in_channels,
+            out_channels,
+            kernel,
+            Conv1dOptions::default(),
+            backend,
+        )
+    }
+
+    pub fn with_options(
+        in_channels: usize,
+        out_channels: usize,
+        kernel: usize,
+        options: Conv1dOptions,
+        backend: Arc<dyn Backend>,
+    ) -> crate::TensorResult<Self> {
+        let (weight, bias) = conv_params(
+            in_channels,
+            out_channels,
+            &[kernel],
+            options.groups,
+            options.bias,
+            &backend,
+        )?;
+        let geom = ConvGeometry::conv1d(kernel, options.stride, options.padding, options.dilation);
+        Ok(Conv1d {
+            weight,
+            bias,
+            op: Arc::new(Conv::new(geom, options.groups)),
+        })
+    }
+
+    /// He uniform weights (for ReLU networks); bias stays zero.
+    pub fn init_he(&mut self) -> crate::TensorResult<()> {
+        init_he(&mut self.weight)
+    }
+
+    pub fn geometry(&self) -> ConvGeometry {
+        self.op.geometry()
+    }
+
+    pub fn groups(&self) -> usize {
+        self.op.groups()
+    }
+}
+
+impl Module for Conv2d {
+    fn parameters(&self) -> Vec<&Parameter> {
+        conv_parameters(&self.weight, &self.bias)
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        conv_parameters_mut(&mut self.weight, &mut self.bias)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        conv_named(&self.weight, &self.bias)
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        conv_named_mut(&mut self.weight, &mut self.bias)
+    }
+
+    fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
+        self.op
+            .run(x, self.weight.data(), self.bias.as_ref().map(|b| b.data()))
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        conv_forward_graph(&self.op, &self.weight, &self.bias, g, x_id)
+    }
+}
+
+impl Module for Conv1d {
+    fn parameters(&self) -> Vec<&Parameter> {
+        conv_parameters(&self.weight, &self.bias)
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        conv_parameters_mut(&mut self.weight, &mut self.bias)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        conv_named(&self.weight, &self.bias)
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        conv_named_mut(&mut self.weight, &mut self.bias)
+    }
+
+    fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
+        self.op
+            .run(x, self.weight.data(), self.bias.as_ref().map(|b| b.data()))
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        conv_forward_graph(&self.op, &self.weight, &self.bias, g, x_id)
+    }
+}
+
+impl Layer for Conv2d {}
+impl Layer for Conv1d {}
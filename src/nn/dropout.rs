@@ -0,0 +1,262 @@
+//! Stochastic regularisation: Dropout, AlphaDropout and DropPath (stochastic depth). Masks
+//! are drawn from [crate::runtime::with_rng], so [crate::set_seed] makes runs reproducible,
+//! and each call's mask is kept in its graph op for backward. All are the identity in
+//! evaluation mode (see [Module::set_training]).
+
+use super::module::Module;
+use super::Layer;
+use crate::autograd::{Graph, NodeId};
+use crate::ops::dropout::Dropout as DropoutOp;
+use crate::parameter::Parameter;
+use crate::runtime::with_rng;
+use crate::shape::ShapeError;
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use rand::Rng;
+use std::sync::Arc;
+
+/// SELU's negative saturation value, -lambda * alpha.
+const SELU_SATURATION: f32 = -1.050_701 * 1.673_263_2;
+
+fn check_p(name: &str, p: f32) -> TensorResult<f32> {
+    if (0.0..=1.0).contains(&p) {
+        return Ok(p);
+    }
+    Err(TensorError::Shape(ShapeError(format!(
+        "{}: drop probability {} not in [0, 1]",
+        name, p
+    ))))
+}
+
+/// `n` keep decisions, each true with probability 1 - p.
+fn keep_mask(n: usize, p: f32) -> Vec<bool> {
+    with_rng(|rng| (0..n).map(|_| rng.gen::<f32>() >= p).collect())
+}
+
+/// Inverted-dropout scale: kept values are divided by the keep probability.
+fn inverted(keep: &[bool], p: f32) -> Vec<f32> {
+    let scale = if p < 1.0 { 1.0 / (1.0 - p) } else { 0.0 };
+    keep.iter().map(|&k| if k { scale } else { 0.0 }).collect()
+}
+
+fn dropout_forward(op: Option<DropoutOp>, x: &Tensor) -> TensorResult<Tensor> {
+    match op {
+        Some(op) => op.run(x),
+        None => Ok(x.clone()),
+    }
+}
+
+fn dropout_forward_graph(
+    op: Option<DropoutOp>,
+    g: &mut Graph,
+    x_id: NodeId,
+) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+    match op {
+        Some(op) => Ok((g.apply_op(Arc::new(op), &[x_id])?, vec![])),
+        None => Ok((x_id, vec![])),
+    }
+}
+
+/// Zeroes each element with probability p in training and scales the rest by 1 / (1 - p),
+/// so evaluation needs no rescaling.
+pub struct Dropout {
+    p: f32,
+    training: bool,
+}
+
+impl Dropout {
+    /// Errors unless 0 <= p <= 1.
+    pub fn new(p: f32) -> TensorResult<Self> {
+        Ok(Dropout {
+            p: check_p("Dropout", p)?,
+            training: true,
+        })
+    }
+
+    pub fn p(&self) -> f32 {
+        self.p
+    }
+
+    /// None when this call is the identity.
+    fn op(&self, x: &Tensor) -> Option<DropoutOp> {
+        if !self.training || self.p == 0.0 {
+            return None;
+        }
+        let keep = keep_mask(x.numel(), self.p);
+        Some(DropoutOp::new(inverted(&keep, self.p), None))
+    }
+}
+
+/// Dropout for self-normalising (SELU) networks: dropped values are set to SELU's negative
+/// saturation and an affine correction keeps zero mean and unit variance.
+pub struct AlphaDropout {
+    p: f32,
+    training: bool,
+}
+
+impl AlphaDropout {
+    /// Errors unless 0 <= p < 1: at p = 1 every value is dropped, and the output would be
+    /// the constant that has zero mean, i.e. no signal at all (the affine scale is infinite).
+    pub fn new(p: f32) -> TensorResult<Self> {
+        let p = check_p("AlphaDropout", p)?;
+        if p == 1.0 {
+            return Err(TensorError::Shape(ShapeError(
+                "AlphaDropout: drop probability must be below 1".into(),
+            )));
+        }
+        Ok(AlphaDropout { p, training: true })
+    }
+
+    pub fn p(&self) -> f32 {
+        self.p
+    }
+
+    /// y = a * (x * m + alpha' * (1 - m)) + b with a = (q + alpha'^2 q p)^-1/2 and
+    /// b = -a * alpha' * p, for keep probability q = 1 - p.
+    fn op(&self, x: &Tensor) -> Option<DropoutOp> {
+        if !self.training || self.p == 0.0 {
+            return None;
+        }
+        let (p, q, alpha) = (self.p, 1.0 - self.p, SELU_SATURATION);
+        let a = (q + alpha * alpha * q * p).powf(-0.5);
+        let b = -a * alpha * p;
+        let keep = keep_mask(x.numel(), p);
+        let scale = keep.iter().map(|&k| if k { a } else { 0.0 }).collect();
+        let shift = keep
+            .iter()
+            .map(|&k| if k { b } else { a * alpha + b })
+            .collect();
+        Some(DropoutOp::new(scale, Some(shift)))
+    }
+}
+
+/// Stochastic depth: drops the whole input of a sample (dim 0) with probability p and scales
+/// kept samples by 1 / (1 - p). Meant for residual branches, `x + DropPath(f(x))`.
+pub struct DropPath {
+    p: f32,
+    training: bool,
+}
+
+impl DropPath {
+    /// Errors unless 0 <= p <= 1.
+    pub fn new(p: f32) -> TensorResult<Self> {
+        Ok(DropPath {
+            p: check_p("DropPath", p)?,
+            training: true,
+        })
+    }
+
+    pub fn p(&self) -> f32 {
+        self.p
+    }
+
+    fn op(&self, x: &Tensor) -> Option<DropoutOp> {
+        if !self.training || self.p == 0.0 {
+            return None;
+        }
+        let samples = x.shape().dims().first().copied().unwrap_or(1).max(1);
+        let per_sample = inverted(&keep_mask(samples, self.p), self.p);
+        let n = x.numel() / samples;
+        let scale = per_sample
+            .iter()
+            .flat_map(|&s| std::iter::repeat_n(s, n))
+            .collect();
+        Some(DropoutOp::new(scale, None))
+    }
+}
+
+impl Module for Dropout {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![]
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    fn is_deterministic(&self) -> bool {
+        !self.training || self.p == 0.0
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        dropout_forward(self.op(x), x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let op = self.op(g.data(x_id)?);
+        dropout_forward_graph(op, g, x_id)
+    }
+}
+
+impl Module for AlphaDropout {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![]
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    fn is_deterministic(&self) -> bool {
+        !self.training || self.p == 0.0
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        dropout_forward(self.op(x), x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let op = self.op(g.data(x_id)?);
+        dropout_forward_graph(op, g, x_id)
+    }
+}
+
+impl Module for DropPath {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![]
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    fn is_deterministic(&self) -> bool {
+        !self.training || self.p == 0.0
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        dropout_forward(self.op(x), x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let op = self.op(g.data(x_id)?);
+        dropout_forward_graph(op, g, x_id)
+    }
+}
+
+impl Layer for Dropout {}
+impl Layer for AlphaDropout {}
+impl Layer for DropPath {}
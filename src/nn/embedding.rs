@@ -0,0 +1,319 @@
+//! Embedding and EmbeddingBag: lookup tables indexed by integer ids. Indices are passed as a
+//! tensor of whole numbers (exact in f32 up to 2^24). forward_graph binds only the rows a
+//! batch touches ([Graph::param_rows]), so the weight receives a [crate::SparseGrad] and the
+//! optimizers update just those rows, whatever the vocabulary size.
+
+use super::module::Module;
+use super::Layer;
+use crate::autograd::{Graph, NodeId};
+use crate::ops::embedding::Embedding as EmbeddingOp;
+use crate::parameter::Parameter;
+use crate::runtime::with_rng;
+use crate::shape::{Shape, ShapeError};
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub use crate::ops::embedding::BagMode;
+
+/// Options shared by [Embedding] and [EmbeddingBag].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EmbeddingOptions {
+    /// Index that looks up zeros and never receives gradient (skipped inside bags).
+    pub padding_idx: Option<usize>,
+    /// Looked-up rows with a larger L2 norm are rescaled to this norm before use. The scale
+    /// is not differentiated and the stored weight is left as is.
+    pub max_norm: Option<f32>,
+}
+
+/// One call's lookups: the distinct rows touched and, per index, its slot among them.
+struct Lookups {
+    rows: Vec<usize>,
+    slots: Vec<Option<usize>>,
+}
+
+fn table(
+    num_embeddings: usize,
+    dim: usize,
+    options: &EmbeddingOptions,
+    backend: Arc<dyn crate::backend::Backend>,
+) -> TensorResult<Parameter> {
+    if options.padding_idx.is_some_and(|p| p >= num_embeddings) {
+        return Err(TensorError::Shape(ShapeError(format!(
+            "embedding: padding_idx {:?} outside {} embeddings",
+            options.padding_idx, num_embeddings
+        ))));
+    }
+    Ok(Parameter::new(
+        backend.zeros(&Shape::new(vec![num_embeddings, dim]))?,
+    ))
+}
+
+/// Uniform(-bound, bound) weights, with the padding row (if any) zero.
+fn init_uniform(
+    weight: &mut Parameter,
+    options: &EmbeddingOptions,
+    bound: f32,
+) -> TensorResult<()> {
+    let shape = weight.data().shape().clone();
+    let dim = shape.dims()[1];
+    let mut data: Vec<f32> = with_rng(|rng| {
+        (0..shape.numel())
+            .map(|_| rng.gen_range(-bound..=bound))
+            .collect()
+    });
+    if let Some(p) = options.padding_idx {
+        data[p * dim..(p + 1) * dim].fill(0.0);
+    }
+    *weight.data_mut() = Tensor::from_vec(data, shape, weight.data().backend())?;
+    Ok(())
+}
+
+fn lookups(
+    weight: &Parameter,
+    options: &EmbeddingOptions,
+    indices: &Tensor,
+) -> TensorResult<Lookups> {
+    let num = weight.data().shape().dims()[0];
+    let mut rows = Vec::new();
+    let mut seen = HashMap::new();
+    let mut slots = Vec::with_capacity(indices.numel());
+    for &v in indices.data() {
+        if !(v >= 0.0 && v.fract() == 0.0 && (v as usize) < num) {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "embedding: index {} is not in 0..{}",
+                v, num
+            ))));
+        }
+        let idx = v as usize;
+        if options.padding_idx == Some(idx) {
+            slots.push(None);
+            continue;
+        }
+        let slot = *seen.entry(idx).or_insert_with(|| {
+            rows.push(idx);
+            rows.len() - 1
+        });
+        slots.push(Some(slot));
+    }
+    Ok(Lookups { rows, slots })
+}
+
+/// The touched rows as [rows, D], max-norm rescaled.
+fn gather(weight: &Parameter, options: &EmbeddingOptions, rows: &[usize]) -> TensorResult<Tensor> {
+    let data = weight.data();
+    let dim = data.shape().dims()[1];
+    let mut out = data.backend().alloc(rows.len() * dim);
+    for (dst, &r) in out.chunks_mut(dim.max(1)).zip(rows) {
+        dst.copy_from_slice(&data.data()[r * dim..(r + 1) * dim]);
+        if let Some(max_norm) = options.max_norm {
+            let norm = dst.iter().map(|v| v * v).sum::<f32>().sqrt();
+            if norm > max_norm {
+                let scale = max_norm / (norm + 1e-7);
+                dst.iter_mut().for_each(|v| *v *= scale);
+            }
+        }
+    }
+    Tensor::from_vec(out, Shape::new(vec![rows.len(), dim]), data.backend())
+}
+
+fn embed_forward(
+    weight: &Parameter,
+    options: &EmbeddingOptions,
+    indices: &Tensor,
+    op: impl FnOnce(Vec<Option<usize>>) -> TensorResult<EmbeddingOp>,
+) -> TensorResult<Tensor> {
+    let l = lookups(weight, options, indices)?;
+    let rows = gather(weight, options, &l.rows)?;
+    op(l.slots)?.run(&rows)
+}
+
+fn embed_forward_graph(
+    weight: &Parameter,
+    options: &EmbeddingOptions,
+    g: &mut Graph,
+    x_id: NodeId,
+    op: impl FnOnce(Vec<Option<usize>>) -> TensorResult<EmbeddingOp>,
+) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+    let to_graph = |e: TensorError| crate::GraphError(e.to_string());
+    let l = lookups(weight, options, g.data(x_id)?).map_err(to_graph)?;
+    let rows = gather(weight, options, &l.rows).map_err(to_graph)?;
+    let op = op(l.slots).map_err(to_graph)?;
+    let rows_id = g.bind_param_rows(weight.id(), rows, l.rows);
+    let out_id = g.apply_op(Arc::new(op), &[rows_id])?;
+    Ok((out_id, vec![rows_id]))
+}
+
+/// Lookup table [num_embeddings, dim]: indices of any shape [...] give [..., dim].
+pub struct Embedding {
+    pub weight: Parameter,
+    options: EmbeddingOptions,
+}
+
+impl Embedding {
+    /// Weights are zeros by default; use [Self::init_uniform] to init.
+    pub fn new(
+        num_embeddings: usize,
+        dim: usize,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        Self::with_options(num_embeddings, dim, EmbeddingOptions::default(), backend)
+    }
+
+    pub fn with_options(
+        num_embeddings: usize,
+        dim: usize,
+        options: EmbeddingOptions,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        Ok(Embedding {
+            weight: table(num_embeddings, dim, &options, backend)?,
+            options,
+        })
+    }
+
+    /// Draw weights from Uniform(-bound, bound); the padding row stays zero.
+    pub fn init_uniform(&mut self, bound: f32) -> TensorResult<()> {
+        init_uniform(&mut self.weight, &self.options, bound)
+    }
+
+    pub fn options(&self) -> EmbeddingOptions {
+        self.options
+    }
+}
+
+/// Embedding lookups pooled per bag: indices [B, L] (L ids per bag; pad shorter bags with
+/// `padding_idx`) give [B, dim].
+pub struct EmbeddingBag {
+    pub weight: Parameter,
+    mode: BagMode,
+    options: EmbeddingOptions,
+}
+
+impl EmbeddingBag {
+    /// Weights are zeros by default; use [Self::init_uniform] to init.
+    pub fn new(
+        num_embeddings: usize,
+        dim: usize,
+        mode: BagMode,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        Self::with_options(
+            num_embeddings,
+            dim,
+            mode,
+            EmbeddingOptions::default(),
+            backend,
+        )
+    }
+
+    pub fn with_options(
+        num_embeddings: usize,
+        dim: usize,
+        mode: BagMode,
+        options: EmbeddingOptions,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        Ok(EmbeddingBag {
+            weight: table(num_embeddings, dim, &options, backend)?,
+            mode,
+            options,
+        })
+    }
+
+    /// Draw weights from Uniform(-bound, bound); the padding row stays zero.
+    pub fn init_uniform(&mut self, bound: f32) -> TensorResult<()> {
+        init_uniform(&mut self.weight, &self.options, bound)
+    }
+
+    pub fn mode(&self) -> BagMode {
+        self.mode
+    }
+
+    pub fn options(&self) -> EmbeddingOptions {
+        self.options
+    }
+
+    fn op(&self, indices: &Tensor) -> impl FnOnce(Vec<Option<usize>>) -> TensorResult<EmbeddingOp> {
+        let dims = indices.shape().dims().to_vec();
+        let mode = self.mode;
+        move |slots| match dims[..] {
+            [_, len] => Ok(EmbeddingOp::bags(slots, len, mode)),
+            _ => Err(TensorError::Shape(ShapeError(format!(
+                "EmbeddingBag: expected indices [B, L], got {:?}",
+                dims
+            )))),
+        }
+    }
+}
+
+impl Module for Embedding {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![&self.weight]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![&mut self.weight]
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        vec![("weight".into(), &self.weight)]
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        vec![("weight".into(), &mut self.weight)]
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        let dims = x.shape().dims().to_vec();
+        embed_forward(&self.weight, &self.options, x, |slots| {
+            Ok(EmbeddingOp::new(slots, dims))
+        })
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let dims = g.data(x_id)?.shape().dims().to_vec();
+        embed_forward_graph(&self.weight, &self.options, g, x_id, |slots| {
+            Ok(EmbeddingOp::new(slots, dims))
+        })
+    }
+}
+
+impl Module for EmbeddingBag {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![&self.weight]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![&mut self.weight]
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        vec![("weight".into(), &self.weight)]
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        vec![("weight".into(), &mut self.weight)]
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        embed_forward(&self.weight, &self.options, x, self.op(x))
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let op = self.op(g.data(x_id)?);
+        embed_forward_graph(&self.weight, &self.options, g, x_id, op)
+    }
+}
+
+impl Layer for Embedding {}
+impl Layer for EmbeddingBag {}
@@ -1,8 +1,10 @@
-//! Linear: y = x @ W + b. One Parameter for weight, one for bias.
+//! Linear: y = x @ W + b. One Parameter for weight, one for bias. Inputs of rank > 2
+//! ([..., in], e.g. a [T, B, H] sequence) are flattened to rows and reshaped back.
 
 use super::module::Module;
 use super::Layer;
 use crate::autograd::{Graph, NodeId};
+use crate::ops::view::Reshape;
 use crate::parameter::Parameter;
 use crate::tensor::Tensor;
 use std::sync::Arc;
@@ -36,6 +38,15 @@ impl Linear {
         Ok(())
     }
 
+    /// Input dims with the last replaced by out_features.
+    fn out_dims(&self, in_dims: &[usize]) -> Vec<usize> {
+        let mut dims = in_dims.to_vec();
+        if let Some(last) = dims.last_mut() {
+            *last = self.weight.data().shape().dims()[1];
+        }
+        dims
+    }
+
     pub fn named(name: impl AsRef<str>, in_features: usize, out_features: usize, backend: Arc<dyn crate::backend::Backend>) -> crate::TensorResult<Self> {
         let prefix = name.as_ref();
         let mut linear = Self::new(in_features, out_features, backend)?;
@@ -45,6 +56,14 @@ impl Linear {
     }
 }
 
+/// [N, in] rows for an input of rank > 2; None when it is already a matrix.
+fn rows_of(dims: &[usize]) -> Option<Vec<usize>> {
+    match dims.split_last() {
+        Some((&last, lead)) if dims.len() > 2 => Some(vec![lead.iter().product(), last]),
+        _ => None,
+    }
+}
+
 impl Module for Linear {
     fn parameters(&self) -> Vec<&Parameter> {
         vec![&self.weight, &self.bias]
@@ -54,9 +73,26 @@ impl Module for Linear {
         vec![&mut self.weight, &mut self.bias]
     }
 
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        vec![("weight".into(), &self.weight), ("bias".into(), &self.bias)]
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        vec![("weight".into(), &mut self.weight), ("bias".into(), &mut self.bias)]
+    }
+
     fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
-        let out = x.matmul(self.weight.data())?;
-        out.add_broadcast(self.bias.data())
+        match rows_of(x.shape().dims()) {
+            Some(rows) => {
+                let flat = Reshape::new(rows).run(x)?;
+                let out = flat.matmul(self.weight.data())?.add_broadcast(self.bias.data())?;
+                Reshape::new(self.out_dims(x.shape().dims())).run(&out)
+            }
+            None => {
+                let out = x.matmul(self.weight.data())?;
+                out.add_broadcast(self.bias.data())
+            }
+        }
     }
 
     fn forward_graph(
@@ -64,10 +100,19 @@ impl Module for Linear {
         g: &mut Graph,
         x_id: NodeId,
     ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
-        let w_id = g.var(self.weight.data().clone());
-        let b_id = g.var(self.bias.data().clone());
-        let matmul_id = g.matmul(x_id, w_id)?;
-        let out_id = g.add_broadcast(matmul_id, b_id)?;
+        let w_id = g.param(&self.weight);
+        let b_id = g.param(&self.bias);
+        let dims = g.data(x_id)?.shape().dims().to_vec();
+        let rows = rows_of(&dims);
+        let in_id = match &rows {
+            Some(rows) => g.reshape(x_id, rows)?,
+            None => x_id,
+        };
+        let matmul_id = g.matmul(in_id, w_id)?;
+        let mut out_id = g.add_broadcast(matmul_id, b_id)?;
+        if rows.is_some() {
+            out_id = g.reshape(out_id, &self.out_dims(&dims))?;
+        }
         Ok((out_id, vec![w_id, b_id]))
     }
 }
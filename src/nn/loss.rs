@@ -26,6 +26,15 @@ pub fn mse_graph(
     target: &Tensor,
 ) -> crate::GraphResult<NodeId> {
     let target_id = g.var(target.clone());
+    mse_graph_node(g, pred_id, target_id)
+}
+
+/// MSE in graph against an existing target node (e.g. a runtime input of a compiled [crate::Plan]).
+pub fn mse_graph_node(
+    g: &mut Graph,
+    pred_id: NodeId,
+    target_id: NodeId,
+) -> crate::GraphResult<NodeId> {
     let diff_id = g.sub(pred_id, target_id)?;
     let n = g.data(pred_id)?.numel() as f32;
     let backend = g.data(pred_id)?.backend();
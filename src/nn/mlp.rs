@@ -1,8 +1,10 @@
-//! Simple MLP: Linear -> ReLU -> Linear. For classification use with CE loss.
+//! MLPs: fixed two-layer [MLP2] (Linear -> ReLU -> Linear) and [MLPBuilder] for any depth,
+//! built as a [Sequential]. For classification use with CE loss.
 
-use super::module::Module;
-use super::Layer;
+use super::module::{prefixed, Module};
+use super::{Layer, ReLU, Sequential, Sigmoid};
 use crate::autograd::{Graph, NodeId};
+use crate::backend::Activation;
 use crate::parameter::Parameter;
 use crate::tensor::Tensor;
 use std::sync::Arc;
@@ -47,6 +49,18 @@ impl Module for MLP2 {
         p1.into_iter().chain(p2).collect()
     }
 
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        let mut named = prefixed("linear1", self.linear1.named_parameters());
+        named.extend(prefixed("linear2", self.linear2.named_parameters()));
+        named
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        let mut named = prefixed("linear1", self.linear1.named_parameters_mut());
+        named.extend(prefixed("linear2", self.linear2.named_parameters_mut()));
+        named
+    }
+
     fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
         let h = self.linear1.forward(x)?;
         let h = h.relu()?;
@@ -67,3 +81,109 @@ impl Module for MLP2 {
 }
 
 impl Layer for MLP2 {}
+
+/// Builds a per-layer module for `features` units (e.g. a normalisation or dropout layer).
+pub type LayerFactory =
+    Box<dyn Fn(usize, Arc<dyn crate::backend::Backend>) -> crate::TensorResult<Box<dyn Module>>>;
+
+/// Builder for an MLP of any depth. Each hidden layer is
+/// Linear -> [norm] -> activation -> [dropout]; the output layer is a plain Linear.
+pub struct MLPBuilder {
+    in_features: usize,
+    out_features: usize,
+    hidden: Vec<usize>,
+    activation: Activation,
+    norm: Option<LayerFactory>,
+    dropout: Option<LayerFactory>,
+    xavier: bool,
+}
+
+impl MLPBuilder {
+    /// No hidden layers (a single Linear) and ReLU activation until configured.
+    pub fn new(in_features: usize, out_features: usize) -> Self {
+        MLPBuilder {
+            in_features,
+            out_features,
+            hidden: Vec::new(),
+            activation: Activation::ReLU,
+            norm: None,
+            dropout: None,
+            xavier: false,
+        }
+    }
+
+    pub fn hidden(mut self, sizes: &[usize]) -> Self {
+        self.hidden = sizes.to_vec();
+        self
+    }
+
+    /// Activation after each hidden layer; Identity adds none.
+    pub fn activation(mut self, activation: Activation) -> Self {
+        self.activation = activation;
+        self
+    }
+
+    /// Insert `f(features, backend)` after each hidden Linear, before the activation.
+    pub fn norm(
+        mut self,
+        f: impl Fn(usize, Arc<dyn crate::backend::Backend>) -> crate::TensorResult<Box<dyn Module>>
+            + 'static,
+    ) -> Self {
+        self.norm = Some(Box::new(f));
+        self
+    }
+
+    /// Insert `f(features, backend)` after each hidden activation.
+    pub fn dropout(
+        mut self,
+        f: impl Fn(usize, Arc<dyn crate::backend::Backend>) -> crate::TensorResult<Box<dyn Module>>
+            + 'static,
+    ) -> Self {
+        self.dropout = Some(Box::new(f));
+        self
+    }
+
+    /// Xavier-initialise every Linear (weights are zeros otherwise).
+    pub fn xavier(mut self) -> Self {
+        self.xavier = true;
+        self
+    }
+
+    pub fn build(
+        &self,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> crate::TensorResult<Sequential> {
+        let mut net = Sequential::new();
+        let mut features = self.in_features;
+        for &h in &self.hidden {
+            net.push(self.linear(features, h, &backend)?);
+            if let Some(norm) = &self.norm {
+                net.push_boxed(norm(h, backend.clone())?);
+            }
+            match self.activation {
+                Activation::Identity => {}
+                Activation::ReLU => net.push(ReLU::new()),
+                Activation::Sigmoid => net.push(Sigmoid::new()),
+            }
+            if let Some(dropout) = &self.dropout {
+                net.push_boxed(dropout(h, backend.clone())?);
+            }
+            features = h;
+        }
+        net.push(self.linear(features, self.out_features, &backend)?);
+        Ok(net)
+    }
+
+    fn linear(
+        &self,
+        in_features: usize,
+        out_features: usize,
+        backend: &Arc<dyn crate::backend::Backend>,
+    ) -> crate::TensorResult<super::Linear> {
+        let mut linear = super::Linear::new(in_features, out_features, backend.clone())?;
+        if self.xavier {
+            linear.init_xavier()?;
+        }
+        Ok(linear)
+    }
+}
@@ -1,15 +1,45 @@
-//! Neural network abstraction: Module, Layer, Linear, Activation, Loss.
+//! Neural network abstraction: Module, Layer, Linear, Conv, Pool, Activation, Loss, Sequential,
+//! recurrent and attention layers.
 
 pub mod activation;
+pub mod attention;
+pub mod batch_norm;
+pub mod checkpoint;
+pub mod conv;
+pub mod dropout;
+pub mod embedding;
 pub mod layer;
 pub mod linear;
 pub mod loss;
 pub mod mlp;
 pub mod module;
+pub mod norm;
+pub mod pool;
+pub mod positional;
+pub mod recurrent;
+pub mod sequential;
+pub mod transformer;
 
 pub use activation::{ReLU, Sigmoid};
+pub use attention::{AttentionMask, MultiheadAttention};
+pub use batch_norm::{BatchNorm1d, BatchNorm2d};
+pub use checkpoint::{checkpoint, Checkpointed};
+pub use conv::{Conv1d, Conv1dOptions, Conv2d, Conv2dOptions};
+pub use dropout::{AlphaDropout, DropPath, Dropout};
+pub use embedding::{BagMode, Embedding, EmbeddingBag, EmbeddingOptions};
 pub use layer::Layer;
 pub use linear::Linear;
-pub use loss::{ce_graph, mse, mse_graph};
-pub use mlp::MLP2;
-pub use module::Module;
+pub use loss::{ce_graph, mse, mse_graph, mse_graph_node};
+pub use mlp::{LayerFactory, MLPBuilder, MLP2};
+pub use module::{LoadReport, Module, ShapeMismatch, StateDict};
+pub use norm::{GroupNorm, LayerNorm, RMSNorm};
+pub use pool::{
+    AdaptiveAvgPool2d, AvgPool1d, AvgPool2d, GlobalAvgPool, GlobalMaxPool, MaxPool1d, MaxPool2d,
+};
+pub use positional::{LearnedPositionalEncoding, SinusoidalPositionalEncoding};
+pub use recurrent::{
+    CellKind, CellState, GRUCell, LSTMCell, RNNCell, Recurrent, RecurrentCell, RecurrentOptions,
+    RecurrentState, GRU, LSTM, RNN,
+};
+pub use sequential::Sequential;
+pub use transformer::{TransformerDecoderLayer, TransformerEncoderLayer, TransformerOptions};
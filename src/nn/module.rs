@@ -3,52 +3,301 @@
 use crate::autograd::{Graph, NodeId};
 use crate::parameter::{Parameter, ParameterState};
 use crate::tensor::Tensor;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::sync::Arc;
 
+/// Parameter and buffer states keyed by dotted path (see [Module::named_parameters]).
+pub type StateDict = BTreeMap<String, ParameterState>;
+
+/// A key present in both module and state dict with different shapes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShapeMismatch {
+    pub key: String,
+    pub expected: Vec<usize>,
+    pub found: Vec<usize>,
+}
+
+/// Outcome of [Module::load_named_state_dict].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadReport {
+    pub loaded: Vec<String>,
+    /// Module parameters or buffers with no entry in the state dict.
+    pub missing: Vec<String>,
+    /// State dict entries matching no module parameter or buffer.
+    pub unexpected: Vec<String>,
+    pub shape_mismatched: Vec<ShapeMismatch>,
+}
+
+impl LoadReport {
+    /// True if every key matched with the right shape.
+    pub fn is_exact(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty() && self.shape_mismatched.is_empty()
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "loaded {}", self.loaded.len())?;
+        if !self.missing.is_empty() {
+            write!(f, "; missing {:?}", self.missing)?;
+        }
+        if !self.unexpected.is_empty() {
+            write!(f, "; unexpected {:?}", self.unexpected)?;
+        }
+        for m in &self.shape_mismatched {
+            write!(f, "; {} expects {:?}, got {:?}", m.key, m.expected, m.found)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prefix each name with `prefix.` (for composite modules naming their children).
+pub fn prefixed<T>(prefix: &str, named: Vec<(String, T)>) -> Vec<(String, T)> {
+    named
+        .into_iter()
+        .map(|(name, p)| (format!("{}.{}", prefix, name), p))
+        .collect()
+}
+
 /// Module: has parameters and can forward (pure or with graph).
 pub trait Module {
-    /// All trainable parameters (order must match forward_graph param nodes).
+    /// All trainable parameters. forward_graph binds them with [Graph::param], so gradients
+    /// are matched by parameter id rather than by order.
     fn parameters(&self) -> Vec<&Parameter>;
 
     /// Mutable parameters (for optimizer).
     fn parameters_mut(&mut self) -> Vec<&mut Parameter>;
 
+    /// Parameters with dotted paths derived from module structure (`linear1.weight`), in
+    /// [Self::parameters] order. Leaf modules name their own parameters and containers
+    /// prefix their children's names (see [prefixed]). The default uses each parameter's
+    /// name if set, else its index.
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        self.parameters()
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| (p.name().map_or_else(|| i.to_string(), str::to_string), p))
+            .collect()
+    }
+
+    /// Mutable counterpart of [Self::named_parameters]; must produce the same names.
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        self.parameters_mut()
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| (p.name().map_or_else(|| i.to_string(), str::to_string), p))
+            .collect()
+    }
+
     /// Forward (inference): no graph, just Tensor in -> Tensor out.
     fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor>;
 
     /// Forward and build graph. Caller has already created input node x_id.
-    /// Returns (output_node_id, param_node_ids) so caller can copy grads back to parameters.
+    /// Returns (output_node_id, param_node_ids); after backward, [Graph::write_grads]
+    /// copies gradients back to the parameters bound with [Graph::param] (in any order).
+    /// A module that creates its weights with [Graph::var] instead must return their nodes in
+    /// [Self::parameters] order: [Graph::write_grads_with], which the Trainer uses, falls
+    /// back to that position for parameters without an id binding.
     fn forward_graph(
         &self,
         g: &mut Graph,
         x_id: NodeId,
     ) -> crate::GraphResult<(NodeId, Vec<NodeId>)>;
 
-    /// Collect all parameter states in order (for save).
+    /// Switch between training (true, the default) and evaluation behaviour, e.g. batch vs
+    /// running statistics in BatchNorm. Independent of whether [Self::forward] or
+    /// [Self::forward_graph] is used. Containers forward it to their children; the default
+    /// does nothing.
+    fn set_training(&mut self, _training: bool) {}
+
+    /// False while a forward pass draws random numbers or updates buffers (dropout and
+    /// BatchNorm in training mode), so its graph can't be replayed as a fixed program (see
+    /// [crate::train::Trainer::compile_step_batch] and [crate::trace::trace]). Containers ask their
+    /// children; the default is true.
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    /// Shorthand for `set_training(false)`.
+    fn eval(&mut self) {
+        self.set_training(false)
+    }
+
+    /// Carry recurrent state across calls (truncated BPTT, streaming inference): while on,
+    /// each forward of a recurrent layer starts from the final state of the previous one.
+    /// The state is carried as values, so gradients stop at call boundaries. Every call
+    /// clears the carried state. Containers forward it to their children; the default does
+    /// nothing.
+    fn set_carry_state(&mut self, _carry: bool) {}
+
+    /// Non-trainable state (e.g. BatchNorm running statistics) with dotted paths like
+    /// [Self::named_parameters]. Saved in state dicts, never seen by optimizers.
+    fn named_buffers(&self) -> Vec<(String, Tensor)> {
+        Vec::new()
+    }
+
+    /// Replace the buffer at `name` (a [Self::named_buffers] path) with `value` of the same
+    /// shape. Containers route the path to the child that owns it.
+    fn set_buffer(&mut self, name: &str, _value: Tensor) -> crate::TensorResult<()> {
+        Err(crate::TensorError::Shape(crate::ShapeError(format!(
+            "no buffer named {:?}",
+            name
+        ))))
+    }
+
+    /// Move all parameters and buffers to `backend` (and so to its [crate::backend::Device]).
+    fn to(&mut self, backend: Arc<dyn crate::backend::Backend>) -> crate::TensorResult<()> {
+        for p in self.parameters_mut() {
+            p.to(backend.clone())?;
+        }
+        for (name, b) in self.named_buffers() {
+            self.set_buffer(&name, b.to(backend.clone())?)?;
+        }
+        Ok(())
+    }
+
+    /// Parameter and buffer states keyed by [Self::named_parameters] /
+    /// [Self::named_buffers] path.
+    fn named_state_dict(&self) -> StateDict {
+        let mut states: StateDict = self
+            .named_parameters()
+            .into_iter()
+            .map(|(name, p)| {
+                let mut state = p.to_state();
+                state.name = Some(name.clone());
+                (name, state)
+            })
+            .collect();
+        for (name, b) in self.named_buffers() {
+            states.insert(name.clone(), buffer_state(name, &b));
+        }
+        states
+    }
+
+    /// Load parameters and buffers by name. Strict: any missing, unexpected or
+    /// shape-mismatched key is an error and nothing is loaded. Non-strict: matching keys are
+    /// loaded, the rest reported.
+    fn load_named_state_dict(
+        &mut self,
+        states: &StateDict,
+        backend: Arc<dyn crate::backend::Backend>,
+        strict: bool,
+    ) -> crate::TensorResult<LoadReport> {
+        let mut report = LoadReport::default();
+        let buffers = self.named_buffers();
+        let mut params = self.named_parameters_mut();
+        let expected = params
+            .iter()
+            .map(|(name, p)| (name, p.data().shape().dims().to_vec()))
+            .chain(buffers.iter().map(|(name, b)| (name, b.shape().dims().to_vec())));
+        let mut matched = Vec::new();
+        for (i, (name, dims)) in expected.enumerate() {
+            match states.get(name) {
+                None => report.missing.push(name.clone()),
+                Some(s) if s.shape != dims => report.shape_mismatched.push(ShapeMismatch {
+                    key: name.clone(),
+                    expected: dims,
+                    found: s.shape.clone(),
+                }),
+                Some(s) => matched.push((i, s)),
+            }
+        }
+        report.unexpected = states
+            .keys()
+            .filter(|k| {
+                !params.iter().any(|(name, _)| name == *k)
+                    && !buffers.iter().any(|(name, _)| name == *k)
+            })
+            .cloned()
+            .collect();
+        if strict && !report.is_exact() {
+            return Err(crate::TensorError::Shape(crate::ShapeError(format!(
+                "load_named_state_dict (strict): {}",
+                report
+            ))));
+        }
+        let mut buffer_loads = Vec::new();
+        for (i, s) in matched {
+            let data = backend
+                .from_vec(s.data.clone(), crate::Shape::new(s.shape.clone()))
+                .map_err(crate::TensorError::from)?;
+            match params.get_mut(i) {
+                Some((name, p)) => {
+                    *p.data_mut() = data;
+                    report.loaded.push(name.clone());
+                }
+                None => buffer_loads.push((buffers[i - params.len()].0.clone(), data)),
+            }
+        }
+        drop(params);
+        for (name, data) in buffer_loads {
+            self.set_buffer(&name, data)?;
+            report.loaded.push(name);
+        }
+        Ok(report)
+    }
+
+    /// Collect all parameter states in order, then buffer states (for save). See
+    /// [Self::named_state_dict] for a name-keyed version.
     fn state_dict(&self) -> Vec<ParameterState> {
-        self.parameters().iter().map(|p| p.to_state()).collect()
+        let mut states: Vec<ParameterState> =
+            self.parameters().iter().map(|p| p.to_state()).collect();
+        states.extend(
+            self.named_buffers()
+                .into_iter()
+                .map(|(name, b)| buffer_state(name, &b)),
+        );
+        states
     }
 
-    /// Load parameter states in order (for load). State count must match parameter count.
+    /// Load parameter then buffer states in order (for load). State count must match
+    /// parameter plus buffer count.
     fn load_state_dict(
         &mut self,
         states: &[ParameterState],
         backend: Arc<dyn crate::backend::Backend>,
     ) -> crate::TensorResult<()> {
+        let buffers = self.named_buffers();
         let mut params = self.parameters_mut();
-        if params.len() != states.len() {
+        if params.len() + buffers.len() != states.len() {
             return Err(crate::TensorError::Shape(crate::ShapeError(
                 format!(
-                    "load_state_dict: got {} states, module has {} parameters",
+                    "load_state_dict: got {} states, module has {} parameters and {} buffers",
                     states.len(),
-                    params.len()
-                )
-                .into(),
+                    params.len(),
+                    buffers.len()
+                ),
             )));
         }
-        for (p, s) in params.iter_mut().zip(states.iter()) {
+        let (param_states, buffer_states) = states.split_at(params.len());
+        for (p, s) in params.iter_mut().zip(param_states.iter()) {
             p.load_state(s, backend.clone())?;
         }
+        drop(params);
+        for ((name, b), s) in buffers.iter().zip(buffer_states) {
+            if s.shape != b.shape().dims() {
+                return Err(crate::TensorError::Shape(crate::ShapeError(format!(
+                    "load_state_dict: buffer {} expects {:?}, got {:?}",
+                    name,
+                    b.shape().dims(),
+                    s.shape
+                ))));
+            }
+            let data = backend
+                .from_vec(s.data.clone(), crate::Shape::new(s.shape.clone()))
+                .map_err(crate::TensorError::from)?;
+            self.set_buffer(name, data)?;
+        }
         Ok(())
     }
 }
+
+/// State of buffer `name` (same format as parameter states).
+fn buffer_state(name: String, t: &Tensor) -> ParameterState {
+    ParameterState {
+        name: Some(name),
+        shape: t.shape().dims().to_vec(),
+        data: t.data().to_vec(),
+    }
+}
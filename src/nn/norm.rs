@@ -0,0 +1,226 @@
+//! LayerNorm, RMSNorm and GroupNorm layers. Unlike BatchNorm they normalise each sample on
+//! its own, so they behave the same in training and evaluation and have no buffers. Each
+//! runs as a single fused op (see [crate::ops::norm]).
+
+use super::module::Module;
+use super::Layer;
+use crate::autograd::{Graph, NodeId};
+use crate::ops::norm::{self, DEFAULT_EPS};
+use crate::parameter::Parameter;
+use crate::shape::{Shape, ShapeError};
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use std::sync::Arc;
+
+/// Parameter of `dims` filled with ones (scale) or zeros (shift).
+fn affine(
+    dims: &[usize],
+    ones: bool,
+    backend: &Arc<dyn crate::backend::Backend>,
+) -> TensorResult<Parameter> {
+    let shape = Shape::new(dims.to_vec());
+    let data = if ones {
+        backend.ones(&shape)?
+    } else {
+        backend.zeros(&shape)?
+    };
+    Ok(Parameter::new(data))
+}
+
+/// Layer normalisation over the trailing `normalized_shape` dims of the input (e.g. the
+/// feature dim of [N, D] or [N, T, D]), with elementwise weight (ones) and bias (zeros).
+pub struct LayerNorm {
+    pub weight: Parameter,
+    pub bias: Parameter,
+    op: Arc<norm::LayerNorm>,
+}
+
+impl LayerNorm {
+    /// eps 1e-5; use [Self::with_eps] to change it.
+    pub fn new(
+        normalized_shape: &[usize],
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        Ok(LayerNorm {
+            weight: affine(normalized_shape, true, &backend)?,
+            bias: affine(normalized_shape, false, &backend)?,
+            op: Arc::new(norm::LayerNorm::new(DEFAULT_EPS)),
+        })
+    }
+
+    pub fn with_eps(mut self, eps: f32) -> Self {
+        self.op = Arc::new(norm::LayerNorm::new(eps));
+        self
+    }
+}
+
+/// RMS normalisation over the trailing `normalized_shape` dims: weight * x / rms(x), with
+/// weight initialised to ones and no bias.
+pub struct RMSNorm {
+    pub weight: Parameter,
+    op: Arc<norm::RMSNorm>,
+}
+
+impl RMSNorm {
+    /// eps 1e-5; use [Self::with_eps] to change it.
+    pub fn new(
+        normalized_shape: &[usize],
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        Ok(RMSNorm {
+            weight: affine(normalized_shape, true, &backend)?,
+            op: Arc::new(norm::RMSNorm::new(DEFAULT_EPS)),
+        })
+    }
+
+    pub fn with_eps(mut self, eps: f32) -> Self {
+        self.op = Arc::new(norm::RMSNorm::new(eps));
+        self
+    }
+}
+
+/// Group normalisation of [N, C, ...]: the C channels are split into `groups` groups, each
+/// normalised per sample over its channels and positions, with per-channel weight and bias.
+/// One group is LayerNorm over (C, ...); C groups is instance normalisation.
+pub struct GroupNorm {
+    pub weight: Parameter,
+    pub bias: Parameter,
+    op: Arc<norm::GroupNorm>,
+}
+
+impl GroupNorm {
+    /// Errors unless `groups` divides `channels`. eps 1e-5; use [Self::with_eps] to change it.
+    pub fn new(
+        groups: usize,
+        channels: usize,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        if groups == 0 || !channels.is_multiple_of(groups) {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "GroupNorm: {} channels do not split into {} groups",
+                channels, groups
+            ))));
+        }
+        Ok(GroupNorm {
+            weight: affine(&[channels], true, &backend)?,
+            bias: affine(&[channels], false, &backend)?,
+            op: Arc::new(norm::GroupNorm::new(groups, DEFAULT_EPS)),
+        })
+    }
+
+    pub fn with_eps(mut self, eps: f32) -> Self {
+        self.op = Arc::new(norm::GroupNorm::new(self.op.groups(), eps));
+        self
+    }
+
+    pub fn groups(&self) -> usize {
+        self.op.groups()
+    }
+}
+
+impl Module for LayerNorm {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![&self.weight, &self.bias]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![&mut self.weight, &mut self.bias]
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        vec![("weight".into(), &self.weight), ("bias".into(), &self.bias)]
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        vec![
+            ("weight".into(), &mut self.weight),
+            ("bias".into(), &mut self.bias),
+        ]
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        self.op.run(x, self.weight.data(), self.bias.data())
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let w_id = g.param(&self.weight);
+        let b_id = g.param(&self.bias);
+        let out_id = g.apply_op(self.op.clone(), &[x_id, w_id, b_id])?;
+        Ok((out_id, vec![w_id, b_id]))
+    }
+}
+
+impl Module for RMSNorm {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![&self.weight]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![&mut self.weight]
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        vec![("weight".into(), &self.weight)]
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        vec![("weight".into(), &mut self.weight)]
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        self.op.run(x, self.weight.data())
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let w_id = g.param(&self.weight);
+        let out_id = g.apply_op(self.op.clone(), &[x_id, w_id])?;
+        Ok((out_id, vec![w_id]))
+    }
+}
+
+impl Module for GroupNorm {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![&self.weight, &self.bias]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![&mut self.weight, &mut self.bias]
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        vec![("weight".into(), &self.weight), ("bias".into(), &self.bias)]
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        vec![
+            ("weight".into(), &mut self.weight),
+            ("bias".into(), &mut self.bias),
+        ]
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        self.op.run(x, self.weight.data(), self.bias.data())
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let w_id = g.param(&self.weight);
+        let b_id = g.param(&self.bias);
+        let out_id = g.apply_op(self.op.clone(), &[x_id, w_id, b_id])?;
+        Ok((out_id, vec![w_id, b_id]))
+    }
+}
+
+impl Layer for LayerNorm {}
+impl Layer for RMSNorm {}
+impl Layer for GroupNorm {}
@@ -0,0 +1,363 @@
+//! Pooling layers (no parameters): MaxPool1d/2d, AvgPool1d/2d, AdaptiveAvgPool2d and the
+//! global pools, which reduce all spatial dims to [N, C] (e.g. before a Linear head).
+
+use super::module::Module;
+use super::Layer;
+use crate::autograd::{Graph, NodeId};
+use crate::backend::ConvGeometry;
+use crate::ops::pool::{Pool, PoolMode, PoolWindow};
+use crate::parameter::Parameter;
+use crate::tensor::Tensor;
+use std::sync::Arc;
+
+fn pool_forward_graph(
+    op: &Arc<Pool>,
+    g: &mut Graph,
+    x_id: NodeId,
+) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+    let out_id = g.apply_op(op.clone(), &[x_id])?;
+    Ok((out_id, vec![]))
+}
+
+fn fixed(mode: PoolMode, geom: ConvGeometry) -> Arc<Pool> {
+    Arc::new(Pool::new(mode, PoolWindow::Fixed(geom)))
+}
+
+/// Window geometry of a fixed-window pool.
+fn geometry(op: &Pool) -> ConvGeometry {
+    match op.window() {
+        PoolWindow::Fixed(geom) => geom,
+        w => unreachable!("fixed-window pool with {:?}", w),
+    }
+}
+
+/// 2D max pooling over [N, C, H, W]. Stride defaults to the kernel; padding is ignored.
+pub struct MaxPool2d {
+    op: Arc<Pool>,
+}
+
+impl MaxPool2d {
+    pub fn new(kernel: (usize, usize)) -> Self {
+        MaxPool2d {
+            op: fixed(PoolMode::Max, ConvGeometry::new(kernel).with_stride(kernel)),
+        }
+    }
+
+    pub fn with_stride(self, stride: (usize, usize)) -> Self {
+        MaxPool2d {
+            op: fixed(PoolMode::Max, geometry(&self.op).with_stride(stride)),
+        }
+    }
+
+    pub fn with_padding(self, padding: (usize, usize)) -> Self {
+        MaxPool2d {
+            op: fixed(PoolMode::Max, geometry(&self.op).with_padding(padding)),
+        }
+    }
+
+    pub fn geometry(&self) -> ConvGeometry {
+        geometry(&self.op)
+    }
+}
+
+/// 1D max pooling over [N, C, L]. Stride defaults to the kernel; padding is ignored.
+pub struct MaxPool1d {
+    op: Arc<Pool>,
+}
+
+impl MaxPool1d {
+    pub fn new(kernel: usize) -> Self {
+        MaxPool1d {
+            op: fixed(PoolMode::Max, ConvGeometry::conv1d(kernel, kernel, 0, 1)),
+        }
+    }
+
+    pub fn with_stride(self, stride: usize) -> Self {
+        MaxPool1d {
+            op: fixed(PoolMode::Max, geometry(&self.op).with_stride((1, stride))),
+        }
+    }
+
+    pub fn with_padding(self, padding: usize) -> Self {
+        MaxPool1d {
+            op: fixed(PoolMode::Max, geometry(&self.op).with_padding((0, padding))),
+        }
+    }
+
+    pub fn geometry(&self) -> ConvGeometry {
+        geometry(&self.op)
+    }
+}
+
+/// 2D average pooling over [N, C, H, W]. Stride defaults to the kernel; padding counts as
+/// zeros in the average.
+pub struct AvgPool2d {
+    op: Arc<Pool>,
+}
+
+impl AvgPool2d {
+    pub fn new(kernel: (usize, usize)) -> Self {
+        AvgPool2d {
+            op: fixed(PoolMode::Avg, ConvGeometry::new(kernel).with_stride(kernel)),
+        }
+    }
+
+    pub fn with_stride(self, stride: (usize, usize)) -> Self {
+        AvgPool2d {
+            op: fixed(PoolMode::Avg, geometry(&self.op).with_stride(stride)),
+        }
+    }
+
+    pub fn with_padding(self, padding: (usize, usize)) -> Self {
+        AvgPool2d {
+            op: fixed(PoolMode::Avg, geometry(&self.op).with_padding(padding)),
+        }
+    }
+
+    pub fn geometry(&self) -> ConvGeometry {
+        geometry(&self.op)
+    }
+}
+
+/// 1D average pooling over [N, C, L]. Stride defaults to the kernel; padding counts as
+/// zeros in the average.
+pub struct AvgPool1d {
+    op: Arc<Pool>,
+}
+
+impl AvgPool1d {
+    pub fn new(kernel: usize) -> Self {
+        AvgPool1d {
+            op: fixed(PoolMode::Avg, ConvGeometry::conv1d(kernel, kernel, 0, 1)),
+        }
+    }
+
+    pub fn with_stride(self, stride: usize) -> Self {
+        AvgPool1d {
+            op: fixed(PoolMode::Avg, geometry(&self.op).with_stride((1, stride))),
+        }
+    }
+
+    pub fn with_padding(self, padding: usize) -> Self {
+        AvgPool1d {
+            op: fixed(PoolMode::Avg, geometry(&self.op).with_padding((0, padding))),
+        }
+    }
+
+    pub fn geometry(&self) -> ConvGeometry {
+        geometry(&self.op)
+    }
+}
+
+/// Average pooling of [N, C, H, W] to a fixed [N, C, OH, OW], whatever H and W are.
+pub struct AdaptiveAvgPool2d {
+    op: Arc<Pool>,
+}
+
+impl AdaptiveAvgPool2d {
+    pub fn new(output: (usize, usize)) -> Self {
+        AdaptiveAvgPool2d {
+            op: Arc::new(Pool::new(PoolMode::Avg, PoolWindow::Adaptive(output))),
+        }
+    }
+}
+
+/// Mean over all spatial dims: [N, C, ...] -> [N, C].
+pub struct GlobalAvgPool {
+    op: Arc<Pool>,
+}
+
+impl GlobalAvgPool {
+    pub fn new() -> Self {
+        GlobalAvgPool {
+            op: Arc::new(Pool::new(PoolMode::Avg, PoolWindow::Global)),
+        }
+    }
+}
+
+impl Default for GlobalAvgPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Max over all spatial dims: [N, C, ...] -> [N, C].
+pub struct GlobalMaxPool {
+    op: Arc<Pool>,
+}
+
+impl GlobalMaxPool {
+    pub fn new() -> Self {
+        GlobalMaxPool {
+            op: Arc::new(Pool::new(PoolMode::Max, PoolWindow::Global)),
+        }
+    }
+}
+
+impl Default for GlobalMaxPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Module for MaxPool2d {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![]
+    }
+
+    fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
+        self.op.run(x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        pool_forward_graph(&self.op, g, x_id)
+    }
+}
+
+impl Module for MaxPool1d {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![]
+    }
+
+    fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
+        self.op.run(x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        pool_forward_graph(&self.op, g, x_id)
+    }
+}
+
+impl Module for AvgPool2d {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![]
+    }
+
+    fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
+        self.op.run(x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        pool_forward_graph(&self.op, g, x_id)
+    }
+}
+
+impl Module for AvgPool1d {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![]
+    }
+
+    fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
+        self.op.run(x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        pool_forward_graph(&self.op, g, x_id)
+    }
+}
+
+impl Module for AdaptiveAvgPool2d {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![]
+    }
+
+    fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
+        self.op.run(x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        pool_forward_graph(&self.op, g, x_id)
+    }
+}
+
+impl Module for GlobalAvgPool {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![]
+    }
+
+    fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
+        self.op.run(x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        pool_forward_graph(&self.op, g, x_id)
+    }
+}
+
+impl Module for GlobalMaxPool {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![]
+    }
+
+    fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
+        self.op.run(x)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        pool_forward_graph(&self.op, g, x_id)
+    }
+}
+
+impl Layer for MaxPool2d {}
+impl Layer for MaxPool1d {}
+impl Layer for AvgPool2d {}
+impl Layer for AvgPool1d {}
+impl Layer for AdaptiveAvgPool2d {}
+impl Layer for GlobalAvgPool {}
+impl Layer for GlobalMaxPool {}
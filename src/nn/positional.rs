@@ -0,0 +1,182 @@
+//! Positional encodings for batch-first [B, T, E] sequences: row t of a [max_len, E] table
+//! is added to position t of every sequence. The sinusoidal table is fixed; the learned one
+//! is a parameter.
+
+use super::module::Module;
+use super::Layer;
+use crate::autograd::{Graph, GraphError, GraphResult, NodeId};
+use crate::ops::view::{Narrow, Reshape};
+use crate::parameter::Parameter;
+use crate::runtime::with_rng;
+use crate::shape::{Shape, ShapeError};
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use rand::Rng;
+use std::sync::Arc;
+
+/// (B, T, E) of `x` after checking it fits a [max_len, E] table.
+fn positions(x: &[usize], table: &[usize]) -> Result<(usize, usize, usize), String> {
+    match x {
+        &[b, t, e] if t <= table[0] && e == table[1] => Ok((b, t, e)),
+        _ => Err(format!(
+            "positional encoding: input {:?} does not fit a {:?} table",
+            x, table
+        )),
+    }
+}
+
+/// x + table[..T], added via [B, T * E] rows so the bias broadcast applies.
+fn add_table(x: &Tensor, table: &Tensor) -> TensorResult<Tensor> {
+    let (b, t, e) = positions(x.shape().dims(), table.shape().dims())
+        .map_err(|e| TensorError::Shape(ShapeError(e)))?;
+    let rows = Narrow::new(0, 0, t).run(table)?;
+    let rows = Reshape::new(vec![t * e]).run(&rows)?;
+    let flat = Reshape::new(vec![b, t * e]).run(x)?;
+    Reshape::new(vec![b, t, e]).run(&flat.add_broadcast(&rows)?)
+}
+
+/// Graph counterpart of [add_table], with the table already a node.
+fn add_table_graph(g: &mut Graph, x_id: NodeId, table_id: NodeId) -> GraphResult<NodeId> {
+    let (b, t, e) = positions(
+        g.data(x_id)?.shape().dims(),
+        g.data(table_id)?.shape().dims(),
+    )
+    .map_err(GraphError)?;
+    let rows = g.narrow(table_id, 0, 0, t)?;
+    let rows = g.reshape(rows, &[t * e])?;
+    let flat = g.reshape(x_id, &[b, t * e])?;
+    let sum = g.add_broadcast(flat, rows)?;
+    g.reshape(sum, &[b, t, e])
+}
+
+/// Fixed sin/cos encodings: pe[t, 2i] = sin(t / 10000^(2i / E)), pe[t, 2i + 1] = cos(same).
+/// The table is a buffer (`table`), so [Module::to] moves it with the model.
+pub struct SinusoidalPositionalEncoding {
+    table: Tensor,
+}
+
+impl SinusoidalPositionalEncoding {
+    pub fn new(
+        max_len: usize,
+        d_model: usize,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        let mut data = Vec::with_capacity(max_len * d_model);
+        for t in 0..max_len {
+            for i in 0..d_model {
+                let freq = 10000f32.powf(-((i - i % 2) as f32) / d_model as f32);
+                let angle = t as f32 * freq;
+                data.push(if i % 2 == 0 { angle.sin() } else { angle.cos() });
+            }
+        }
+        let table = Tensor::from_vec(data, Shape::new(vec![max_len, d_model]), backend)?;
+        Ok(SinusoidalPositionalEncoding { table })
+    }
+
+    /// The [max_len, E] table.
+    pub fn table(&self) -> &Tensor {
+        &self.table
+    }
+}
+
+/// A [max_len, E] table of trainable position embeddings, zeros by default.
+pub struct LearnedPositionalEncoding {
+    pub weight: Parameter,
+}
+
+impl LearnedPositionalEncoding {
+    pub fn new(
+        max_len: usize,
+        d_model: usize,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        Ok(LearnedPositionalEncoding {
+            weight: Parameter::new(backend.zeros(&Shape::new(vec![max_len, d_model]))?),
+        })
+    }
+
+    /// Uniform(-bound, bound) weights.
+    pub fn init_uniform(&mut self, bound: f32) -> TensorResult<()> {
+        let shape = self.weight.data().shape().clone();
+        let data = with_rng(|rng| {
+            (0..shape.numel())
+                .map(|_| rng.gen_range(-bound..=bound))
+                .collect()
+        });
+        *self.weight.data_mut() = Tensor::from_vec(data, shape, self.weight.data().backend())?;
+        Ok(())
+    }
+}
+
+impl Module for SinusoidalPositionalEncoding {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![]
+    }
+
+    fn named_buffers(&self) -> Vec<(String, Tensor)> {
+        vec![("table".into(), self.table.clone())]
+    }
+
+    fn set_buffer(&mut self, name: &str, value: Tensor) -> TensorResult<()> {
+        if name != "table" || value.shape().dims() != self.table.shape().dims() {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "buffer {}: expected table {:?}, got {:?}",
+                name,
+                self.table.shape().dims(),
+                value.shape().dims()
+            ))));
+        }
+        self.table = value;
+        Ok(())
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        add_table(x, &self.table)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let table_id = g.var(self.table.clone());
+        Ok((add_table_graph(g, x_id, table_id)?, vec![]))
+    }
+}
+
+impl Module for LearnedPositionalEncoding {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![&self.weight]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![&mut self.weight]
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        vec![("weight".into(), &self.weight)]
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        vec![("weight".into(), &mut self.weight)]
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        add_table(x, self.weight.data())
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let w_id = g.param(&self.weight);
+        Ok((add_table_graph(g, x_id, w_id)?, vec![w_id]))
+    }
+}
+
+impl Layer for SinusoidalPositionalEncoding {}
+impl Layer for LearnedPositionalEncoding {}
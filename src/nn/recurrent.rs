@@ -0,0 +1,632 @@
+//! Recurrent layers over [T, B, F] sequences: Elman RNN (tanh), LSTM and GRU cells, and
+//! multi-layer, optionally bidirectional wrappers that unroll them. Each step is built from
+//! graph ops (matmul, sigmoid, tanh, [Graph::narrow] of the stacked gates, ...), so
+//! backpropagation through time needs no special support. Weights use [super::Linear]'s
+//! [in, out] layout with the gates stacked along out: LSTM (i, f, g, o), GRU (r, z, n).
+
+use super::module::Module;
+use super::Layer;
+use crate::autograd::{Graph, GraphError, GraphResult, NodeId};
+use crate::parameter::Parameter;
+use crate::runtime::with_rng;
+use crate::shape::{Shape, ShapeError};
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use rand::Rng;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+/// State of one cell inside a graph: h [B, H] and, for LSTM, the cell vector c [B, H].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CellState {
+    pub h: NodeId,
+    pub c: Option<NodeId>,
+}
+
+/// The step function of a cell type; see [Elman], [Lstm] and [Gru].
+pub trait CellKind: Send + Sync + 'static {
+    /// Gate blocks per hidden unit, stacked along the weights' out dim.
+    const GATES: usize;
+    /// Whether the state has a cell vector besides h.
+    const HAS_CELL: bool;
+    const NAME: &'static str;
+
+    /// Next state from the input projection gx = x W_ih + b_ih and the hidden projection
+    /// gh = h W_hh + b_hh, both [B, GATES * H].
+    fn recur(
+        g: &mut Graph,
+        gx: NodeId,
+        gh: NodeId,
+        state: &CellState,
+        hidden: usize,
+    ) -> GraphResult<CellState>;
+}
+
+/// Elman RNN: h' = tanh(gx + gh).
+pub struct Elman;
+
+/// LSTM: i, f, o = sigmoid, g = tanh of the gates; c' = f * c + i * g, h' = o * tanh(c').
+pub struct Lstm;
+
+/// GRU: r, z = sigmoid(gx + gh) of their blocks, n = tanh(gx_n + r * gh_n) and
+/// h' = (1 - z) * n + z * h.
+pub struct Gru;
+
+/// Block `i` of H columns from stacked gates [B, G * H].
+fn gate(g: &mut Graph, gates: NodeId, i: usize, hidden: usize) -> GraphResult<NodeId> {
+    g.narrow(gates, 1, i * hidden, hidden)
+}
+
+impl CellKind for Elman {
+    const GATES: usize = 1;
+    const HAS_CELL: bool = false;
+    const NAME: &'static str = "RNN";
+
+    fn recur(
+        g: &mut Graph,
+        gx: NodeId,
+        gh: NodeId,
+        _state: &CellState,
+        _hidden: usize,
+    ) -> GraphResult<CellState> {
+        let pre = g.add(gx, gh)?;
+        Ok(CellState {
+            h: g.tanh(pre)?,
+            c: None,
+        })
+    }
+}
+
+impl CellKind for Lstm {
+    const GATES: usize = 4;
+    const HAS_CELL: bool = true;
+    const NAME: &'static str = "LSTM";
+
+    fn recur(
+        g: &mut Graph,
+        gx: NodeId,
+        gh: NodeId,
+        state: &CellState,
+        hidden: usize,
+    ) -> GraphResult<CellState> {
+        let c = state
+            .c
+            .ok_or_else(|| GraphError("LSTM: state has no cell vector".into()))?;
+        let gates = g.add(gx, gh)?;
+        let i = gate(g, gates, 0, hidden)?;
+        let i = g.sigmoid(i)?;
+        let f = gate(g, gates, 1, hidden)?;
+        let f = g.sigmoid(f)?;
+        let cand = gate(g, gates, 2, hidden)?;
+        let cand = g.tanh(cand)?;
+        let o = gate(g, gates, 3, hidden)?;
+        let o = g.sigmoid(o)?;
+        let kept = g.mul(f, c)?;
+        let added = g.mul(i, cand)?;
+        let c = g.add(kept, added)?;
+        let squashed = g.tanh(c)?;
+        Ok(CellState {
+            h: g.mul(o, squashed)?,
+            c: Some(c),
+        })
+    }
+}
+
+impl CellKind for Gru {
+    const GATES: usize = 3;
+    const HAS_CELL: bool = false;
+    const NAME: &'static str = "GRU";
+
+    fn recur(
+        g: &mut Graph,
+        gx: NodeId,
+        gh: NodeId,
+        state: &CellState,
+        hidden: usize,
+    ) -> GraphResult<CellState> {
+        let sigmoid_gate = |g: &mut Graph, i: usize| -> GraphResult<NodeId> {
+            let x = gate(g, gx, i, hidden)?;
+            let h = gate(g, gh, i, hidden)?;
+            let pre = g.add(x, h)?;
+            g.sigmoid(pre)
+        };
+        let r = sigmoid_gate(g, 0)?;
+        let z = sigmoid_gate(g, 1)?;
+        let xn = gate(g, gx, 2, hidden)?;
+        let hn = gate(g, gh, 2, hidden)?;
+        let reset = g.mul(r, hn)?;
+        let pre = g.add(xn, reset)?;
+        let n = g.tanh(pre)?;
+        // (1 - z) * n + z * h = n + z * (h - n)
+        let diff = g.sub(state.h, n)?;
+        let update = g.mul(z, diff)?;
+        Ok(CellState {
+            h: g.add(n, update)?,
+            c: None,
+        })
+    }
+}
+
+/// One recurrent cell: W_ih [F, G * H], W_hh [H, G * H] and biases b_ih, b_hh [G * H].
+pub struct RecurrentCell<K> {
+    pub weight_ih: Parameter,
+    pub weight_hh: Parameter,
+    pub bias_ih: Parameter,
+    pub bias_hh: Parameter,
+    kind: PhantomData<K>,
+}
+
+pub type RNNCell = RecurrentCell<Elman>;
+pub type LSTMCell = RecurrentCell<Lstm>;
+pub type GRUCell = RecurrentCell<Gru>;
+
+impl<K: CellKind> RecurrentCell<K> {
+    /// Weights are zeros by default; use [Self::init_uniform] to init.
+    pub fn new(
+        input_size: usize,
+        hidden_size: usize,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        let gates = K::GATES * hidden_size;
+        let zeros = |dims: Vec<usize>| -> TensorResult<Parameter> {
+            Ok(Parameter::new(backend.zeros(&Shape::new(dims))?))
+        };
+        Ok(RecurrentCell {
+            weight_ih: zeros(vec![input_size, gates])?,
+            weight_hh: zeros(vec![hidden_size, gates])?,
+            bias_ih: zeros(vec![gates])?,
+            bias_hh: zeros(vec![gates])?,
+            kind: PhantomData,
+        })
+    }
+
+    pub fn input_size(&self) -> usize {
+        self.weight_ih.data().shape().dims()[0]
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.weight_hh.data().shape().dims()[0]
+    }
+
+    /// All weights and biases from Uniform(-1/sqrt(H), 1/sqrt(H)).
+    pub fn init_uniform(&mut self) -> TensorResult<()> {
+        let bound = 1.0 / (self.hidden_size().max(1) as f32).sqrt();
+        for p in self.parameters_mut() {
+            let shape = p.data().shape().clone();
+            let data: Vec<f32> = with_rng(|rng| {
+                (0..shape.numel())
+                    .map(|_| rng.gen_range(-bound..=bound))
+                    .collect()
+            });
+            *p.data_mut() = Tensor::from_vec(data, shape, p.data().backend())?;
+        }
+        Ok(())
+    }
+
+    pub fn parameters(&self) -> Vec<&Parameter> {
+        vec![
+            &self.weight_ih,
+            &self.weight_hh,
+            &self.bias_ih,
+            &self.bias_hh,
+        ]
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![
+            &mut self.weight_ih,
+            &mut self.weight_hh,
+            &mut self.bias_ih,
+            &mut self.bias_hh,
+        ]
+    }
+
+    /// Zero h (and c) for a batch of `batch`.
+    pub fn zero_state(&self, g: &mut Graph, batch: usize) -> GraphResult<CellState> {
+        let backend = self.weight_hh.data().backend();
+        let mut zeros = || -> GraphResult<NodeId> {
+            let t = backend
+                .zeros(&Shape::new(vec![batch, self.hidden_size()]))
+                .map_err(|e| GraphError(e.to_string()))?;
+            Ok(g.var(t))
+        };
+        Ok(CellState {
+            h: zeros()?,
+            c: if K::HAS_CELL { Some(zeros()?) } else { None },
+        })
+    }
+
+    /// x W_ih + b_ih for x [N, F]; project a whole [T * B, F] sequence at once and feed
+    /// per-step rows to [Self::step_projected].
+    pub fn project_input(&self, g: &mut Graph, x: NodeId) -> GraphResult<NodeId> {
+        let w = g.param(&self.weight_ih);
+        let b = g.param(&self.bias_ih);
+        let xw = g.matmul(x, w)?;
+        g.add_broadcast(xw, b)
+    }
+
+    /// One step from a projected input gx [B, G * H].
+    pub fn step_projected(
+        &self,
+        g: &mut Graph,
+        gx: NodeId,
+        state: &CellState,
+    ) -> GraphResult<CellState> {
+        let w = g.param(&self.weight_hh);
+        let b = g.param(&self.bias_hh);
+        let hw = g.matmul(state.h, w)?;
+        let gh = g.add_broadcast(hw, b)?;
+        K::recur(g, gx, gh, state, self.hidden_size())
+    }
+
+    /// One step for x [B, F].
+    pub fn step(&self, g: &mut Graph, x: NodeId, state: &CellState) -> GraphResult<CellState> {
+        let gx = self.project_input(g, x)?;
+        self.step_projected(g, gx, state)
+    }
+}
+
+/// Layers, directions and output of a [Recurrent] stack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecurrentOptions {
+    pub num_layers: usize,
+    /// Add a second cell per layer that reads the sequence backwards; outputs are
+    /// concatenated along the feature dim.
+    pub bidirectional: bool,
+    /// Output the top layer's h at every step, [T, B, D * H], instead of its final h,
+    /// [B, D * H] (D = 2 when bidirectional).
+    pub return_sequences: bool,
+}
+
+impl Default for RecurrentOptions {
+    fn default() -> Self {
+        RecurrentOptions {
+            num_layers: 1,
+            bidirectional: false,
+            return_sequences: false,
+        }
+    }
+}
+
+/// Final (or initial) state of a [Recurrent] stack as values: h, and for LSTM c, per
+/// layer and direction ([B, H] each), layer-major with the forward direction first.
+#[derive(Clone, Debug)]
+pub struct RecurrentState {
+    pub h: Vec<Tensor>,
+    /// Empty unless the cells have a cell vector (LSTM).
+    pub c: Vec<Tensor>,
+}
+
+/// Multi-layer, optionally bidirectional recurrent layer over [T, B, F] inputs; see
+/// [RecurrentOptions] for the output. Starts from zero state unless a state is passed to
+/// [Self::forward_state] / [Self::forward_graph_state] or carried between calls
+/// ([Module::set_carry_state]; bidirectional layers carry the forward direction only).
+pub struct Recurrent<K> {
+    /// Layer-major, forward direction first.
+    cells: Vec<RecurrentCell<K>>,
+    options: RecurrentOptions,
+    carry: bool,
+    carried: Mutex<Option<RecurrentState>>,
+}
+
+pub type RNN = Recurrent<Elman>;
+pub type LSTM = Recurrent<Lstm>;
+pub type GRU = Recurrent<Gru>;
+
+fn graph_error(e: GraphError) -> TensorError {
+    TensorError::Shape(ShapeError(e.0))
+}
+
+impl<K: CellKind> Recurrent<K> {
+    /// One layer, one direction, final h as output. Weights are zeros by default; use
+    /// [Self::init_uniform] to init.
+    pub fn new(
+        input_size: usize,
+        hidden_size: usize,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        Self::with_options(
+            input_size,
+            hidden_size,
+            RecurrentOptions::default(),
+            backend,
+        )
+    }
+
+    pub fn with_options(
+        input_size: usize,
+        hidden_size: usize,
+        options: RecurrentOptions,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        if options.num_layers == 0 {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "{}: num_layers must be at least 1",
+                K::NAME
+            ))));
+        }
+        let dirs = if options.bidirectional { 2 } else { 1 };
+        let mut cells = Vec::with_capacity(options.num_layers * dirs);
+        for layer in 0..options.num_layers {
+            let layer_in = if layer == 0 {
+                input_size
+            } else {
+                dirs * hidden_size
+            };
+            for _ in 0..dirs {
+                cells.push(RecurrentCell::new(layer_in, hidden_size, backend.clone())?);
+            }
+        }
+        Ok(Recurrent {
+            cells,
+            options,
+            carry: false,
+            carried: Mutex::new(None),
+        })
+    }
+
+    /// Uniform(-1/sqrt(H), 1/sqrt(H)) for every cell.
+    pub fn init_uniform(&mut self) -> TensorResult<()> {
+        self.cells.iter_mut().try_for_each(|c| c.init_uniform())
+    }
+
+    pub fn options(&self) -> RecurrentOptions {
+        self.options
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.cells[0].hidden_size()
+    }
+
+    /// Cells, layer-major with the forward direction first.
+    pub fn cells(&self) -> &[RecurrentCell<K>] {
+        &self.cells
+    }
+
+    pub fn cells_mut(&mut self) -> &mut [RecurrentCell<K>] {
+        &mut self.cells
+    }
+
+    fn directions(&self) -> usize {
+        if self.options.bidirectional {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Forward from `initial` (zeros if None), returning the output and the final state.
+    /// Ignores any carried state.
+    pub fn forward_state(
+        &self,
+        x: &Tensor,
+        initial: Option<&RecurrentState>,
+    ) -> TensorResult<(Tensor, RecurrentState)> {
+        let mut g = Graph::new();
+        let x_id = g.var(x.clone());
+        let (out, _, state) = self
+            .forward_graph_state(&mut g, x_id, initial)
+            .map_err(graph_error)?;
+        Ok((g.data(out).map_err(graph_error)?.clone(), state))
+    }
+
+    /// [Module::forward_graph] from `initial` (zeros if None); also returns the final state
+    /// as values. `initial` enters the graph as constants, so no gradient flows into it.
+    pub fn forward_graph_state(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+        initial: Option<&RecurrentState>,
+    ) -> GraphResult<(NodeId, Vec<NodeId>, RecurrentState)> {
+        let (out, params, finals) = self.unroll(g, x_id, initial)?;
+        let h = finals
+            .iter()
+            .map(|s| g.data(s.h).cloned())
+            .collect::<GraphResult<_>>()?;
+        let c = finals
+            .iter()
+            .filter_map(|s| s.c)
+            .map(|c| g.data(c).cloned())
+            .collect::<GraphResult<_>>()?;
+        Ok((out, params, RecurrentState { h, c }))
+    }
+
+    fn initial_states(
+        &self,
+        g: &mut Graph,
+        batch: usize,
+        initial: Option<&RecurrentState>,
+    ) -> GraphResult<Vec<CellState>> {
+        let Some(state) = initial else {
+            return self.cells.iter().map(|c| c.zero_state(g, batch)).collect();
+        };
+        let n = self.cells.len();
+        let want = [batch, self.hidden_size()];
+        let c_len = if K::HAS_CELL { n } else { 0 };
+        let shapes_ok = state
+            .h
+            .iter()
+            .chain(&state.c)
+            .all(|t| t.shape().dims() == want);
+        if state.h.len() != n || state.c.len() != c_len || !shapes_ok {
+            return Err(GraphError(format!(
+                "{}: initial state needs {} h and {} c tensors of {:?}",
+                K::NAME,
+                n,
+                c_len,
+                want
+            )));
+        }
+        Ok((0..n)
+            .map(|k| CellState {
+                h: g.var(state.h[k].clone()),
+                c: state.c.get(k).map(|c| g.var(c.clone())),
+            })
+            .collect())
+    }
+
+    /// Unrolled graph: (output, parameter nodes, final state of each cell).
+    fn unroll(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+        initial: Option<&RecurrentState>,
+    ) -> GraphResult<(NodeId, Vec<NodeId>, Vec<CellState>)> {
+        let dims = g.data(x_id)?.shape().dims().to_vec();
+        let input_size = self.cells[0].input_size();
+        let (steps, batch) = match dims[..] {
+            [t, b, f] if t > 0 && f == input_size => (t, b),
+            _ => {
+                return Err(GraphError(format!(
+                    "{}: expected input [T, B, {}], got {:?}",
+                    K::NAME,
+                    input_size,
+                    dims
+                )))
+            }
+        };
+        let states = self.initial_states(g, batch, initial)?;
+        let params = self.parameters().into_iter().map(|p| g.param(p)).collect();
+        let dirs = self.directions();
+        let join = |g: &mut Graph, ids: &[NodeId], dim: usize| match ids {
+            [id] => Ok(*id),
+            _ => g.concat(ids, dim),
+        };
+
+        // Each layer reads and writes [T * B, features]; row block t is step t.
+        let mut layer_in = g.reshape(x_id, &[steps * batch, input_size])?;
+        let mut finals = Vec::with_capacity(self.cells.len());
+        for layer in 0..self.options.num_layers {
+            let keep_outputs = layer + 1 < self.options.num_layers || self.options.return_sequences;
+            let mut dir_outs = Vec::with_capacity(dirs);
+            for d in 0..dirs {
+                let cell = &self.cells[layer * dirs + d];
+                let gx = cell.project_input(g, layer_in)?;
+                let mut state = states[layer * dirs + d];
+                let mut outs = vec![0; steps];
+                for i in 0..steps {
+                    let t = if d == 0 { i } else { steps - 1 - i };
+                    let gx_t = g.narrow(gx, 0, t * batch, batch)?;
+                    state = cell.step_projected(g, gx_t, &state)?;
+                    outs[t] = state.h;
+                }
+                finals.push(state);
+                if keep_outputs {
+                    dir_outs.push(join(g, &outs, 0)?);
+                }
+            }
+            if keep_outputs {
+                layer_in = join(g, &dir_outs, 1)?;
+            }
+        }
+
+        let out = if self.options.return_sequences {
+            g.reshape(layer_in, &[steps, batch, dirs * self.hidden_size()])?
+        } else {
+            let top: Vec<NodeId> = finals[finals.len() - dirs..].iter().map(|s| s.h).collect();
+            join(g, &top, 1)?
+        };
+        Ok((out, params, finals))
+    }
+
+    fn carried(&self) -> Option<RecurrentState> {
+        if !self.carry {
+            return None;
+        }
+        self.carried.lock().unwrap().clone()
+    }
+
+    /// Keep the final state for the next call. Only the forward direction is carried: the
+    /// reverse one ends at the start of this call's sequence, so it does not continue into
+    /// the next call and restarts from zeros there.
+    fn store_carried(&self, mut state: RecurrentState) -> TensorResult<()> {
+        if !self.carry {
+            return Ok(());
+        }
+        let dirs = self.directions();
+        for k in (0..self.cells.len()).filter(|k| k % dirs == 1) {
+            for t in [state.h.get_mut(k), state.c.get_mut(k)]
+                .into_iter()
+                .flatten()
+            {
+                *t = t.backend().zeros(t.shape()).map_err(TensorError::from)?;
+            }
+        }
+        *self.carried.lock().unwrap() = Some(state);
+        Ok(())
+    }
+}
+
+impl<K: CellKind> Module for Recurrent<K> {
+    fn parameters(&self) -> Vec<&Parameter> {
+        self.cells.iter().flat_map(|c| c.parameters()).collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        self.cells
+            .iter_mut()
+            .flat_map(|c| c.parameters_mut())
+            .collect()
+    }
+
+    /// `weight_ih_l0`, `bias_hh_l1_reverse`, ...
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        let dirs = self.directions();
+        self.cells
+            .iter()
+            .enumerate()
+            .flat_map(|(k, c)| {
+                let suffix = cell_suffix(k, dirs);
+                ["weight_ih", "weight_hh", "bias_ih", "bias_hh"]
+                    .into_iter()
+                    .zip(c.parameters())
+                    .map(move |(name, p)| (format!("{}{}", name, suffix), p))
+            })
+            .collect()
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        let dirs = self.directions();
+        self.cells
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(k, c)| {
+                let suffix = cell_suffix(k, dirs);
+                ["weight_ih", "weight_hh", "bias_ih", "bias_hh"]
+                    .into_iter()
+                    .zip(c.parameters_mut())
+                    .map(move |(name, p)| (format!("{}{}", name, suffix), p))
+            })
+            .collect()
+    }
+
+    /// Runs the unrolled graph without backward.
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        let (out, state) = self.forward_state(x, self.carried().as_ref())?;
+        self.store_carried(state)?;
+        Ok(out)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let (out, params, state) = self.forward_graph_state(g, x_id, self.carried().as_ref())?;
+        self.store_carried(state)
+            .map_err(|e| GraphError(e.to_string()))?;
+        Ok((out, params))
+    }
+
+    fn set_carry_state(&mut self, carry: bool) {
+        self.carry = carry;
+        *self.carried.get_mut().unwrap() = None;
+    }
+}
+
+/// `_l{layer}` plus `_reverse` for the backward direction.
+fn cell_suffix(k: usize, dirs: usize) -> String {
+    let reverse = if k % dirs == 1 { "_reverse" } else { "" };
+    format!("_l{}{}", k / dirs, reverse)
+}
+
+impl<K: CellKind> Layer for Recurrent<K> {}
@@ -0,0 +1,149 @@
+//! Sequential: chains boxed modules; output of each is the input of the next.
+
+use super::module::{prefixed, Module};
+use super::Layer;
+use crate::autograd::{Graph, NodeId};
+use crate::parameter::Parameter;
+use crate::tensor::Tensor;
+
+/// Ordered container of modules. Parameters are those of the children, in order.
+#[derive(Default)]
+pub struct Sequential {
+    layers: Vec<Box<dyn Module>>,
+}
+
+impl Sequential {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_layers(layers: Vec<Box<dyn Module>>) -> Self {
+        Sequential { layers }
+    }
+
+    /// Append a module (builder style).
+    pub fn with(mut self, layer: impl Module + 'static) -> Self {
+        self.push(layer);
+        self
+    }
+
+    pub fn push(&mut self, layer: impl Module + 'static) {
+        self.layers.push(Box::new(layer));
+    }
+
+    pub fn push_boxed(&mut self, layer: Box<dyn Module>) {
+        self.layers.push(layer);
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn layers(&self) -> &[Box<dyn Module>] {
+        &self.layers
+    }
+
+    pub fn layers_mut(&mut self) -> &mut [Box<dyn Module>] {
+        &mut self.layers
+    }
+}
+
+impl Module for Sequential {
+    fn parameters(&self) -> Vec<&Parameter> {
+        self.layers.iter().flat_map(|l| l.parameters()).collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        self.layers
+            .iter_mut()
+            .flat_map(|l| l.parameters_mut())
+            .collect()
+    }
+
+    /// Children are named by index: `0.weight`, `2.bias`.
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        self.layers
+            .iter()
+            .enumerate()
+            .flat_map(|(i, l)| prefixed(&i.to_string(), l.named_parameters()))
+            .collect()
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        self.layers
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(i, l)| prefixed(&i.to_string(), l.named_parameters_mut()))
+            .collect()
+    }
+
+    fn set_training(&mut self, training: bool) {
+        for layer in &mut self.layers {
+            layer.set_training(training);
+        }
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.layers.iter().all(|l| l.is_deterministic())
+    }
+
+    fn set_carry_state(&mut self, carry: bool) {
+        for layer in &mut self.layers {
+            layer.set_carry_state(carry);
+        }
+    }
+
+    fn named_buffers(&self) -> Vec<(String, Tensor)> {
+        self.layers
+            .iter()
+            .enumerate()
+            .flat_map(|(i, l)| prefixed(&i.to_string(), l.named_buffers()))
+            .collect()
+    }
+
+    fn set_buffer(&mut self, name: &str, value: Tensor) -> crate::TensorResult<()> {
+        let child = name
+            .split_once('.')
+            .and_then(|(i, rest)| Some((self.layers.get_mut(i.parse::<usize>().ok()?)?, rest)));
+        match child {
+            Some((layer, rest)) => layer.set_buffer(rest, value),
+            None => Err(crate::TensorError::Shape(crate::ShapeError(format!(
+                "no buffer named {:?}",
+                name
+            )))),
+        }
+    }
+
+    /// Empty container is the identity.
+    fn forward(&self, x: &Tensor) -> crate::TensorResult<Tensor> {
+        let Some((first, rest)) = self.layers.split_first() else {
+            return Ok(x.clone());
+        };
+        let mut h = first.forward(x)?;
+        for layer in rest {
+            h = layer.forward(&h)?;
+        }
+        Ok(h)
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let mut h_id = x_id;
+        let mut param_ids = Vec::new();
+        for layer in &self.layers {
+            let (out_id, ids) = layer.forward_graph(g, h_id)?;
+            h_id = out_id;
+            param_ids.extend(ids);
+        }
+        Ok((h_id, param_ids))
+    }
+}
+
+impl Layer for Sequential {}
@@ -0,0 +1,432 @@
+//! Transformer encoder and decoder layers on batch-first [B, T, E] inputs, built from
+//! [MultiheadAttention], [Linear] and [LayerNorm]. Each sublayer is wrapped in a residual
+//! connection, normalised after the addition (post-norm, the original layout) or before the
+//! sublayer (pre-norm, `norm_first`).
+
+use super::attention::{evaluate, AttentionMask, MultiheadAttention};
+use super::module::{prefixed, Module};
+use super::{Dropout, Layer, LayerNorm, Linear};
+use crate::autograd::{Graph, GraphResult, NodeId};
+use crate::parameter::Parameter;
+use crate::tensor::{Tensor, TensorResult};
+use std::sync::Arc;
+
+/// Options shared by [TransformerEncoderLayer] and [TransformerDecoderLayer].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransformerOptions {
+    /// Dropout on the attention weights, the feedforward hidden layer and each sublayer
+    /// output before the residual addition. 0 by default.
+    pub dropout: f32,
+    /// Normalise each sublayer's input (pre-norm) instead of the residual sum (post-norm).
+    pub norm_first: bool,
+}
+
+/// The residual wrapping and feedforward block shared by both layers.
+struct Sublayers {
+    dropout: Dropout,
+    norm_first: bool,
+}
+
+impl Sublayers {
+    fn new(options: &TransformerOptions) -> TensorResult<Self> {
+        Ok(Sublayers {
+            dropout: Dropout::new(options.dropout)?,
+            norm_first: options.norm_first,
+        })
+    }
+
+    /// x + block(norm(x)) when norm_first, else norm(x + block(x)); dropout on block's output.
+    fn residual(
+        &self,
+        g: &mut Graph,
+        x: NodeId,
+        norm: &LayerNorm,
+        params: &mut Vec<NodeId>,
+        block: impl FnOnce(&mut Graph, NodeId) -> GraphResult<(NodeId, Vec<NodeId>)>,
+    ) -> GraphResult<NodeId> {
+        let mut normed = |g: &mut Graph, x: NodeId| {
+            let (y, p) = norm.forward_graph(g, x)?;
+            params.extend(p);
+            Ok::<_, crate::GraphError>(y)
+        };
+        let input = if self.norm_first { normed(g, x)? } else { x };
+        let (y, p) = block(g, input)?;
+        let (y, _) = self.dropout.forward_graph(g, y)?;
+        let sum = g.add(x, y)?;
+        let out = if self.norm_first {
+            sum
+        } else {
+            normed(g, sum)?
+        };
+        params.extend(p);
+        Ok(out)
+    }
+
+    /// linear2(dropout(relu(linear1(x)))).
+    fn feedforward(
+        &self,
+        g: &mut Graph,
+        x: NodeId,
+        linear1: &Linear,
+        linear2: &Linear,
+    ) -> GraphResult<(NodeId, Vec<NodeId>)> {
+        let (h, mut params) = linear1.forward_graph(g, x)?;
+        let h = g.relu(h)?;
+        let (h, _) = self.dropout.forward_graph(g, h)?;
+        let (y, p) = linear2.forward_graph(g, h)?;
+        params.extend(p);
+        Ok((y, params))
+    }
+}
+
+/// Self-attention then feedforward, each in a residual block.
+pub struct TransformerEncoderLayer {
+    pub self_attn: MultiheadAttention,
+    pub linear1: Linear,
+    pub linear2: Linear,
+    pub norm1: LayerNorm,
+    pub norm2: LayerNorm,
+    sub: Sublayers,
+    options: TransformerOptions,
+}
+
+impl TransformerEncoderLayer {
+    /// Post-norm without dropout. Weights are zeros by default; use [Self::init_xavier].
+    pub fn new(
+        d_model: usize,
+        nhead: usize,
+        dim_feedforward: usize,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        Self::with_options(
+            d_model,
+            nhead,
+            dim_feedforward,
+            TransformerOptions::default(),
+            backend,
+        )
+    }
+
+    pub fn with_options(
+        d_model: usize,
+        nhead: usize,
+        dim_feedforward: usize,
+        options: TransformerOptions,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        Ok(TransformerEncoderLayer {
+            self_attn: MultiheadAttention::new(d_model, nhead, backend.clone())?
+                .with_dropout(options.dropout)?,
+            norm1: LayerNorm::new(&[d_model], backend.clone())?,
+            norm2: LayerNorm::new(&[d_model], backend.clone())?,
+            linear1: Linear::new(d_model, dim_feedforward, backend.clone())?,
+            linear2: Linear::new(dim_feedforward, d_model, backend)?,
+            sub: Sublayers::new(&options)?,
+            options,
+        })
+    }
+
+    pub fn init_xavier(&mut self) -> TensorResult<()> {
+        self.self_attn.init_xavier()?;
+        self.linear1.init_xavier()?;
+        self.linear2.init_xavier()
+    }
+
+    pub fn options(&self) -> TransformerOptions {
+        self.options
+    }
+
+    /// [Module::forward_graph] with `mask` applied to the self-attention.
+    pub fn forward_graph_masked(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+        mask: &AttentionMask,
+    ) -> GraphResult<(NodeId, Vec<NodeId>)> {
+        let mut params = Vec::new();
+        let x = self
+            .sub
+            .residual(g, x_id, &self.norm1, &mut params, |g, x| {
+                self.self_attn.attend_graph(g, x, x, x, mask)
+            })?;
+        let x = self.sub.residual(g, x, &self.norm2, &mut params, |g, x| {
+            self.sub.feedforward(g, x, &self.linear1, &self.linear2)
+        })?;
+        Ok((x, params))
+    }
+
+    /// Tensor counterpart of [Self::forward_graph_masked].
+    pub fn forward_masked(&self, x: &Tensor, mask: &AttentionMask) -> TensorResult<Tensor> {
+        evaluate(&[x], |g, ids| {
+            Ok(self.forward_graph_masked(g, ids[0], mask)?.0)
+        })
+    }
+
+    fn children(&self) -> [(&'static str, &dyn Module); 5] {
+        [
+            ("self_attn", &self.self_attn),
+            ("linear1", &self.linear1),
+            ("linear2", &self.linear2),
+            ("norm1", &self.norm1),
+            ("norm2", &self.norm2),
+        ]
+    }
+
+    fn children_mut(&mut self) -> [(&'static str, &mut dyn Module); 5] {
+        [
+            ("self_attn", &mut self.self_attn),
+            ("linear1", &mut self.linear1),
+            ("linear2", &mut self.linear2),
+            ("norm1", &mut self.norm1),
+            ("norm2", &mut self.norm2),
+        ]
+    }
+}
+
+/// Self-attention, cross-attention over an encoder output (`memory`) and feedforward, each in
+/// a residual block. The self-attention is causal. Without memory the cross-attention block
+/// is skipped, which makes [Module::forward] a decoder-only (GPT-style) block.
+pub struct TransformerDecoderLayer {
+    pub self_attn: MultiheadAttention,
+    pub cross_attn: MultiheadAttention,
+    pub norm1: LayerNorm,
+    pub norm2: LayerNorm,
+    pub linear1: Linear,
+    pub linear2: Linear,
+    pub norm3: LayerNorm,
+    sub: Sublayers,
+    options: TransformerOptions,
+}
+
+impl TransformerDecoderLayer {
+    /// Post-norm without dropout. Weights are zeros by default; use [Self::init_xavier].
+    pub fn new(
+        d_model: usize,
+        nhead: usize,
+        dim_feedforward: usize,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        Self::with_options(
+            d_model,
+            nhead,
+            dim_feedforward,
+            TransformerOptions::default(),
+            backend,
+        )
+    }
+
+    pub fn with_options(
+        d_model: usize,
+        nhead: usize,
+        dim_feedforward: usize,
+        options: TransformerOptions,
+        backend: Arc<dyn crate::backend::Backend>,
+    ) -> TensorResult<Self> {
+        let attn = || {
+            MultiheadAttention::new(d_model, nhead, backend.clone())?.with_dropout(options.dropout)
+        };
+        Ok(TransformerDecoderLayer {
+            self_attn: attn()?.with_causal(true),
+            cross_attn: attn()?,
+            norm1: LayerNorm::new(&[d_model], backend.clone())?,
+            norm2: LayerNorm::new(&[d_model], backend.clone())?,
+            norm3: LayerNorm::new(&[d_model], backend.clone())?,
+            linear1: Linear::new(d_model, dim_feedforward, backend.clone())?,
+            linear2: Linear::new(dim_feedforward, d_model, backend)?,
+            sub: Sublayers::new(&options)?,
+            options,
+        })
+    }
+
+    pub fn init_xavier(&mut self) -> TensorResult<()> {
+        self.self_attn.init_xavier()?;
+        self.cross_attn.init_xavier()?;
+        self.linear1.init_xavier()?;
+        self.linear2.init_xavier()
+    }
+
+    pub fn options(&self) -> TransformerOptions {
+        self.options
+    }
+
+    /// Decode `tgt` [B, T, E] attending to `memory` [B, S, E] (if any). `tgt_mask` is applied
+    /// to the self-attention on top of its causal mask; `memory_mask` to the cross-attention.
+    pub fn forward_graph_with(
+        &self,
+        g: &mut Graph,
+        tgt: NodeId,
+        memory: Option<NodeId>,
+        tgt_mask: &AttentionMask,
+        memory_mask: &AttentionMask,
+    ) -> GraphResult<(NodeId, Vec<NodeId>)> {
+        let mut params = Vec::new();
+        let mut x = self
+            .sub
+            .residual(g, tgt, &self.norm1, &mut params, |g, x| {
+                self.self_attn.attend_graph(g, x, x, x, tgt_mask)
+            })?;
+        if let Some(memory) = memory {
+            x = self.sub.residual(g, x, &self.norm2, &mut params, |g, x| {
+                self.cross_attn
+                    .attend_graph(g, x, memory, memory, memory_mask)
+            })?;
+        }
+        let x = self.sub.residual(g, x, &self.norm3, &mut params, |g, x| {
+            self.sub.feedforward(g, x, &self.linear1, &self.linear2)
+        })?;
+        Ok((x, params))
+    }
+
+    /// Tensor counterpart of [Self::forward_graph_with].
+    pub fn forward_with(
+        &self,
+        tgt: &Tensor,
+        memory: Option<&Tensor>,
+        tgt_mask: &AttentionMask,
+        memory_mask: &AttentionMask,
+    ) -> TensorResult<Tensor> {
+        let inputs: Vec<&Tensor> = std::iter::once(tgt).chain(memory).collect();
+        evaluate(&inputs, |g, ids| {
+            let memory = ids.get(1).copied();
+            Ok(self
+                .forward_graph_with(g, ids[0], memory, tgt_mask, memory_mask)?
+                .0)
+        })
+    }
+
+    fn children(&self) -> [(&'static str, &dyn Module); 7] {
+        [
+            ("self_attn", &self.self_attn),
+            ("cross_attn", &self.cross_attn),
+            ("linear1", &self.linear1),
+            ("linear2", &self.linear2),
+            ("norm1", &self.norm1),
+            ("norm2", &self.norm2),
+            ("norm3", &self.norm3),
+        ]
+    }
+
+    fn children_mut(&mut self) -> [(&'static str, &mut dyn Module); 7] {
+        [
+            ("self_attn", &mut self.self_attn),
+            ("cross_attn", &mut self.cross_attn),
+            ("linear1", &mut self.linear1),
+            ("linear2", &mut self.linear2),
+            ("norm1", &mut self.norm1),
+            ("norm2", &mut self.norm2),
+            ("norm3", &mut self.norm3),
+        ]
+    }
+}
+
+impl Module for TransformerEncoderLayer {
+    fn parameters(&self) -> Vec<&Parameter> {
+        self.children()
+            .into_iter()
+            .flat_map(|(_, m)| m.parameters())
+            .collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        self.children_mut()
+            .into_iter()
+            .flat_map(|(_, m)| m.parameters_mut())
+            .collect()
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        self.children()
+            .into_iter()
+            .flat_map(|(name, m)| prefixed(name, m.named_parameters()))
+            .collect()
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        self.children_mut()
+            .into_iter()
+            .flat_map(|(name, m)| prefixed(name, m.named_parameters_mut()))
+            .collect()
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.sub.dropout.set_training(training);
+        for (_, m) in self.children_mut() {
+            m.set_training(training);
+        }
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.sub.dropout.is_deterministic()
+            && self.children().iter().all(|(_, m)| m.is_deterministic())
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        evaluate(&[x], |g, ids| Ok(self.forward_graph(g, ids[0])?.0))
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        self.forward_graph_masked(g, x_id, &AttentionMask::default())
+    }
+}
+
+impl Module for TransformerDecoderLayer {
+    fn parameters(&self) -> Vec<&Parameter> {
+        self.children()
+            .into_iter()
+            .flat_map(|(_, m)| m.parameters())
+            .collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        self.children_mut()
+            .into_iter()
+            .flat_map(|(_, m)| m.parameters_mut())
+            .collect()
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Parameter)> {
+        self.children()
+            .into_iter()
+            .flat_map(|(name, m)| prefixed(name, m.named_parameters()))
+            .collect()
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter)> {
+        self.children_mut()
+            .into_iter()
+            .flat_map(|(name, m)| prefixed(name, m.named_parameters_mut()))
+            .collect()
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.sub.dropout.set_training(training);
+        for (_, m) in self.children_mut() {
+            m.set_training(training);
+        }
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.sub.dropout.is_deterministic()
+            && self.children().iter().all(|(_, m)| m.is_deterministic())
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        evaluate(&[x], |g, ids| Ok(self.forward_graph(g, ids[0])?.0))
+    }
+
+    fn forward_graph(
+        &self,
+        g: &mut Graph,
+        x_id: NodeId,
+    ) -> crate::GraphResult<(NodeId, Vec<NodeId>)> {
+        let none = AttentionMask::default();
+        self.forward_graph_with(g, x_id, None, &none, &none)
+    }
+}
+
+impl Layer for TransformerEncoderLayer {}
+impl Layer for TransformerDecoderLayer {}
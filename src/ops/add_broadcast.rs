@@ -1,6 +1,6 @@
 //! AddBroadcast: a (e.g. [N,K]) + b (e.g. [K]) with broadcast. Backward: grad_a = grad_out, grad_b = sum(grad_out, dim=0) reshaped to [K].
 
-use super::{Op, OpError, OpId, OpResult};
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
 use crate::shape::Shape;
 use crate::tensor::Tensor;
 
@@ -15,6 +15,14 @@ impl Op for AddBroadcast {
         "AddBroadcast"
     }
 
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(GradCheckSpec::new(vec![
+            vec![vec![3, 4], vec![4]],
+            vec![vec![1, 1], vec![1]],
+            vec![vec![5, 1], vec![1]],
+        ]))
+    }
+
     fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
         if inputs.len() != 2 {
             return Err(OpError("AddBroadcast requires 2 inputs".into()));
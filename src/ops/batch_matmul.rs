@@ -0,0 +1,161 @@
+//! Batched matmul: c[n] = alpha * a[n] @ b[n] for a [N, M, K] and b [N, K, P], or b stored
+//! as [N, P, K] and used transposed (attention scores q @ k^T). Backward:
+//! grad_a = alpha * grad_c @ b^T, grad_b = alpha * a^T @ grad_c (transposed back if needed).
+
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
+use crate::backend::gemm::sgemm;
+use crate::shape::{Shape, ShapeError};
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use std::sync::Arc;
+
+/// Row-major [rows, cols] -> [cols, rows].
+fn transposed(rows: usize, cols: usize, data: &[f32]) -> Vec<f32> {
+    let mut out = vec![0.0; rows * cols];
+    for i in 0..rows {
+        for j in 0..cols {
+            out[j * rows + i] = data[i * cols + j];
+        }
+    }
+    out
+}
+
+pub struct BatchMatMul {
+    transpose_b: bool,
+    alpha: f32,
+}
+
+impl BatchMatMul {
+    pub fn new(transpose_b: bool, alpha: f32) -> Self {
+        BatchMatMul { transpose_b, alpha }
+    }
+
+    /// (N, M, K, P) after checking both inputs.
+    fn dims(&self, a: &Tensor, b: &Tensor) -> TensorResult<(usize, usize, usize, usize)> {
+        let (ad, bd) = (a.shape().dims(), b.shape().dims());
+        let dims = match (ad, bd) {
+            (&[n, m, k], &[bn, x, y]) if bn == n => {
+                let (bk, p) = if self.transpose_b { (y, x) } else { (x, y) };
+                (bk == k).then_some((n, m, k, p))
+            }
+            _ => None,
+        };
+        dims.ok_or_else(|| {
+            TensorError::Shape(ShapeError(format!(
+                "batch_matmul: cannot multiply {:?} by {:?}{}",
+                ad,
+                bd,
+                if self.transpose_b { "^T" } else { "" }
+            )))
+        })
+    }
+
+    /// b[n] as a row-major [K, P] matrix.
+    fn rhs(&self, b: &[f32], k: usize, p: usize) -> Vec<f32> {
+        if self.transpose_b {
+            transposed(p, k, b)
+        } else {
+            b.to_vec()
+        }
+    }
+
+    pub(crate) fn run(&self, a: &Tensor, b: &Tensor) -> TensorResult<Tensor> {
+        let (n, m, k, p) = self.dims(a, b)?;
+        let mut out = a.backend().alloc(n * m * p);
+        let (ad, bd) = (a.data(), b.data());
+        for i in 0..n {
+            let rhs = self.rhs(&bd[i * k * p..(i + 1) * k * p], k, p);
+            let dst = &mut out[i * m * p..(i + 1) * m * p];
+            sgemm(m, k, p, &ad[i * m * k..(i + 1) * m * k], &rhs, dst);
+            dst.iter_mut().for_each(|v| *v *= self.alpha);
+        }
+        Tensor::from_vec(out, Shape::new(vec![n, m, p]), a.backend())
+    }
+
+    fn run_backward(&self, grad_out: &Tensor, a: &Tensor, b: &Tensor) -> TensorResult<Vec<Tensor>> {
+        let (n, m, k, p) = self.dims(a, b)?;
+        if grad_out.shape().dims() != [n, m, p] {
+            return Err(TensorError::Shape(ShapeError(
+                "batch_matmul backward: grad shape != output shape".into(),
+            )));
+        }
+        let mut grad_a = a.backend().alloc(a.numel());
+        let mut grad_b = b.backend().alloc(b.numel());
+        let (ad, bd, gd) = (a.data(), b.data(), grad_out.data());
+        for i in 0..n {
+            let a_i = &ad[i * m * k..(i + 1) * m * k];
+            let g_i = &gd[i * m * p..(i + 1) * m * p];
+            let rhs = self.rhs(&bd[i * k * p..(i + 1) * k * p], k, p);
+            // grad_a = g @ rhs^T: [M, P] @ [P, K]
+            let ga = &mut grad_a[i * m * k..(i + 1) * m * k];
+            sgemm(m, p, k, g_i, &transposed(k, p, &rhs), ga);
+            ga.iter_mut().for_each(|v| *v *= self.alpha);
+            // grad_rhs = a^T @ g: [K, M] @ [M, P], stored in b's layout
+            let mut gr = vec![0.0; k * p];
+            sgemm(k, m, p, &transposed(m, k, a_i), g_i, &mut gr);
+            let gr = if self.transpose_b {
+                transposed(k, p, &gr)
+            } else {
+                gr
+            };
+            for (dst, v) in grad_b[i * k * p..(i + 1) * k * p].iter_mut().zip(gr) {
+                *dst = self.alpha * v;
+            }
+        }
+        Ok(vec![
+            Tensor::from_vec(grad_a, a.shape().clone(), a.backend())?,
+            Tensor::from_vec(grad_b, b.shape().clone(), b.backend())?,
+        ])
+    }
+}
+
+impl Op for BatchMatMul {
+    fn id(&self) -> OpId {
+        OpId::BatchMatMul
+    }
+
+    fn name(&self) -> &'static str {
+        "BatchMatMul"
+    }
+
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        let b = if self.transpose_b {
+            vec![2, 4, 3]
+        } else {
+            vec![2, 3, 4]
+        };
+        Some(GradCheckSpec::new(vec![vec![vec![2, 2, 3], b]]))
+    }
+
+    /// [transpose_b (0 or 1), alpha].
+    fn attrs(&self) -> Vec<f32> {
+        vec![if self.transpose_b { 1.0 } else { 0.0 }, self.alpha]
+    }
+
+    fn with_attrs(&self, attrs: &[f32]) -> Option<Arc<dyn Op>> {
+        match *attrs {
+            [t, alpha] if t == 0.0 || t == 1.0 => Some(Arc::new(BatchMatMul::new(t == 1.0, alpha))),
+            _ => None,
+        }
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 2 {
+            return Err(OpError("BatchMatMul requires 2 inputs".into()));
+        }
+        self.run(inputs[0], inputs[1])
+            .map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 2 {
+            return Err(OpError("BatchMatMul backward requires 2 inputs".into()));
+        }
+        self.run_backward(grad_out, inputs[0], inputs[1])
+            .map_err(|e| OpError(e.to_string()))
+    }
+}
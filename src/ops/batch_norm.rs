@@ -0,0 +1,206 @@
+//! BatchNorm: per-channel normalisation of x [N, C, ...] followed by gamma * x_hat + beta.
+//! With batch statistics (training) the mean and variance depend on x, so
+//! grad_x = gamma * inv_std / M * (M * dy - sum(dy) - x_hat * sum(dy * x_hat)) over the M
+//! values of each channel; with fixed statistics (evaluation) grad_x = dy * gamma * inv_std.
+
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
+use crate::shape::{Shape, ShapeError};
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use std::sync::Arc;
+
+pub struct BatchNorm {
+    eps: f32,
+    /// Fixed (mean, variance) per channel; None normalises with the batch's own statistics.
+    stats: Option<(Vec<f32>, Vec<f32>)>,
+}
+
+/// x viewed as [N, C, S].
+fn layout(x: &Tensor, channels: usize) -> TensorResult<(usize, usize, usize)> {
+    match x.shape().dims() {
+        [n, c, rest @ ..] if *c == channels => Ok((*n, *c, rest.iter().product())),
+        d => Err(TensorError::Shape(ShapeError(format!(
+            "batch_norm: expected [N, {}, ...], got {:?}",
+            channels, d
+        )))),
+    }
+}
+
+/// Per-channel mean and biased variance of x [N, C, ...] (accumulated in f64).
+pub(crate) fn channel_stats(x: &Tensor) -> TensorResult<(Vec<f32>, Vec<f32>)> {
+    let c = x.shape().dims().get(1).copied().unwrap_or(0);
+    let (n, c, s) = layout(x, c)?;
+    let m = (n * s) as f64;
+    let data = x.data();
+    let mut mean = vec![0.0f32; c];
+    let mut var = vec![0.0f32; c];
+    for ch in 0..c {
+        let values =
+            || (0..n).flat_map(move |b| data[(b * c + ch) * s..(b * c + ch + 1) * s].iter());
+        let mu = values().map(|&v| v as f64).sum::<f64>() / m;
+        let sq = values().map(|&v| (v as f64 - mu).powi(2)).sum::<f64>() / m;
+        mean[ch] = mu as f32;
+        var[ch] = sq as f32;
+    }
+    Ok((mean, var))
+}
+
+impl BatchNorm {
+    /// Normalise with batch statistics.
+    pub fn training(eps: f32) -> Self {
+        BatchNorm { eps, stats: None }
+    }
+
+    /// Normalise with fixed per-channel mean and variance.
+    pub fn with_stats(eps: f32, mean: Vec<f32>, var: Vec<f32>) -> Self {
+        BatchNorm {
+            eps,
+            stats: Some((mean, var)),
+        }
+    }
+
+    /// x_hat, inv_std per channel, and the [N, C, S] view, for x with gamma's channel count.
+    fn normalize(
+        &self,
+        x: &Tensor,
+        gamma: &Tensor,
+        beta: &Tensor,
+    ) -> TensorResult<(Vec<f32>, Vec<f32>, [usize; 3])> {
+        let c = gamma.numel();
+        let (n, c, s) = layout(x, c)?;
+        if gamma.shape().dims() != [c] || beta.shape().dims() != [c] {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "batch_norm: expected gamma and beta [{}], got {:?} and {:?}",
+                c,
+                gamma.shape().dims(),
+                beta.shape().dims()
+            ))));
+        }
+        let (mean, var) = match &self.stats {
+            Some((mean, var)) if mean.len() == c && var.len() == c => (mean.clone(), var.clone()),
+            Some((mean, _)) => {
+                return Err(TensorError::Shape(ShapeError(format!(
+                    "batch_norm: {} channels, statistics for {}",
+                    c,
+                    mean.len()
+                ))))
+            }
+            None => channel_stats(x)?,
+        };
+        let inv_std: Vec<f32> = var.iter().map(|v| 1.0 / (v + self.eps).sqrt()).collect();
+        let mut x_hat = x.backend().alloc(x.numel());
+        for (i, (o, &v)) in x_hat.iter_mut().zip(x.data()).enumerate() {
+            let ch = i / s.max(1) % c;
+            *o = (v - mean[ch]) * inv_std[ch];
+        }
+        Ok((x_hat, inv_std, [n, c, s]))
+    }
+
+    /// Forward with tensor errors (shared by [Op::forward] and the nn layers).
+    pub(crate) fn run(&self, x: &Tensor, gamma: &Tensor, beta: &Tensor) -> TensorResult<Tensor> {
+        let (mut y, _, [_, c, s]) = self.normalize(x, gamma, beta)?;
+        let (g, b) = (gamma.data(), beta.data());
+        for (i, v) in y.iter_mut().enumerate() {
+            let ch = i / s.max(1) % c;
+            *v = g[ch] * *v + b[ch];
+        }
+        Tensor::from_vec(y, x.shape().clone(), x.backend())
+    }
+
+    fn run_backward(&self, dy: &Tensor, inputs: &[&Tensor]) -> TensorResult<Vec<Tensor>> {
+        let (x, gamma, beta) = (inputs[0], inputs[1], inputs[2]);
+        if dy.shape().dims() != x.shape().dims() {
+            return Err(TensorError::Shape(ShapeError(
+                "batch_norm backward: grad shape != input shape".into(),
+            )));
+        }
+        let (x_hat, inv_std, [n, c, s]) = self.normalize(x, gamma, beta)?;
+        let backend = x.backend();
+        let (dyv, g) = (dy.data(), gamma.data());
+        // Per channel: sum(dy) and sum(dy * x_hat), which are also grad_beta and grad_gamma.
+        let mut grad_beta = backend.alloc(c);
+        let mut grad_gamma = backend.alloc(c);
+        for (i, (&d, &xh)) in dyv.iter().zip(&x_hat).enumerate() {
+            let ch = i / s.max(1) % c;
+            grad_beta[ch] += d;
+            grad_gamma[ch] += d * xh;
+        }
+        let m = (n * s) as f32;
+        let mut grad_x = backend.alloc(x.numel());
+        for (i, (o, (&d, &xh))) in grad_x.iter_mut().zip(dyv.iter().zip(&x_hat)).enumerate() {
+            let ch = i / s.max(1) % c;
+            *o = match self.stats {
+                Some(_) => d * g[ch] * inv_std[ch],
+                None => g[ch] * inv_std[ch] / m * (m * d - grad_beta[ch] - xh * grad_gamma[ch]),
+            };
+        }
+        Ok(vec![
+            Tensor::from_vec(grad_x, x.shape().clone(), backend.clone())?,
+            Tensor::from_vec(grad_gamma, Shape::new(vec![c]), backend.clone())?,
+            Tensor::from_vec(grad_beta, Shape::new(vec![c]), backend)?,
+        ])
+    }
+}
+
+impl Op for BatchNorm {
+    fn id(&self) -> OpId {
+        OpId::BatchNorm
+    }
+
+    fn name(&self) -> &'static str {
+        "BatchNorm"
+    }
+
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        let c = self.stats.as_ref().map_or(3, |(mean, _)| mean.len());
+        Some(GradCheckSpec::new(vec![
+            vec![vec![4, c], vec![c], vec![c]],
+            vec![vec![2, c, 5], vec![c], vec![c]],
+            vec![vec![2, c, 2, 3], vec![c], vec![c]],
+        ]))
+    }
+
+    /// [eps] with batch statistics; [eps, mean..., var...] with fixed statistics.
+    fn attrs(&self) -> Vec<f32> {
+        match &self.stats {
+            Some((mean, var)) => [&[self.eps][..], mean, var].concat(),
+            None => vec![self.eps],
+        }
+    }
+
+    fn with_attrs(&self, attrs: &[f32]) -> Option<Arc<dyn Op>> {
+        let (&eps, stats) = attrs.split_first()?;
+        if stats.is_empty() {
+            return Some(Arc::new(BatchNorm::training(eps)));
+        }
+        if stats.len() % 2 != 0 {
+            return None;
+        }
+        let (mean, var) = stats.split_at(stats.len() / 2);
+        Some(Arc::new(BatchNorm::with_stats(
+            eps,
+            mean.to_vec(),
+            var.to_vec(),
+        )))
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 3 {
+            return Err(OpError("BatchNorm requires 3 inputs".into()));
+        }
+        self.run(inputs[0], inputs[1], inputs[2])
+            .map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 3 {
+            return Err(OpError("BatchNorm backward requires 3 inputs".into()));
+        }
+        self.run_backward(grad_out, inputs)
+            .map_err(|e| OpError(e.to_string()))
+    }
+}
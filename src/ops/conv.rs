@@ -0,0 +1,321 @@
+//! Conv: grouped 1D/2D convolution via im2col. Forward: per group, out = cols @ w^T (+ b).
+//! Backward: grad_w = grad_out^T @ cols, grad_cols = grad_out @ w folded back with col2im,
+//! grad_b = sum of grad_out over batch and positions.
+
+use super::{usize_attrs, GradCheckSpec, Op, OpError, OpId, OpResult};
+use crate::backend::ConvGeometry;
+use crate::shape::{Shape, ShapeError};
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use std::sync::Arc;
+
+/// Convolution of x [N, C, H, W] (or [N, C, L] for a 1D geometry) with weight
+/// [OC, C/groups, KH, KW] (or [OC, C/groups, K]) and optional bias [OC]. Inputs are
+/// (x, w) or (x, w, b).
+pub struct Conv {
+    geom: ConvGeometry,
+    groups: usize,
+}
+
+/// Sizes of one call, with the input viewed as 4D.
+struct Dims {
+    x: [usize; 4],
+    out_channels: usize,
+    out: (usize, usize),
+    /// Whether x and the output are 3D.
+    one_d: bool,
+}
+
+impl Dims {
+    fn positions(&self) -> usize {
+        self.out.0 * self.out.1
+    }
+
+    fn out_dims(&self) -> Vec<usize> {
+        let [n, _, _, _] = self.x;
+        if self.one_d {
+            vec![n, self.out_channels, self.out.1]
+        } else {
+            vec![n, self.out_channels, self.out.0, self.out.1]
+        }
+    }
+}
+
+impl Conv {
+    pub fn new(geom: ConvGeometry, groups: usize) -> Self {
+        Conv { geom, groups }
+    }
+
+    pub fn geometry(&self) -> ConvGeometry {
+        self.geom
+    }
+
+    pub fn groups(&self) -> usize {
+        self.groups
+    }
+
+    fn dims(&self, x: &Tensor, w: &Tensor, b: Option<&Tensor>) -> TensorResult<Dims> {
+        let err = |msg: String| Err(TensorError::Shape(ShapeError(format!("conv: {}", msg))));
+        let (kh, kw) = self.geom.kernel;
+        let (x4, w4, one_d) = match (x.shape().dims(), w.shape().dims()) {
+            (&[n, c, l], &[oc, cg, k]) if self.geom.is_1d() => ([n, c, 1, l], [oc, cg, 1, k], true),
+            (&[n, c, h, wd], &[oc, cg, a, b]) => ([n, c, h, wd], [oc, cg, a, b], false),
+            (xd, wd) => {
+                return err(format!(
+                    "expected x [N, C, H, W] and w [OC, C/groups, KH, KW] (3D for 1D geometry), \
+                     got {:?} and {:?}",
+                    xd, wd
+                ))
+            }
+        };
+        let [oc, cg, wkh, wkw] = w4;
+        if self.groups == 0 || x4[1] != cg * self.groups || !oc.is_multiple_of(self.groups) {
+            return err(format!(
+                "{} input channels and weight {:?} do not fit {} groups",
+                x4[1],
+                w.shape().dims(),
+                self.groups
+            ));
+        }
+        if (wkh, wkw) != (kh, kw) {
+            return err(format!(
+                "weight kernel {:?} != geometry kernel {:?}",
+                (wkh, wkw),
+                (kh, kw)
+            ));
+        }
+        if let Some(b) = b {
+            if b.shape().dims() != [oc] {
+                return err(format!(
+                    "expected bias [{}], got {:?}",
+                    oc,
+                    b.shape().dims()
+                ));
+            }
+        }
+        Ok(Dims {
+            x: x4,
+            out_channels: oc,
+            out: self.geom.output_size(x4[2], x4[3])?,
+            one_d,
+        })
+    }
+
+    /// im2col of x viewed as 4D: [N*L, C*KH*KW].
+    fn cols(&self, x: &Tensor, d: &Dims) -> TensorResult<Tensor> {
+        let backend = x.backend();
+        let x4 = reshaped(x, &d.x)?;
+        Ok(backend.im2col(&x4, &self.geom)?)
+    }
+
+    /// Column block of `cols` and row block of `w` (as [OC/groups, C/groups*KH*KW]) for group g.
+    fn group(
+        &self,
+        cols: &Tensor,
+        w: &Tensor,
+        d: &Dims,
+        g: usize,
+    ) -> TensorResult<(Tensor, Tensor)> {
+        let ck = w.numel() / d.out_channels.max(1);
+        let ocg = d.out_channels / self.groups;
+        let w_g = Tensor::from_vec(
+            w.data()[g * ocg * ck..(g + 1) * ocg * ck].to_vec(),
+            Shape::new(vec![ocg, ck]),
+            w.backend(),
+        )?;
+        if self.groups == 1 {
+            return Ok((cols.clone(), w_g));
+        }
+        let width = cols.shape().dims()[1];
+        let rows = cols.numel() / width.max(1);
+        let mut data = cols.backend().alloc(rows * ck);
+        for (r, row) in data.chunks_mut(ck.max(1)).enumerate().take(rows) {
+            row.copy_from_slice(&cols.data()[r * width + g * ck..r * width + (g + 1) * ck]);
+        }
+        let cols_g = Tensor::from_vec(data, Shape::new(vec![rows, ck]), cols.backend())?;
+        Ok((cols_g, w_g))
+    }
+
+    /// Forward with tensor errors (shared by [Op::forward] and the nn layers).
+    pub(crate) fn run(&self, x: &Tensor, w: &Tensor, b: Option<&Tensor>) -> TensorResult<Tensor> {
+        let d = self.dims(x, w, b)?;
+        let cols = self.cols(x, &d)?;
+        let (n, l) = (d.x[0], d.positions());
+        let (oc, ocg) = (d.out_channels, d.out_channels / self.groups);
+        let mut out = x.backend().alloc(n * oc * l);
+        for g in 0..self.groups {
+            let (cols_g, w_g) = self.group(&cols, w, &d, g)?;
+            // [N*L, OCg], scattered to channels-first [N, OC, L].
+            let y = cols_g.matmul(&w_g.transpose()?)?;
+            let y = y.data();
+            for s in 0..n {
+                for j in 0..ocg {
+                    let ch = g * ocg + j;
+                    let bias = b.map_or(0.0, |b| b.data()[ch]);
+                    let dst = &mut out[(s * oc + ch) * l..(s * oc + ch + 1) * l];
+                    for (p, o) in dst.iter_mut().enumerate() {
+                        *o = y[(s * l + p) * ocg + j] + bias;
+                    }
+                }
+            }
+        }
+        Tensor::from_vec(out, Shape::new(d.out_dims()), x.backend())
+    }
+
+    fn run_backward(&self, grad_out: &Tensor, inputs: &[&Tensor]) -> TensorResult<Vec<Tensor>> {
+        let (x, w, b) = (inputs[0], inputs[1], inputs.get(2).copied());
+        let d = self.dims(x, w, b)?;
+        if grad_out.shape().dims() != d.out_dims() {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "conv backward: grad {:?} != output {:?}",
+                grad_out.shape().dims(),
+                d.out_dims()
+            ))));
+        }
+        let cols = self.cols(x, &d)?;
+        let (n, l) = (d.x[0], d.positions());
+        let (oc, ocg) = (d.out_channels, d.out_channels / self.groups);
+        let ck = w.numel() / oc.max(1);
+        let width = cols.shape().dims()[1];
+        let backend = x.backend();
+        let go = grad_out.data();
+        let mut grad_cols = backend.alloc(cols.numel());
+        let mut grad_w = backend.alloc(w.numel());
+        for g in 0..self.groups {
+            let (cols_g, w_g) = self.group(&cols, w, &d, g)?;
+            // grad_out for this group's channels as [N*L, OCg].
+            let mut gm = backend.alloc(n * l * ocg);
+            for s in 0..n {
+                for j in 0..ocg {
+                    let src = &go[(s * oc + g * ocg + j) * l..(s * oc + g * ocg + j + 1) * l];
+                    for (p, v) in src.iter().enumerate() {
+                        gm[(s * l + p) * ocg + j] = *v;
+                    }
+                }
+            }
+            let gm = Tensor::from_vec(gm, Shape::new(vec![n * l, ocg]), backend.clone())?;
+            let gw = gm.transpose()?.matmul(&cols_g)?;
+            grad_w[g * ocg * ck..(g + 1) * ocg * ck].copy_from_slice(gw.data());
+            let gc = gm.matmul(&w_g)?;
+            for (r, row) in gc.data().chunks(ck.max(1)).enumerate().take(n * l) {
+                grad_cols[r * width + g * ck..r * width + (g + 1) * ck].copy_from_slice(row);
+            }
+        }
+        let grad_cols = Tensor::from_vec(grad_cols, cols.shape().clone(), backend.clone())?;
+        let grad_x = backend.col2im(&grad_cols, &Shape::new(d.x.to_vec()), &self.geom)?;
+        let mut grads = vec![
+            reshaped(&grad_x, x.shape().dims())?,
+            Tensor::from_vec(grad_w, w.shape().clone(), backend.clone())?,
+        ];
+        if b.is_some() {
+            let mut grad_b = backend.alloc(oc);
+            for (i, chunk) in go.chunks(l.max(1)).enumerate().take(n * oc) {
+                grad_b[i % oc] += chunk.iter().sum::<f32>();
+            }
+            grads.push(Tensor::from_vec(grad_b, Shape::new(vec![oc]), backend)?);
+        }
+        Ok(grads)
+    }
+}
+
+/// Kernel, stride, padding and dilation of `g` as (height, width) pairs, as [Op::attrs].
+pub(super) fn geometry_attrs(g: &ConvGeometry) -> Vec<f32> {
+    [g.kernel, g.stride, g.padding, g.dilation]
+        .into_iter()
+        .flat_map(|(h, w)| [h as f32, w as f32])
+        .collect()
+}
+
+/// Inverse of [geometry_attrs].
+pub(super) fn geometry_from_attrs(attrs: &[f32]) -> Option<ConvGeometry> {
+    let [kh, kw, sh, sw, ph, pw, dh, dw] = usize_attrs(attrs)?;
+    Some(
+        ConvGeometry::new((kh, kw))
+            .with_stride((sh, sw))
+            .with_padding((ph, pw))
+            .with_dilation((dh, dw)),
+    )
+}
+
+/// Copy of `t` with shape `dims` (same element count).
+fn reshaped(t: &Tensor, dims: &[usize]) -> TensorResult<Tensor> {
+    let backend = t.backend();
+    let mut data = backend.alloc(t.numel());
+    data.copy_from_slice(t.data());
+    Tensor::from_vec(data, Shape::new(dims.to_vec()), backend)
+}
+
+impl Op for Conv {
+    fn id(&self) -> OpId {
+        OpId::Conv
+    }
+
+    fn name(&self) -> &'static str {
+        "Conv"
+    }
+
+    /// Two channels per group and an input just large enough for two output positions
+    /// per axis; 1D geometries also get a 3D case.
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        let (kh, kw) = self.geom.kernel;
+        let span = |k: usize, s: usize, p: usize, d: usize| {
+            (d * (k.max(1) - 1) + 1 + s).saturating_sub(2 * p).max(1)
+        };
+        let h = span(
+            kh,
+            self.geom.stride.0,
+            self.geom.padding.0,
+            self.geom.dilation.0,
+        );
+        let w = span(
+            kw,
+            self.geom.stride.1,
+            self.geom.padding.1,
+            self.geom.dilation.1,
+        );
+        let (c, oc) = (2 * self.groups, 2 * self.groups);
+        let mut cases = vec![
+            vec![vec![2, c, h, w], vec![oc, 2, kh, kw], vec![oc]],
+            vec![vec![1, c, h, w], vec![oc, 2, kh, kw]],
+        ];
+        if self.geom.is_1d() {
+            cases.push(vec![vec![2, c, w], vec![oc, 2, kw], vec![oc]]);
+        }
+        Some(GradCheckSpec::new(cases))
+    }
+
+    /// [geometry_attrs], then groups.
+    fn attrs(&self) -> Vec<f32> {
+        let mut attrs = geometry_attrs(&self.geom);
+        attrs.push(self.groups as f32);
+        attrs
+    }
+
+    fn with_attrs(&self, attrs: &[f32]) -> Option<Arc<dyn Op>> {
+        let (geom, groups) = attrs.split_at_checked(8)?;
+        match usize_attrs(groups)? {
+            [0] => None,
+            [groups] => Some(Arc::new(Conv::new(geometry_from_attrs(geom)?, groups))),
+        }
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if !(2..=3).contains(&inputs.len()) {
+            return Err(OpError("Conv requires 2 or 3 inputs".into()));
+        }
+        self.run(inputs[0], inputs[1], inputs.get(2).copied())
+            .map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if !(2..=3).contains(&inputs.len()) {
+            return Err(OpError("Conv backward requires 2 or 3 inputs".into()));
+        }
+        self.run_backward(grad_out, inputs)
+            .map_err(|e| OpError(e.to_string()))
+    }
+}
@@ -0,0 +1,96 @@
+//! Dropout: y = x * scale + shift with a per-element scale (the mask, already divided by the
+//! keep probability) and optional shift, both drawn by the caller for one call. The op
+//! instance keeps them for backward: grad_x = grad_out * scale.
+
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
+use crate::shape::ShapeError;
+use crate::tensor::{Tensor, TensorError, TensorResult};
+
+pub struct Dropout {
+    scale: Vec<f32>,
+    shift: Option<Vec<f32>>,
+}
+
+impl Dropout {
+    /// `scale` (and `shift`, if given) hold one value per input element.
+    pub fn new(scale: Vec<f32>, shift: Option<Vec<f32>>) -> Self {
+        Dropout { scale, shift }
+    }
+
+    pub fn scale(&self) -> &[f32] {
+        &self.scale
+    }
+
+    fn check(&self, x: &Tensor) -> TensorResult<()> {
+        let shift = self.shift.as_ref().map_or(x.numel(), Vec::len);
+        if self.scale.len() != x.numel() || shift != x.numel() {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "dropout: mask of {} values for input {:?}",
+                self.scale.len(),
+                x.shape().dims()
+            ))));
+        }
+        Ok(())
+    }
+
+    /// Forward with tensor errors (shared by [Op::forward] and the nn layers).
+    pub(crate) fn run(&self, x: &Tensor) -> TensorResult<Tensor> {
+        self.check(x)?;
+        let mut out = x.backend().alloc(x.numel());
+        for (i, (o, &v)) in out.iter_mut().zip(x.data()).enumerate() {
+            *o = v * self.scale[i] + self.shift.as_ref().map_or(0.0, |s| s[i]);
+        }
+        Tensor::from_vec(out, x.shape().clone(), x.backend())
+    }
+
+    fn run_backward(&self, grad_out: &Tensor, x: &Tensor) -> TensorResult<Tensor> {
+        self.check(x)?;
+        if grad_out.shape().dims() != x.shape().dims() {
+            return Err(TensorError::Shape(ShapeError(
+                "dropout backward: grad shape != input shape".into(),
+            )));
+        }
+        let mut grad = x.backend().alloc(x.numel());
+        for ((g, &d), &s) in grad.iter_mut().zip(grad_out.data()).zip(&self.scale) {
+            *g = d * s;
+        }
+        Tensor::from_vec(grad, x.shape().clone(), x.backend())
+    }
+}
+
+impl Op for Dropout {
+    fn id(&self) -> OpId {
+        OpId::Dropout
+    }
+
+    fn name(&self) -> &'static str {
+        "Dropout"
+    }
+
+    /// A flat input matching the mask.
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(GradCheckSpec::new(vec![vec![vec![self.scale.len()]]]))
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 1 {
+            return Err(OpError("Dropout requires 1 input".into()));
+        }
+        self.run(inputs[0]).map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 1 {
+            return Err(OpError("Dropout backward requires 1 input".into()));
+        }
+        let grad = self
+            .run_backward(grad_out, inputs[0])
+            .map_err(|e| OpError(e.to_string()))?;
+        Ok(vec![grad])
+    }
+}
@@ -0,0 +1,171 @@
+//! Embedding lookup: gathers rows of a [U, D] table, optionally pooling consecutive bags of
+//! lookups by sum or mean. The table is the set of rows bound for one call (see
+//! [crate::Graph::param_rows]) and the indices are baked into the op, so backward only
+//! produces a [U, D] gradient: grad_table[slot(i)] += grad_out[i] (/ bag count for mean).
+
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
+use crate::shape::{Shape, ShapeError};
+use crate::tensor::{Tensor, TensorError, TensorResult};
+
+/// How an embedding bag pools its lookups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BagMode {
+    Sum,
+    /// Mean over the bag's non-padding lookups (zeros for an all-padding bag).
+    Mean,
+}
+
+pub struct Embedding {
+    /// Table row each lookup reads; None (padding) reads zeros and gets no gradient.
+    slots: Vec<Option<usize>>,
+    /// Output dims without the trailing D.
+    out_dims: Vec<usize>,
+    /// Pooling over consecutive runs of the given number of lookups.
+    bag: Option<(BagMode, usize)>,
+}
+
+impl Embedding {
+    /// Plain lookup: output [out_dims..., D] with one lookup per output row.
+    pub fn new(slots: Vec<Option<usize>>, out_dims: Vec<usize>) -> Self {
+        Embedding {
+            slots,
+            out_dims,
+            bag: None,
+        }
+    }
+
+    /// Bags of `bag_len` consecutive lookups pooled by `mode`: output [slots / bag_len, D].
+    pub fn bags(slots: Vec<Option<usize>>, bag_len: usize, mode: BagMode) -> Self {
+        let bags = slots.len() / bag_len.max(1);
+        Embedding {
+            slots,
+            out_dims: vec![bags],
+            bag: Some((mode, bag_len.max(1))),
+        }
+    }
+
+    /// (D, output row of each lookup, its weight).
+    fn layout(&self, table: &Tensor) -> TensorResult<(usize, Vec<(usize, f32)>)> {
+        let (u, d) = match table.shape().dims() {
+            &[u, d] => (u, d),
+            dims => {
+                return Err(TensorError::Shape(ShapeError(format!(
+                    "embedding: expected table [U, D], got {:?}",
+                    dims
+                ))))
+            }
+        };
+        if let Some(s) = self.slots.iter().flatten().find(|&&s| s >= u) {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "embedding: slot {} outside table of {} rows",
+                s, u
+            ))));
+        }
+        let targets = match self.bag {
+            None => (0..self.slots.len()).map(|i| (i, 1.0)).collect(),
+            Some((mode, len)) => {
+                if !self.slots.len().is_multiple_of(len) {
+                    return Err(TensorError::Shape(ShapeError(format!(
+                        "embedding: {} lookups do not split into bags of {}",
+                        self.slots.len(),
+                        len
+                    ))));
+                }
+                let mut targets = Vec::with_capacity(self.slots.len());
+                for (b, bag) in self.slots.chunks(len).enumerate() {
+                    let count = bag.iter().flatten().count().max(1) as f32;
+                    let weight = match mode {
+                        BagMode::Sum => 1.0,
+                        BagMode::Mean => 1.0 / count,
+                    };
+                    targets.extend(std::iter::repeat_n((b, weight), len));
+                }
+                targets
+            }
+        };
+        Ok((d, targets))
+    }
+
+    fn out_shape(&self, d: usize) -> Shape {
+        let mut dims = self.out_dims.clone();
+        dims.push(d);
+        Shape::new(dims)
+    }
+
+    /// Forward with tensor errors (shared by [Op::forward] and the nn layers).
+    pub(crate) fn run(&self, table: &Tensor) -> TensorResult<Tensor> {
+        let (d, targets) = self.layout(table)?;
+        let shape = self.out_shape(d);
+        let mut out = table.backend().alloc(shape.numel());
+        let t = table.data();
+        for (slot, (row, weight)) in self.slots.iter().zip(targets) {
+            if let Some(s) = slot {
+                let dst = &mut out[row * d..(row + 1) * d];
+                for (o, v) in dst.iter_mut().zip(&t[s * d..(s + 1) * d]) {
+                    *o += weight * v;
+                }
+            }
+        }
+        Tensor::from_vec(out, shape, table.backend())
+    }
+
+    fn run_backward(&self, grad_out: &Tensor, table: &Tensor) -> TensorResult<Tensor> {
+        let (d, targets) = self.layout(table)?;
+        if grad_out.shape().dims() != self.out_shape(d).dims() {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "embedding backward: grad {:?} != output {:?}",
+                grad_out.shape().dims(),
+                self.out_shape(d).dims()
+            ))));
+        }
+        let mut grad = table.backend().alloc(table.numel());
+        let go = grad_out.data();
+        for (slot, (row, weight)) in self.slots.iter().zip(targets) {
+            if let Some(s) = slot {
+                let dst = &mut grad[s * d..(s + 1) * d];
+                for (g, v) in dst.iter_mut().zip(&go[row * d..(row + 1) * d]) {
+                    *g += weight * v;
+                }
+            }
+        }
+        Tensor::from_vec(grad, table.shape().clone(), table.backend())
+    }
+}
+
+impl Op for Embedding {
+    fn id(&self) -> OpId {
+        OpId::Embedding
+    }
+
+    fn name(&self) -> &'static str {
+        "Embedding"
+    }
+
+    /// A table just large enough for the slots, with 3 columns.
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        let rows = self.slots.iter().flatten().max().map_or(1, |s| s + 1);
+        Some(GradCheckSpec::new(vec![vec![vec![rows, 3]]]))
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 1 {
+            return Err(OpError("Embedding requires 1 input".into()));
+        }
+        self.run(inputs[0]).map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 1 {
+            return Err(OpError("Embedding backward requires 1 input".into()));
+        }
+        let grad = self
+            .run_backward(grad_out, inputs[0])
+            .map_err(|e| OpError(e.to_string()))?;
+        Ok(vec![grad])
+    }
+}
@@ -0,0 +1,90 @@
+//! FusedLinear: act(x @ w + b) in one kernel. Backward: grad_pre = act'(grad_out),
+//! grad_x = grad_pre @ w^T, grad_w = x^T @ grad_pre, grad_b = sum(grad_pre, dim=0).
+
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
+use crate::backend::Activation;
+use crate::shape::Shape;
+use crate::tensor::Tensor;
+
+pub struct FusedLinear {
+    act: Activation,
+}
+
+impl FusedLinear {
+    pub fn new(act: Activation) -> Self {
+        FusedLinear { act }
+    }
+
+    pub fn activation(&self) -> Activation {
+        self.act
+    }
+}
+
+impl Op for FusedLinear {
+    fn id(&self) -> OpId {
+        match self.act {
+            Activation::Identity => OpId::Linear,
+            Activation::ReLU => OpId::LinearReLU,
+            Activation::Sigmoid => OpId::LinearSigmoid,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self.act {
+            Activation::Identity => "Linear",
+            Activation::ReLU => "LinearReLU",
+            Activation::Sigmoid => "LinearSigmoid",
+        }
+    }
+
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(
+            GradCheckSpec::new(vec![
+                vec![vec![2, 3], vec![3, 4], vec![4]],
+                vec![vec![1, 1], vec![1, 1], vec![1]],
+                vec![vec![3, 1], vec![1, 2], vec![2]],
+            ])
+            .with_min_abs(0.05),
+        )
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 3 {
+            return Err(OpError("FusedLinear requires 3 inputs".into()));
+        }
+        inputs[0]
+            .linear(inputs[1], inputs[2], self.act)
+            .map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 3 {
+            return Err(OpError("FusedLinear backward requires 3 inputs".into()));
+        }
+        let (x, w) = (inputs[0], inputs[1]);
+        // ReLU output > 0 exactly where its input > 0, so the saved output suffices.
+        let grad_pre = match self.act {
+            Activation::Identity => Ok(grad_out.clone()),
+            Activation::ReLU => fwd_output.relu_backward(grad_out),
+            Activation::Sigmoid => fwd_output.sigmoid_backward(grad_out),
+        }
+        .map_err(|e| OpError(e.to_string()))?;
+        let w_t = w.transpose().map_err(|e| OpError(e.to_string()))?;
+        let x_t = x.transpose().map_err(|e| OpError(e.to_string()))?;
+        let grad_x = grad_pre.matmul(&w_t).map_err(|e| OpError(e.to_string()))?;
+        let grad_w = x_t.matmul(&grad_pre).map_err(|e| OpError(e.to_string()))?;
+        let summed = grad_pre.sum_dim(0).map_err(|e| OpError(e.to_string()))?;
+        let grad_b = Tensor::from_vec(
+            summed.data().to_vec(),
+            Shape::new(vec![inputs[2].numel()]),
+            summed.backend(),
+        )
+        .map_err(|e| OpError(e.to_string()))?;
+        Ok(vec![grad_x, grad_w, grad_b])
+    }
+}
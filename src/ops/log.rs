@@ -1,6 +1,6 @@
 //! Log: forward log(a); backward grad_out / a.
 
-use super::{Op, OpError, OpId, OpResult};
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
 use crate::tensor::Tensor;
 
 pub struct Log;
@@ -14,6 +14,13 @@ impl Op for Log {
         "Log"
     }
 
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(
+            GradCheckSpec::new(vec![vec![vec![3, 4]], vec![vec![1, 1]], vec![vec![5]]])
+                .with_range(0.5, 2.0),
+        )
+    }
+
     fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
         if inputs.len() != 1 {
             return Err(OpError("Log requires 1 input".into()));
@@ -1,6 +1,6 @@
 //! MatMul: matrix multiply. Forward a@b; backward grad_a=grad_out@b^T, grad_b=a^T@grad_out.
 
-use super::{Op, OpError, OpId, OpResult};
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
 use crate::tensor::Tensor;
 
 pub struct MatMul;
@@ -14,6 +14,15 @@ impl Op for MatMul {
         "MatMul"
     }
 
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(GradCheckSpec::new(vec![
+            vec![vec![2, 3], vec![3, 4]],
+            vec![vec![1, 1], vec![1, 1]],
+            vec![vec![1, 5], vec![5, 1]],
+            vec![vec![4, 1], vec![1, 3]],
+        ]))
+    }
+
     fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
         if inputs.len() != 2 {
             return Err(OpError("MatMul requires 2 inputs".into()));
@@ -2,20 +2,32 @@
 //! Each op (Add, MatMul, ReLU, ...) is an independent entity; adding a new op
 //! = implement trait + register, no changes to engine logic.
 
+use crate::backend::{Activation, ConvGeometry};
+use crate::shape::Shape;
 use crate::tensor::Tensor;
 use std::sync::Arc;
 use thiserror::Error;
 
 pub mod add;
 pub mod add_broadcast;
+pub mod batch_matmul;
+pub mod batch_norm;
+pub mod conv;
+pub mod dropout;
+pub mod embedding;
+pub mod linear;
 pub mod sub;
 pub mod matmul;
 pub mod relu;
 pub mod mul;
+pub mod norm;
+pub mod pool;
 pub mod sigmoid;
 pub mod sum;
+pub mod tanh;
 pub mod softmax;
 pub mod log;
+pub mod view;
 
 #[derive(Error, Debug)]
 #[error("op error: {0}")]
@@ -36,6 +48,38 @@ pub enum OpId {
     Sum,
     Softmax,
     Log,
+    Tanh,
+    /// Checkpointed segment: recomputes its sub-graph during backward.
+    Checkpoint,
+    /// Fused x @ w + b (and activation variants), produced by plan fusion.
+    Linear,
+    LinearReLU,
+    LinearSigmoid,
+    /// Grouped 1D/2D convolution; carries its geometry, so it is applied per call.
+    Conv,
+    /// Max / average pooling; carry their window, so they are applied per call.
+    MaxPool,
+    AvgPool,
+    /// Per-channel batch normalisation; carries its statistics mode, so it is applied per call.
+    BatchNorm,
+    /// Fused normalisations over trailing dims (LayerNorm, RMSNorm) or channel groups.
+    LayerNorm,
+    RMSNorm,
+    /// Carries its group count, so it is applied per call.
+    GroupNorm,
+    /// Dropout-style masking; carries the mask drawn for one call.
+    Dropout,
+    /// Embedding lookup; carries the indices of one call.
+    Embedding,
+    /// Layout ops (see [view]); carry their geometry, so they are applied per call.
+    Narrow,
+    Concat,
+    Reshape,
+    Permute,
+    /// Batched matmul; carries its transpose flag and scale, so it is applied per call.
+    BatchMatMul,
+    /// Op defined outside this crate, identified by a unique name.
+    Custom(&'static str),
 }
 
 /// Unified operator trait: forward, backward, and shape constraints.
@@ -61,9 +105,94 @@ pub trait Op: Send + Sync {
     fn name(&self) -> &'static str {
         "Op"
     }
+
+    /// Inputs for the gradient conformance harness ([crate::autograd::check::check_registry]).
+    /// None fails [crate::autograd::check::check_registry] for a registered op; opt out
+    /// explicitly with [GradCheckSpec::skip]. Per-call ops that are never registered may
+    /// return None.
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        None
+    }
+
+    /// Scalar settings of this instance (e.g. a norm's eps). Traced programs save them and,
+    /// when they differ from the registered op's, rebuild the op with [Op::with_attrs].
+    fn attrs(&self) -> Vec<f32> {
+        Vec::new()
+    }
+
+    /// The same op with `attrs` (as returned by [Op::attrs]); None if it has no settings.
+    fn with_attrs(&self, _attrs: &[f32]) -> Option<Arc<dyn Op>> {
+        None
+    }
+}
+
+/// Integer settings saved by [Op::attrs], or None unless each is a whole number in
+/// `0..=2^24` (exact in f32).
+pub(crate) fn usize_attr_list(attrs: &[f32]) -> Option<Vec<usize>> {
+    attrs
+        .iter()
+        .map(|&a| ((0.0..=(1 << 24) as f32).contains(&a) && a.fract() == 0.0).then_some(a as usize))
+        .collect()
+}
+
+/// As [usize_attr_list], for exactly `N` settings.
+pub(crate) fn usize_attrs<const N: usize>(attrs: &[f32]) -> Option<[usize; N]> {
+    usize_attr_list(attrs)?.try_into().ok()
+}
+
+/// How the gradient conformance harness exercises an op: input shapes per case and the
+/// range random input values are drawn from.
+#[derive(Clone, Debug)]
+pub struct GradCheckSpec {
+    /// One entry per case: the shape of each input.
+    pub cases: Vec<Vec<Shape>>,
+    /// Inputs are drawn uniformly from [low, high).
+    pub low: f32,
+    pub high: f32,
+    /// Values with |v| < min_abs are pushed to +-min_abs (keeps clear of kinks at 0).
+    pub min_abs: f32,
+    /// Set by [Self::skip]: the op is deliberately not gradient-checked, for this reason.
+    pub skip: Option<String>,
+}
+
+impl GradCheckSpec {
+    /// Cases given as dims, e.g. `vec![vec![vec![2, 3], vec![3, 4]]]` for one two-input case.
+    pub fn new(cases: Vec<Vec<Vec<usize>>>) -> Self {
+        GradCheckSpec {
+            cases: cases
+                .into_iter()
+                .map(|c| c.into_iter().map(Shape::new).collect())
+                .collect(),
+            low: -1.0,
+            high: 1.0,
+            min_abs: 0.0,
+            skip: None,
+        }
+    }
+
+    /// Explicitly opt out of the harness (e.g. a non-differentiable op); the reason shows up
+    /// in the op's report.
+    pub fn skip(reason: impl Into<String>) -> Self {
+        GradCheckSpec {
+            skip: Some(reason.into()),
+            ..Self::new(vec![])
+        }
+    }
+
+    pub fn with_range(mut self, low: f32, high: f32) -> Self {
+        self.low = low;
+        self.high = high;
+        self
+    }
+
+    pub fn with_min_abs(mut self, min_abs: f32) -> Self {
+        self.min_abs = min_abs;
+        self
+    }
 }
 
 /// Registry: map OpId -> Box<dyn Op>. Engine uses this to run backward.
+#[derive(Clone)]
 pub struct OpRegistry {
     ops: std::collections::HashMap<OpId, Arc<dyn Op>>,
 }
@@ -83,6 +212,23 @@ impl OpRegistry {
         reg.register(Arc::new(sum::Sum));
         reg.register(Arc::new(softmax::Softmax));
         reg.register(Arc::new(log::Log));
+        reg.register(Arc::new(tanh::Tanh));
+        reg.register(Arc::new(norm::LayerNorm::default()));
+        reg.register(Arc::new(norm::RMSNorm::default()));
+        reg.register(Arc::new(norm::GroupNorm::new(2, norm::DEFAULT_EPS)));
+        reg.register(Arc::new(batch_norm::BatchNorm::training(norm::DEFAULT_EPS)));
+        reg.register(Arc::new(conv::Conv::new(ConvGeometry::new((2, 2)), 1)));
+        let window = pool::PoolWindow::Fixed(ConvGeometry::new((2, 2)).with_stride((2, 2)));
+        reg.register(Arc::new(pool::Pool::new(pool::PoolMode::Max, window)));
+        reg.register(Arc::new(pool::Pool::new(pool::PoolMode::Avg, window)));
+        reg.register(Arc::new(linear::FusedLinear::new(Activation::Identity)));
+        reg.register(Arc::new(linear::FusedLinear::new(Activation::ReLU)));
+        reg.register(Arc::new(linear::FusedLinear::new(Activation::Sigmoid)));
+        reg.register(Arc::new(view::Narrow::new(0, 0, 1)));
+        reg.register(Arc::new(view::Concat::new(0)));
+        reg.register(Arc::new(view::Reshape::new(vec![2, 3])));
+        reg.register(Arc::new(view::Permute::new(vec![1, 0])));
+        reg.register(Arc::new(batch_matmul::BatchMatMul::new(false, 1.0)));
         reg
     }
 
@@ -93,6 +239,18 @@ impl OpRegistry {
     pub fn get(&self, id: OpId) -> Option<Arc<dyn Op>> {
         self.ops.get(&id).cloned()
     }
+
+    /// Look up a registered op by [Op::name].
+    pub fn get_by_name(&self, name: &str) -> Option<Arc<dyn Op>> {
+        self.ops.values().find(|op| op.name() == name).cloned()
+    }
+
+    /// All registered ops, sorted by name for deterministic iteration.
+    pub fn ops(&self) -> Vec<Arc<dyn Op>> {
+        let mut ops: Vec<Arc<dyn Op>> = self.ops.values().cloned().collect();
+        ops.sort_by_key(|op| op.name());
+        ops
+    }
 }
 
 impl Default for OpRegistry {
@@ -1,6 +1,6 @@
 //! Mul: element-wise multiplication. Forward a*b; backward grad_a=grad_out*b, grad_b=grad_out*a.
 
-use super::{Op, OpError, OpId, OpResult};
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
 use crate::tensor::Tensor;
 
 pub struct Mul;
@@ -14,6 +14,15 @@ impl Op for Mul {
         "Mul"
     }
 
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(GradCheckSpec::new(vec![
+            vec![vec![3, 4], vec![3, 4]],
+            vec![vec![1, 1], vec![1, 1]],
+            vec![vec![1, 5], vec![1, 5]],
+            vec![vec![4], vec![4]],
+        ]))
+    }
+
     fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
         if inputs.len() != 2 {
             return Err(OpError("Mul requires 2 inputs".into()));
@@ -0,0 +1,396 @@
+//! LayerNorm, RMSNorm and GroupNorm as fused ops over a shared row kernel: x is split into
+//! rows of contiguous values, each normalised with its own statistics (LayerNorm, GroupNorm:
+//! mean and variance; RMSNorm: root mean square only) and then scaled (and shifted) per
+//! element. With g = dy * weight and D values per row, backward is
+//! grad_x = inv_std / D * (D * g - sum(g) - x_hat * sum(g * x_hat)), without the sum(g)
+//! term for RMSNorm.
+
+use super::{usize_attrs, GradCheckSpec, Op, OpError, OpId, OpResult};
+use crate::shape::ShapeError;
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use std::sync::Arc;
+
+/// Default eps of the registered instances and the nn layers.
+pub const DEFAULT_EPS: f32 = 1e-5;
+
+/// Rows of `row` (> 0) values; element i uses affine index (i / inner) % period.
+struct RowNorm {
+    row: usize,
+    inner: usize,
+    period: usize,
+    centred: bool,
+    eps: f32,
+}
+
+impl RowNorm {
+    fn affine_index(&self, i: usize) -> usize {
+        i / self.inner % self.period
+    }
+
+    /// x_hat and inv_std of one row.
+    fn normalize_row(&self, x: &[f32]) -> (Vec<f32>, f32) {
+        let d = x.len() as f64;
+        let mean = if self.centred {
+            x.iter().map(|&v| v as f64).sum::<f64>() / d
+        } else {
+            0.0
+        };
+        let var = x.iter().map(|&v| (v as f64 - mean).powi(2)).sum::<f64>() / d;
+        let inv_std = (1.0 / (var + self.eps as f64).sqrt()) as f32;
+        let x_hat = x.iter().map(|&v| (v - mean as f32) * inv_std).collect();
+        (x_hat, inv_std)
+    }
+
+    fn forward(&self, x: &Tensor, w: &Tensor, b: Option<&Tensor>) -> TensorResult<Tensor> {
+        let mut out = x.backend().alloc(x.numel());
+        let (wd, bd) = (w.data(), b.map(|b| b.data()));
+        let rows = x.data().chunks(self.row).zip(out.chunks_mut(self.row));
+        for (r, (xs, os)) in rows.enumerate() {
+            let (x_hat, _) = self.normalize_row(xs);
+            for (j, (o, xh)) in os.iter_mut().zip(x_hat).enumerate() {
+                let a = self.affine_index(r * self.row + j);
+                *o = wd[a] * xh + bd.map_or(0.0, |b| b[a]);
+            }
+        }
+        Tensor::from_vec(out, x.shape().clone(), x.backend())
+    }
+
+    /// Gradients for x, w and (if `bias`) b.
+    fn backward(
+        &self,
+        dy: &Tensor,
+        x: &Tensor,
+        w: &Tensor,
+        bias: bool,
+    ) -> TensorResult<Vec<Tensor>> {
+        if dy.shape().dims() != x.shape().dims() {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "norm backward: grad {:?} != input {:?}",
+                dy.shape().dims(),
+                x.shape().dims()
+            ))));
+        }
+        let backend = x.backend();
+        let wd = w.data();
+        let mut grad_x = backend.alloc(x.numel());
+        let mut grad_w = backend.alloc(w.numel());
+        let mut grad_b = backend.alloc(w.numel());
+        let d = self.row as f32;
+        let rows = x
+            .data()
+            .chunks(self.row)
+            .zip(dy.data().chunks(self.row))
+            .zip(grad_x.chunks_mut(self.row));
+        for (r, ((xs, dys), gxs)) in rows.enumerate() {
+            let (x_hat, inv_std) = self.normalize_row(xs);
+            let (mut sum_g, mut sum_gx) = (0.0f32, 0.0f32);
+            for (j, (&dyj, &xh)) in dys.iter().zip(&x_hat).enumerate() {
+                let a = self.affine_index(r * self.row + j);
+                let g = dyj * wd[a];
+                sum_g += g;
+                sum_gx += g * xh;
+                grad_w[a] += dyj * xh;
+                grad_b[a] += dyj;
+            }
+            if !self.centred {
+                sum_g = 0.0;
+            }
+            for (j, (gx, &xh)) in gxs.iter_mut().zip(&x_hat).enumerate() {
+                let g = dys[j] * wd[self.affine_index(r * self.row + j)];
+                *gx = inv_std / d * (d * g - sum_g - xh * sum_gx);
+            }
+        }
+        let mut grads = vec![
+            Tensor::from_vec(grad_x, x.shape().clone(), backend.clone())?,
+            Tensor::from_vec(grad_w, w.shape().clone(), backend.clone())?,
+        ];
+        if bias {
+            grads.push(Tensor::from_vec(grad_b, w.shape().clone(), backend)?);
+        }
+        Ok(grads)
+    }
+}
+
+fn shape_err<T>(msg: String) -> TensorResult<T> {
+    Err(TensorError::Shape(ShapeError(msg)))
+}
+
+/// Row kernel normalising x over trailing dims equal to w's dims (LayerNorm, RMSNorm).
+fn trailing(name: &str, x: &Tensor, w: &Tensor, centred: bool, eps: f32) -> TensorResult<RowNorm> {
+    let (xd, wd) = (x.shape().dims(), w.shape().dims());
+    if wd.is_empty() || w.numel() == 0 || !xd.ends_with(wd) {
+        return shape_err(format!(
+            "{}: weight {:?} does not match trailing dims of {:?}",
+            name, wd, xd
+        ));
+    }
+    Ok(RowNorm {
+        row: w.numel(),
+        inner: 1,
+        period: w.numel(),
+        centred,
+        eps,
+    })
+}
+
+/// Layer normalisation of x over its trailing dims, which equal the dims of weight and bias.
+/// Inputs are (x, weight, bias).
+pub struct LayerNorm {
+    eps: f32,
+}
+
+impl LayerNorm {
+    pub fn new(eps: f32) -> Self {
+        LayerNorm { eps }
+    }
+
+    /// Forward with tensor errors (shared by [Op::forward] and the nn layers).
+    pub(crate) fn run(&self, x: &Tensor, w: &Tensor, b: &Tensor) -> TensorResult<Tensor> {
+        let kernel = trailing("layer_norm", x, w, true, self.eps)?;
+        if b.shape().dims() != w.shape().dims() {
+            return shape_err(format!(
+                "layer_norm: bias {:?} != weight {:?}",
+                b.shape().dims(),
+                w.shape().dims()
+            ));
+        }
+        kernel.forward(x, w, Some(b))
+    }
+}
+
+impl Default for LayerNorm {
+    fn default() -> Self {
+        Self::new(DEFAULT_EPS)
+    }
+}
+
+impl Op for LayerNorm {
+    fn id(&self) -> OpId {
+        OpId::LayerNorm
+    }
+
+    fn name(&self) -> &'static str {
+        "LayerNorm"
+    }
+
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(GradCheckSpec::new(vec![
+            vec![vec![4, 5], vec![5], vec![5]],
+            vec![vec![2, 3, 4], vec![3, 4], vec![3, 4]],
+        ]))
+    }
+
+    fn attrs(&self) -> Vec<f32> {
+        vec![self.eps]
+    }
+
+    fn with_attrs(&self, attrs: &[f32]) -> Option<Arc<dyn Op>> {
+        match attrs {
+            [eps] => Some(Arc::new(LayerNorm::new(*eps))),
+            _ => None,
+        }
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 3 {
+            return Err(OpError("LayerNorm requires 3 inputs".into()));
+        }
+        self.run(inputs[0], inputs[1], inputs[2])
+            .map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 3 {
+            return Err(OpError("LayerNorm backward requires 3 inputs".into()));
+        }
+        trailing("layer_norm", inputs[0], inputs[1], true, self.eps)
+            .and_then(|k| k.backward(grad_out, inputs[0], inputs[1], true))
+            .map_err(|e| OpError(e.to_string()))
+    }
+}
+
+/// Root-mean-square normalisation over x's trailing dims (no centring, no bias):
+/// weight * x / rms(x). Inputs are (x, weight).
+pub struct RMSNorm {
+    eps: f32,
+}
+
+impl RMSNorm {
+    pub fn new(eps: f32) -> Self {
+        RMSNorm { eps }
+    }
+
+    /// Forward with tensor errors (shared by [Op::forward] and the nn layers).
+    pub(crate) fn run(&self, x: &Tensor, w: &Tensor) -> TensorResult<Tensor> {
+        trailing("rms_norm", x, w, false, self.eps)?.forward(x, w, None)
+    }
+}
+
+impl Default for RMSNorm {
+    fn default() -> Self {
+        Self::new(DEFAULT_EPS)
+    }
+}
+
+impl Op for RMSNorm {
+    fn id(&self) -> OpId {
+        OpId::RMSNorm
+    }
+
+    fn name(&self) -> &'static str {
+        "RMSNorm"
+    }
+
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(GradCheckSpec::new(vec![
+            vec![vec![4, 5], vec![5]],
+            vec![vec![2, 3, 4], vec![4]],
+        ]))
+    }
+
+    fn attrs(&self) -> Vec<f32> {
+        vec![self.eps]
+    }
+
+    fn with_attrs(&self, attrs: &[f32]) -> Option<Arc<dyn Op>> {
+        match attrs {
+            [eps] => Some(Arc::new(RMSNorm::new(*eps))),
+            _ => None,
+        }
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 2 {
+            return Err(OpError("RMSNorm requires 2 inputs".into()));
+        }
+        self.run(inputs[0], inputs[1])
+            .map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 2 {
+            return Err(OpError("RMSNorm backward requires 2 inputs".into()));
+        }
+        trailing("rms_norm", inputs[0], inputs[1], false, self.eps)
+            .and_then(|k| k.backward(grad_out, inputs[0], inputs[1], false))
+            .map_err(|e| OpError(e.to_string()))
+    }
+}
+
+/// Group normalisation of x [N, C, ...]: channels are split into `groups` groups, each
+/// normalised over its channels and all spatial positions, then scaled and shifted per
+/// channel. Inputs are (x, weight [C], bias [C]).
+pub struct GroupNorm {
+    groups: usize,
+    eps: f32,
+}
+
+impl GroupNorm {
+    pub fn new(groups: usize, eps: f32) -> Self {
+        GroupNorm { groups, eps }
+    }
+
+    pub fn groups(&self) -> usize {
+        self.groups
+    }
+
+    fn kernel(&self, x: &Tensor, w: &Tensor, b: &Tensor) -> TensorResult<RowNorm> {
+        let (c, s) = match x.shape().dims() {
+            [_, c, rest @ ..] => (*c, rest.iter().product::<usize>()),
+            d => return shape_err(format!("group_norm: expected [N, C, ...], got {:?}", d)),
+        };
+        if self.groups == 0 || c == 0 || !c.is_multiple_of(self.groups) {
+            return shape_err(format!(
+                "group_norm: {} channels do not split into {} groups",
+                c, self.groups
+            ));
+        }
+        if w.shape().dims() != [c] || b.shape().dims() != [c] {
+            return shape_err(format!(
+                "group_norm: expected weight and bias [{}], got {:?} and {:?}",
+                c,
+                w.shape().dims(),
+                b.shape().dims()
+            ));
+        }
+        Ok(RowNorm {
+            // Without spatial positions x is empty; a row of 1 keeps chunks() from panicking.
+            row: (c / self.groups * s).max(1),
+            inner: s.max(1),
+            period: c,
+            centred: true,
+            eps: self.eps,
+        })
+    }
+
+    /// Forward with tensor errors (shared by [Op::forward] and the nn layers).
+    pub(crate) fn run(&self, x: &Tensor, w: &Tensor, b: &Tensor) -> TensorResult<Tensor> {
+        self.kernel(x, w, b)?.forward(x, w, Some(b))
+    }
+}
+
+impl Op for GroupNorm {
+    fn id(&self) -> OpId {
+        OpId::GroupNorm
+    }
+
+    fn name(&self) -> &'static str {
+        "GroupNorm"
+    }
+
+    /// Two channels per group, with and without spatial dims.
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        let c = 2 * self.groups;
+        Some(GradCheckSpec::new(vec![
+            vec![vec![2, c, 3], vec![c], vec![c]],
+            vec![vec![2, c, 2, 2], vec![c], vec![c]],
+        ]))
+    }
+
+    /// [groups, eps].
+    fn attrs(&self) -> Vec<f32> {
+        vec![self.groups as f32, self.eps]
+    }
+
+    fn with_attrs(&self, attrs: &[f32]) -> Option<Arc<dyn Op>> {
+        let [groups, eps] = attrs else {
+            return None;
+        };
+        match usize_attrs(&[*groups])? {
+            [0] => None,
+            [groups] => Some(Arc::new(GroupNorm::new(groups, *eps))),
+        }
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 3 {
+            return Err(OpError("GroupNorm requires 3 inputs".into()));
+        }
+        self.run(inputs[0], inputs[1], inputs[2])
+            .map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 3 {
+            return Err(OpError("GroupNorm backward requires 3 inputs".into()));
+        }
+        self.kernel(inputs[0], inputs[1], inputs[2])
+            .and_then(|k| k.backward(grad_out, inputs[0], inputs[1], true))
+            .map_err(|e| OpError(e.to_string()))
+    }
+}
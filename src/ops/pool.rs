@@ -0,0 +1,287 @@
+//! Pool: max / average pooling over the spatial dims of [N, C, H, W] (or [N, C, L]), each
+//! channel independently. Backward routes max gradients to the (first) argmax of each
+//! window and spreads average gradients evenly over it.
+
+use super::conv::{geometry_attrs, geometry_from_attrs};
+use super::{usize_attrs, GradCheckSpec, Op, OpError, OpId, OpResult};
+use crate::backend::ConvGeometry;
+use crate::shape::{Shape, ShapeError};
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolMode {
+    Max,
+    Avg,
+}
+
+/// Where each output cell reads from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolWindow {
+    /// Sliding window. Max ignores padding; average counts it as zeros (divides by KH*KW).
+    Fixed(ConvGeometry),
+    /// Output of the given (OH, OW); cell i covers [floor(i*H/OH), ceil((i+1)*H/OH)).
+    Adaptive((usize, usize)),
+    /// Whole spatial extent of any rank >= 3 input; the output is [N, C].
+    Global,
+}
+
+pub struct Pool {
+    mode: PoolMode,
+    window: PoolWindow,
+}
+
+/// Input spatial indices of one output cell, and the average divisor.
+struct Cell {
+    taps: Vec<usize>,
+    divisor: f32,
+}
+
+/// One call's layout: planes of `plane` input elements, output dims and per-cell taps.
+struct Layout {
+    planes: usize,
+    plane: usize,
+    out_dims: Vec<usize>,
+    cells: Vec<Cell>,
+}
+
+impl Pool {
+    pub fn new(mode: PoolMode, window: PoolWindow) -> Self {
+        Pool { mode, window }
+    }
+
+    pub fn mode(&self) -> PoolMode {
+        self.mode
+    }
+
+    pub fn window(&self) -> PoolWindow {
+        self.window
+    }
+
+    fn layout(&self, x: &Tensor) -> TensorResult<Layout> {
+        let dims = x.shape().dims();
+        let err = |msg: String| TensorError::Shape(ShapeError(format!("pool: {}", msg)));
+        let (n, c, h, w, one_d) = match (dims, self.window) {
+            (&[n, c, ref rest @ ..], PoolWindow::Global) if !rest.is_empty() => {
+                (n, c, 1, rest.iter().product(), true)
+            }
+            (&[n, c, l], PoolWindow::Fixed(g)) if g.is_1d() => (n, c, 1, l, true),
+            (&[n, c, h, w], _) => (n, c, h, w, false),
+            (d, _) => {
+                return Err(err(format!(
+                    "unsupported input {:?} for {:?}",
+                    d, self.window
+                )))
+            }
+        };
+        let mut cells = Vec::new();
+        let (oh, ow) = match self.window {
+            PoolWindow::Global => {
+                cells.push(Cell {
+                    taps: (0..h * w).collect(),
+                    divisor: (h * w) as f32,
+                });
+                (1, 1)
+            }
+            PoolWindow::Fixed(g) => {
+                let (oh, ow) = g.output_size(h, w)?;
+                let (kh, kw) = g.kernel;
+                for oy in 0..oh {
+                    for ox in 0..ow {
+                        let mut taps = Vec::with_capacity(kh * kw);
+                        for ky in 0..kh {
+                            let iy =
+                                (oy * g.stride.0 + ky * g.dilation.0).wrapping_sub(g.padding.0);
+                            for kx in 0..kw {
+                                let ix =
+                                    (ox * g.stride.1 + kx * g.dilation.1).wrapping_sub(g.padding.1);
+                                if iy < h && ix < w {
+                                    taps.push(iy * w + ix);
+                                }
+                            }
+                        }
+                        cells.push(Cell {
+                            taps,
+                            divisor: (kh * kw) as f32,
+                        });
+                    }
+                }
+                (oh, ow)
+            }
+            PoolWindow::Adaptive((oh, ow)) => {
+                if oh == 0 || ow == 0 || oh > h || ow > w {
+                    return Err(err(format!(
+                        "cannot pool ({}, {}) into ({}, {})",
+                        h, w, oh, ow
+                    )));
+                }
+                let bin = |i: usize, size: usize, out: usize| {
+                    i * size / out..((i + 1) * size).div_ceil(out)
+                };
+                for oy in 0..oh {
+                    for ox in 0..ow {
+                        let taps: Vec<usize> = bin(oy, h, oh)
+                            .flat_map(|iy| bin(ox, w, ow).map(move |ix| iy * w + ix))
+                            .collect();
+                        let divisor = taps.len() as f32;
+                        cells.push(Cell { taps, divisor });
+                    }
+                }
+                (oh, ow)
+            }
+        };
+        if self.mode == PoolMode::Max && cells.iter().any(|c| c.taps.is_empty()) {
+            return Err(err("a max window lies entirely in padding".into()));
+        }
+        let out_dims = match (self.window, one_d) {
+            (PoolWindow::Global, _) => vec![n, c],
+            (_, true) => vec![n, c, ow],
+            (_, false) => vec![n, c, oh, ow],
+        };
+        Ok(Layout {
+            planes: n * c,
+            plane: h * w,
+            out_dims,
+            cells,
+        })
+    }
+
+    /// Input index each output element reads (max) for one plane.
+    fn argmax(cell: &Cell, plane: &[f32]) -> usize {
+        let mut best = cell.taps[0];
+        for &t in &cell.taps[1..] {
+            if plane[t] > plane[best] {
+                best = t;
+            }
+        }
+        best
+    }
+
+    /// Forward with tensor errors (shared by [Op::forward] and the nn layers).
+    pub(crate) fn run(&self, x: &Tensor) -> TensorResult<Tensor> {
+        let layout = self.layout(x)?;
+        let backend = x.backend();
+        let cells = layout.cells.len();
+        let mut out = backend.alloc(layout.planes * cells);
+        for (plane, out) in x
+            .data()
+            .chunks(layout.plane.max(1))
+            .zip(out.chunks_mut(cells.max(1)))
+        {
+            for (o, cell) in out.iter_mut().zip(&layout.cells) {
+                *o = match self.mode {
+                    PoolMode::Max => plane[Self::argmax(cell, plane)],
+                    PoolMode::Avg => {
+                        cell.taps.iter().map(|&t| plane[t]).sum::<f32>() / cell.divisor
+                    }
+                };
+            }
+        }
+        Tensor::from_vec(out, Shape::new(layout.out_dims), backend)
+    }
+
+    fn run_backward(&self, grad_out: &Tensor, x: &Tensor) -> TensorResult<Tensor> {
+        let layout = self.layout(x)?;
+        if grad_out.shape().dims() != layout.out_dims {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "pool backward: grad {:?} != output {:?}",
+                grad_out.shape().dims(),
+                layout.out_dims
+            ))));
+        }
+        let backend = x.backend();
+        let cells = layout.cells.len();
+        let mut grad = backend.alloc(x.numel());
+        for ((plane, g), go) in x
+            .data()
+            .chunks(layout.plane.max(1))
+            .zip(grad.chunks_mut(layout.plane.max(1)))
+            .zip(grad_out.data().chunks(cells.max(1)))
+        {
+            for (&go, cell) in go.iter().zip(&layout.cells) {
+                match self.mode {
+                    PoolMode::Max => g[Self::argmax(cell, plane)] += go,
+                    PoolMode::Avg => {
+                        for &t in &cell.taps {
+                            g[t] += go / cell.divisor;
+                        }
+                    }
+                }
+            }
+        }
+        Tensor::from_vec(grad, x.shape().clone(), backend)
+    }
+}
+
+impl Op for Pool {
+    fn id(&self) -> OpId {
+        match self.mode {
+            PoolMode::Max => OpId::MaxPool,
+            PoolMode::Avg => OpId::AvgPool,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self.mode {
+            PoolMode::Max => "MaxPool",
+            PoolMode::Avg => "AvgPool",
+        }
+    }
+
+    /// A 2-image, 2-channel input of 5x6 (1D geometries: length 7); max cases keep inputs
+    /// apart so perturbations do not change the argmax.
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        let dims = match self.window {
+            PoolWindow::Fixed(g) if g.is_1d() => vec![2, 2, 7],
+            _ => vec![2, 2, 5, 6],
+        };
+        let spec = GradCheckSpec::new(vec![vec![dims]]);
+        Some(match self.mode {
+            PoolMode::Max => spec.with_min_abs(0.05),
+            PoolMode::Avg => spec,
+        })
+    }
+
+    /// The window (the mode is part of the op's id): [0, geometry...] for a fixed window
+    /// (see [super::conv::geometry_attrs]), [1, OH, OW] adaptive, [2] global.
+    fn attrs(&self) -> Vec<f32> {
+        match self.window {
+            PoolWindow::Fixed(g) => [vec![0.0], geometry_attrs(&g)].concat(),
+            PoolWindow::Adaptive((oh, ow)) => vec![1.0, oh as f32, ow as f32],
+            PoolWindow::Global => vec![2.0],
+        }
+    }
+
+    fn with_attrs(&self, attrs: &[f32]) -> Option<Arc<dyn Op>> {
+        let (kind, rest) = attrs.split_first()?;
+        let window = match (*kind, rest) {
+            (0.0, geom) => PoolWindow::Fixed(geometry_from_attrs(geom)?),
+            (1.0, out) => PoolWindow::Adaptive(usize_attrs(out).map(|[oh, ow]| (oh, ow))?),
+            (2.0, []) => PoolWindow::Global,
+            _ => return None,
+        };
+        Some(Arc::new(Pool::new(self.mode, window)))
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 1 {
+            return Err(OpError("Pool requires 1 input".into()));
+        }
+        self.run(inputs[0]).map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 1 {
+            return Err(OpError("Pool backward requires 1 input".into()));
+        }
+        let grad = self
+            .run_backward(grad_out, inputs[0])
+            .map_err(|e| OpError(e.to_string()))?;
+        Ok(vec![grad])
+    }
+}
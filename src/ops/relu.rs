@@ -1,6 +1,6 @@
 //! ReLU: max(0,x). Forward relu(a); backward grad = grad_out * (a > 0).
 
-use super::{Op, OpError, OpId, OpResult};
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
 use crate::tensor::Tensor;
 
 pub struct ReLU;
@@ -14,6 +14,13 @@ impl Op for ReLU {
         "ReLU"
     }
 
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(
+            GradCheckSpec::new(vec![vec![vec![3, 4]], vec![vec![1, 1]], vec![vec![5]]])
+                .with_min_abs(0.05),
+        )
+    }
+
     fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
         if inputs.len() != 1 {
             return Err(OpError("ReLU requires 1 input".into()));
@@ -1,6 +1,6 @@
 //! Sigmoid: 1/(1+exp(-x)). Forward sigmoid(a); backward grad * out * (1-out).
 
-use super::{Op, OpError, OpId, OpResult};
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
 use crate::tensor::Tensor;
 
 pub struct Sigmoid;
@@ -14,6 +14,13 @@ impl Op for Sigmoid {
         "Sigmoid"
     }
 
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(
+            GradCheckSpec::new(vec![vec![vec![3, 4]], vec![vec![1, 1]], vec![vec![5]]])
+                .with_range(-3.0, 3.0),
+        )
+    }
+
     fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
         if inputs.len() != 1 {
             return Err(OpError("Sigmoid requires 1 input".into()));
@@ -1,6 +1,6 @@
 //! Softmax along last dimension. Forward: softmax_last_dim; backward: y * (grad_out - sum(grad_out * y)).
 
-use super::{Op, OpError, OpId, OpResult};
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
 use crate::tensor::Tensor;
 
 pub struct Softmax;
@@ -14,6 +14,18 @@ impl Op for Softmax {
         "Softmax"
     }
 
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(
+            GradCheckSpec::new(vec![
+                vec![vec![3, 4]],
+                vec![vec![1, 1]],
+                vec![vec![2, 1]],
+                vec![vec![5]],
+            ])
+            .with_range(-2.0, 2.0),
+        )
+    }
+
     fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
         if inputs.len() != 1 {
             return Err(OpError("Softmax requires 1 input".into()));
@@ -1,6 +1,6 @@
 //! Sum: reduce to scalar. Forward sum(a); backward grad = broadcast grad_out to input shape.
 
-use super::{Op, OpError, OpId, OpResult};
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
 use crate::tensor::Tensor;
 
 pub struct Sum;
@@ -14,6 +14,10 @@ impl Op for Sum {
         "Sum"
     }
 
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(GradCheckSpec::new(vec![vec![vec![3, 4]], vec![vec![1]], vec![vec![1, 1]]]))
+    }
+
     fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
         if inputs.len() != 1 {
             return Err(OpError("Sum requires 1 input".into()));
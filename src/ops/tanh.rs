@@ -0,0 +1,53 @@
+//! Tanh: forward tanh(a) on the host; backward grad * (1 - out^2).
+
+use super::{GradCheckSpec, Op, OpError, OpId, OpResult};
+use crate::tensor::Tensor;
+
+pub struct Tanh;
+
+impl Op for Tanh {
+    fn id(&self) -> OpId {
+        OpId::Tanh
+    }
+
+    fn name(&self) -> &'static str {
+        "Tanh"
+    }
+
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(GradCheckSpec::new(vec![vec![vec![3, 4]], vec![vec![5]]]).with_range(-2.0, 2.0))
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 1 {
+            return Err(OpError("Tanh requires 1 input".into()));
+        }
+        let x = inputs[0];
+        let mut out = x.backend().alloc(x.numel());
+        for (o, &v) in out.iter_mut().zip(x.data()) {
+            *o = v.tanh();
+        }
+        Tensor::from_vec(out, x.shape().clone(), x.backend()).map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 1 {
+            return Err(OpError("Tanh backward requires 1 input".into()));
+        }
+        if grad_out.numel() != fwd_output.numel() {
+            return Err(OpError("Tanh backward: grad shape != output shape".into()));
+        }
+        let mut grad = fwd_output.backend().alloc(fwd_output.numel());
+        for ((g, &d), &y) in grad.iter_mut().zip(grad_out.data()).zip(fwd_output.data()) {
+            *g = d * (1.0 - y * y);
+        }
+        let grad = Tensor::from_vec(grad, inputs[0].shape().clone(), fwd_output.backend())
+            .map_err(|e| OpError(e.to_string()))?;
+        Ok(vec![grad])
+    }
+}
@@ -0,0 +1,431 @@
+//! Layout ops that only move values: Narrow (a range of one dim), Concat (inputs joined along
+//! one dim), Reshape and Permute. Each carries its geometry, so it is applied per call; backward
+//! moves the gradient back to where each value came from.
+
+use super::{usize_attr_list, usize_attrs, GradCheckSpec, Op, OpError, OpId, OpResult};
+use crate::shape::{Shape, ShapeError};
+use crate::tensor::{Tensor, TensorError, TensorResult};
+use std::sync::Arc;
+
+/// (outer, size of `dim`, inner) of `dims`, or an error if `dim` is out of range.
+fn split_at(op: &str, dims: &[usize], dim: usize) -> TensorResult<(usize, usize, usize)> {
+    if dim >= dims.len() {
+        return Err(TensorError::Shape(ShapeError(format!(
+            "{}: dim {} out of range for {:?}",
+            op, dim, dims
+        ))));
+    }
+    let outer = dims[..dim].iter().product();
+    let inner = dims[dim + 1..].iter().product();
+    Ok((outer, dims[dim], inner))
+}
+
+/// Rank dim + 2 dims of 2, with `size` at `dim` (gradient-check inputs).
+fn check_dims(dim: usize, size: usize) -> Vec<usize> {
+    let mut dims = vec![2; dim + 2];
+    dims[dim] = size;
+    dims
+}
+
+/// `len` entries of `dim` starting at `start`; the output keeps the dim.
+pub struct Narrow {
+    dim: usize,
+    start: usize,
+    len: usize,
+}
+
+impl Narrow {
+    pub fn new(dim: usize, start: usize, len: usize) -> Self {
+        Narrow { dim, start, len }
+    }
+
+    /// (outer, size of dim, inner) after checking the range fits.
+    fn layout(&self, x: &Tensor) -> TensorResult<(usize, usize, usize)> {
+        let (outer, size, inner) = split_at("narrow", x.shape().dims(), self.dim)?;
+        if self.start + self.len > size {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "narrow: {}..{} outside dim {} of size {}",
+                self.start,
+                self.start + self.len,
+                self.dim,
+                size
+            ))));
+        }
+        Ok((outer, size, inner))
+    }
+
+    pub(crate) fn run(&self, x: &Tensor) -> TensorResult<Tensor> {
+        let (outer, size, inner) = self.layout(x)?;
+        let block = self.len * inner;
+        let mut out = x.backend().alloc(outer * block);
+        for (o, dst) in out.chunks_mut(block.max(1)).take(outer).enumerate() {
+            let src = (o * size + self.start) * inner;
+            dst.copy_from_slice(&x.data()[src..src + block]);
+        }
+        let mut dims = x.shape().dims().to_vec();
+        dims[self.dim] = self.len;
+        Tensor::from_vec(out, Shape::new(dims), x.backend())
+    }
+
+    fn run_backward(&self, grad_out: &Tensor, x: &Tensor) -> TensorResult<Tensor> {
+        let (outer, size, inner) = self.layout(x)?;
+        let block = self.len * inner;
+        if grad_out.numel() != outer * block {
+            return Err(TensorError::Shape(ShapeError(
+                "narrow backward: grad shape != output shape".into(),
+            )));
+        }
+        let mut grad = x.backend().alloc(x.numel());
+        for (o, src) in grad_out.data().chunks(block.max(1)).take(outer).enumerate() {
+            let dst = (o * size + self.start) * inner;
+            grad[dst..dst + block].copy_from_slice(src);
+        }
+        Tensor::from_vec(grad, x.shape().clone(), x.backend())
+    }
+}
+
+impl Op for Narrow {
+    fn id(&self) -> OpId {
+        OpId::Narrow
+    }
+
+    fn name(&self) -> &'static str {
+        "Narrow"
+    }
+
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        let size = self.start + self.len + 1;
+        Some(GradCheckSpec::new(vec![vec![check_dims(self.dim, size)]]))
+    }
+
+    /// [dim, start, len].
+    fn attrs(&self) -> Vec<f32> {
+        vec![self.dim as f32, self.start as f32, self.len as f32]
+    }
+
+    fn with_attrs(&self, attrs: &[f32]) -> Option<Arc<dyn Op>> {
+        let [dim, start, len] = usize_attrs(attrs)?;
+        Some(Arc::new(Narrow::new(dim, start, len)))
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 1 {
+            return Err(OpError("Narrow requires 1 input".into()));
+        }
+        self.run(inputs[0]).map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 1 {
+            return Err(OpError("Narrow backward requires 1 input".into()));
+        }
+        let grad = self
+            .run_backward(grad_out, inputs[0])
+            .map_err(|e| OpError(e.to_string()))?;
+        Ok(vec![grad])
+    }
+}
+
+/// Inputs joined along `dim`; all other dims must match.
+pub struct Concat {
+    dim: usize,
+}
+
+impl Concat {
+    pub fn new(dim: usize) -> Self {
+        Concat { dim }
+    }
+
+    /// (outer, inner, size of dim per input) after checking the inputs agree.
+    fn layout(&self, inputs: &[&Tensor]) -> TensorResult<(usize, usize, Vec<usize>)> {
+        let first = inputs
+            .first()
+            .ok_or_else(|| TensorError::Shape(ShapeError("concat: no inputs".into())))?;
+        let (outer, _, inner) = split_at("concat", first.shape().dims(), self.dim)?;
+        let mut sizes = Vec::with_capacity(inputs.len());
+        for t in inputs {
+            let (a, b) = (first.shape().dims(), t.shape().dims());
+            let same_rest = a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .enumerate()
+                    .all(|(i, (x, y))| i == self.dim || x == y);
+            if !same_rest {
+                return Err(TensorError::Shape(ShapeError(format!(
+                    "concat: {:?} and {:?} differ outside dim {}",
+                    a, b, self.dim
+                ))));
+            }
+            sizes.push(b[self.dim]);
+        }
+        Ok((outer, inner, sizes))
+    }
+
+    pub(crate) fn run(&self, inputs: &[&Tensor]) -> TensorResult<Tensor> {
+        let (outer, inner, sizes) = self.layout(inputs)?;
+        let total: usize = sizes.iter().sum();
+        let mut out = inputs[0].backend().alloc(outer * total * inner);
+        let mut offset = 0;
+        for (t, size) in inputs.iter().zip(&sizes) {
+            let block = size * inner;
+            let data = t.data();
+            for o in 0..outer {
+                let dst = (o * total) * inner + offset;
+                out[dst..dst + block].copy_from_slice(&data[o * block..(o + 1) * block]);
+            }
+            offset += block;
+        }
+        let mut dims = inputs[0].shape().dims().to_vec();
+        dims[self.dim] = total;
+        Tensor::from_vec(out, Shape::new(dims), inputs[0].backend())
+    }
+
+    fn run_backward(&self, grad_out: &Tensor, inputs: &[&Tensor]) -> TensorResult<Vec<Tensor>> {
+        let (outer, inner, sizes) = self.layout(inputs)?;
+        let total: usize = sizes.iter().sum();
+        if grad_out.numel() != outer * total * inner {
+            return Err(TensorError::Shape(ShapeError(
+                "concat backward: grad shape != output shape".into(),
+            )));
+        }
+        let go = grad_out.data();
+        let mut offset = 0;
+        let mut grads = Vec::with_capacity(inputs.len());
+        for (t, size) in inputs.iter().zip(&sizes) {
+            let block = size * inner;
+            let mut grad = t.backend().alloc(t.numel());
+            for o in 0..outer {
+                let src = (o * total) * inner + offset;
+                grad[o * block..(o + 1) * block].copy_from_slice(&go[src..src + block]);
+            }
+            offset += block;
+            grads.push(Tensor::from_vec(grad, t.shape().clone(), t.backend())?);
+        }
+        Ok(grads)
+    }
+}
+
+impl Op for Concat {
+    fn id(&self) -> OpId {
+        OpId::Concat
+    }
+
+    fn name(&self) -> &'static str {
+        "Concat"
+    }
+
+    /// Two inputs of sizes 1 and 2 along the dim.
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(GradCheckSpec::new(vec![vec![
+            check_dims(self.dim, 1),
+            check_dims(self.dim, 2),
+        ]]))
+    }
+
+    /// [dim].
+    fn attrs(&self) -> Vec<f32> {
+        vec![self.dim as f32]
+    }
+
+    fn with_attrs(&self, attrs: &[f32]) -> Option<Arc<dyn Op>> {
+        let [dim] = usize_attrs(attrs)?;
+        Some(Arc::new(Concat::new(dim)))
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        self.run(inputs).map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        self.run_backward(grad_out, inputs)
+            .map_err(|e| OpError(e.to_string()))
+    }
+}
+
+/// Same values with new dims (row-major order is kept).
+pub struct Reshape {
+    dims: Vec<usize>,
+}
+
+impl Reshape {
+    pub fn new(dims: Vec<usize>) -> Self {
+        Reshape { dims }
+    }
+
+    pub(crate) fn run(&self, x: &Tensor) -> TensorResult<Tensor> {
+        let shape = Shape::new(self.dims.clone());
+        if shape.numel() != x.numel() {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "reshape: {:?} to {:?} changes the element count",
+                x.shape().dims(),
+                self.dims
+            ))));
+        }
+        let mut out = x.backend().alloc(x.numel());
+        out.copy_from_slice(x.data());
+        Tensor::from_vec(out, shape, x.backend())
+    }
+}
+
+impl Op for Reshape {
+    fn id(&self) -> OpId {
+        OpId::Reshape
+    }
+
+    fn name(&self) -> &'static str {
+        "Reshape"
+    }
+
+    /// A flat input with the target's element count.
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        let n = self.dims.iter().product();
+        Some(GradCheckSpec::new(vec![vec![vec![n]]]))
+    }
+
+    /// The target dims.
+    fn attrs(&self) -> Vec<f32> {
+        self.dims.iter().map(|&d| d as f32).collect()
+    }
+
+    fn with_attrs(&self, attrs: &[f32]) -> Option<Arc<dyn Op>> {
+        Some(Arc::new(Reshape::new(usize_attr_list(attrs)?)))
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 1 {
+            return Err(OpError("Reshape requires 1 input".into()));
+        }
+        self.run(inputs[0]).map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 1 {
+            return Err(OpError("Reshape backward requires 1 input".into()));
+        }
+        let back = Reshape::new(inputs[0].shape().dims().to_vec());
+        let grad = back.run(grad_out).map_err(|e| OpError(e.to_string()))?;
+        Ok(vec![grad])
+    }
+}
+
+/// Dims reordered: output dim i is input dim `perm[i]`.
+pub struct Permute {
+    perm: Vec<usize>,
+}
+
+impl Permute {
+    pub fn new(perm: Vec<usize>) -> Self {
+        Permute { perm }
+    }
+
+    fn inverse(&self) -> Permute {
+        let mut inv = vec![0; self.perm.len()];
+        for (i, &p) in self.perm.iter().enumerate() {
+            inv[p] = i;
+        }
+        Permute::new(inv)
+    }
+
+    pub(crate) fn run(&self, x: &Tensor) -> TensorResult<Tensor> {
+        let dims = x.shape().dims();
+        let mut seen = vec![false; dims.len()];
+        let valid = self.perm.len() == dims.len()
+            && self
+                .perm
+                .iter()
+                .all(|&p| p < dims.len() && !std::mem::replace(&mut seen[p], true));
+        if !valid {
+            return Err(TensorError::Shape(ShapeError(format!(
+                "permute: {:?} is not a permutation of the dims of {:?}",
+                self.perm, dims
+            ))));
+        }
+        let mut strides = vec![1; dims.len()];
+        for i in (0..dims.len().saturating_sub(1)).rev() {
+            strides[i] = strides[i + 1] * dims[i + 1];
+        }
+        let out_dims: Vec<usize> = self.perm.iter().map(|&p| dims[p]).collect();
+        let src_strides: Vec<usize> = self.perm.iter().map(|&p| strides[p]).collect();
+        let mut out = x.backend().alloc(x.numel());
+        let data = x.data();
+        // Walk the output in order, carrying the multi-index and the matching source offset.
+        let mut index = vec![0; dims.len()];
+        let mut src = 0;
+        for o in out.iter_mut() {
+            *o = data[src];
+            for d in (0..index.len()).rev() {
+                index[d] += 1;
+                src += src_strides[d];
+                if index[d] < out_dims[d] {
+                    break;
+                }
+                src -= src_strides[d] * out_dims[d];
+                index[d] = 0;
+            }
+        }
+        Tensor::from_vec(out, Shape::new(out_dims), x.backend())
+    }
+}
+
+impl Op for Permute {
+    fn id(&self) -> OpId {
+        OpId::Permute
+    }
+
+    fn name(&self) -> &'static str {
+        "Permute"
+    }
+
+    /// Input dims 2, 3, 4, ... for a permutation of that rank.
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        let dims = (0..self.perm.len()).map(|i| i + 2).collect();
+        Some(GradCheckSpec::new(vec![vec![dims]]))
+    }
+
+    /// The permutation.
+    fn attrs(&self) -> Vec<f32> {
+        self.perm.iter().map(|&p| p as f32).collect()
+    }
+
+    fn with_attrs(&self, attrs: &[f32]) -> Option<Arc<dyn Op>> {
+        Some(Arc::new(Permute::new(usize_attr_list(attrs)?)))
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        if inputs.len() != 1 {
+            return Err(OpError("Permute requires 1 input".into()));
+        }
+        self.run(inputs[0]).map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        if inputs.len() != 1 {
+            return Err(OpError("Permute backward requires 1 input".into()));
+        }
+        let grad = self
+            .inverse()
+            .run(grad_out)
+            .map_err(|e| OpError(e.to_string()))?;
+        Ok(vec![grad])
+    }
+}
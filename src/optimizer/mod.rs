@@ -1,6 +1,6 @@
 //! Optimizer: updates parameters using gradients. SGD, Adam, etc.
 
-use crate::parameter::Parameter;
+use crate::parameter::{Parameter, SparseGrad};
 use crate::tensor::Tensor;
 use thiserror::Error;
 
@@ -16,7 +16,28 @@ pub trait Optimizer {
     fn step(&mut self, parameters: &mut [&mut Parameter]) -> OptimizerResult<()>;
 }
 
-/// SGD: param = param - lr * grad.
+/// Apply `f(value, grad)` to each element of the rows a sparse gradient touches.
+fn for_sparse_rows(
+    data: &mut [f32],
+    sparse: &SparseGrad,
+    mut f: impl FnMut(&mut f32, f32),
+) -> OptimizerResult<()> {
+    let row_len = sparse.row_len();
+    for (k, &r) in sparse.rows.iter().enumerate() {
+        let dst = data
+            .get_mut(r * row_len..(r + 1) * row_len)
+            .ok_or_else(|| OptimizerError(format!("sparse grad row {} out of range", r)))?;
+        for (d, &g) in dst
+            .iter_mut()
+            .zip(&sparse.values.data()[k * row_len..(k + 1) * row_len])
+        {
+            f(d, g);
+        }
+    }
+    Ok(())
+}
+
+/// SGD: param = param - lr * grad. Sparse gradients update only their rows.
 pub struct SGD {
     pub lr: f32,
 }
@@ -33,6 +54,10 @@ impl Optimizer for SGD {
             if p.is_frozen() {
                 continue;
             }
+            if let Some(sparse) = p.sparse_grad().cloned() {
+                let lr = self.lr;
+                for_sparse_rows(p.data_mut().data_mut(), &sparse, |d, g| *d -= lr * g)?;
+            }
             let grad = match p.grad() {
                 Some(g) => g.clone(),
                 None => continue,
@@ -51,7 +76,8 @@ impl Optimizer for SGD {
     }
 }
 
-/// Adam: first-order and second-order moment with bias correction.
+/// Adam: first-order and second-order moment with bias correction. A parameter with only a
+/// sparse gradient is updated lazily: only touched rows advance their moments and values.
 /// State: (m, v) per parameter, stored in same order as parameters.
 pub struct Adam {
     pub lr: f32,
@@ -107,9 +133,39 @@ impl Optimizer for Adam {
             if p.is_frozen() {
                 continue;
             }
-            let grad = match p.grad() {
-                Some(g) => g.clone(),
-                None => continue,
+            let sparse = p.sparse_grad().cloned();
+            let grad = match (p.grad(), sparse) {
+                (Some(g), None) => g.clone(),
+                (Some(g), Some(s)) => s
+                    .to_dense(g.shape())
+                    .and_then(|s| g.add(&s))
+                    .map_err(|e| OptimizerError(e.to_string()))?,
+                // Lazy update: moments and values of untouched rows are left as they are.
+                (None, Some(s)) => {
+                    let (m, v) = &mut self.state[i];
+                    let (m_data, v_data) = (m.data_mut(), v.data_mut());
+                    let param_data = p.data_mut().data_mut();
+                    let (beta1, beta2, lr, eps) = (self.beta1, self.beta2, self.lr, self.eps);
+                    let row_len = s.row_len();
+                    for (k, &r) in s.rows.iter().enumerate() {
+                        let grads = &s.values.data()[k * row_len..(k + 1) * row_len];
+                        for (j, &g) in (r * row_len..).zip(grads) {
+                            if j >= param_data.len() {
+                                return Err(OptimizerError(format!(
+                                    "sparse grad row {} out of range",
+                                    r
+                                )));
+                            }
+                            m_data[j] = beta1 * m_data[j] + (1.0 - beta1) * g;
+                            v_data[j] = beta2 * v_data[j] + (1.0 - beta2) * g * g;
+                            let m_j = m_data[j] / (1.0 - beta1_t);
+                            let v_j = v_data[j] / (1.0 - beta2_t);
+                            param_data[j] -= lr * m_j / (v_j.sqrt() + eps);
+                        }
+                    }
+                    continue;
+                }
+                (None, None) => continue,
             };
             let (m, v) = &mut self.state[i];
             let grad_data = grad.data();
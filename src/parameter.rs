@@ -2,15 +2,33 @@
 
 use crate::tensor::Tensor;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+/// Stable parameter identity. Graphs bind parameter leaves by id so gradients are written
+/// back to the right [Parameter] regardless of ordering. Clones get a fresh id;
+/// [Parameter::share] keeps it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ParamId(u64);
+
+impl ParamId {
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        ParamId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 /// Parameter: wraps a Tensor as a trainable parameter. Can be frozen, named, and serialized.
-#[derive(Clone)]
+/// Cloning makes an independent parameter (new id); use [Self::share] to tie weights.
 pub struct Parameter {
+    /// Identity used by [crate::Graph::param].
+    id: ParamId,
     /// Underlying tensor (data).
     data: Tensor,
     /// Gradient (set after backward from graph).
     grad: Option<Tensor>,
+    /// Row-sparse gradient (e.g. from embedding lookups), in addition to `grad`.
+    sparse_grad: Option<SparseGrad>,
     /// Optional name for grouping / logging.
     name: Option<String>,
     /// If true, optimizer will not update this parameter.
@@ -20,8 +38,10 @@ pub struct Parameter {
 impl Parameter {
     pub fn new(data: Tensor) -> Self {
         Parameter {
+            id: ParamId::next(),
             data,
             grad: None,
+            sparse_grad: None,
             name: None,
             frozen: false,
         }
@@ -29,13 +49,30 @@ impl Parameter {
 
     pub fn named(name: impl Into<String>, data: Tensor) -> Self {
         Parameter {
+            id: ParamId::next(),
             data,
             grad: None,
+            sparse_grad: None,
             name: Some(name.into()),
             frozen: false,
         }
     }
 
+    /// Stable identity (kept by [Self::share], not by clones).
+    pub fn id(&self) -> ParamId {
+        self.id
+    }
+
+    /// A copy with the same identity, for deliberate weight tying: in one graph both copies
+    /// bind to a single node (holding the first copy's data), and each receives the summed
+    /// gradient of every use, so identical optimizer steps keep them equal.
+    pub fn share(&self) -> Parameter {
+        Parameter {
+            id: self.id,
+            ..self.clone()
+        }
+    }
+
     /// Reference to the underlying tensor (data).
     pub fn data(&self) -> &Tensor {
         &self.data
@@ -72,9 +109,143 @@ impl Parameter {
         self.grad = g;
     }
 
-    /// Zero out gradient (clear so next backward can accumulate).
+    /// Add `g` to the current gradient (or set it if there is none).
+    pub fn accumulate_grad(&mut self, g: &Tensor) -> crate::TensorResult<()> {
+        self.grad = Some(match self.grad.take() {
+            None => g.clone(),
+            Some(existing) => existing.add(g)?,
+        });
+        Ok(())
+    }
+
+    /// Row-sparse gradient, if any. The full gradient is [Self::grad] plus this.
+    pub fn sparse_grad(&self) -> Option<&SparseGrad> {
+        self.sparse_grad.as_ref()
+    }
+
+    /// Add a row-sparse gradient (merged with any existing one).
+    pub fn accumulate_sparse_grad(&mut self, g: &SparseGrad) -> crate::TensorResult<()> {
+        self.sparse_grad = Some(match self.sparse_grad.take() {
+            None => g.clone(),
+            Some(existing) => existing.merge(g)?,
+        });
+        Ok(())
+    }
+
+    /// Zero out gradients, dense and sparse (clear so next backward can accumulate).
     pub fn zero_grad(&mut self) {
         self.grad = None;
+        self.sparse_grad = None;
+    }
+
+    /// Move data (and gradient, if any) to `backend`. Identity, name and frozen flag are kept.
+    pub fn to(&mut self, backend: Arc<dyn crate::backend::Backend>) -> crate::TensorResult<()> {
+        self.data = self.data.to(backend.clone())?;
+        if let Some(g) = &self.grad {
+            self.grad = Some(g.to(backend.clone())?);
+        }
+        if let Some(g) = &mut self.sparse_grad {
+            g.values = g.values.to(backend)?;
+        }
+        Ok(())
+    }
+}
+
+impl Clone for Parameter {
+    fn clone(&self) -> Self {
+        Parameter {
+            id: ParamId::next(),
+            data: self.data.clone(),
+            grad: self.grad.clone(),
+            sparse_grad: self.sparse_grad.clone(),
+            name: self.name.clone(),
+            frozen: self.frozen,
+        }
+    }
+}
+
+/// Gradient of a parameter viewed as [rows, row_len] that is zero outside `rows`. Optimizers
+/// update only these rows, so a lookup into a huge table costs O(rows touched).
+#[derive(Clone)]
+pub struct SparseGrad {
+    /// Sorted, unique row indices.
+    pub rows: Vec<usize>,
+    /// Gradient of each listed row: [rows.len(), row_len].
+    pub values: Tensor,
+}
+
+impl SparseGrad {
+    /// `values` [rows.len(), row_len]; duplicate rows are summed and rows sorted.
+    pub fn new(rows: Vec<usize>, values: Tensor) -> crate::TensorResult<Self> {
+        let dims = values.shape().dims();
+        if dims.len() != 2 || dims[0] != rows.len() {
+            return Err(crate::TensorError::Shape(crate::ShapeError(format!(
+                "sparse grad: {} rows with values {:?}",
+                rows.len(),
+                dims
+            ))));
+        }
+        let row_len = dims[1];
+        let mut order: Vec<usize> = (0..rows.len()).collect();
+        order.sort_by_key(|&i| rows[i]);
+        let mut unique: Vec<usize> = Vec::with_capacity(rows.len());
+        let mut data: Vec<f32> = Vec::with_capacity(values.numel());
+        for i in order {
+            let src = &values.data()[i * row_len..(i + 1) * row_len];
+            if unique.last() == Some(&rows[i]) {
+                let start = data.len() - row_len;
+                for (d, v) in data[start..].iter_mut().zip(src) {
+                    *d += v;
+                }
+            } else {
+                unique.push(rows[i]);
+                data.extend_from_slice(src);
+            }
+        }
+        let shape = crate::shape::Shape::new(vec![unique.len(), row_len]);
+        Ok(SparseGrad {
+            rows: unique,
+            values: Tensor::from_vec(data, shape, values.backend())?,
+        })
+    }
+
+    pub fn row_len(&self) -> usize {
+        self.values.shape().dims()[1]
+    }
+
+    /// Sum of two sparse gradients of the same parameter.
+    pub fn merge(&self, other: &SparseGrad) -> crate::TensorResult<SparseGrad> {
+        if self.row_len() != other.row_len() {
+            return Err(crate::TensorError::Shape(crate::ShapeError(format!(
+                "sparse grad: row lengths {} and {}",
+                self.row_len(),
+                other.row_len()
+            ))));
+        }
+        let rows = self.rows.iter().chain(&other.rows).copied().collect();
+        let mut data = self.values.data().to_vec();
+        data.extend_from_slice(other.values.data());
+        let shape =
+            crate::shape::Shape::new(vec![self.rows.len() + other.rows.len(), self.row_len()]);
+        SparseGrad::new(rows, Tensor::from_vec(data, shape, self.values.backend())?)
+    }
+
+    /// Dense gradient of `shape` (leading dim indexed by the rows).
+    pub fn to_dense(&self, shape: &crate::shape::Shape) -> crate::TensorResult<Tensor> {
+        let backend = self.values.backend();
+        let row_len = self.row_len();
+        let mut data = backend.alloc(shape.numel());
+        for (k, &r) in self.rows.iter().enumerate() {
+            let dst = data.get_mut(r * row_len..(r + 1) * row_len).ok_or_else(|| {
+                crate::TensorError::Shape(crate::ShapeError(format!(
+                    "sparse grad: row {} outside {:?}",
+                    r,
+                    shape.dims()
+                )))
+            })?;
+            dst.copy_from_slice(&self.values.data()[k * row_len..(k + 1) * row_len]);
+        }
+        Tensor::from_vec(data, shape.clone(), backend)
     }
 }
 
@@ -103,8 +274,10 @@ impl Parameter {
             .from_vec(state.data, shape)
             .map_err(crate::tensor::TensorError::from)?;
         Ok(Parameter {
+            id: ParamId::next(),
             data,
             grad: None,
+            sparse_grad: None,
             name: state.name,
             frozen: false,
         })
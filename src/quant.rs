@@ -0,0 +1,343 @@
+//! Post-training int8 quantisation for inference. Weights are stored as int8 with a scale and
+//! zero point per output channel; activations entering each layer are quantised per tensor
+//! with a range calibrated from a [DataLoader]. Matmuls run in int8 with i32 accumulation
+//! ([crate::backend::gemm::igemm]) and produce f32, so f32 activations (ReLU) sit between
+//! layers. [compare] measures the error against the f32 model.
+
+use crate::backend::gemm::{igemm, IGEMM_MAX_K};
+use crate::data::{DataLoader, Dataset};
+use crate::nn::{Linear, Module, MLP2};
+use crate::shape::Shape;
+use crate::tensor::Tensor;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("quantisation error: {0}")]
+pub struct QuantError(pub String);
+
+pub type QuantResult<T> = Result<T, QuantError>;
+
+/// Affine int8 mapping: real = (q - zero_point) * scale, q in [-128, 127].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QParams {
+    pub scale: f32,
+    pub zero_point: i32,
+}
+
+impl QParams {
+    /// Parameters covering [min, max], widened to include 0 so it is exactly representable.
+    pub fn from_range(min: f32, max: f32) -> QuantResult<Self> {
+        if !min.is_finite() || !max.is_finite() || min > max {
+            return Err(QuantError(format!("invalid range [{}, {}]", min, max)));
+        }
+        let (min, max) = (min.min(0.0), max.max(0.0));
+        let scale = if max > min { (max - min) / 255.0 } else { 1.0 };
+        let zero_point = (-128.0 - min / scale).round().clamp(-128.0, 127.0) as i32;
+        Ok(QParams { scale, zero_point })
+    }
+
+    pub fn quantize(&self, x: f32) -> i8 {
+        ((x / self.scale).round() + self.zero_point as f32).clamp(-128.0, 127.0) as i8
+    }
+
+    pub fn dequantize(&self, q: i8) -> f32 {
+        (q as i32 - self.zero_point) as f32 * self.scale
+    }
+}
+
+/// Running min/max of observed values.
+#[derive(Clone, Copy, Debug)]
+pub struct MinMaxObserver {
+    min: f32,
+    max: f32,
+}
+
+impl Default for MinMaxObserver {
+    fn default() -> Self {
+        MinMaxObserver {
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+        }
+    }
+}
+
+impl MinMaxObserver {
+    pub fn observe(&mut self, values: &[f32]) {
+        for &v in values {
+            self.min = self.min.min(v);
+            self.max = self.max.max(v);
+        }
+    }
+
+    /// Parameters for the observed range; errors if nothing was observed.
+    pub fn qparams(&self) -> QuantResult<QParams> {
+        if self.min > self.max {
+            return Err(QuantError("observer saw no values".into()));
+        }
+        QParams::from_range(self.min, self.max)
+    }
+}
+
+/// Inference-only model with quantised weights.
+pub trait QuantizedModule {
+    /// Forward on f32 input [M, in_features]; returns f32 output.
+    fn forward(&self, x: &Tensor) -> QuantResult<Tensor>;
+}
+
+/// Linear layer with int8 weights (per output channel) and a calibrated input range.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QuantizedLinear {
+    in_features: usize,
+    out_features: usize,
+    /// Quantised weight, transposed to [out, in] so each channel is contiguous.
+    weight_t: Vec<i8>,
+    weight_qparams: Vec<QParams>,
+    /// Per channel sum of quantised weights, for the input zero-point correction.
+    weight_sums: Vec<i32>,
+    bias: Vec<f32>,
+    input_qparams: QParams,
+}
+
+impl QuantizedLinear {
+    /// Quantise `linear` with a known input range.
+    pub fn new(linear: &Linear, input_qparams: QParams) -> QuantResult<Self> {
+        let w = linear.weight.data();
+        let (k, n) = match w.shape().dims() {
+            [k, n] => (*k, *n),
+            d => return Err(QuantError(format!("expected 2D weight, got {:?}", d))),
+        };
+        if k > IGEMM_MAX_K {
+            return Err(QuantError(format!(
+                "in_features {} exceeds the int8 GEMM limit {} (i32 accumulation)",
+                k, IGEMM_MAX_K
+            )));
+        }
+        let wd = w.data();
+        let mut weight_t = vec![0i8; n * k];
+        let mut weight_qparams = Vec::with_capacity(n);
+        let mut weight_sums = Vec::with_capacity(n);
+        for j in 0..n {
+            let mut obs = MinMaxObserver::default();
+            obs.observe(&(0..k).map(|p| wd[p * n + j]).collect::<Vec<_>>());
+            let qp = if k == 0 {
+                QParams::from_range(0.0, 0.0)?
+            } else {
+                obs.qparams()?
+            };
+            let row = &mut weight_t[j * k..(j + 1) * k];
+            for (p, q) in row.iter_mut().enumerate() {
+                *q = qp.quantize(wd[p * n + j]);
+            }
+            weight_sums.push(row.iter().map(|&q| q as i32).sum());
+            weight_qparams.push(qp);
+        }
+        Ok(QuantizedLinear {
+            in_features: k,
+            out_features: n,
+            weight_t,
+            weight_qparams,
+            weight_sums,
+            bias: linear.bias.data().data().to_vec(),
+            input_qparams,
+        })
+    }
+
+    /// Quantise `linear`, calibrating the input range on every batch of `loader`.
+    pub fn calibrate<D: Dataset>(linear: &Linear, loader: &mut DataLoader<D>) -> QuantResult<Self> {
+        let mut obs = MinMaxObserver::default();
+        for_each_batch(loader, |x| {
+            obs.observe(x.data());
+            Ok(())
+        })?;
+        Self::new(linear, obs.qparams()?)
+    }
+
+    pub fn input_qparams(&self) -> QParams {
+        self.input_qparams
+    }
+
+    pub fn weight_qparams(&self) -> &[QParams] {
+        &self.weight_qparams
+    }
+
+    /// Bytes of quantised weights, scales, zero points and bias.
+    pub fn size_bytes(&self) -> usize {
+        self.weight_t.len() + self.out_features * (4 + 4 + 4 + 4)
+    }
+}
+
+impl QuantizedModule for QuantizedLinear {
+    fn forward(&self, x: &Tensor) -> QuantResult<Tensor> {
+        let (m, k, n) = match x.shape().dims() {
+            [m, k] if *k == self.in_features => (*m, *k, self.out_features),
+            d => {
+                return Err(QuantError(format!(
+                    "expected input [M, {}], got {:?}",
+                    self.in_features, d
+                )))
+            }
+        };
+        let xq: Vec<i8> = x
+            .data()
+            .iter()
+            .map(|&v| self.input_qparams.quantize(v))
+            .collect();
+        let mut acc = vec![0i32; m * n];
+        igemm(m, k, n, &xq, &self.weight_t, &mut acc);
+        // sum_k (xq - zx)(wq - zw) = xq.wq - zw * sum(xq) - zx * sum(wq) + k * zx * zw, in i64:
+        // each term is bounded by k * 128 * 128, but their partial sums need not fit in i32.
+        let zx = self.input_qparams.zero_point;
+        let mut out = x.backend().alloc(m * n);
+        for i in 0..m {
+            let x_sum: i64 = xq[i * k..(i + 1) * k].iter().map(|&q| q as i64).sum();
+            for j in 0..n {
+                let qp = self.weight_qparams[j];
+                let (zx, zw) = (zx as i64, qp.zero_point as i64);
+                let a = acc[i * n + j] as i64 - zw * x_sum - zx * self.weight_sums[j] as i64
+                    + k as i64 * zx * zw;
+                out[i * n + j] = a as f32 * self.input_qparams.scale * qp.scale + self.bias[j];
+            }
+        }
+        Tensor::from_vec(out, Shape::new(vec![m, n]), x.backend())
+            .map_err(|e| QuantError(e.to_string()))
+    }
+}
+
+/// Quantised [MLP2]: linear1 -> ReLU (f32) -> linear2.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QuantizedMLP2 {
+    pub linear1: QuantizedLinear,
+    pub linear2: QuantizedLinear,
+}
+
+impl QuantizedMLP2 {
+    /// Quantise `model`, calibrating both layers' input ranges on every batch of `loader`.
+    pub fn calibrate<D: Dataset>(model: &MLP2, loader: &mut DataLoader<D>) -> QuantResult<Self> {
+        let (mut obs1, mut obs2) = (MinMaxObserver::default(), MinMaxObserver::default());
+        for_each_batch(loader, |x| {
+            obs1.observe(x.data());
+            let h = model.linear1.forward(x).and_then(|h| h.relu());
+            obs2.observe(h.map_err(|e| QuantError(e.to_string()))?.data());
+            Ok(())
+        })?;
+        Ok(QuantizedMLP2 {
+            linear1: QuantizedLinear::new(&model.linear1, obs1.qparams()?)?,
+            linear2: QuantizedLinear::new(&model.linear2, obs2.qparams()?)?,
+        })
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        self.linear1.size_bytes() + self.linear2.size_bytes()
+    }
+}
+
+impl QuantizedModule for QuantizedMLP2 {
+    fn forward(&self, x: &Tensor) -> QuantResult<Tensor> {
+        let h = self.linear1.forward(x)?;
+        let h = h.relu().map_err(|e| QuantError(e.to_string()))?;
+        self.linear2.forward(&h)
+    }
+}
+
+/// Output error of a quantised model against its f32 original.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AccuracyReport {
+    pub samples: usize,
+    pub max_abs_err: f32,
+    pub mean_abs_err: f32,
+    /// Fraction of samples whose argmax output (predicted class) is the same.
+    pub argmax_agreement: f32,
+}
+
+impl fmt::Display for AccuracyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} samples: max |err| {:.6}, mean |err| {:.6}, argmax agreement {:.2}%",
+            self.samples,
+            self.max_abs_err,
+            self.mean_abs_err,
+            self.argmax_agreement * 100.0
+        )
+    }
+}
+
+/// Run both models over every batch of `loader` and compare outputs.
+pub fn compare<M, Q, D>(
+    reference: &M,
+    quantized: &Q,
+    loader: &mut DataLoader<D>,
+) -> QuantResult<AccuracyReport>
+where
+    M: Module + ?Sized,
+    Q: QuantizedModule + ?Sized,
+    D: Dataset,
+{
+    let mut report = AccuracyReport::default();
+    let (mut err_sum, mut elems, mut agree) = (0.0f64, 0usize, 0usize);
+    for_each_batch(loader, |x| {
+        let want = reference
+            .forward(x)
+            .map_err(|e| QuantError(e.to_string()))?;
+        let got = quantized.forward(x)?;
+        if want.shape().dims() != got.shape().dims() {
+            return Err(QuantError(format!(
+                "output shapes differ: {:?} vs {:?}",
+                want.shape().dims(),
+                got.shape().dims()
+            )));
+        }
+        for (&w, &g) in want.data().iter().zip(got.data()) {
+            let e = (w - g).abs();
+            report.max_abs_err = report.max_abs_err.max(e);
+            err_sum += e as f64;
+        }
+        elems += want.numel();
+        let classes = want.shape().dims().last().copied().unwrap_or(1).max(1);
+        for (w, g) in want.data().chunks(classes).zip(got.data().chunks(classes)) {
+            report.samples += 1;
+            if argmax(w) == argmax(g) {
+                agree += 1;
+            }
+        }
+        Ok(())
+    })?;
+    if elems > 0 {
+        report.mean_abs_err = (err_sum / elems as f64) as f32;
+    }
+    if report.samples > 0 {
+        report.argmax_agreement = agree as f32 / report.samples as f32;
+    }
+    Ok(report)
+}
+
+fn argmax(v: &[f32]) -> usize {
+    let mut best = 0;
+    for (i, &x) in v.iter().enumerate() {
+        if x > v[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+/// Call `f` with each batch of `loader` stacked into [batch, features], from the start.
+/// The loader is reset before and after.
+fn for_each_batch<D: Dataset>(
+    loader: &mut DataLoader<D>,
+    mut f: impl FnMut(&Tensor) -> QuantResult<()>,
+) -> QuantResult<()> {
+    loader.reset();
+    let mut result = Ok(());
+    while let Some((inputs, _)) = loader.next_batch() {
+        let x = Tensor::stack(&inputs, 0).map_err(|e| QuantError(e.to_string()));
+        result = x.and_then(|x| f(&x));
+        if result.is_err() {
+            break;
+        }
+    }
+    loader.reset();
+    result
+}
@@ -6,7 +6,7 @@ use rand::SeedableRng;
 use std::cell::RefCell;
 
 thread_local! {
-    static RNG: RefCell<Option<StdRng>> = RefCell::new(None);
+    static RNG: RefCell<Option<StdRng>> = const { RefCell::new(None) };
 }
 
 /// Set the global random seed for this thread. Call before model init or training
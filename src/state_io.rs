@@ -1,5 +1,6 @@
-//! Save/load state_dict (Vec<ParameterState>) to/from JSON files.
+//! Save/load state_dict (Vec<ParameterState> or name-keyed [StateDict]) to/from JSON files.
 
+use crate::nn::StateDict;
 use crate::parameter::ParameterState;
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
@@ -18,3 +19,20 @@ pub fn load_state_dict(path: impl AsRef<Path>) -> Result<Vec<ParameterState>, st
     let r = BufReader::new(f);
     serde_json::from_reader(r).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
 }
+
+/// Save a name-keyed state dict to a JSON object file.
+pub fn save_named_state_dict(
+    path: impl AsRef<Path>,
+    states: &StateDict,
+) -> Result<(), std::io::Error> {
+    let f = File::create(path)?;
+    let w = BufWriter::new(f);
+    serde_json::to_writer(w, states).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Load a name-keyed state dict from a JSON object file.
+pub fn load_named_state_dict(path: impl AsRef<Path>) -> Result<StateDict, std::io::Error> {
+    let f = File::open(path)?;
+    let r = BufReader::new(f);
+    serde_json::from_reader(r).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
@@ -1,7 +1,8 @@
 //! Tensor: pure numerical storage and shape. No grad, no graph (those live in autograd).
 //! All ops (matmul, add, relu) are invoked via the Backend trait.
 
-use crate::backend::{Backend, BackendError, BackendResult};
+use crate::backend::lazy::{Deferred, Expr, LazyData};
+use crate::backend::{Activation, Backend, BackendError, BackendResult, Device};
 use crate::shape::{Shape, ShapeError};
 use std::sync::Arc;
 use thiserror::Error;
@@ -12,18 +13,31 @@ pub enum TensorError {
     Backend(#[from] BackendError),
     #[error("shape error: {0}")]
     Shape(#[from] ShapeError),
+    #[error("device mismatch in {op}: {lhs} vs {rhs}")]
+    DeviceMismatch {
+        op: &'static str,
+        lhs: Device,
+        rhs: Device,
+    },
 }
 
 pub type TensorResult<T> = Result<T, TensorError>;
 
 /// Tensor: data + shape + backend reference. No gradient or graph node.
-#[derive(Clone)]
+/// The buffer comes from and returns to the backend ([Backend::alloc] / [Backend::release]).
+/// Tensors from a [crate::LazyBackend] hold a deferred expression instead, evaluated on
+/// first access to the data (or [Tensor::realize]).
 pub struct Tensor {
-    data: Vec<f32>,
+    storage: Storage,
     shape: Shape,
     backend: Arc<dyn Backend>,
 }
 
+enum Storage {
+    Ready(Arc<Vec<f32>>),
+    Lazy(LazyData),
+}
+
 impl Tensor {
     /// Create a tensor from data and shape using the given backend.
     /// Caller must ensure data.len() == shape.numel().
@@ -36,12 +50,21 @@ impl Tensor {
             ))));
         }
         Ok(Tensor {
-            data,
+            storage: Storage::Ready(Arc::new(data)),
             shape,
             backend,
         })
     }
 
+    /// Tensor whose data is computed from `deferred` when first needed.
+    pub(crate) fn deferred(deferred: Deferred, shape: Shape, backend: Arc<dyn Backend>) -> Self {
+        Tensor {
+            storage: Storage::Lazy(LazyData::new(deferred)),
+            shape,
+            backend,
+        }
+    }
+
     /// Create via backend (backend allocates/copies).
     pub fn from_vec_backend(
         data: Vec<f32>,
@@ -51,14 +74,55 @@ impl Tensor {
         backend.from_vec(data, shape)
     }
 
-    /// Raw data slice (read-only view).
+    /// The data buffer, evaluating a deferred expression first.
+    fn buffer(&self) -> &Arc<Vec<f32>> {
+        match &self.storage {
+            Storage::Ready(data) => data,
+            Storage::Lazy(lazy) => lazy.get(self.numel(), &*self.backend),
+        }
+    }
+
+    /// Raw data slice (read-only view). Evaluates a deferred expression first.
     pub fn data(&self) -> &[f32] {
-        &self.data
+        self.buffer()
     }
 
-    /// Mutable data slice (for in-place updates, e.g. optimizer).
+    /// Mutable data slice (for in-place updates, e.g. optimizer). A lazy tensor becomes an
+    /// eager one, dropping its expression.
     pub fn data_mut(&mut self) -> &mut [f32] {
-        &mut self.data
+        if let Storage::Lazy(_) = self.storage {
+            self.storage = Storage::Ready(Arc::clone(self.buffer()));
+        }
+        match &mut self.storage {
+            Storage::Ready(data) => Arc::make_mut(data).as_mut_slice(),
+            Storage::Lazy(_) => unreachable!("lazy storage was replaced above"),
+        }
+    }
+
+    /// Evaluate a deferred expression now (no-op for eager tensors).
+    pub fn realize(&self) {
+        self.data();
+    }
+
+    /// False while the data is a pending lazy expression.
+    pub fn is_realized(&self) -> bool {
+        match &self.storage {
+            Storage::Ready(_) => true,
+            Storage::Lazy(lazy) => lazy.realized().is_some(),
+        }
+    }
+
+    /// The pending expression, if the data has not been computed yet.
+    pub(crate) fn pending_expr(&self) -> Option<Arc<Expr>> {
+        match &self.storage {
+            Storage::Ready(_) => None,
+            Storage::Lazy(lazy) => lazy.pending_expr(),
+        }
+    }
+
+    /// The data buffer, shared rather than copied.
+    pub(crate) fn shared_data(&self) -> Arc<Vec<f32>> {
+        Arc::clone(self.buffer())
     }
 
     /// Shape of this tensor.
@@ -71,28 +135,56 @@ impl Tensor {
         Arc::clone(&self.backend)
     }
 
+    /// Device the data lives on.
+    pub fn device(&self) -> Device {
+        self.backend.device()
+    }
+
+    /// Copy to `backend` (possibly another device). The result is bound to `backend`.
+    pub fn to(&self, backend: Arc<dyn Backend>) -> TensorResult<Tensor> {
+        backend
+            .from_vec(self.data().to_vec(), self.shape.clone())
+            .map_err(TensorError::from)
+    }
+
     /// Number of elements.
     pub fn numel(&self) -> usize {
         self.shape.numel()
     }
 
+    /// Error unless every tensor in `others` is on this tensor's device.
+    fn same_device(&self, op: &'static str, others: &[&Tensor]) -> TensorResult<()> {
+        let lhs = self.device();
+        for t in others {
+            let rhs = t.device();
+            if rhs != lhs {
+                return Err(TensorError::DeviceMismatch { op, lhs, rhs });
+            }
+        }
+        Ok(())
+    }
+
     /// Matrix multiply: self @ rhs. (M,K) @ (K,N) -> (M,N)
     pub fn matmul(&self, rhs: &Tensor) -> TensorResult<Tensor> {
+        self.same_device("matmul", &[rhs])?;
         self.backend.matmul(self, rhs).map_err(TensorError::from)
     }
 
     /// Element-wise add.
     pub fn add(&self, rhs: &Tensor) -> TensorResult<Tensor> {
+        self.same_device("add", &[rhs])?;
         self.backend.add(self, rhs).map_err(TensorError::from)
     }
 
     /// Element-wise multiply.
     pub fn mul(&self, rhs: &Tensor) -> TensorResult<Tensor> {
+        self.same_device("mul", &[rhs])?;
         self.backend.mul(self, rhs).map_err(TensorError::from)
     }
 
     /// Element-wise subtract.
     pub fn sub(&self, rhs: &Tensor) -> TensorResult<Tensor> {
+        self.same_device("sub", &[rhs])?;
         self.backend.sub(self, rhs).map_err(TensorError::from)
     }
 
@@ -133,17 +225,25 @@ impl Tensor {
 
     /// Softmax backward: grad_in = y * (grad_out - sum(grad_out * y, last_dim)).
     pub fn softmax_backward(&self, grad_out: &Tensor) -> TensorResult<Tensor> {
+        self.same_device("softmax_backward", &[grad_out])?;
         self.backend.softmax_backward(grad_out, self).map_err(TensorError::from)
     }
 
     /// Broadcast add (e.g. bias + matrix).
     pub fn add_broadcast(&self, rhs: &Tensor) -> TensorResult<Tensor> {
+        self.same_device("add_broadcast", &[rhs])?;
         self.backend.add_broadcast(self, rhs).map_err(TensorError::from)
     }
 
+    /// Fused act(self @ w + b). Self [M,K], w [K,N], b [N].
+    pub fn linear(&self, w: &Tensor, b: &Tensor, act: Activation) -> TensorResult<Tensor> {
+        self.same_device("linear", &[w, b])?;
+        self.backend.linear(self, w, b, act).map_err(TensorError::from)
+    }
+
     /// Fill with zeros (in-place). Used for zero_grad.
     pub fn zero_fill(&mut self) {
-        self.data.fill(0.0);
+        self.data_mut().fill(0.0);
     }
 
     /// Transpose last two dimensions. For 2D (M,N) -> (N,M).
@@ -158,20 +258,23 @@ impl Tensor {
 
     /// Element-wise division self / rhs.
     pub fn div(&self, rhs: &Tensor) -> TensorResult<Tensor> {
+        self.same_device("div", &[rhs])?;
         self.backend.div(self, rhs).map_err(TensorError::from)
     }
 
     /// ReLU backward: grad_out * (self > 0).
     pub fn relu_backward(&self, grad_out: &Tensor) -> TensorResult<Tensor> {
+        self.same_device("relu_backward", &[grad_out])?;
         self.backend.relu_backward(grad_out, self).map_err(TensorError::from)
     }
 
     /// Sigmoid backward: grad_out * fwd_output * (1 - fwd_output). Self is fwd_output.
     pub fn sigmoid_backward(&self, grad_out: &Tensor) -> TensorResult<Tensor> {
+        self.same_device("sigmoid_backward", &[grad_out])?;
         self.backend.sigmoid_backward(grad_out, self).map_err(TensorError::from)
     }
 
-    /// Stack tensors along dimension 0. All tensors must have the same shape and backend.
+    /// Stack tensors along dimension 0. All tensors must have the same shape and device.
     /// Result shape: [tensors.len(), dim0, dim1, ...].
     pub fn stack(tensors: &[Tensor], dim: usize) -> TensorResult<Tensor> {
         if tensors.is_empty() {
@@ -187,6 +290,7 @@ impl Tensor {
         let elem_shape = first.shape();
         let numel_per = elem_shape.numel();
         for t in tensors.iter().skip(1) {
+            first.same_device("stack", &[t])?;
             if !t.shape().same_as(elem_shape) {
                 return Err(TensorError::Shape(ShapeError(
                     "stack: all tensors must have same shape".into(),
@@ -206,11 +310,43 @@ impl Tensor {
     }
 }
 
+impl Clone for Tensor {
+    /// Copies the data; a pending tensor clones its expression instead.
+    fn clone(&self) -> Self {
+        if let Storage::Lazy(lazy) = &self.storage {
+            if let Some(d) = lazy.pending() {
+                return Tensor::deferred(d.clone(), self.shape.clone(), Arc::clone(&self.backend));
+            }
+        }
+        let src = self.data();
+        let mut data = self.backend.alloc(src.len());
+        data.copy_from_slice(src);
+        Tensor {
+            storage: Storage::Ready(Arc::new(data)),
+            shape: self.shape.clone(),
+            backend: Arc::clone(&self.backend),
+        }
+    }
+}
+
+impl Drop for Tensor {
+    /// Hands the buffer back to the backend unless a lazy expression still shares it.
+    fn drop(&mut self) {
+        let data = match &mut self.storage {
+            Storage::Ready(data) => Some(data),
+            Storage::Lazy(lazy) => lazy.realized_mut(),
+        };
+        if let Some(buf) = data.and_then(Arc::get_mut) {
+            self.backend.release(std::mem::take(buf));
+        }
+    }
+}
+
 impl std::fmt::Debug for Tensor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Tensor")
             .field("shape", &self.shape)
-            .field("data_len", &self.data.len())
+            .field("realized", &self.is_realized())
             .finish()
     }
 }
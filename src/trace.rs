@@ -0,0 +1,299 @@
+//! Static tracing: run `Module::forward_graph` once and record a shape-specialised, optimised
+//! program that can be re-run on new inputs without building Graph nodes. Programs are
+//! saved as JSON next to the weights from [crate::state_io], so a deployment binary can
+//! load and run them without the Rust model definition.
+
+use crate::autograd::plan::{Instr, Plan, Slot};
+use crate::autograd::Graph;
+use crate::backend::Backend;
+use crate::nn::Module;
+use crate::ops::OpRegistry;
+use crate::parameter::ParameterState;
+use crate::shape::Shape;
+use crate::tensor::Tensor;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("trace error: {0}")]
+pub struct TraceError(pub String);
+
+pub type TraceResult<T> = Result<T, TraceError>;
+
+/// A traced model: compiled plan with runtime inputs (x, parameters...) and bound weights.
+pub struct TracedProgram {
+    plan: Plan,
+    input_shape: Shape,
+    params: Vec<Tensor>,
+    param_names: Vec<Option<String>>,
+}
+
+/// Trace `model` on `example_input`. The program only accepts inputs of the same shape.
+/// The model must be [Module::is_deterministic] (call [Module::eval] first), and parameters
+/// looked up by rows ([Graph::param_rows], e.g. embeddings) are rejected: both would be
+/// frozen into the program as they were while tracing.
+pub fn trace<M: Module + ?Sized>(model: &M, example_input: &Tensor) -> TraceResult<TracedProgram> {
+    if !model.is_deterministic() {
+        return Err(TraceError(
+            "model is not deterministic; put it in eval mode before tracing".into(),
+        ));
+    }
+    let mut g = Graph::new();
+    let x_id = g.var(example_input.clone());
+    let (out_id, param_ids) = model
+        .forward_graph(&mut g, x_id)
+        .map_err(|e| TraceError(e.to_string()))?;
+    let params = model.parameters();
+    let mut inputs = vec![x_id];
+    for (i, p) in params.iter().enumerate() {
+        if g.has_param_rows(p.id()) {
+            return Err(TraceError(format!(
+                "parameter {} is bound by rows and can't be traced",
+                p.name().unwrap_or("?")
+            )));
+        }
+        let leaf = g
+            .param_leaf(p, param_ids.get(i).copied())
+            .map_err(|e| TraceError(e.to_string()))?;
+        let node = match leaf {
+            Some(node) => node,
+            None => g.var(p.data().clone()),
+        };
+        inputs.push(node);
+    }
+    let plan = Plan::compile(&g, &inputs, &[out_id]).map_err(|e| TraceError(e.to_string()))?;
+    Ok(TracedProgram {
+        plan,
+        input_shape: example_input.shape().clone(),
+        params: params.iter().map(|p| p.data().clone()).collect(),
+        param_names: params.iter().map(|p| p.name().map(String::from)).collect(),
+    })
+}
+
+/// Serialisable program structure (weights are stored separately as a state dict).
+#[derive(Serialize, Deserialize)]
+pub struct ProgramState {
+    pub input_shape: Vec<usize>,
+    /// Expected parameter shapes, in the order of the weights state dict.
+    pub param_shapes: Vec<Vec<usize>>,
+    pub constants: Vec<ParameterState>,
+    pub instrs: Vec<InstrState>,
+    pub outputs: Vec<Slot>,
+}
+
+/// One serialised instruction. Slot::Input(0) is x, Slot::Input(k + 1) is parameter k.
+#[derive(Serialize, Deserialize)]
+pub struct InstrState {
+    /// Op name ([crate::Op::name]), resolved through the registry on load.
+    pub op: String,
+    /// The op's [crate::Op::attrs] (e.g. a norm's eps), applied on load when they differ
+    /// from the registered instance's.
+    #[serde(default)]
+    pub attrs: Vec<f32>,
+    pub inputs: Vec<Slot>,
+}
+
+impl TracedProgram {
+    /// Run on a new input of the traced shape. Returns the model output.
+    pub fn run(&self, x: &Tensor) -> TraceResult<Tensor> {
+        if !x.shape().same_as(&self.input_shape) {
+            return Err(TraceError(format!(
+                "input shape {} does not match traced shape {}",
+                x.shape(),
+                self.input_shape
+            )));
+        }
+        let mut inputs: Vec<&Tensor> = vec![x];
+        inputs.extend(self.params.iter());
+        let mut outs = self
+            .plan
+            .run_refs(&inputs)
+            .map_err(|e| TraceError(e.to_string()))?;
+        outs.pop()
+            .ok_or_else(|| TraceError("program has no output".into()))
+    }
+
+    /// Input shape the program was traced with.
+    pub fn input_shape(&self) -> &Shape {
+        &self.input_shape
+    }
+
+    /// Compiled plan (for inspection).
+    pub fn plan(&self) -> &Plan {
+        &self.plan
+    }
+
+    /// Bound weights as a state dict (save with [crate::save_state_dict]).
+    pub fn state_dict(&self) -> Vec<ParameterState> {
+        self.params
+            .iter()
+            .zip(self.param_names.iter())
+            .map(|(t, name)| ParameterState {
+                name: name.clone(),
+                shape: t.shape().dims().to_vec(),
+                data: t.data().to_vec(),
+            })
+            .collect()
+    }
+
+    /// Replace the bound weights (same count and shapes as traced).
+    pub fn load_state_dict(
+        &mut self,
+        states: &[ParameterState],
+        backend: Arc<dyn Backend>,
+    ) -> TraceResult<()> {
+        if states.len() != self.params.len() {
+            return Err(TraceError(format!(
+                "got {} weights, program has {} parameters",
+                states.len(),
+                self.params.len()
+            )));
+        }
+        let mut params = Vec::with_capacity(states.len());
+        for (s, old) in states.iter().zip(self.params.iter()) {
+            if s.shape != old.shape().dims() {
+                return Err(TraceError(format!(
+                    "weight shape {:?} != traced shape {:?}",
+                    s.shape,
+                    old.shape().dims()
+                )));
+            }
+            params.push(
+                backend
+                    .from_vec(s.data.clone(), Shape::new(s.shape.clone()))
+                    .map_err(|e| TraceError(e.to_string()))?,
+            );
+        }
+        self.params = params;
+        self.param_names = states.iter().map(|s| s.name.clone()).collect();
+        Ok(())
+    }
+
+    /// Program structure without weights. Fails for ops that are not in the default registry.
+    pub fn to_state(&self) -> TraceResult<ProgramState> {
+        let registry = OpRegistry::new();
+        let instrs = self
+            .plan
+            .instrs()
+            .iter()
+            .map(|i| {
+                let name = i.op.name();
+                let attrs = i.op.attrs();
+                match registry.get_by_name(name) {
+                    Some(op)
+                        if op.id() == i.op.id()
+                            && (op.attrs() == attrs || op.with_attrs(&attrs).is_some()) => {}
+                    _ => return Err(TraceError(format!("op {} cannot be serialised", name))),
+                }
+                Ok(InstrState {
+                    op: name.to_string(),
+                    attrs,
+                    inputs: i.inputs.clone(),
+                })
+            })
+            .collect::<TraceResult<Vec<_>>>()?;
+        Ok(ProgramState {
+            input_shape: self.input_shape.dims().to_vec(),
+            param_shapes: self
+                .params
+                .iter()
+                .map(|t| t.shape().dims().to_vec())
+                .collect(),
+            constants: self
+                .plan
+                .constants()
+                .iter()
+                .map(|t| ParameterState {
+                    name: None,
+                    shape: t.shape().dims().to_vec(),
+                    data: t.data().to_vec(),
+                })
+                .collect(),
+            instrs,
+            outputs: self.plan.outputs().to_vec(),
+        })
+    }
+
+    /// Rebuild a program from its structure and weights, dispatching ops through `registry`.
+    pub fn from_state(
+        state: ProgramState,
+        weights: &[ParameterState],
+        registry: &OpRegistry,
+        backend: Arc<dyn Backend>,
+    ) -> TraceResult<Self> {
+        let to_tensor = |s: &ParameterState| {
+            backend
+                .from_vec(s.data.clone(), Shape::new(s.shape.clone()))
+                .map_err(|e| TraceError(e.to_string()))
+        };
+        let constants = state
+            .constants
+            .iter()
+            .map(to_tensor)
+            .collect::<TraceResult<Vec<_>>>()?;
+        let instrs = state
+            .instrs
+            .into_iter()
+            .map(|i| {
+                let mut op = registry
+                    .get_by_name(&i.op)
+                    .ok_or_else(|| TraceError(format!("unknown op {}", i.op)))?;
+                if op.attrs() != i.attrs {
+                    op = op.with_attrs(&i.attrs).ok_or_else(|| {
+                        TraceError(format!("op {} does not take attrs {:?}", i.op, i.attrs))
+                    })?;
+                }
+                Ok(Instr {
+                    op,
+                    inputs: i.inputs,
+                })
+            })
+            .collect::<TraceResult<Vec<_>>>()?;
+        let plan = Plan::from_parts(
+            1 + state.param_shapes.len(),
+            constants,
+            instrs,
+            state.outputs,
+        )
+        .map_err(|e| TraceError(e.to_string()))?;
+        let mut program = TracedProgram {
+            plan,
+            input_shape: Shape::new(state.input_shape),
+            params: state
+                .param_shapes
+                .into_iter()
+                .map(|s| {
+                    backend
+                        .zeros(&Shape::new(s))
+                        .map_err(|e| TraceError(e.to_string()))
+                })
+                .collect::<TraceResult<Vec<_>>>()?,
+            param_names: Vec::new(),
+        };
+        program.load_state_dict(weights, backend)?;
+        Ok(program)
+    }
+
+    /// Save the program structure to a JSON file. Save weights with [crate::save_state_dict].
+    pub fn save(&self, path: impl AsRef<Path>) -> TraceResult<()> {
+        let state = self.to_state()?;
+        let f = File::create(path).map_err(|e| TraceError(e.to_string()))?;
+        serde_json::to_writer(BufWriter::new(f), &state).map_err(|e| TraceError(e.to_string()))
+    }
+
+    /// Load a program saved with [Self::save] and bind `weights` (from [crate::load_state_dict]).
+    pub fn load(
+        path: impl AsRef<Path>,
+        weights: &[ParameterState],
+        backend: Arc<dyn Backend>,
+    ) -> TraceResult<Self> {
+        let f = File::open(path).map_err(|e| TraceError(e.to_string()))?;
+        let state: ProgramState =
+            serde_json::from_reader(BufReader::new(f)).map_err(|e| TraceError(e.to_string()))?;
+        Self::from_state(state, weights, &OpRegistry::new(), backend)
+    }
+}
@@ -1,8 +1,9 @@
 //! Training loop: zero_grad -> forward -> loss -> backward -> optimizer step.
 //! Training is a first-class citizen: explicit, controllable.
 
-use crate::autograd::Graph;
-use crate::nn::{ce_graph, mse_graph, Module};
+use crate::autograd::{Graph, Plan};
+use crate::nn::{ce_graph, mse_graph, mse_graph_node, Module};
+use crate::ops::view::Narrow;
 use crate::optimizer::Optimizer;
 use crate::tensor::Tensor;
 use thiserror::Error;
@@ -53,11 +54,8 @@ impl<M: Module, O: Optimizer> Trainer<M, O> {
         }
         g.backward(loss_id).map_err(|e| TrainError(e.to_string()))?;
 
-        for (p, &node_id) in params.iter_mut().zip(param_ids.iter()) {
-            if let Some(grad) = g.grad(node_id).map_err(|e| TrainError(e.to_string()))? {
-                p.set_grad(Some(grad.clone()));
-            }
-        }
+        g.write_grads_with(&mut params, &param_ids)
+            .map_err(|e| TrainError(e.to_string()))?;
 
         let loss_data = g.data(loss_id).map_err(|e| TrainError(e.to_string()))?;
         let loss_val = loss_data.data()[0];
@@ -91,11 +89,8 @@ impl<M: Module, O: Optimizer> Trainer<M, O> {
         }
         g.backward(loss_id).map_err(|e| TrainError(e.to_string()))?;
 
-        for (p, &node_id) in params.iter_mut().zip(param_ids.iter()) {
-            if let Some(grad) = g.grad(node_id).map_err(|e| TrainError(e.to_string()))? {
-                p.set_grad(Some(grad.clone()));
-            }
-        }
+        g.write_grads_with(&mut params, &param_ids)
+            .map_err(|e| TrainError(e.to_string()))?;
 
         let loss_data = g.data(loss_id).map_err(|e| TrainError(e.to_string()))?;
         let loss_val = loss_data.data()[0];
@@ -129,11 +124,8 @@ impl<M: Module, O: Optimizer> Trainer<M, O> {
         }
         g.backward(loss_id).map_err(|e| TrainError(e.to_string()))?;
 
-        for (p, &node_id) in params.iter_mut().zip(param_ids.iter()) {
-            if let Some(grad) = g.grad(node_id).map_err(|e| TrainError(e.to_string()))? {
-                p.set_grad(Some(grad.clone()));
-            }
-        }
+        g.write_grads_with(&mut params, &param_ids)
+            .map_err(|e| TrainError(e.to_string()))?;
 
         let loss_data = g.data(loss_id).map_err(|e| TrainError(e.to_string()))?;
         let loss_val = loss_data.data()[0];
@@ -145,6 +137,116 @@ impl<M: Module, O: Optimizer> Trainer<M, O> {
         Ok(TrainStepResult { loss: loss_val })
     }
 
+    /// Truncated backpropagation through time over a sequence-first input [T, B, ...] with
+    /// per-step targets [T, B, ...]: time is split into chunks of `chunk_len` steps (the last
+    /// may be shorter) and each chunk is one [Self::step_batch]. Recurrent state is carried
+    /// from chunk to chunk as values ([Module::set_carry_state]), so gradients stop at chunk
+    /// boundaries; it starts from zeros and is dropped afterwards. Returns the mean chunk loss.
+    pub fn step_tbptt(
+        &mut self,
+        backend: std::sync::Arc<dyn crate::backend::Backend>,
+        input: &Tensor,
+        target: &Tensor,
+        chunk_len: usize,
+    ) -> TrainResult<TrainStepResult> {
+        let steps = input.shape().dims().first().copied().unwrap_or(0);
+        if chunk_len == 0 || steps == 0 || target.shape().dims().first() != Some(&steps) {
+            return Err(TrainError(format!(
+                "step_tbptt: need chunk_len > 0 and one T > 0 for both, got {:?} / {:?}",
+                input.shape().dims(),
+                target.shape().dims()
+            )));
+        }
+        self.model.set_carry_state(true);
+        let mut run = || -> TrainResult<f32> {
+            let mut total = 0.0f32;
+            let mut chunks = 0usize;
+            for start in (0..steps).step_by(chunk_len) {
+                let len = chunk_len.min(steps - start);
+                let narrow = Narrow::new(0, start, len);
+                let x = narrow.run(input).map_err(|e| TrainError(e.to_string()))?;
+                let t = narrow.run(target).map_err(|e| TrainError(e.to_string()))?;
+                total += self.step_batch(backend.clone(), &x, &t)?.loss;
+                chunks += 1;
+            }
+            Ok(total / chunks as f32)
+        };
+        let loss = run();
+        self.model.set_carry_state(false);
+        Ok(TrainStepResult { loss: loss? })
+    }
+
+    /// Trace one MSE batch step into a compiled [Plan] with runtime inputs
+    /// (input, target, parameters...). Reuse it with [Self::step_planned] for batches of the
+    /// same shape to skip per-step graph construction. The plan replays the ops traced here,
+    /// so models that aren't [Module::is_deterministic] (dropout or BatchNorm in training
+    /// mode) are rejected before tracing; use [Self::step_batch] for those. So are models
+    /// that look up rows of a parameter ([Graph::param_rows], e.g. embeddings): the rows are
+    /// fixed while tracing, so the plan could not feed the weight back in.
+    pub fn compile_step_batch(&self, input: &Tensor, target: &Tensor) -> TrainResult<Plan> {
+        if !self.model.is_deterministic() {
+            return Err(TrainError(
+                "compile_step_batch: model is not deterministic; use step_batch".into(),
+            ));
+        }
+        let mut g = Graph::new();
+        let x_id = g.var(input.clone());
+        let t_id = g.var(target.clone());
+        let (out_id, param_ids) = self
+            .model
+            .forward_graph(&mut g, x_id)
+            .map_err(|e| TrainError(e.to_string()))?;
+        let loss_id = mse_graph_node(&mut g, out_id, t_id).map_err(|e| TrainError(e.to_string()))?;
+        // Plan inputs follow parameters() order; step_planned binds them the same way.
+        let mut inputs = vec![x_id, t_id];
+        for (i, p) in self.model.parameters().into_iter().enumerate() {
+            if g.has_param_rows(p.id()) {
+                return Err(TrainError(format!(
+                    "compile_step_batch: parameter {} is bound by rows; use step_batch",
+                    p.name().unwrap_or("?")
+                )));
+            }
+            let leaf = g
+                .param_leaf(p, param_ids.get(i).copied())
+                .map_err(|e| TrainError(e.to_string()))?;
+            let node = match leaf {
+                Some(node) => node,
+                None => g.var(p.data().clone()),
+            };
+            inputs.push(node);
+        }
+        Plan::compile(&g, &inputs, &[loss_id]).map_err(|e| TrainError(e.to_string()))
+    }
+
+    /// One batch step using a plan from [Self::compile_step_batch]: no graph is built.
+    /// Gradients are zeroed and accumulated into the parameters as in [Self::step_batch].
+    pub fn step_planned(
+        &mut self,
+        plan: &Plan,
+        input: &Tensor,
+        target: &Tensor,
+    ) -> TrainResult<TrainStepResult> {
+        let mut params = self.model.parameters_mut();
+        let mut inputs = vec![input.clone(), target.clone()];
+        inputs.extend(params.iter().map(|p| p.data().clone()));
+        let grads = plan
+            .backward(&inputs, 0, None)
+            .map_err(|e| TrainError(e.to_string()))?;
+        for (p, g) in params.iter_mut().zip(grads.input_grads.into_iter().skip(2)) {
+            p.zero_grad();
+            if let Some(g) = g {
+                p.accumulate_grad(&g).map_err(|e| TrainError(e.to_string()))?;
+            }
+        }
+        let loss_val = grads.outputs[0].data()[0];
+
+        self.optimizer
+            .step(&mut params)
+            .map_err(|e| TrainError(e.to_string()))?;
+
+        Ok(TrainStepResult { loss: loss_val })
+    }
+
     /// Run one epoch: iterate dataloader by batch, stack each batch, call step_batch once per batch.
     pub fn run_epoch<D: crate::data::Dataset>(
         &mut self,
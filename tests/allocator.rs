@@ -0,0 +1,47 @@
+//! Op results stay bound to the calling backend, and a pooled backend reuses buffers across
+//! training steps.
+
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{Backend, CpuBackend, Linear, PoolAllocator, Shape, Tensor};
+use std::sync::Arc;
+
+#[test]
+fn op_results_keep_backend_identity() {
+    let backend: Arc<dyn Backend> = Arc::new(CpuBackend::with_allocator(Arc::new(
+        PoolAllocator::default(),
+    )));
+    let x = Tensor::from_vec(
+        vec![1.0, -2.0, 3.0, -4.0],
+        Shape::new(vec![2, 2]),
+        backend.clone(),
+    )
+    .unwrap();
+    let y = x.matmul(&x).unwrap().relu().unwrap().sum().unwrap();
+    assert!(Arc::ptr_eq(&y.backend(), &backend));
+}
+
+#[test]
+fn pool_reuses_buffers_across_steps() {
+    let pool = Arc::new(PoolAllocator::default());
+    let backend: Arc<dyn Backend> = Arc::new(CpuBackend::with_allocator(pool.clone()));
+    let model = Linear::new(8, 4, backend.clone()).unwrap();
+    let mut trainer = Trainer::new(model, SGD::new(0.01));
+    let x = Tensor::from_vec(vec![0.1; 16 * 8], Shape::new(vec![16, 8]), backend.clone()).unwrap();
+    let t = Tensor::from_vec(vec![0.5; 16 * 4], Shape::new(vec![16, 4]), backend.clone()).unwrap();
+
+    for _ in 0..3 {
+        trainer.step_batch(backend.clone(), &x, &t).unwrap();
+    }
+    let warm = backend.alloc_stats().unwrap();
+    for _ in 0..10 {
+        trainer.step_batch(backend.clone(), &x, &t).unwrap();
+    }
+    let stats = backend.alloc_stats().unwrap();
+    assert_eq!(
+        stats.bytes_allocated, warm.bytes_allocated,
+        "steady state allocates nothing new"
+    );
+    assert!(stats.reused > warm.reused);
+    assert!(stats.pooled_bytes > 0);
+}
@@ -0,0 +1,316 @@
+//! Multi-head attention, Transformer layers and positional encodings: attention values and
+//! masks, gradients through the new ops and layers, and training through Trainer.
+
+mod common;
+
+use common::{assert_close, tensor};
+use dl_core::autograd::check::{check_gradients, check_op, HarnessConfig};
+use dl_core::nn::{
+    AttentionMask, LearnedPositionalEncoding, Linear, Module, MultiheadAttention, Sequential,
+    SinusoidalPositionalEncoding, TransformerDecoderLayer, TransformerEncoderLayer,
+    TransformerOptions,
+};
+use dl_core::ops::batch_matmul::BatchMatMul;
+use dl_core::ops::view::Permute;
+use dl_core::ops::Op;
+use dl_core::optimizer::Adam;
+use dl_core::train::Trainer;
+use dl_core::{set_seed, CpuBackend, Graph, Tensor};
+use std::sync::Arc;
+
+fn seq(dims: &[usize]) -> Tensor {
+    let n: usize = dims.iter().product();
+    tensor(
+        (0..n).map(|i| ((i * 7) % 11) as f32 * 0.2 - 1.0).collect(),
+        dims,
+    )
+}
+
+/// Attention with identity projections, computed directly: x [B, T, E], `masked(b, i, j)`.
+fn reference(x: &Tensor, heads: usize, masked: impl Fn(usize, usize, usize) -> bool) -> Vec<f32> {
+    let (b, t, e) = match x.shape().dims() {
+        &[b, t, e] => (b, t, e),
+        _ => unreachable!(),
+    };
+    let dk = e / heads;
+    let at = |n: usize, i: usize, d: usize| x.data()[(n * t + i) * e + d];
+    let mut out = vec![0.0; b * t * e];
+    for n in 0..b {
+        for h in 0..heads {
+            for i in 0..t {
+                let scores: Vec<f32> = (0..t)
+                    .map(|j| {
+                        let dot: f32 = (h * dk..(h + 1) * dk)
+                            .map(|d| at(n, i, d) * at(n, j, d))
+                            .sum();
+                        if masked(n, i, j) {
+                            -1e9
+                        } else {
+                            dot / (dk as f32).sqrt()
+                        }
+                    })
+                    .collect();
+                let max = scores.iter().cloned().fold(f32::MIN, f32::max);
+                let exps: Vec<f32> = scores.iter().map(|s| (s - max).exp()).collect();
+                let total: f32 = exps.iter().sum();
+                for d in h * dk..(h + 1) * dk {
+                    out[(n * t + i) * e + d] = (0..t).map(|j| exps[j] / total * at(n, j, d)).sum();
+                }
+            }
+        }
+    }
+    out
+}
+
+type Build<'a> = Box<dyn Fn(&mut Graph, &[usize]) -> dl_core::GraphResult<usize> + 'a>;
+
+fn identity(linear: &mut Linear, e: usize) {
+    let data = (0..e * e)
+        .map(|i| if i / e == i % e { 1.0 } else { 0.0 })
+        .collect();
+    *linear.weight.data_mut() = tensor(data, &[e, e]);
+}
+
+#[test]
+fn attention_values_and_masks() {
+    let backend = Arc::new(CpuBackend::new());
+    let x = seq(&[2, 3, 4]);
+    for heads in [1, 2] {
+        let mut mha = MultiheadAttention::new(4, heads, backend.clone()).unwrap();
+        identity(&mut mha.q_proj, 4);
+        identity(&mut mha.k_proj, 4);
+        identity(&mut mha.v_proj, 4);
+        identity(&mut mha.out_proj, 4);
+        assert_close(
+            mha.forward(&x).unwrap().data(),
+            &reference(&x, heads, |_, _, _| false),
+            1e-4,
+        );
+        let causal = mha.attend(&x, &x, &x, &AttentionMask::causal()).unwrap();
+        assert_close(causal.data(), &reference(&x, heads, |_, i, j| j > i), 1e-4);
+        // The last key of the second sequence is padding.
+        let padded = AttentionMask::default()
+            .with_key_padding(tensor(vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0], &[2, 3]));
+        let out = mha.attend(&x, &x, &x, &padded).unwrap();
+        assert_close(
+            out.data(),
+            &reference(&x, heads, |n, _, j| n == 1 && j == 2),
+            1e-4,
+        );
+    }
+
+    // Bad configurations and inputs are errors, not panics.
+    assert!(MultiheadAttention::new(6, 4, backend.clone()).is_err());
+    let mha = MultiheadAttention::new(4, 2, backend.clone()).unwrap();
+    assert!(mha
+        .attend(
+            &x,
+            &seq(&[2, 3, 2]),
+            &seq(&[2, 3, 2]),
+            &AttentionMask::default()
+        )
+        .is_err());
+    let bad_pad = AttentionMask::default().with_key_padding(tensor(vec![0.0; 3], &[1, 3]));
+    assert!(mha.attend(&x, &x, &x, &bad_pad).is_err());
+
+    // Sinusoidal table values; adding it to zeros gives the table per sequence.
+    let pe = SinusoidalPositionalEncoding::new(8, 4, backend.clone()).unwrap();
+    let row1 = &pe.table().data()[4..8];
+    assert_close(
+        row1,
+        &[1f32.sin(), 1f32.cos(), 0.01f32.sin(), 0.01f32.cos()],
+        1e-4,
+    );
+    let y = pe.forward(&tensor(vec![0.0; 24], &[2, 3, 4])).unwrap();
+    assert_close(&y.data()[12..], &pe.table().data()[..12], 1e-4);
+    assert!(pe.forward(&seq(&[1, 9, 4])).is_err());
+
+    // The decoder's self-attention is causal: changing later positions leaves earlier
+    // outputs unchanged.
+    set_seed(1);
+    let mut decoder = TransformerDecoderLayer::new(4, 2, 8, backend.clone()).unwrap();
+    decoder.init_xavier().unwrap();
+    let a = decoder.forward(&x).unwrap();
+    let mut changed = x.data().to_vec();
+    changed[8..12].iter_mut().for_each(|v| *v += 1.0);
+    let b = decoder.forward(&tensor(changed, &[2, 3, 4])).unwrap();
+    assert_close(&a.data()[..8], &b.data()[..8], 1e-4);
+    assert!(a.data()[8..12] != b.data()[8..12]);
+}
+
+#[test]
+fn gradients_through_attention() {
+    let backend = Arc::new(CpuBackend::new());
+    let ops: Vec<Box<dyn Op>> = vec![
+        Box::new(BatchMatMul::new(false, 0.7)),
+        Box::new(BatchMatMul::new(true, 1.0)),
+        Box::new(Permute::new(vec![2, 0, 1])),
+    ];
+    for op in &ops {
+        let report = check_op(op.as_ref(), backend.clone(), &HarnessConfig::default()).unwrap();
+        assert!(report.passed(), "{}: {:?}", op.name(), report.failures);
+    }
+
+    set_seed(3);
+    let mut mha = MultiheadAttention::new(4, 2, backend.clone()).unwrap();
+    mha.init_xavier().unwrap();
+    let mask = AttentionMask::causal()
+        .with_key_padding(tensor(vec![0.0, 0.0, 1.0, 0.0, 0.0, 0.0], &[2, 3]));
+    let mut encoders = Vec::new();
+    for norm_first in [false, true] {
+        let options = TransformerOptions {
+            norm_first,
+            ..Default::default()
+        };
+        let mut layer =
+            TransformerEncoderLayer::with_options(4, 2, 6, options, backend.clone()).unwrap();
+        layer.init_xavier().unwrap();
+        encoders.push(layer);
+    }
+    let mut decoder = TransformerDecoderLayer::new(4, 2, 6, backend.clone()).unwrap();
+    decoder.init_xavier().unwrap();
+    let mut learned = LearnedPositionalEncoding::new(5, 4, backend.clone()).unwrap();
+    learned.init_uniform(0.5).unwrap();
+
+    let q = seq(&[2, 3, 4]);
+    let kv = tensor(
+        seq(&[2, 2, 4])
+            .data()
+            .iter()
+            .map(|v| v * 0.7 + 0.1)
+            .collect(),
+        &[2, 2, 4],
+    );
+    let r = tensor((0..24).map(|j| (j % 5) as f32 - 2.0).collect(), &[2, 3, 4]);
+    let weighted = |g: &mut Graph, y| {
+        let r_id = g.var(r.clone());
+        let prod = g.mul(y, r_id)?;
+        g.sum(prod)
+    };
+    let cases: Vec<(&str, Vec<Tensor>, Build)> = vec![
+        (
+            "cross-attention",
+            vec![q.clone(), kv.clone()],
+            Box::new(|g: &mut Graph, ids: &[usize]| {
+                let (y, _) =
+                    mha.attend_graph(g, ids[0], ids[1], ids[1], &AttentionMask::default())?;
+                weighted(g, y)
+            }),
+        ),
+        (
+            "masked self-attention",
+            vec![q.clone()],
+            Box::new(|g: &mut Graph, ids: &[usize]| {
+                let (y, _) = mha.attend_graph(g, ids[0], ids[0], ids[0], &mask)?;
+                weighted(g, y)
+            }),
+        ),
+        (
+            "post-norm encoder",
+            vec![q.clone()],
+            Box::new(|g: &mut Graph, ids: &[usize]| {
+                let (y, _) = encoders[0].forward_graph_masked(g, ids[0], &mask)?;
+                weighted(g, y)
+            }),
+        ),
+        (
+            "pre-norm encoder",
+            vec![q.clone()],
+            Box::new(|g: &mut Graph, ids: &[usize]| {
+                let (y, _) = encoders[1].forward_graph(g, ids[0])?;
+                weighted(g, y)
+            }),
+        ),
+        (
+            "decoder with memory",
+            vec![q.clone(), kv.clone()],
+            Box::new(|g: &mut Graph, ids: &[usize]| {
+                let none = AttentionMask::default();
+                let (y, _) = decoder.forward_graph_with(g, ids[0], Some(ids[1]), &none, &none)?;
+                weighted(g, y)
+            }),
+        ),
+        (
+            "learned positions",
+            vec![q.clone()],
+            Box::new(|g: &mut Graph, ids: &[usize]| {
+                let (y, _) = learned.forward_graph(g, ids[0])?;
+                weighted(g, y)
+            }),
+        ),
+    ];
+    for (name, inputs, build) in &cases {
+        check_gradients(build, inputs, 3e-3, 1e-2, 1e-3)
+            .unwrap_or_else(|e| panic!("{}: {}", name, e));
+    }
+}
+
+#[test]
+fn transformer_trains_and_names_parameters() {
+    let backend = Arc::new(CpuBackend::new());
+    let decoder = TransformerDecoderLayer::new(4, 2, 8, backend.clone()).unwrap();
+    let names: Vec<String> = decoder
+        .named_parameters()
+        .into_iter()
+        .map(|(n, _)| n)
+        .collect();
+    assert_eq!(names.len(), 26);
+    assert_eq!(names[0], "self_attn.q_proj.weight");
+    assert_eq!(names[8], "cross_attn.q_proj.weight");
+    assert_eq!(names[16], "linear1.weight");
+    assert_eq!(names[25], "norm3.bias");
+
+    // Each position predicts the mean of its sequence, which needs attention across steps.
+    set_seed(5);
+    let (batch, steps) = (16, 5);
+    let values: Vec<f32> = (0..batch * steps)
+        .map(|i| ((i * 37) % 17) as f32 / 8.0 - 1.0)
+        .collect();
+    let target: Vec<f32> = values
+        .chunks(steps)
+        .flat_map(|s| {
+            let mean = s.iter().sum::<f32>() / steps as f32;
+            std::iter::repeat_n(mean, steps)
+        })
+        .collect();
+    let input = tensor(values, &[batch, steps, 1]);
+    let target = tensor(target, &[batch, steps, 1]);
+
+    let mut embed = Linear::new(1, 8, backend.clone()).unwrap();
+    embed.init_xavier().unwrap();
+    let options = TransformerOptions {
+        dropout: 0.1,
+        norm_first: true,
+    };
+    let mut encoder =
+        TransformerEncoderLayer::with_options(8, 2, 16, options, backend.clone()).unwrap();
+    encoder.init_xavier().unwrap();
+    let mut head = Linear::new(8, 1, backend.clone()).unwrap();
+    head.init_xavier().unwrap();
+    let net = Sequential::new()
+        .with(embed)
+        .with(SinusoidalPositionalEncoding::new(steps, 8, backend.clone()).unwrap())
+        .with(encoder)
+        .with(head);
+
+    let mut trainer = Trainer::new(net, Adam::new(0.01));
+    let initial = trainer
+        .step_batch(backend.clone(), &input, &target)
+        .unwrap()
+        .loss;
+    for _ in 0..200 {
+        trainer
+            .step_batch(backend.clone(), &input, &target)
+            .unwrap();
+    }
+    trainer.model.eval();
+    let pred = trainer.model.forward(&input).unwrap();
+    let loss: f32 = pred
+        .data()
+        .iter()
+        .zip(target.data())
+        .map(|(p, t)| (p - t) * (p - t))
+        .sum::<f32>()
+        / pred.numel() as f32;
+    assert!(loss < initial * 0.2, "loss {} -> {}", initial, loss);
+}
@@ -0,0 +1,24 @@
+//! The public conformance suite passes for the in-tree backends.
+
+use dl_core::backend::conformance;
+use dl_core::{CheckedBackend, CpuBackend, ParallelCpuBackend, PoolAllocator, ProfilingBackend};
+use std::sync::Arc;
+
+#[test]
+fn in_tree_backends_conform() {
+    let report = conformance::run_all(&CpuBackend::new());
+    assert!(report.passed(), "cpu: {}", report);
+    assert!(report.cases > 100);
+
+    let pooled = CpuBackend::with_allocator(Arc::new(PoolAllocator::default()));
+    let report = conformance::run_all(&pooled);
+    assert!(report.passed(), "pooled cpu: {}", report);
+
+    let par = ParallelCpuBackend::new(4).with_min_parallel_work(1);
+    let report = conformance::run_all(&par);
+    assert!(report.passed(), "parallel: {}", report);
+
+    let wrapped = ProfilingBackend::new(CheckedBackend::new(CpuBackend::new()));
+    let report = conformance::run_all(&wrapped);
+    assert!(report.passed(), "profiled+checked: {}", report);
+}
@@ -0,0 +1,88 @@
+//! A backend implementing only the primitive ops gets every composite from the trait defaults.
+
+use dl_core::backend::{conformance, BackendResult};
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{Backend, CpuBackend, Linear, Shape, Tensor};
+use std::sync::Arc;
+
+/// Primitives only; kernels borrowed from the CPU backend, constructors bound to self.
+#[derive(Clone)]
+struct Minimal {
+    cpu: CpuBackend,
+}
+
+impl Backend for Minimal {
+    fn from_vec(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor> {
+        Tensor::from_vec(data, shape, Arc::new(self.clone()))
+            .map_err(|e| dl_core::BackendError(e.to_string()))
+    }
+    fn matmul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.matmul(a, b)
+    }
+    fn add(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.add(a, b)
+    }
+    fn mul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.mul(a, b)
+    }
+    fn div(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.div(a, b)
+    }
+    fn relu(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.relu(a)
+    }
+    fn relu_backward(&self, grad_out: &Tensor, input: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.relu_backward(grad_out, input)
+    }
+    fn exp(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.exp(a)
+    }
+    fn log(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.log(a)
+    }
+    fn sum_dim(&self, a: &Tensor, dim: usize) -> BackendResult<Tensor> {
+        self.cpu.sum_dim(a, dim)
+    }
+    fn transpose(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.transpose(a)
+    }
+    fn softmax_last_dim(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.softmax_last_dim(a)
+    }
+}
+
+#[test]
+fn primitive_only_backend_conforms() {
+    let b = Minimal {
+        cpu: CpuBackend::new(),
+    };
+    assert_eq!(b.capabilities(), Default::default());
+    assert!(CpuBackend::new().capabilities().fused_linear);
+    let report = conformance::run_all(&b);
+    assert!(report.passed(), "{}", report);
+}
+
+#[test]
+fn primitive_only_backend_trains_like_cpu() {
+    let run = |backend: Arc<dyn Backend>| {
+        let model = Linear::new(3, 2, backend.clone()).unwrap();
+        let mut trainer = Trainer::new(model, SGD::new(0.5));
+        let x = (0..12).map(|i| i as f32 * 0.1).collect();
+        let x = Tensor::from_vec(x, Shape::new(vec![4, 3]), backend.clone()).unwrap();
+        let t = vec![1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0];
+        let t = Tensor::from_vec(t, Shape::new(vec![4, 2]), backend.clone()).unwrap();
+        let losses: Vec<f32> = (0..5)
+            .map(|_| trainer.step_batch(backend.clone(), &x, &t).unwrap().loss)
+            .collect();
+        (losses, trainer.model.weight.data().data().to_vec())
+    };
+    let minimal = Arc::new(Minimal {
+        cpu: CpuBackend::new(),
+    });
+    let (l_min, w_min) = run(minimal);
+    let (l_cpu, w_cpu) = run(Arc::new(CpuBackend::new()));
+    for (a, b) in l_min.iter().zip(&l_cpu).chain(w_min.iter().zip(&w_cpu)) {
+        assert!((a - b).abs() < 1e-5, "{} vs {}", a, b);
+    }
+}
@@ -0,0 +1,131 @@
+//! BatchNorm: gradients in both modes, running statistics and eval mode, and buffers that
+//! live in state dicts but not among the trainable parameters.
+
+mod common;
+
+use common::{assert_close, check_module_gradients, tensor};
+use dl_core::autograd::check::{check_op, HarnessConfig};
+use dl_core::nn::{BatchNorm1d, BatchNorm2d, Module};
+use dl_core::ops::batch_norm::BatchNorm;
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{set_seed, CpuBackend, MLPBuilder};
+use std::sync::Arc;
+
+#[test]
+fn batch_norm_gradients_in_both_modes() {
+    let backend = Arc::new(CpuBackend::new());
+    for op in [
+        BatchNorm::training(1e-5),
+        BatchNorm::with_stats(1e-5, vec![0.5, -1.0, 0.0], vec![2.0, 0.5, 1.0]),
+    ] {
+        let report = check_op(&op, backend.clone(), &HarnessConfig::default()).unwrap();
+        assert!(report.passed(), "{:?}", report.failures);
+    }
+
+    // Through the module, with a weighted sum so the loss is not invariant to x.
+    let bn = BatchNorm2d::new(2, backend.clone()).unwrap();
+    let x = tensor(
+        (0..24).map(|i| ((i * 7) % 24) as f32 * 0.25).collect(),
+        &[2, 2, 2, 3],
+    );
+    check_module_gradients(&bn, &x).unwrap();
+}
+
+#[test]
+fn running_statistics_and_eval_mode() {
+    let backend = Arc::new(CpuBackend::new());
+    let mut bn = BatchNorm1d::new(2, backend).unwrap();
+    // Channel 0: 1, 3, 5, 7 (mean 4, var 5); channel 1: constant 2.
+    let x = tensor(vec![1.0, 2.0, 3.0, 2.0, 5.0, 2.0, 7.0, 2.0], &[4, 2]);
+
+    let y = bn.forward(&x).unwrap();
+    let s = 1.0 / (5.0f32 + 1e-5).sqrt();
+    assert_close(
+        y.data(),
+        &[-3.0 * s, 0.0, -s, 0.0, s, 0.0, 3.0 * s, 0.0],
+        1e-4,
+    );
+    // running = 0.9 * running + 0.1 * batch, with the unbiased variance (20 / 3).
+    assert_close(bn.running_mean().data(), &[0.4, 0.2], 1e-4);
+    assert_close(
+        bn.running_var().data(),
+        &[0.9 + 0.1 * 20.0 / 3.0, 0.9],
+        1e-4,
+    );
+
+    // Eval mode normalises with the running statistics and leaves them alone.
+    bn.eval();
+    assert!(!bn.is_training());
+    let y = bn.forward(&x).unwrap();
+    let (mean, var) = (bn.running_mean(), bn.running_var());
+    let want: Vec<f32> = x
+        .data()
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (v - mean.data()[i % 2]) / (var.data()[i % 2] + 1e-5).sqrt())
+        .collect();
+    assert_close(y.data(), &want, 1e-4);
+    assert_close(bn.running_mean().data(), &[0.4, 0.2], 1e-4);
+
+    // A single sample per channel has no batch variance to use in training mode.
+    bn.set_training(true);
+    assert!(bn.forward(&tensor(vec![1.0, 2.0], &[1, 2])).is_err());
+}
+
+#[test]
+fn buffers_are_saved_but_not_trained() {
+    set_seed(3);
+    let backend = Arc::new(CpuBackend::new());
+    let builder = MLPBuilder::new(3, 1)
+        .hidden(&[4])
+        .norm(|f, b| Ok(Box::new(BatchNorm1d::new(f, b)?)))
+        .xavier();
+    let net = builder.build(backend.clone()).unwrap();
+    let param_names: Vec<String> = net.named_parameters().into_iter().map(|(n, _)| n).collect();
+    assert_eq!(
+        param_names,
+        ["0.weight", "0.bias", "1.weight", "1.bias", "3.weight", "3.bias"]
+    );
+    let buffer_names: Vec<String> = net.named_buffers().into_iter().map(|(n, _)| n).collect();
+    assert_eq!(buffer_names, ["1.running_mean", "1.running_var"]);
+
+    let x = tensor(
+        (0..24).map(|i| (i % 7) as f32 * 0.3 - 1.0).collect(),
+        &[8, 3],
+    );
+    let t = tensor((0..8).map(|i| (i % 2) as f32).collect(), &[8, 1]);
+    let mut trainer = Trainer::new(net, SGD::new(0.05));
+    for _ in 0..5 {
+        trainer.step_batch(backend.clone(), &x, &t).unwrap();
+    }
+    let mut net = trainer.model;
+    let states = net.named_state_dict();
+    assert_eq!(states.len(), 8);
+    assert!(states["1.running_mean"].data.iter().any(|&v| v != 0.0));
+
+    // A fresh model in eval mode reproduces the trained one after loading, buffers included.
+    let mut fresh = builder.build(backend.clone()).unwrap();
+    let report = fresh
+        .load_named_state_dict(&states, backend.clone(), true)
+        .unwrap();
+    assert!(report.is_exact());
+    net.eval();
+    fresh.eval();
+    assert_eq!(
+        net.forward(&x).unwrap().data(),
+        fresh.forward(&x).unwrap().data()
+    );
+
+    // The positional state dict appends buffers after the parameters.
+    let positional = net.state_dict();
+    assert_eq!(positional.len(), 8);
+    assert_eq!(positional[6].name.as_deref(), Some("1.running_mean"));
+    let mut again = builder.build(backend.clone()).unwrap();
+    again.load_state_dict(&positional, backend).unwrap();
+    again.eval();
+    assert_eq!(
+        net.forward(&x).unwrap().data(),
+        again.forward(&x).unwrap().data()
+    );
+}
@@ -0,0 +1,122 @@
+//! CheckedBackend: a correct backend passes, a faulty one is caught at the first bad op.
+
+use dl_core::backend::BackendResult;
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{Backend, CheckedBackend, CpuBackend, Linear, ParallelCpuBackend, Shape, Tensor};
+use std::sync::Arc;
+
+/// CPU backend with a cheap sigmoid approximation (the kind of bug a fast backend introduces).
+#[derive(Clone)]
+struct FastSigmoid {
+    cpu: CpuBackend,
+}
+
+impl Backend for FastSigmoid {
+    fn sigmoid(&self, a: &Tensor) -> BackendResult<Tensor> {
+        let data = a
+            .data()
+            .iter()
+            .map(|&x| 0.5 + 0.5 * x / (1.0 + x.abs()))
+            .collect();
+        Ok(Tensor::from_vec(data, a.shape().clone(), a.backend()).unwrap())
+    }
+    fn matmul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.matmul(a, b)
+    }
+    fn add(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.add(a, b)
+    }
+    fn mul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.mul(a, b)
+    }
+    fn sub(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.sub(a, b)
+    }
+    fn relu(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.relu(a)
+    }
+    fn sum(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.sum(a)
+    }
+    fn sum_dim(&self, a: &Tensor, dim: usize) -> BackendResult<Tensor> {
+        self.cpu.sum_dim(a, dim)
+    }
+    fn from_vec(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor> {
+        self.cpu.from_vec(data, shape)
+    }
+    fn zeros(&self, shape: &Shape) -> BackendResult<Tensor> {
+        self.cpu.zeros(shape)
+    }
+    fn ones(&self, shape: &Shape) -> BackendResult<Tensor> {
+        self.cpu.ones(shape)
+    }
+    fn exp(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.exp(a)
+    }
+    fn log(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.log(a)
+    }
+    fn add_broadcast(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.add_broadcast(a, b)
+    }
+    fn transpose(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.transpose(a)
+    }
+    fn scale(&self, a: &Tensor, s: f32) -> BackendResult<Tensor> {
+        self.cpu.scale(a, s)
+    }
+    fn div(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.div(a, b)
+    }
+    fn relu_backward(&self, g: &Tensor, x: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.relu_backward(g, x)
+    }
+    fn sigmoid_backward(&self, g: &Tensor, y: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.sigmoid_backward(g, y)
+    }
+    fn softmax_last_dim(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.softmax_last_dim(a)
+    }
+    fn softmax_backward(&self, g: &Tensor, y: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.softmax_backward(g, y)
+    }
+}
+
+#[test]
+fn conforming_backend_trains_without_divergence() {
+    let checked = Arc::new(CheckedBackend::new(ParallelCpuBackend::new(2)));
+    let backend: Arc<dyn Backend> = checked.clone();
+    let model = Linear::new(3, 2, backend.clone()).unwrap();
+    let mut trainer = Trainer::new(model, SGD::new(0.1));
+    let x = Tensor::from_vec(vec![0.3; 12], Shape::new(vec![4, 3]), backend.clone()).unwrap();
+    let t = Tensor::from_vec(vec![1.0; 8], Shape::new(vec![4, 2]), backend.clone()).unwrap();
+    for _ in 0..3 {
+        trainer.step_batch(backend.clone(), &x, &t).unwrap();
+    }
+    assert!(checked.checked_ops() > 0);
+    assert_eq!(checked.first_divergence(), None);
+}
+
+#[test]
+fn faulty_backend_reports_first_divergence() {
+    let checked = Arc::new(CheckedBackend::new(FastSigmoid {
+        cpu: CpuBackend::new(),
+    }));
+    let backend: Arc<dyn Backend> = checked.clone();
+    let x = Tensor::from_vec(
+        vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6],
+        Shape::new(vec![2, 3]),
+        backend.clone(),
+    )
+    .unwrap();
+    let y = x.relu().unwrap().scale(2.0).unwrap();
+    let err = y.sigmoid().unwrap_err().to_string();
+    assert!(err.contains("sigmoid on inputs [[2, 3]]"), "{}", err);
+
+    let d = checked.first_divergence().unwrap();
+    assert_eq!(d.op, "sigmoid");
+    assert_eq!(d.input_shapes, vec![vec![2, 3]]);
+    assert!(d.detail.starts_with("element 0"));
+    assert_eq!(checked.checked_ops(), 3);
+}
@@ -0,0 +1,81 @@
+//! Gradient checkpointing: recomputed segments give the same gradients with fewer stored nodes.
+
+use dl_core::autograd::Graph;
+use dl_core::nn::{checkpoint, mse_graph, Checkpointed};
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{set_seed, CpuBackend, Module, Shape, Tensor, MLP2};
+use std::sync::Arc;
+
+fn input(backend: &Arc<CpuBackend>) -> (Tensor, Tensor) {
+    let x = Tensor::from_vec(
+        vec![0.5, -1.0, 0.25, 2.0, -0.3, 0.8],
+        Shape::new(vec![3, 2]),
+        backend.clone(),
+    )
+    .unwrap();
+    let y = Tensor::from_vec(
+        vec![1.0, 0.0, -1.0],
+        Shape::new(vec![3, 1]),
+        backend.clone(),
+    )
+    .unwrap();
+    (x, y)
+}
+
+#[test]
+fn test_checkpoint_matches_plain_gradients() {
+    set_seed(5);
+    let backend = Arc::new(CpuBackend::new());
+    let mut model = MLP2::new(2, 4, 1, backend.clone()).unwrap();
+    model.init_xavier().unwrap();
+    let (x, y) = input(&backend);
+
+    let mut plain = Graph::new();
+    let x_id = plain.var(x.clone());
+    let (out, plain_params) = model.forward_graph(&mut plain, x_id).unwrap();
+    let loss = mse_graph(&mut plain, out, &y).unwrap();
+    plain.backward(loss).unwrap();
+
+    let mut ckpt = Graph::new();
+    let x_id = ckpt.var(x.clone());
+    let (out, ckpt_params) = checkpoint(&mut ckpt, &model, x_id).unwrap();
+    let ckpt_loss = mse_graph(&mut ckpt, out, &y).unwrap();
+    ckpt.backward(ckpt_loss).unwrap();
+
+    assert!(
+        ckpt.len() < plain.len(),
+        "{} vs {}",
+        ckpt.len(),
+        plain.len()
+    );
+    let l0 = plain.data(loss).unwrap().data()[0];
+    let l1 = ckpt.data(ckpt_loss).unwrap().data()[0];
+    assert!((l0 - l1).abs() < 1e-6);
+    assert_eq!(plain_params.len(), ckpt_params.len());
+    for (&p, &c) in plain_params.iter().zip(ckpt_params.iter()) {
+        let gp = plain.grad(p).unwrap().unwrap().data().to_vec();
+        let gc = ckpt.grad(c).unwrap().unwrap().data().to_vec();
+        for (a, b) in gp.iter().zip(gc.iter()) {
+            assert!((a - b).abs() < 1e-6, "grad mismatch: {} vs {}", a, b);
+        }
+    }
+    let gx = ckpt.grad(x_id).unwrap().unwrap();
+    assert_eq!(gx.shape().dims(), &[3, 2]);
+}
+
+#[test]
+fn test_checkpointed_module_trains() {
+    set_seed(7);
+    let backend = Arc::new(CpuBackend::new());
+    let mut inner = MLP2::new(2, 4, 1, backend.clone()).unwrap();
+    inner.init_xavier().unwrap();
+    let (x, y) = input(&backend);
+    let mut trainer = Trainer::new(Checkpointed::new(inner), SGD::new(0.1));
+    let first = trainer.step_batch(backend.clone(), &x, &y).unwrap().loss;
+    let mut last = first;
+    for _ in 0..50 {
+        last = trainer.step_batch(backend.clone(), &x, &y).unwrap().loss;
+    }
+    assert!(last < first, "loss should decrease: {} -> {}", first, last);
+}
@@ -0,0 +1,37 @@
+//! Helpers shared by the layer tests (`mod common;` in each test crate).
+#![allow(dead_code)]
+
+use dl_core::autograd::check::check_gradients;
+use dl_core::nn::Module;
+use dl_core::{CpuBackend, Graph, Shape, Tensor};
+use std::sync::Arc;
+
+/// CPU tensor of `dims` holding `data`.
+pub fn tensor(data: Vec<f32>, dims: &[usize]) -> Tensor {
+    Tensor::from_vec(data, Shape::new(dims.to_vec()), Arc::new(CpuBackend::new())).unwrap()
+}
+
+/// Element-wise |got - want| < tol.
+pub fn assert_close(got: &[f32], want: &[f32], tol: f32) {
+    assert_eq!(got.len(), want.len());
+    for (g, w) in got.iter().zip(want) {
+        assert!((g - w).abs() < tol, "got {:?}, want {:?}", got, want);
+    }
+}
+
+/// [check_gradients] of sum(module(x) * r), where r weights the outputs with a repeating
+/// ramp so the loss is not invariant to x and positions get distinct gradients.
+pub fn check_module_gradients(module: &dyn Module, x: &Tensor) -> Result<(), String> {
+    let build = |g: &mut Graph, ids: &[usize]| {
+        let (y, _) = module.forward_graph(g, ids[0])?;
+        let dims = g.data(y)?.shape().dims().to_vec();
+        let n = dims.iter().product();
+        let r_id = g.var(tensor(
+            (0..n).map(|j| (j % 5) as f32 - 2.0).collect(),
+            &dims,
+        ));
+        let weighted = g.mul(y, r_id)?;
+        g.sum(weighted)
+    };
+    check_gradients(&build, std::slice::from_ref(x), 1e-2, 1e-2, 1e-3)
+}
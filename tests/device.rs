@@ -0,0 +1,161 @@
+//! Device identity: mixing devices fails, Tensor::to and Module::to move data between them.
+
+use dl_core::backend::BackendResult;
+use dl_core::nn::{Module, SinusoidalPositionalEncoding};
+use dl_core::{
+    Backend, BackendError, CpuBackend, Device, Linear, ParallelCpuBackend, Sequential, Shape,
+    Tensor, TensorError,
+};
+use std::sync::Arc;
+
+/// Simulated accelerator: host memory, but reports its own device. Kernels run on the CPU
+/// backend, which binds op results to the dispatching tensor's backend (this one);
+/// constructors are rebound here.
+#[derive(Clone)]
+struct FakeAccel {
+    cpu: CpuBackend,
+}
+
+impl FakeAccel {
+    fn new() -> Self {
+        FakeAccel {
+            cpu: CpuBackend::new(),
+        }
+    }
+
+    fn bind(&self, r: BackendResult<Tensor>) -> BackendResult<Tensor> {
+        let t = r?;
+        Tensor::from_vec(t.data().to_vec(), t.shape().clone(), Arc::new(self.clone()))
+            .map_err(|e| BackendError(e.to_string()))
+    }
+}
+
+impl Backend for FakeAccel {
+    fn device(&self) -> Device {
+        Device::accelerator("fake", 0)
+    }
+    fn matmul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.matmul(a, b)
+    }
+    fn add(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.add(a, b)
+    }
+    fn mul(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.mul(a, b)
+    }
+    fn sub(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.sub(a, b)
+    }
+    fn relu(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.relu(a)
+    }
+    fn sum(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.sum(a)
+    }
+    fn sum_dim(&self, a: &Tensor, dim: usize) -> BackendResult<Tensor> {
+        self.cpu.sum_dim(a, dim)
+    }
+    fn from_vec(&self, data: Vec<f32>, shape: Shape) -> BackendResult<Tensor> {
+        self.bind(self.cpu.from_vec(data, shape))
+    }
+    fn zeros(&self, shape: &Shape) -> BackendResult<Tensor> {
+        self.bind(self.cpu.zeros(shape))
+    }
+    fn ones(&self, shape: &Shape) -> BackendResult<Tensor> {
+        self.bind(self.cpu.ones(shape))
+    }
+    fn sigmoid(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.sigmoid(a)
+    }
+    fn exp(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.exp(a)
+    }
+    fn log(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.log(a)
+    }
+    fn add_broadcast(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.add_broadcast(a, b)
+    }
+    fn transpose(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.transpose(a)
+    }
+    fn scale(&self, a: &Tensor, s: f32) -> BackendResult<Tensor> {
+        self.cpu.scale(a, s)
+    }
+    fn div(&self, a: &Tensor, b: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.div(a, b)
+    }
+    fn relu_backward(&self, g: &Tensor, x: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.relu_backward(g, x)
+    }
+    fn sigmoid_backward(&self, g: &Tensor, y: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.sigmoid_backward(g, y)
+    }
+    fn softmax_last_dim(&self, a: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.softmax_last_dim(a)
+    }
+    fn softmax_backward(&self, g: &Tensor, y: &Tensor) -> BackendResult<Tensor> {
+        self.cpu.softmax_backward(g, y)
+    }
+}
+
+#[test]
+fn mixing_devices_errors_and_to_transfers() {
+    let cpu: Arc<dyn Backend> = Arc::new(CpuBackend::new());
+    let accel: Arc<dyn Backend> = Arc::new(FakeAccel::new());
+    let a = Tensor::from_vec(vec![1.0, 2.0], Shape::new(vec![2]), cpu.clone()).unwrap();
+    let b = a.to(accel.clone()).unwrap();
+    assert_eq!(b.device(), Device::accelerator("fake", 0));
+    assert_eq!(b.data(), a.data());
+
+    match a.add(&b) {
+        Err(TensorError::DeviceMismatch { op, lhs, rhs }) => {
+            assert_eq!((op, lhs, rhs), ("add", Device::CPU, b.device()));
+        }
+        other => panic!("expected device mismatch, got {:?}", other),
+    }
+    // Same memory space: CPU and parallel CPU tensors mix freely.
+    let p = a.to(Arc::new(ParallelCpuBackend::new(2))).unwrap();
+    assert_eq!(a.add(&p).unwrap().data(), &[2.0, 4.0]);
+    assert_eq!(b.add(&b).unwrap().device(), b.device());
+}
+
+#[test]
+fn module_to_moves_parameters() {
+    let cpu: Arc<dyn Backend> = Arc::new(CpuBackend::new());
+    let accel: Arc<dyn Backend> = Arc::new(FakeAccel::new());
+    let mut model = Linear::new(3, 2, cpu.clone()).unwrap();
+    let x = Tensor::from_vec(vec![0.5; 6], Shape::new(vec![2, 3]), cpu.clone()).unwrap();
+    let expected = model.forward(&x).unwrap();
+
+    model.to(accel.clone()).unwrap();
+    assert!(model
+        .parameters()
+        .iter()
+        .all(|p| p.data().device() == accel.device()));
+    assert!(matches!(
+        model.forward(&x),
+        Err(TensorError::DeviceMismatch { .. })
+    ));
+    let y = model.forward(&x.to(accel).unwrap()).unwrap();
+    assert_eq!(y.device(), Device::accelerator("fake", 0));
+    assert_eq!(y.data(), expected.data());
+}
+
+#[test]
+fn module_to_moves_buffers() {
+    let cpu: Arc<dyn Backend> = Arc::new(CpuBackend::new());
+    let accel: Arc<dyn Backend> = Arc::new(FakeAccel::new());
+    let mut model = Sequential::new()
+        .with(SinusoidalPositionalEncoding::new(4, 3, cpu.clone()).unwrap())
+        .with(Linear::new(3, 2, cpu.clone()).unwrap());
+    let x = Tensor::from_vec(vec![0.5; 9], Shape::new(vec![1, 3, 3]), cpu.clone()).unwrap();
+    let expected = model.forward(&x).unwrap();
+
+    model.to(accel.clone()).unwrap();
+    let buffers = model.named_buffers();
+    assert_eq!(buffers[0].0, "0.table");
+    assert_eq!(buffers[0].1.device(), accel.device());
+    let y = model.forward(&x.to(accel).unwrap()).unwrap();
+    assert_eq!(y.data(), expected.data());
+}
@@ -0,0 +1,118 @@
+//! Dropout, AlphaDropout and DropPath: seeded masks, mask-aware backward, identity in eval
+//! mode and use as MLPBuilder's dropout slot.
+
+mod common;
+
+use common::tensor;
+use dl_core::nn::{AlphaDropout, DropPath, Dropout, Module};
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{set_seed, CpuBackend, Graph, MLPBuilder};
+use std::sync::Arc;
+
+#[test]
+fn dropout_masks_are_seeded_and_used_in_backward() {
+    let x = tensor(vec![1.0; 1000], &[10, 100]);
+    let mut drop = Dropout::new(0.25).unwrap();
+    set_seed(42);
+    let a = drop.forward(&x).unwrap();
+    set_seed(42);
+    let b = drop.forward(&x).unwrap();
+    assert_eq!(a.data(), b.data());
+    assert_ne!(a.data(), drop.forward(&x).unwrap().data());
+    let dropped = a.data().iter().filter(|&&v| v == 0.0).count();
+    assert!((200..300).contains(&dropped), "dropped {}", dropped);
+    assert!(a
+        .data()
+        .iter()
+        .all(|&v| v == 0.0 || (v - 1.0 / 0.75).abs() < 1e-6));
+
+    // The gradient of sum(y) is the mask drawn for that forward.
+    set_seed(7);
+    let mut g = Graph::new();
+    let x_id = g.var(x.clone());
+    let (y, _) = drop.forward_graph(&mut g, x_id).unwrap();
+    let y_data = g.data(y).unwrap().clone();
+    let loss = g.sum(y).unwrap();
+    g.backward(loss).unwrap();
+    assert_eq!(g.grad(x_id).unwrap().unwrap().data(), y_data.data());
+
+    // Evaluation mode (and p = 0) is the identity and adds no graph node.
+    drop.eval();
+    assert_eq!(drop.forward(&x).unwrap().data(), x.data());
+    let mut g = Graph::new();
+    let x_id = g.var(x.clone());
+    assert_eq!(drop.forward_graph(&mut g, x_id).unwrap().0, x_id);
+    let zero = Dropout::new(0.0).unwrap();
+    assert_eq!(zero.forward(&x).unwrap().data(), x.data());
+    assert!(Dropout::new(1.5).is_err());
+}
+
+#[test]
+fn alpha_dropout_and_drop_path() {
+    set_seed(3);
+    // Uniform on [-sqrt(3), sqrt(3)): zero mean, unit variance.
+    let n = 40_000;
+    let x = tensor(
+        (0..n)
+            .map(|i| ((i * 7919) % n) as f32 / n as f32 * 2.0 * 3f32.sqrt() - 3f32.sqrt())
+            .collect(),
+        &[n],
+    );
+    let y = AlphaDropout::new(0.2).unwrap().forward(&x).unwrap();
+    let mean = y.data().iter().sum::<f32>() / n as f32;
+    let var = y.data().iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n as f32;
+    assert!(mean.abs() < 0.03, "mean {}", mean);
+    assert!((var - 1.0).abs() < 0.05, "var {}", var);
+    // p = 1 has no finite affine correction; just below it the output stays finite.
+    assert!(AlphaDropout::new(1.0).is_err());
+    let y = AlphaDropout::new(0.999).unwrap().forward(&x).unwrap();
+    assert!(y.data().iter().all(|v| v.is_finite()));
+
+    // Each sample is either dropped entirely or kept and scaled by 1 / (1 - p).
+    let x = tensor(vec![1.0; 64 * 6], &[64, 2, 3]);
+    let y = DropPath::new(0.5).unwrap().forward(&x).unwrap();
+    let mut kept = 0;
+    for sample in y.data().chunks(6) {
+        assert!(sample.iter().all(|&v| v == sample[0]));
+        assert!(sample[0] == 0.0 || sample[0] == 2.0);
+        kept += (sample[0] == 2.0) as usize;
+    }
+    assert!((16..48).contains(&kept), "kept {}", kept);
+}
+
+#[test]
+fn dropout_in_mlp_builder_trains_and_evaluates_deterministically() {
+    set_seed(9);
+    let backend = Arc::new(CpuBackend::new());
+    let net = MLPBuilder::new(3, 1)
+        .hidden(&[32])
+        .dropout(|_, _| Ok(Box::new(Dropout::new(0.2)?)))
+        .xavier()
+        .build(backend.clone())
+        .unwrap();
+    let x = tensor(
+        (0..24).map(|i| (i % 7) as f32 * 0.3 - 1.0).collect(),
+        &[8, 3],
+    );
+    let t = tensor((0..8).map(|i| (i % 2) as f32).collect(), &[8, 1]);
+
+    let mut trainer = Trainer::new(net, SGD::new(0.05));
+    let initial = trainer.step_batch(backend.clone(), &x, &t).unwrap().loss;
+    let mut loss = initial;
+    for _ in 0..200 {
+        loss = trainer.step_batch(backend.clone(), &x, &t).unwrap().loss;
+    }
+    assert!(loss < initial * 0.5, "loss {} -> {}", initial, loss);
+
+    let mut net = trainer.model;
+    assert_ne!(
+        net.forward(&x).unwrap().data(),
+        net.forward(&x).unwrap().data()
+    );
+    net.eval();
+    assert_eq!(
+        net.forward(&x).unwrap().data(),
+        net.forward(&x).unwrap().data()
+    );
+}
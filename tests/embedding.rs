@@ -0,0 +1,174 @@
+//! Embedding and EmbeddingBag: lookups, padding and max-norm, row-sparse gradients and
+//! optimizers that only touch the looked-up rows.
+
+mod common;
+
+use common::tensor;
+use dl_core::autograd::check::{check_op, HarnessConfig};
+use dl_core::nn::{BagMode, Embedding, EmbeddingBag, EmbeddingOptions, Linear, Module, Sequential};
+use dl_core::ops::embedding;
+use dl_core::optimizer::{Adam, Optimizer, SGD};
+use dl_core::train::Trainer;
+use dl_core::{set_seed, CpuBackend, Graph, Parameter, Tensor};
+use std::sync::Arc;
+
+/// Table whose row i is [i, 10 i].
+fn table(num: usize) -> Tensor {
+    tensor(
+        (0..num).flat_map(|i| [i as f32, 10.0 * i as f32]).collect(),
+        &[num, 2],
+    )
+}
+
+#[test]
+fn lookups_bags_padding_and_max_norm() {
+    let backend = Arc::new(CpuBackend::new());
+    let mut emb = Embedding::new(5, 2, backend.clone()).unwrap();
+    *emb.weight.data_mut() = table(5);
+    let y = emb
+        .forward(&tensor(vec![3.0, 1.0, 3.0, 0.0], &[2, 2]))
+        .unwrap();
+    assert_eq!(y.shape().dims(), &[2, 2, 2]);
+    assert_eq!(y.data(), &[3.0, 30.0, 1.0, 10.0, 3.0, 30.0, 0.0, 0.0]);
+    assert!(emb.forward(&tensor(vec![5.0], &[1])).is_err());
+    assert!(emb.forward(&tensor(vec![1.5], &[1])).is_err());
+
+    // Padding looks up zeros and is skipped by the mean.
+    let options = EmbeddingOptions {
+        padding_idx: Some(0),
+        ..Default::default()
+    };
+    let mut bag =
+        EmbeddingBag::with_options(5, 2, BagMode::Mean, options, backend.clone()).unwrap();
+    *bag.weight.data_mut() = table(5);
+    let y = bag
+        .forward(&tensor(vec![1.0, 3.0, 0.0, 4.0, 0.0, 0.0], &[2, 3]))
+        .unwrap();
+    assert_eq!(y.data(), &[2.0, 20.0, 4.0, 40.0]);
+    let mut sum = EmbeddingBag::new(5, 2, BagMode::Sum, backend.clone()).unwrap();
+    *sum.weight.data_mut() = table(5);
+    let y = sum
+        .forward(&tensor(vec![1.0, 3.0, 2.0, 2.0], &[2, 2]))
+        .unwrap();
+    assert_eq!(y.data(), &[4.0, 40.0, 4.0, 40.0]);
+
+    let mut init = Embedding::with_options(5, 2, options, backend.clone()).unwrap();
+    set_seed(1);
+    init.init_uniform(0.5).unwrap();
+    assert_eq!(&init.weight.data().data()[..2], &[0.0, 0.0]);
+    assert!(init.weight.data().data()[2..]
+        .iter()
+        .all(|v| v.abs() <= 0.5));
+
+    // Rows longer than max_norm are rescaled on lookup; the stored table is untouched.
+    let options = EmbeddingOptions {
+        max_norm: Some(1.0),
+        ..Default::default()
+    };
+    let mut clipped = Embedding::with_options(5, 2, options, backend).unwrap();
+    *clipped.weight.data_mut() = tensor(
+        vec![0.3, 0.4, 3.0, 4.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        &[5, 2],
+    );
+    let y = clipped.forward(&tensor(vec![0.0, 1.0], &[2])).unwrap();
+    for (g, w) in y.data().iter().zip([0.3, 0.4, 0.6, 0.8]) {
+        assert!((g - w).abs() < 1e-5, "{:?}", y.data());
+    }
+    assert_eq!(clipped.weight.data().data()[2], 3.0);
+}
+
+#[test]
+fn gradients_are_row_sparse() {
+    let backend = Arc::new(CpuBackend::new());
+    let ops = [
+        embedding::Embedding::new(vec![Some(2), None, Some(0), Some(2)], vec![2, 2]),
+        embedding::Embedding::bags(vec![Some(1), Some(1), None, Some(0)], 2, BagMode::Mean),
+    ];
+    for op in &ops {
+        let report = check_op(op, backend.clone(), &HarnessConfig::default()).unwrap();
+        assert!(report.passed(), "{:?}", report.failures);
+    }
+
+    let options = EmbeddingOptions {
+        padding_idx: Some(0),
+        ..Default::default()
+    };
+    let mut emb = Embedding::with_options(1000, 2, options, backend).unwrap();
+    *emb.weight.data_mut() = table(1000);
+    let mut g = Graph::new();
+    let x_id = g.var(tensor(vec![7.0, 0.0, 3.0, 7.0], &[4]));
+    let (y, params) = emb.forward_graph(&mut g, x_id).unwrap();
+    // Only the touched rows are bound, not the whole table.
+    assert_eq!(g.data(params[0]).unwrap().shape().dims(), &[2, 2]);
+    let loss = g.sum(y).unwrap();
+    g.backward(loss).unwrap();
+    g.write_grads(&mut emb.parameters_mut()).unwrap();
+    assert!(emb.weight.grad().is_none());
+    let sparse = emb.weight.sparse_grad().unwrap();
+    assert_eq!(sparse.rows, vec![3, 7]);
+    assert_eq!(sparse.values.data(), &[1.0, 1.0, 2.0, 2.0]);
+
+    // Rows past the table are rejected, even when rows hold no values.
+    let mut g = Graph::new();
+    assert!(g.param_rows(&emb.weight, &[3, 1000]).is_err());
+    let empty_rows = Parameter::new(tensor(vec![], &[3, 0]));
+    assert!(g.param_rows(&empty_rows, &[2]).is_ok());
+    assert!(g.param_rows(&empty_rows, &[3]).is_err());
+}
+
+#[test]
+fn optimizers_update_only_touched_rows() {
+    let backend = Arc::new(CpuBackend::new());
+    let mut optimizers: Vec<Box<dyn Optimizer>> =
+        vec![Box::new(SGD::new(0.1)), Box::new(Adam::new(0.1))];
+    for opt in optimizers.iter_mut() {
+        let mut emb = Embedding::new(6, 2, backend.clone()).unwrap();
+        *emb.weight.data_mut() = table(6);
+        for _ in 0..2 {
+            let mut g = Graph::new();
+            let x_id = g.var(tensor(vec![1.0, 4.0], &[2]));
+            let (y, _) = emb.forward_graph(&mut g, x_id).unwrap();
+            let loss = g.sum(y).unwrap();
+            g.backward(loss).unwrap();
+            let mut params = emb.parameters_mut();
+            params[0].zero_grad();
+            g.write_grads(&mut params).unwrap();
+            opt.step(&mut params).unwrap();
+        }
+        let w = emb.weight.data().data().to_vec();
+        for (i, row) in w.chunks(2).enumerate() {
+            let orig = [i as f32, 10.0 * i as f32];
+            if i == 1 || i == 4 {
+                assert!(row[0] < orig[0] && row[1] < orig[1], "row {}: {:?}", i, row);
+            } else {
+                assert_eq!(row, orig, "row {}", i);
+            }
+        }
+    }
+
+    // Bag of token ids -> linear readout learns the share of ids below 4.
+    set_seed(5);
+    let mut bag = EmbeddingBag::new(8, 4, BagMode::Mean, backend.clone()).unwrap();
+    bag.init_uniform(0.5).unwrap();
+    let net = Sequential::new()
+        .with(bag)
+        .with(Linear::new(4, 1, backend.clone()).unwrap());
+    let x = tensor(
+        vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 0.0, 2.0, 5.0, 7.0],
+        &[4, 3],
+    );
+    let t = tensor(
+        x.data()
+            .chunks(3)
+            .map(|b| b.iter().filter(|&&v| v < 4.0).count() as f32 / 3.0)
+            .collect(),
+        &[4, 1],
+    );
+    let mut trainer = Trainer::new(net, Adam::new(0.05));
+    let initial = trainer.step_batch(backend.clone(), &x, &t).unwrap().loss;
+    let mut loss = initial;
+    for _ in 0..200 {
+        loss = trainer.step_batch(backend.clone(), &x, &t).unwrap().loss;
+    }
+    assert!(loss < initial * 0.1, "loss {} -> {}", initial, loss);
+}
@@ -0,0 +1,51 @@
+//! Blocked GEMM kernels agree with an f64 reference on edge-tile and multi-block shapes.
+
+use dl_core::backend::gemm::{sgemm_with, GemmKernel};
+use dl_core::{set_seed, with_rng};
+use rand::Rng;
+
+fn random(n: usize) -> Vec<f32> {
+    with_rng(|rng| (0..n).map(|_| rng.gen_range(-1.0f32..1.0)).collect())
+}
+
+#[test]
+fn every_available_kernel_matches_reference() {
+    set_seed(11);
+    // Shapes cover partial MR/NR tiles, k spanning several KC blocks, and empty dims.
+    let shapes = [
+        (1, 1, 1),
+        (7, 13, 5),
+        (6, 16, 16),
+        (97, 300, 33),
+        (130, 520, 70),
+        (0, 4, 3),
+        (3, 0, 2),
+    ];
+    for kernel in GemmKernel::available() {
+        for &(m, k, n) in &shapes {
+            let (a, b) = (random(m * k), random(k * n));
+            let mut c = vec![f32::NAN; m * n];
+            sgemm_with(kernel, m, k, n, &a, &b, &mut c);
+            for i in 0..m {
+                for j in 0..n {
+                    let exact: f64 = (0..k)
+                        .map(|p| a[i * k + p] as f64 * b[p * n + j] as f64)
+                        .sum();
+                    let got = c[i * n + j] as f64;
+                    assert!(
+                        (got - exact).abs() <= 1e-4 * (k as f64).sqrt().max(1.0),
+                        "{} kernel, {}x{}x{} at ({}, {}): {} vs {}",
+                        kernel.name(),
+                        m,
+                        k,
+                        n,
+                        i,
+                        j,
+                        got,
+                        exact
+                    );
+                }
+            }
+        }
+    }
+}
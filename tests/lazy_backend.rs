@@ -0,0 +1,92 @@
+//! LazyBackend: element-wise chains run as one fused loop and match the eager CPU results.
+
+use dl_core::backend::conformance;
+use dl_core::nn::loss::mse;
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{Backend, CpuBackend, LazyBackend, Linear, Shape, Tensor};
+use std::sync::Arc;
+
+fn ramp(n: usize, k: usize) -> Vec<f32> {
+    (0..n).map(|i| ((i * k % 17) as f32 - 8.0) * 0.1).collect()
+}
+
+#[test]
+fn mse_is_one_pass_and_matches_cpu() {
+    let lazy = Arc::new(LazyBackend::new(CpuBackend::new()));
+    assert!(conformance::run_all(&*lazy).passed());
+    let cpu: Arc<dyn Backend> = Arc::new(CpuBackend::new());
+    let shape = Shape::new(vec![64, 33]);
+    let (p, t) = (ramp(64 * 33, 5), ramp(64 * 33, 3));
+
+    let want = {
+        let pred = Tensor::from_vec(p.clone(), shape.clone(), cpu.clone()).unwrap();
+        let target = Tensor::from_vec(t.clone(), shape.clone(), cpu).unwrap();
+        mse(&pred, &target).unwrap().data()[0]
+    };
+
+    let backend: Arc<dyn Backend> = lazy.clone();
+    let pred = Tensor::from_vec(p, shape.clone(), backend.clone()).unwrap();
+    let target = Tensor::from_vec(t, shape, backend).unwrap();
+    lazy.reset_stats();
+    let before = lazy.alloc_stats().unwrap().bytes_allocated;
+    let loss = mse(&pred, &target).unwrap();
+    assert!(!loss.is_realized());
+    assert_eq!(loss.data()[0], want);
+    // scale, add, mul and sum in one loop over the data, then the final [1] scale.
+    let stats = lazy.stats();
+    assert_eq!((stats.deferred, stats.kernels), (4, 2));
+    assert_eq!(lazy.alloc_stats().unwrap().bytes_allocated - before, 8);
+}
+
+#[test]
+fn bias_and_activation_fuse_and_training_matches_cpu() {
+    let lazy = Arc::new(LazyBackend::new(CpuBackend::new()));
+    let backend: Arc<dyn Backend> = lazy.clone();
+    let x = Tensor::from_vec(ramp(8 * 3, 7), Shape::new(vec![8, 3]), backend.clone()).unwrap();
+    let w = Tensor::from_vec(ramp(3 * 4, 2), Shape::new(vec![3, 4]), backend.clone()).unwrap();
+    let b = Tensor::from_vec(ramp(4, 1), Shape::new(vec![4]), backend.clone()).unwrap();
+    lazy.reset_stats();
+    let h = x
+        .matmul(&w)
+        .unwrap()
+        .add_broadcast(&b)
+        .unwrap()
+        .sigmoid()
+        .unwrap();
+    h.realize();
+    assert_eq!(lazy.stats().kernels, 1);
+
+    let run = |backend: Arc<dyn Backend>| {
+        let model = Linear::new(3, 2, backend.clone()).unwrap();
+        let mut trainer = Trainer::new(model, SGD::new(0.5));
+        let x = Tensor::from_vec(ramp(12, 7), Shape::new(vec![4, 3]), backend.clone()).unwrap();
+        let t = Tensor::from_vec(ramp(8, 3), Shape::new(vec![4, 2]), backend.clone()).unwrap();
+        let losses: Vec<f32> = (0..5)
+            .map(|_| trainer.step_batch(backend.clone(), &x, &t).unwrap().loss)
+            .collect();
+        (losses, trainer.model.weight.data().data().to_vec())
+    };
+    assert_eq!(run(backend), run(Arc::new(CpuBackend::new())));
+}
+
+#[test]
+fn pending_tensors_clone_realize_once_and_turn_eager_on_write() {
+    let lazy = Arc::new(LazyBackend::new(CpuBackend::new()));
+    let backend: Arc<dyn Backend> = lazy.clone();
+    let x = Tensor::from_vec(vec![1.0, -2.0, 3.0], Shape::new(vec![3]), backend).unwrap();
+    lazy.reset_stats();
+    let y = x.relu().unwrap().scale(2.0).unwrap();
+    let mut copy = y.clone();
+    assert!(!copy.is_realized());
+
+    assert_eq!(y.data(), &[2.0, 0.0, 6.0]);
+    assert_eq!(y.data(), &[2.0, 0.0, 6.0]);
+    assert_eq!(lazy.stats().kernels, 1);
+
+    copy.data_mut()[1] = 5.0;
+    assert!(copy.is_realized());
+    assert_eq!(copy.data(), &[2.0, 5.0, 6.0]);
+    assert_eq!(y.clone().data(), &[2.0, 0.0, 6.0]);
+    assert_eq!(lazy.stats().kernels, 2);
+}
@@ -0,0 +1,104 @@
+//! Named parameters and name-keyed state dicts: structural paths, file round trip, strict and
+//! non-strict loading.
+
+use dl_core::nn::ShapeMismatch;
+use dl_core::{
+    load_named_state_dict, save_named_state_dict, set_seed, CpuBackend, Linear, MLPBuilder, Module,
+    Shape, Tensor, MLP2,
+};
+use std::sync::Arc;
+
+#[test]
+fn named_parameters_follow_module_structure() {
+    let backend = Arc::new(CpuBackend::new());
+    let mlp = MLP2::new(3, 4, 2, backend.clone()).unwrap();
+    let names: Vec<String> = mlp.named_parameters().into_iter().map(|(n, _)| n).collect();
+    assert_eq!(
+        names,
+        [
+            "linear1.weight",
+            "linear1.bias",
+            "linear2.weight",
+            "linear2.bias"
+        ]
+    );
+
+    let seq = MLPBuilder::new(3, 2).hidden(&[4]).build(backend).unwrap();
+    let names: Vec<String> = seq.named_parameters().into_iter().map(|(n, _)| n).collect();
+    assert_eq!(names, ["0.weight", "0.bias", "2.weight", "2.bias"]);
+}
+
+#[test]
+fn named_state_dict_round_trips_through_file() {
+    set_seed(5);
+    let backend = Arc::new(CpuBackend::new());
+    let mut model = MLP2::new(3, 4, 2, backend.clone()).unwrap();
+    model.init_xavier().unwrap();
+    let x = Tensor::from_vec(
+        vec![0.5, -1.0, 2.0],
+        Shape::new(vec![1, 3]),
+        backend.clone(),
+    )
+    .unwrap();
+
+    let path = std::env::temp_dir().join("dl_core_named_state_dict_test.json");
+    save_named_state_dict(&path, &model.named_state_dict()).unwrap();
+    let states = load_named_state_dict(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    let mut model2 = MLP2::new(3, 4, 2, backend.clone()).unwrap();
+    let report = model2
+        .load_named_state_dict(&states, backend.clone(), true)
+        .unwrap();
+    assert!(report.is_exact());
+    assert_eq!(report.loaded.len(), 4);
+    assert_eq!(
+        model.forward(&x).unwrap().data(),
+        model2.forward(&x).unwrap().data()
+    );
+}
+
+#[test]
+fn strict_and_non_strict_report_key_problems() {
+    let backend = Arc::new(CpuBackend::new());
+    let source = MLP2::new(3, 4, 2, backend.clone()).unwrap();
+    let mut states = source.named_state_dict();
+    states.remove("linear2.bias");
+    let mut other = Linear::new(3, 5, backend.clone())
+        .unwrap()
+        .named_state_dict();
+    states.insert("head.bias".into(), other.remove("bias").unwrap());
+    states.insert("linear1.weight".into(), other.remove("weight").unwrap());
+
+    let mut target = MLP2::new(3, 4, 2, backend.clone()).unwrap();
+    let before = target.linear1.bias.data().data().to_vec();
+    let err = target
+        .load_named_state_dict(&states, backend.clone(), true)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("linear2.bias") && err.contains("head.bias"),
+        "{}",
+        err
+    );
+    assert_eq!(target.linear1.bias.data().data(), &before[..]);
+
+    let report = target
+        .load_named_state_dict(&states, backend, false)
+        .unwrap();
+    assert_eq!(report.loaded, ["linear1.bias", "linear2.weight"]);
+    assert_eq!(report.missing, ["linear2.bias"]);
+    assert_eq!(report.unexpected, ["head.bias"]);
+    assert_eq!(
+        report.shape_mismatched,
+        [ShapeMismatch {
+            key: "linear1.weight".into(),
+            expected: vec![3, 4],
+            found: vec![3, 5],
+        }]
+    );
+    assert_eq!(
+        target.linear1.bias.data().data(),
+        source.linear1.bias.data().data()
+    );
+}
@@ -0,0 +1,164 @@
+//! LayerNorm, RMSNorm and GroupNorm: values, fused single-node gradients and a deep MLP
+//! that trains with normalisation.
+
+mod common;
+
+use common::{assert_close, check_module_gradients, tensor};
+use dl_core::autograd::check::{check_op, HarnessConfig};
+use dl_core::nn::{GroupNorm, LayerNorm, Module, RMSNorm};
+use dl_core::ops::norm;
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{set_seed, CpuBackend, Graph, MLPBuilder, Op};
+use std::sync::Arc;
+
+#[test]
+fn norm_values() {
+    let backend = Arc::new(CpuBackend::new());
+    // Rows 1, 2, 3, 4 (mean 2.5, var 1.25) and a constant row.
+    let x = tensor(vec![1.0, 2.0, 3.0, 4.0, 5.0, 5.0, 5.0, 5.0], &[2, 4]);
+    let s = 1.0 / (1.25f32 + 1e-5).sqrt();
+    let mut ln = LayerNorm::new(&[4], backend.clone()).unwrap();
+    let y = ln.forward(&x).unwrap();
+    assert_close(
+        y.data(),
+        &[-1.5 * s, -0.5 * s, 0.5 * s, 1.5 * s, 0.0, 0.0, 0.0, 0.0],
+        1e-4,
+    );
+    *ln.bias.data_mut() = tensor(vec![1.0; 4], &[4]);
+    assert_close(&ln.forward(&x).unwrap().data()[4..], &[1.0; 4], 1e-4);
+
+    // RMS of 1, 2, 3, 4 is sqrt(7.5); no centring.
+    let rms = RMSNorm::new(&[4], backend.clone()).unwrap();
+    let r = 1.0 / (7.5f32 + 1e-5).sqrt();
+    assert_close(
+        &rms.forward(&x).unwrap().data()[..4],
+        &[r, 2.0 * r, 3.0 * r, 4.0 * r],
+        1e-4,
+    );
+
+    // One group is LayerNorm over (C, L); C groups normalise each channel on its own.
+    let x = tensor(
+        (0..24).map(|i| ((i * 5) % 24) as f32 * 0.5).collect(),
+        &[2, 3, 4],
+    );
+    let one = GroupNorm::new(1, 3, backend.clone()).unwrap();
+    let ln = LayerNorm::new(&[3, 4], backend.clone()).unwrap();
+    assert_close(
+        one.forward(&x).unwrap().data(),
+        ln.forward(&x).unwrap().data(),
+        1e-4,
+    );
+    let per_channel = GroupNorm::new(3, 3, backend.clone()).unwrap();
+    let y = per_channel.forward(&x).unwrap();
+    let rows = tensor(x.data().to_vec(), &[6, 4]);
+    let ln = LayerNorm::new(&[4], backend.clone()).unwrap();
+    assert_close(y.data(), ln.forward(&rows).unwrap().data(), 1e-4);
+
+    assert!(GroupNorm::new(2, 3, backend.clone()).is_err());
+    assert!(LayerNorm::new(&[5], backend).unwrap().forward(&x).is_err());
+}
+
+#[test]
+fn norm_layers_are_fused_and_pass_gradient_checks() {
+    let backend = Arc::new(CpuBackend::new());
+    for groups in [1, 2, 3] {
+        let op = norm::GroupNorm::new(groups, 1e-5);
+        let report = check_op(&op, backend.clone(), &HarnessConfig::default()).unwrap();
+        assert!(report.passed(), "groups {}: {:?}", groups, report.failures);
+    }
+    for op in [
+        Box::new(norm::LayerNorm::default()) as Box<dyn dl_core::ops::Op>,
+        Box::new(norm::RMSNorm::default()),
+    ] {
+        let report = check_op(op.as_ref(), backend.clone(), &HarnessConfig::default()).unwrap();
+        assert!(report.passed(), "{}: {:?}", op.name(), report.failures);
+    }
+
+    let modules: Vec<(Box<dyn Module>, Vec<usize>)> = vec![
+        (
+            Box::new(LayerNorm::new(&[5], backend.clone()).unwrap()),
+            vec![3, 5],
+        ),
+        (
+            Box::new(LayerNorm::new(&[2, 3], backend.clone()).unwrap()),
+            vec![2, 2, 3],
+        ),
+        (
+            Box::new(RMSNorm::new(&[4], backend.clone()).unwrap()),
+            vec![2, 3, 4],
+        ),
+        (
+            Box::new(GroupNorm::new(2, 4, backend.clone()).unwrap()),
+            vec![2, 4, 2, 2],
+        ),
+    ];
+    for (i, (module, dims)) in modules.iter().enumerate() {
+        let n: usize = dims.iter().product();
+        let x = tensor(
+            (0..n).map(|j| ((j * 7) % n) as f32 * 0.2 - 1.0).collect(),
+            dims,
+        );
+        // Input leaf, parameter leaves and a single fused node.
+        let mut g = Graph::new();
+        let x_id = g.var(x.clone());
+        let (_, params) = module.forward_graph(&mut g, x_id).unwrap();
+        assert_eq!(g.len(), 2 + params.len(), "module {}", i);
+
+        check_module_gradients(module.as_ref(), &x)
+            .unwrap_or_else(|e| panic!("module {}: {}", i, e));
+    }
+}
+
+#[test]
+fn norms_of_empty_inputs_are_empty() {
+    let backend = Arc::new(CpuBackend::new());
+    let gn = GroupNorm::new(2, 4, backend.clone()).unwrap();
+    for dims in [[2, 4, 0], [0, 4, 3]] {
+        let x = tensor(vec![], &dims);
+        assert_eq!(gn.forward(&x).unwrap().shape().dims(), dims);
+    }
+    let ln = LayerNorm::new(&[4], backend.clone()).unwrap();
+    assert_eq!(ln.forward(&tensor(vec![], &[0, 4])).unwrap().numel(), 0);
+
+    // Backward gives an empty input gradient and zero parameter gradients.
+    let x = tensor(vec![], &[2, 4, 0]);
+    let (w, b) = (tensor(vec![1.0; 4], &[4]), tensor(vec![0.0; 4], &[4]));
+    let op = norm::GroupNorm::new(2, 1e-5);
+    let y = op.forward(&[&x, &w, &b]).unwrap();
+    let grads = op.backward(&y, &[&x, &w, &b], &y).unwrap();
+    assert_eq!(grads[0].shape().dims(), [2, 4, 0]);
+    assert_eq!(grads[1].data(), vec![0.0; 4]);
+}
+
+#[test]
+fn deep_mlp_with_layer_norm_trains() {
+    set_seed(11);
+    let backend = Arc::new(CpuBackend::new());
+    let net = MLPBuilder::new(4, 1)
+        .hidden(&[16; 6])
+        .norm(|f, b| Ok(Box::new(LayerNorm::new(&[f], b)?)))
+        .xavier()
+        .build(backend.clone())
+        .unwrap();
+    let x = tensor(
+        (0..64)
+            .map(|i| ((i * 13) % 64) as f32 / 32.0 - 1.0)
+            .collect(),
+        &[16, 4],
+    );
+    let targets: Vec<f32> = x
+        .data()
+        .chunks(4)
+        .map(|r| if r[0] * r[1] > 0.0 { 1.0 } else { -1.0 })
+        .collect();
+    let t = tensor(targets, &[16, 1]);
+
+    let mut trainer = Trainer::new(net, SGD::new(0.05));
+    let initial = trainer.step_batch(backend.clone(), &x, &t).unwrap().loss;
+    let mut loss = initial;
+    for _ in 0..300 {
+        loss = trainer.step_batch(backend.clone(), &x, &t).unwrap().loss;
+    }
+    assert!(loss < initial * 0.2, "loss {} -> {}", initial, loss);
+}
@@ -0,0 +1,143 @@
+//! Op-conformance harness: every registered op passes a gradient check, including custom ops.
+
+use dl_core::autograd::check::{check_op, check_registry, HarnessConfig};
+use dl_core::ops::{GradCheckSpec, Op, OpError, OpId, OpRegistry, OpResult};
+use dl_core::{CpuBackend, Tensor};
+use std::sync::Arc;
+
+/// Custom op: x^2 with an optionally wrong backward.
+struct Square {
+    broken: bool,
+}
+
+impl Op for Square {
+    fn id(&self) -> OpId {
+        OpId::Custom("Square")
+    }
+
+    fn name(&self) -> &'static str {
+        "Square"
+    }
+
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        Some(GradCheckSpec::new(vec![vec![vec![2, 3]], vec![vec![1]]]))
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        inputs[0].mul(inputs[0]).map_err(|e| OpError(e.to_string()))
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        let factor = if self.broken { 3.0 } else { 2.0 };
+        let g = grad_out
+            .mul(inputs[0])
+            .and_then(|t| t.scale(factor))
+            .map_err(|e| OpError(e.to_string()))?;
+        Ok(vec![g])
+    }
+}
+
+#[test]
+fn test_builtin_ops_conform() {
+    let reports = check_registry(
+        &OpRegistry::new(),
+        Arc::new(CpuBackend::new()),
+        &HarnessConfig::default(),
+    );
+    assert!(
+        reports.len() >= 13,
+        "expected all built-in ops, got {}",
+        reports.len()
+    );
+    for r in &reports {
+        eprintln!(
+            "  {:<14} cases={} max_abs={:.2e} max_rel={:.2e}",
+            r.op, r.cases, r.max_abs_err, r.max_rel_err
+        );
+        assert!(r.passed(), "{} failed: {:?}", r.op, r.failures);
+    }
+}
+
+#[test]
+fn test_custom_op_is_checked() {
+    let mut registry = OpRegistry::new();
+    registry.register(Arc::new(Square { broken: false }));
+    let reports = check_registry(
+        &registry,
+        Arc::new(CpuBackend::new()),
+        &HarnessConfig::default(),
+    );
+    let square = reports
+        .iter()
+        .find(|r| r.op == "Square")
+        .expect("custom op checked");
+    assert!(square.passed(), "{:?}", square.failures);
+
+    let broken = check_op(
+        &Square { broken: true },
+        Arc::new(CpuBackend::new()),
+        &HarnessConfig::default(),
+    )
+    .unwrap();
+    assert!(!broken.passed());
+    assert!(broken.max_rel_err > 0.1);
+}
+
+/// Identity op whose spec is given at construction.
+struct NoSpec(Option<GradCheckSpec>);
+
+impl Op for NoSpec {
+    fn id(&self) -> OpId {
+        OpId::Custom("NoSpec")
+    }
+
+    fn name(&self) -> &'static str {
+        "NoSpec"
+    }
+
+    fn grad_check_spec(&self) -> Option<GradCheckSpec> {
+        self.0.clone()
+    }
+
+    fn forward(&self, inputs: &[&Tensor]) -> OpResult<Tensor> {
+        Ok(inputs[0].clone())
+    }
+
+    fn backward(
+        &self,
+        grad_out: &Tensor,
+        _inputs: &[&Tensor],
+        _fwd_output: &Tensor,
+    ) -> OpResult<Vec<Tensor>> {
+        Ok(vec![grad_out.clone()])
+    }
+}
+
+#[test]
+fn test_missing_spec_fails_and_size_one_cases_are_generated() {
+    let backend = Arc::new(CpuBackend::new());
+    let config = HarnessConfig::default();
+    let mut registry = OpRegistry::new();
+    registry.register(Arc::new(NoSpec(None)));
+    let reports = check_registry(&registry, backend.clone(), &config);
+    let missing = reports
+        .iter()
+        .find(|r| r.op == "NoSpec")
+        .expect("op without spec reported");
+    assert!(!missing.passed());
+
+    registry.register(Arc::new(NoSpec(Some(GradCheckSpec::skip("identity")))));
+    let reports = check_registry(&registry, backend.clone(), &config);
+    let skipped = reports.iter().find(|r| r.op == "NoSpec").unwrap();
+    assert!(skipped.passed());
+    assert_eq!(skipped.skipped.as_deref(), Some("identity"));
+
+    // [2, 3] also runs as [1, 3] and [2, 1].
+    let square = check_op(&Square { broken: false }, backend, &config).unwrap();
+    assert_eq!(square.cases, 4);
+}
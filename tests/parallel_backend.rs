@@ -0,0 +1,84 @@
+//! ParallelCpuBackend matches CpuBackend and is deterministic for a fixed thread count.
+
+use dl_core::backend::Activation;
+use dl_core::{set_seed, with_rng, Backend, CpuBackend, ParallelCpuBackend, Shape, Tensor};
+use rand::Rng;
+use std::sync::Arc;
+
+fn random(dims: &[usize]) -> Vec<f32> {
+    let n: usize = dims.iter().product();
+    with_rng(|rng| (0..n).map(|_| rng.gen_range(-1.0f32..1.0)).collect())
+}
+
+#[test]
+fn parallel_matches_cpu_bitwise() {
+    set_seed(7);
+    let cpu: Arc<dyn Backend> = Arc::new(CpuBackend::new());
+    let par: Arc<dyn Backend> = Arc::new(ParallelCpuBackend::new(4).with_min_parallel_work(0));
+    let (a, b, bias) = (random(&[33, 17]), random(&[17, 9]), random(&[9]));
+    let t = |be: &Arc<dyn Backend>, d: &[f32], dims: &[usize]| {
+        Tensor::from_vec(d.to_vec(), Shape::new(dims.to_vec()), be.clone()).unwrap()
+    };
+    let run = |be: &Arc<dyn Backend>| {
+        let (a, b, bias) = (
+            t(be, &a, &[33, 17]),
+            t(be, &b, &[17, 9]),
+            t(be, &bias, &[9]),
+        );
+        let mm = be.matmul(&a, &b).unwrap();
+        vec![
+            mm.data().to_vec(),
+            be.linear(&a, &b, &bias, Activation::ReLU)
+                .unwrap()
+                .data()
+                .to_vec(),
+            be.softmax_last_dim(&mm).unwrap().data().to_vec(),
+            be.sum_dim(&a, 0).unwrap().data().to_vec(),
+            be.transpose(&a).unwrap().data().to_vec(),
+            be.sigmoid(&a).unwrap().data().to_vec(),
+        ]
+    };
+    assert_eq!(run(&cpu), run(&par));
+}
+
+#[test]
+fn parallel_sum_is_deterministic() {
+    set_seed(3);
+    let data = random(&[10_001]);
+    let sum = |threads| {
+        let be: Arc<dyn Backend> =
+            Arc::new(ParallelCpuBackend::new(threads).with_min_parallel_work(0));
+        let x = Tensor::from_vec(data.clone(), Shape::new(vec![data.len()]), be.clone()).unwrap();
+        let s = be.sum(&x).unwrap();
+        s.data()[0]
+    };
+    let first = sum(4);
+    for _ in 0..5 {
+        assert_eq!(sum(4).to_bits(), first.to_bits());
+    }
+    let exact: f64 = data.iter().map(|&v| v as f64).sum();
+    assert!((first as f64 - exact).abs() < 1e-3);
+}
+
+#[test]
+fn parallel_pool_is_shared_by_clones_and_reused() {
+    set_seed(5);
+    let (a, b) = (random(&[40, 12]), random(&[12, 7]));
+    let cpu: Arc<dyn Backend> = Arc::new(CpuBackend::new());
+    let par = ParallelCpuBackend::new(3).with_min_parallel_work(0);
+    let t = |be: &Arc<dyn Backend>, d: &[f32], dims: &[usize]| {
+        Tensor::from_vec(d.to_vec(), Shape::new(dims.to_vec()), be.clone()).unwrap()
+    };
+    let mm = |be: &Arc<dyn Backend>| {
+        be.matmul(&t(be, &a, &[40, 12]), &t(be, &b, &[12, 7]))
+            .unwrap()
+            .data()
+            .to_vec()
+    };
+    let expected = mm(&cpu);
+    for _ in 0..50 {
+        let clone: Arc<dyn Backend> = Arc::new(par.clone());
+        assert_eq!(mm(&clone), expected);
+    }
+    drop(par);
+}
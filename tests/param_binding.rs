@@ -0,0 +1,129 @@
+//! Parameter identity binding: gradients reach the right Parameter regardless of order,
+//! a parameter used twice accumulates both contributions, and clones are independent.
+
+use dl_core::autograd::{Graph, NodeId};
+use dl_core::nn::{mse_graph, Module};
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{CpuBackend, GraphResult, Parameter, Shape, Tensor, TensorResult};
+use std::sync::Arc;
+
+/// y = (x @ w) * s, but parameters() lists (s, w) while forward_graph uses (w, s).
+struct Reordered {
+    w: Parameter,
+    s: Parameter,
+}
+
+impl Module for Reordered {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![&self.s, &self.w]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![&mut self.s, &mut self.w]
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        x.matmul(self.w.data())?.mul(self.s.data())
+    }
+
+    fn forward_graph(&self, g: &mut Graph, x_id: NodeId) -> GraphResult<(NodeId, Vec<NodeId>)> {
+        let w = g.param(&self.w);
+        let s = g.param(&self.s);
+        let h = g.matmul(x_id, w)?;
+        let out = g.mul(h, s)?;
+        Ok((out, vec![w, s]))
+    }
+}
+
+fn t(data: Vec<f32>, dims: Vec<usize>, b: &Arc<CpuBackend>) -> Tensor {
+    Tensor::from_vec(data, Shape::new(dims), b.clone()).unwrap()
+}
+
+#[test]
+fn test_grads_follow_identity_not_order() {
+    let b = Arc::new(CpuBackend::new());
+    let model = Reordered {
+        w: Parameter::new(t(vec![1.0, 2.0], vec![2, 1], &b)),
+        s: Parameter::new(t(vec![3.0], vec![1, 1], &b)),
+    };
+    let x = t(vec![1.0, 1.0], vec![1, 2], &b);
+    let y = t(vec![0.0], vec![1, 1], &b);
+    let mut trainer = Trainer::new(model, SGD::new(0.0));
+    trainer.step_batch(b.clone(), &x, &y).unwrap();
+    // pred = 3 * 3 = 9, dL/dpred = 18; dL/ds = 18 * 3 = 54; dL/dw = 18 * 3 * x = [54, 54].
+    let gs = trainer.model.s.grad().unwrap().data().to_vec();
+    let gw = trainer.model.w.grad().unwrap().data().to_vec();
+    assert_eq!(gs, vec![54.0]);
+    assert_eq!(gw, vec![54.0, 54.0]);
+}
+
+#[test]
+fn test_shared_parameter_accumulates() {
+    let b = Arc::new(CpuBackend::new());
+    let mut w = Parameter::new(t(vec![2.0], vec![1, 1], &b));
+    let x = t(vec![3.0], vec![1, 1], &b);
+    let mut g = Graph::new();
+    let x_id = g.var(x);
+    let w1 = g.param(&w);
+    let w2 = g.param(&w);
+    assert_eq!(w1, w2);
+    // y = x @ w @ w -> dy/dw = 2 * x * w = 12.
+    let h = g.matmul(x_id, w1).unwrap();
+    let y = g.matmul(h, w2).unwrap();
+    let target = t(vec![0.0], vec![1, 1], &b);
+    let loss = mse_graph(&mut g, y, &target).unwrap();
+    g.backward(loss).unwrap();
+    // loss = y^2, dloss/dw = 2 * y * 12 = 2 * 12 * 12.
+    g.write_grads(&mut [&mut w]).unwrap();
+    assert_eq!(w.grad().unwrap().data(), &[288.0]);
+}
+
+/// y = x @ w with w entered as a plain var (the positional contract of forward_graph).
+struct VarBound {
+    w: Parameter,
+}
+
+impl Module for VarBound {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![&self.w]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![&mut self.w]
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        x.matmul(self.w.data())
+    }
+
+    fn forward_graph(&self, g: &mut Graph, x_id: NodeId) -> GraphResult<(NodeId, Vec<NodeId>)> {
+        let w = g.var(self.w.data().clone());
+        Ok((g.matmul(x_id, w)?, vec![w]))
+    }
+}
+
+#[test]
+fn test_var_bound_weights_and_clones() {
+    let b = Arc::new(CpuBackend::new());
+    // Weights bound with var get their gradient by position in the returned nodes.
+    let model = VarBound {
+        w: Parameter::new(t(vec![1.0, 2.0], vec![2, 1], &b)),
+    };
+    let x = t(vec![1.0, 1.0], vec![1, 2], &b);
+    let y = t(vec![0.0], vec![1, 1], &b);
+    let mut trainer = Trainer::new(model, SGD::new(0.0));
+    trainer.step_batch(b.clone(), &x, &y).unwrap();
+    assert_eq!(trainer.model.w.grad().unwrap().data(), &[6.0, 6.0]);
+
+    // A clone is an independent parameter; share() ties.
+    let w = Parameter::new(t(vec![2.0], vec![1, 1], &b));
+    let copy = w.clone();
+    let tied = w.share();
+    assert_ne!(copy.id(), w.id());
+    assert_eq!(tied.id(), w.id());
+    let mut g = Graph::new();
+    let node = g.param(&w);
+    assert_ne!(g.param(&copy), node);
+    assert_eq!(g.param(&tied), node);
+}
@@ -0,0 +1,181 @@
+//! Compiled plans: dead-code elimination, CSE, linear fusion, and plan-driven training.
+
+use dl_core::autograd::{CompileOptions, Graph, NodeId, Plan};
+use dl_core::nn::{mse_graph, BatchNorm1d, Dropout, Embedding, Linear, Sequential};
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{
+    set_seed, CpuBackend, GraphResult, Module, Parameter, Shape, Tensor, TensorResult, MLP2,
+};
+use std::sync::Arc;
+
+fn batch(backend: &Arc<CpuBackend>) -> (Tensor, Tensor) {
+    let x = Tensor::from_vec(
+        vec![0.5, -1.0, 0.25, 2.0, -0.3, 0.8, 1.2, 0.1],
+        Shape::new(vec![4, 2]),
+        backend.clone(),
+    )
+    .unwrap();
+    let y = Tensor::from_vec(
+        vec![1.0, 0.0, -1.0, 0.5],
+        Shape::new(vec![4, 1]),
+        backend.clone(),
+    )
+    .unwrap();
+    (x, y)
+}
+
+#[test]
+fn test_plan_dce_and_cse() {
+    let b = Arc::new(CpuBackend::new());
+    let (x, _) = batch(&b);
+    let mut g = Graph::new();
+    let x_id = g.var(x.clone());
+    let r1 = g.relu(x_id).unwrap();
+    let r2 = g.relu(x_id).unwrap();
+    let sum = g.add(r1, r2).unwrap();
+    let _unused = g.sigmoid(x_id).unwrap();
+
+    let options = CompileOptions {
+        cse: false,
+        fuse: false,
+    };
+    let plain = Plan::compile_with(&g, &[x_id], &[sum], options).unwrap();
+    assert_eq!(plain.op_names(), vec!["ReLU", "ReLU", "Add"]);
+    let plan = Plan::compile(&g, &[x_id], &[sum]).unwrap();
+    assert_eq!(plan.op_names(), vec!["ReLU", "Add"]);
+
+    let out = plan.run(&[x]).unwrap();
+    assert_eq!(out[0].data(), g.data(sum).unwrap().data());
+}
+
+#[test]
+fn test_plan_fuses_linear_and_matches_graph() {
+    set_seed(3);
+    let b = Arc::new(CpuBackend::new());
+    let mut model = MLP2::new(2, 5, 1, b.clone()).unwrap();
+    model.init_xavier().unwrap();
+    let (x, y) = batch(&b);
+
+    let mut g = Graph::new();
+    let x_id = g.var(x.clone());
+    let (out, param_ids) = model.forward_graph(&mut g, x_id).unwrap();
+    let loss = mse_graph(&mut g, out, &y).unwrap();
+    let mut inputs = vec![x_id];
+    inputs.extend(param_ids.iter().copied());
+    let plan = Plan::compile(&g, &inputs, &[loss]).unwrap();
+    let names = plan.op_names();
+    assert_eq!(&names[..2], &["LinearReLU", "Linear"]);
+
+    let mut values = vec![x];
+    values.extend(model.parameters().iter().map(|p| p.data().clone()));
+    let grads = plan.backward(&values, 0, None).unwrap();
+    g.backward(loss).unwrap();
+    let l0 = g.data(loss).unwrap().data()[0];
+    assert!((grads.outputs[0].data()[0] - l0).abs() < 1e-6);
+    for (k, &id) in param_ids.iter().enumerate() {
+        let expected = g.grad(id).unwrap().unwrap().data().to_vec();
+        let got = grads.input_grads[k + 1].as_ref().unwrap().data().to_vec();
+        for (a, e) in got.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1e-5, "param {} grad {} vs {}", k, a, e);
+        }
+    }
+}
+
+#[test]
+fn test_step_planned_matches_step_batch() {
+    let b = Arc::new(CpuBackend::new());
+    let (x, y) = batch(&b);
+    let make = || {
+        set_seed(11);
+        let mut m = MLP2::new(2, 4, 1, b.clone()).unwrap();
+        m.init_xavier().unwrap();
+        Trainer::new(m, SGD::new(0.05))
+    };
+    let mut graph_trainer = make();
+    let mut plan_trainer = make();
+    let plan = plan_trainer.compile_step_batch(&x, &y).unwrap();
+    for _ in 0..20 {
+        let lg = graph_trainer.step_batch(b.clone(), &x, &y).unwrap().loss;
+        let lp = plan_trainer.step_planned(&plan, &x, &y).unwrap().loss;
+        assert!((lg - lp).abs() < 1e-5, "graph {} vs plan {}", lg, lp);
+    }
+}
+
+/// y = x @ w with w entered as a plain var (bound by position, not by id).
+struct VarBound {
+    w: Parameter,
+}
+
+impl Module for VarBound {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![&self.w]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![&mut self.w]
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        x.matmul(self.w.data())
+    }
+
+    fn forward_graph(&self, g: &mut Graph, x_id: NodeId) -> GraphResult<(NodeId, Vec<NodeId>)> {
+        let w = g.var(self.w.data().clone());
+        Ok((g.matmul(x_id, w)?, vec![w]))
+    }
+}
+
+#[test]
+fn test_step_planned_binds_var_weights_and_rejects_row_params() {
+    let b = Arc::new(CpuBackend::new());
+    let (x, y) = batch(&b);
+    let w = || {
+        let data = Tensor::from_vec(vec![0.5, -0.5], Shape::new(vec![2, 1]), b.clone());
+        Parameter::new(data.unwrap())
+    };
+    let mut graph_trainer = Trainer::new(VarBound { w: w() }, SGD::new(0.05));
+    let mut plan_trainer = Trainer::new(VarBound { w: w() }, SGD::new(0.05));
+    let plan = plan_trainer.compile_step_batch(&x, &y).unwrap();
+    for _ in 0..5 {
+        let lg = graph_trainer.step_batch(b.clone(), &x, &y).unwrap().loss;
+        let lp = plan_trainer.step_planned(&plan, &x, &y).unwrap().loss;
+        assert!((lg - lp).abs() < 1e-6, "graph {} vs plan {}", lg, lp);
+    }
+    assert_eq!(
+        graph_trainer.model.w.grad().unwrap().data(),
+        plan_trainer.model.w.grad().unwrap().data()
+    );
+
+    // Embedding lookups bind rows fixed at trace time, so planning them is an error.
+    let emb = Embedding::new(5, 1, b.clone()).unwrap();
+    let ids = Tensor::from_vec(vec![0.0, 3.0, 1.0, 3.0], Shape::new(vec![4]), b.clone()).unwrap();
+    let target = Tensor::from_vec(vec![0.0; 4], Shape::new(vec![4, 1]), b.clone()).unwrap();
+    match Trainer::new(emb, SGD::new(0.05)).compile_step_batch(&ids, &target) {
+        Err(e) => assert!(e.to_string().contains("bound by rows"), "{}", e),
+        Ok(_) => panic!("embedding plan compiled"),
+    }
+}
+
+#[test]
+fn test_compile_step_batch_rejects_training_mode_layers() {
+    let b = Arc::new(CpuBackend::new());
+    let (x, y) = batch(&b);
+    let model = Sequential::new()
+        .with(Linear::new(2, 1, b.clone()).unwrap())
+        .with(Dropout::new(0.5).unwrap());
+    let mut trainer = Trainer::new(model, SGD::new(0.05));
+    match trainer.compile_step_batch(&x, &y) {
+        Err(e) => assert!(e.to_string().contains("not deterministic"), "{}", e),
+        Ok(_) => panic!("training-mode dropout compiled"),
+    }
+    trainer.model.eval();
+    assert!(trainer.compile_step_batch(&x, &y).is_ok());
+
+    // Rejected before tracing, so the running statistics are left alone.
+    let mut trainer = Trainer::new(BatchNorm1d::new(1, b.clone()).unwrap(), SGD::new(0.05));
+    assert!(trainer.compile_step_batch(&y, &y).is_err());
+    assert_eq!(trainer.model.running_mean().data(), vec![0.0]);
+    trainer.model.eval();
+    assert!(trainer.compile_step_batch(&y, &y).is_ok());
+}
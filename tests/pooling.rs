@@ -0,0 +1,152 @@
+//! Pooling layers: values, argmax gradient routing, check_gradients for every module and a
+//! conv -> pool -> linear network that trains.
+
+mod common;
+
+use common::{check_module_gradients, tensor};
+use dl_core::autograd::check::{check_op, HarnessConfig};
+use dl_core::nn::{
+    AdaptiveAvgPool2d, AvgPool1d, AvgPool2d, GlobalAvgPool, GlobalMaxPool, MaxPool1d, MaxPool2d,
+    Module,
+};
+use dl_core::ops::pool::{Pool, PoolMode, PoolWindow};
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{
+    set_seed, Conv2d, ConvGeometry, CpuBackend, Graph, Linear, ReLU, Sequential, Shape, Tensor,
+};
+use std::sync::Arc;
+
+/// Distinct values (spaced well beyond the check step) in a scrambled order.
+fn scrambled(dims: &[usize]) -> Tensor {
+    let n: usize = dims.iter().product();
+    tensor(
+        (0..n).map(|i| ((i * 37) % n) as f32 * 0.1 - 1.0).collect(),
+        dims,
+    )
+}
+
+#[test]
+fn max_and_avg_pool_values_and_routing() {
+    #[rustfmt::skip]
+    let x = tensor(vec![
+        1.0, 5.0, 2.0, 0.0,
+        3.0, 4.0, 8.0, 1.0,
+        0.0, 2.0, 6.0, 7.0,
+        9.0, 1.0, 3.0, 5.0,
+    ], &[1, 1, 4, 4]);
+    let max = MaxPool2d::new((2, 2));
+    assert_eq!(max.forward(&x).unwrap().data(), [5.0, 8.0, 9.0, 7.0]);
+    let avg = AvgPool2d::new((2, 2));
+    assert_eq!(avg.forward(&x).unwrap().data(), [3.25, 2.75, 3.0, 5.25]);
+    // Padding counts as zeros in the average: corner window sees only x[0][0].
+    let padded = AvgPool2d::new((2, 2)).with_padding((1, 1));
+    assert_eq!(padded.forward(&x).unwrap().data()[0], 0.25);
+
+    let mut g = Graph::new();
+    let x_id = g.var(x.clone());
+    let (y, _) = max.forward_graph(&mut g, x_id).unwrap();
+    let loss = g.sum(y).unwrap();
+    g.backward(loss).unwrap();
+    #[rustfmt::skip]
+    let want = [
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0,
+        1.0, 0.0, 0.0, 0.0,
+    ];
+    assert_eq!(g.grad(x_id).unwrap().unwrap().data(), want);
+
+    let global = GlobalAvgPool::new().forward(&x).unwrap();
+    assert_eq!(global.shape().dims(), [1, 1]);
+    let adaptive = AdaptiveAvgPool2d::new((1, 1)).forward(&x).unwrap();
+    assert_eq!(adaptive.shape().dims(), [1, 1, 1, 1]);
+    assert_eq!(global.data(), adaptive.data());
+}
+
+#[test]
+fn pooling_modules_pass_check_gradients() {
+    let modules: Vec<(Box<dyn Module>, Vec<usize>)> = vec![
+        (Box::new(MaxPool2d::new((2, 2))), vec![2, 2, 4, 6]),
+        (
+            Box::new(
+                MaxPool2d::new((3, 3))
+                    .with_stride((2, 1))
+                    .with_padding((1, 1)),
+            ),
+            vec![1, 2, 5, 5],
+        ),
+        (
+            Box::new(
+                AvgPool2d::new((3, 2))
+                    .with_stride((1, 2))
+                    .with_padding((1, 0)),
+            ),
+            vec![2, 1, 4, 6],
+        ),
+        (Box::new(MaxPool1d::new(3).with_stride(2)), vec![2, 3, 9]),
+        (Box::new(AvgPool1d::new(2).with_padding(1)), vec![1, 2, 7]),
+        (Box::new(AdaptiveAvgPool2d::new((2, 3))), vec![2, 2, 5, 7]),
+        (Box::new(GlobalAvgPool::new()), vec![2, 3, 4, 5]),
+        (Box::new(GlobalMaxPool::new()), vec![2, 3, 6]),
+    ];
+    for (i, (module, dims)) in modules.iter().enumerate() {
+        check_module_gradients(module.as_ref(), &scrambled(dims))
+            .unwrap_or_else(|e| panic!("module {}: {}", i, e));
+    }
+
+    let windows = [
+        PoolWindow::Fixed(ConvGeometry::new((2, 2)).with_stride((2, 2))),
+        PoolWindow::Fixed(ConvGeometry::conv1d(3, 2, 1, 1)),
+        PoolWindow::Adaptive((2, 4)),
+        PoolWindow::Global,
+    ];
+    for mode in [PoolMode::Max, PoolMode::Avg] {
+        for window in windows {
+            let backend = Arc::new(CpuBackend::new());
+            let report = check_op(&Pool::new(mode, window), backend, &HarnessConfig::default());
+            let report = report.unwrap();
+            assert!(
+                report.passed(),
+                "{:?} {:?}: {:?}",
+                mode,
+                window,
+                report.failures
+            );
+        }
+    }
+}
+
+#[test]
+fn conv_pool_linear_network_trains() {
+    set_seed(8);
+    let backend = Arc::new(CpuBackend::new());
+    let mut conv = Conv2d::new(1, 4, (3, 3), backend.clone()).unwrap();
+    conv.init_he().unwrap();
+    let mut head = Linear::new(4, 2, backend.clone()).unwrap();
+    head.init_xavier().unwrap();
+    let net = Sequential::new()
+        .with(conv)
+        .with(ReLU::new())
+        .with(MaxPool2d::new((2, 2)))
+        .with(GlobalAvgPool::new())
+        .with(head);
+
+    // Bright top half vs bright bottom half.
+    let images: Vec<f32> = (0..8)
+        .flat_map(|s| (0..36).map(move |p| if (p < 18) == (s % 2 == 0) { 1.0 } else { 0.0 }))
+        .collect();
+    let x = Tensor::from_vec(images, Shape::new(vec![8, 1, 6, 6]), backend.clone()).unwrap();
+    let targets: Vec<f32> = (0..8)
+        .flat_map(|s| if s % 2 == 0 { [1.0, 0.0] } else { [0.0, 1.0] })
+        .collect();
+    let t = Tensor::from_vec(targets, Shape::new(vec![8, 2]), backend.clone()).unwrap();
+
+    let mut trainer = Trainer::new(net, SGD::new(0.1));
+    let initial = trainer.step_batch(backend.clone(), &x, &t).unwrap().loss;
+    let mut loss = initial;
+    for _ in 0..200 {
+        loss = trainer.step_batch(backend.clone(), &x, &t).unwrap().loss;
+    }
+    assert!(loss < initial * 0.5, "loss {} -> {}", initial, loss);
+}
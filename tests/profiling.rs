@@ -0,0 +1,79 @@
+//! ProfilingBackend records per-method and per-graph-op statistics during training and
+//! exports a Chrome trace.
+
+use dl_core::backend::profile::{op_scope, Phase, ProfileEntry};
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{Backend, CpuBackend, Linear, ProfilingBackend, Shape, Tensor};
+use std::sync::Arc;
+
+#[test]
+fn profiles_training_steps_by_method_and_op() {
+    let prof = Arc::new(ProfilingBackend::new(CpuBackend::new()));
+    let backend: Arc<dyn Backend> = prof.clone();
+    let model = Linear::new(3, 2, backend.clone()).unwrap();
+    let mut trainer = Trainer::new(model, SGD::new(0.1));
+    let x = Tensor::from_vec(vec![0.5; 12], Shape::new(vec![4, 3]), backend.clone()).unwrap();
+    let t = Tensor::from_vec(vec![1.0; 8], Shape::new(vec![4, 2]), backend.clone()).unwrap();
+    prof.reset();
+    for _ in 0..2 {
+        trainer.step_batch(backend.clone(), &x, &t).unwrap();
+    }
+
+    let methods = prof.by_method();
+    let matmul = methods.iter().find(|e| e.name == "matmul").unwrap();
+    // Per step: forward x@w (48 FLOPs), backward grad@w^T (48) and x^T@grad (48).
+    assert_eq!(matmul.calls, 6);
+    assert_eq!(matmul.flops, 6 * 48);
+    assert_eq!(matmul.bytes, 2 * (8 + 12 + 6) * 4);
+
+    let ops = prof.by_op();
+    for name in ["MatMul", "MatMul.backward", "AddBroadcast"] {
+        assert!(
+            ops.iter().any(|e| e.name == name && e.calls > 0),
+            "missing {}",
+            name
+        );
+    }
+    assert!(prof.summary().contains("MatMul.backward"));
+
+    let trace: serde_json::Value = serde_json::from_str(&prof.chrome_trace()).unwrap();
+    let events = trace["traceEvents"].as_array().unwrap();
+    let total: u64 = methods.iter().map(|e| e.calls).sum();
+    assert_eq!(events.len() as u64, total);
+    assert_eq!(events[0]["ph"], "X");
+}
+
+#[test]
+fn caps_trace_events_and_restores_scope_after_panic() {
+    let prof = Arc::new(ProfilingBackend::new(CpuBackend::new()).with_max_events(2));
+    let backend: Arc<dyn Backend> = prof.clone();
+    let x = Tensor::from_vec(vec![1.0; 4], Shape::new(vec![2, 2]), backend.clone()).unwrap();
+    prof.reset();
+
+    // A panicking op must not leave later calls attributed to it.
+    let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        op_scope("Exploding", Phase::Forward, || {
+            x.add(&x).unwrap();
+            panic!("op failed");
+        })
+    }));
+    assert!(caught.is_err());
+    for _ in 0..3 {
+        x.add(&x).unwrap();
+    }
+
+    let calls = |entries: Vec<ProfileEntry>, name: &str| {
+        entries
+            .into_iter()
+            .find(|e| e.name == name)
+            .map_or(0, |e| e.calls)
+    };
+    assert_eq!(calls(prof.by_method(), "add"), 4);
+    assert_eq!(calls(prof.by_op(), "Exploding"), 1);
+    assert_eq!(calls(prof.by_op(), "(outside graph)"), 3);
+
+    let trace: serde_json::Value = serde_json::from_str(&prof.chrome_trace()).unwrap();
+    assert_eq!(trace["traceEvents"].as_array().unwrap().len(), 2);
+    assert_eq!(prof.dropped_events(), 2);
+}
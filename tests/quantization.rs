@@ -0,0 +1,83 @@
+//! Post-training int8 quantisation: calibrated MLP2 stays close to the f32 model.
+
+use dl_core::backend::gemm::{igemm, IGEMM_MAX_K};
+use dl_core::data::{DataLoader, InMemoryDataset};
+use dl_core::quant::{compare, QParams, QuantizedLinear, QuantizedModule};
+use dl_core::{set_seed, CpuBackend, Linear, QuantizedMLP2, Shape, Tensor, MLP2};
+use std::sync::Arc;
+
+#[test]
+fn igemm_accumulates_exactly_in_i32() {
+    let (m, k, n) = (5, 300, 7);
+    let a: Vec<i8> = (0..m * k).map(|i| (i * 37 % 256) as u8 as i8).collect();
+    let b_t: Vec<i8> = (0..n * k).map(|i| (i * 91 % 256) as u8 as i8).collect();
+    let mut c = vec![0; m * n];
+    igemm(m, k, n, &a, &b_t, &mut c);
+    for i in 0..m {
+        for j in 0..n {
+            let want: i32 = (0..k)
+                .map(|p| a[i * k + p] as i32 * b_t[j * k + p] as i32)
+                .sum();
+            assert_eq!(c[i * n + j], want);
+        }
+    }
+}
+
+#[test]
+fn quantized_mlp_matches_f32_model() {
+    set_seed(7);
+    let backend = Arc::new(CpuBackend::new());
+    let mut model = MLP2::new(16, 32, 4, backend.clone()).unwrap();
+    model.init_xavier().unwrap();
+    let samples = (0..64)
+        .map(|s| {
+            let x = (0..16)
+                .map(|i| ((s * 13 + i * 7) % 23) as f32 / 11.0 - 1.0)
+                .collect();
+            let x = Tensor::from_vec(x, Shape::new(vec![16]), backend.clone()).unwrap();
+            let y = Tensor::from_vec(vec![0.0; 4], Shape::new(vec![4]), backend.clone()).unwrap();
+            (x, y)
+        })
+        .collect();
+    let mut loader = DataLoader::new(InMemoryDataset::new(samples), 16);
+
+    let q = QuantizedMLP2::calibrate(&model, &mut loader).unwrap();
+    let report = compare(&model, &q, &mut loader).unwrap();
+    assert_eq!(report.samples, 64);
+    assert!(report.mean_abs_err < 0.02, "{}", report);
+    assert!(report.argmax_agreement >= 0.95, "{}", report);
+
+    // int8 weights plus per-channel params: under half the f32 bytes even at this size.
+    let f32_bytes = (16 * 32 + 32 + 32 * 4 + 4) * 4;
+    assert!(
+        q.size_bytes() * 2 < f32_bytes,
+        "{} vs {}",
+        q.size_bytes(),
+        f32_bytes
+    );
+
+    let x = Tensor::from_vec(vec![0.0; 16], Shape::new(vec![2, 8]), backend).unwrap();
+    assert!(q.forward(&x).is_err());
+}
+
+#[test]
+fn quantized_linear_bounds_in_features_and_corrects_in_i64() {
+    let backend = Arc::new(CpuBackend::new());
+    let too_wide = Linear::new(IGEMM_MAX_K + 1, 1, backend.clone()).unwrap();
+    let qp = QParams::from_range(0.0, 1.0).unwrap();
+    assert!(QuantizedLinear::new(&too_wide, qp).is_err());
+
+    // All-positive weights and inputs put both zero points at -128: every term fits in i32,
+    // but acc - zw * sum(xq) is about k * 255 * 127.
+    let k = IGEMM_MAX_K;
+    let mut linear = Linear::new(k, 1, backend.clone()).unwrap();
+    *linear.weight.data_mut() =
+        Tensor::from_vec(vec![0.5; k], Shape::new(vec![k, 1]), backend.clone()).unwrap();
+    *linear.bias.data_mut() =
+        Tensor::from_vec(vec![0.0], Shape::new(vec![1]), backend.clone()).unwrap();
+    let q = QuantizedLinear::new(&linear, QParams::from_range(0.0, 1.0).unwrap()).unwrap();
+    let x = Tensor::from_vec(vec![1.0; k], Shape::new(vec![1, k]), backend).unwrap();
+    let y = q.forward(&x).unwrap().data()[0];
+    let want = 0.5 * k as f32;
+    assert!((y - want).abs() / want < 1e-2, "{} vs {}", y, want);
+}
@@ -0,0 +1,240 @@
+//! RNN, LSTM and GRU: step values, output layouts, gradients through time, layout ops and
+//! truncated BPTT with carried state.
+
+mod common;
+
+use common::{assert_close, check_module_gradients, tensor};
+use dl_core::autograd::check::{check_op, HarnessConfig};
+use dl_core::nn::{Module, RecurrentOptions, RecurrentState, Sequential, GRU, LSTM, RNN};
+use dl_core::ops::view::{Concat, Narrow, Reshape};
+use dl_core::ops::Op;
+use dl_core::optimizer::Adam;
+use dl_core::train::Trainer;
+use dl_core::{set_seed, CpuBackend, Graph, Tensor};
+use std::sync::Arc;
+
+fn seq(dims: &[usize]) -> Tensor {
+    let n: usize = dims.iter().product();
+    tensor(
+        (0..n).map(|i| ((i * 7) % 11) as f32 * 0.2 - 1.0).collect(),
+        dims,
+    )
+}
+
+#[test]
+fn recurrent_values_and_layouts() {
+    let backend = Arc::new(CpuBackend::new());
+    // h1 = tanh(2 * 1), h2 = tanh(2 * -1 + 0.5 * h1).
+    let options = RecurrentOptions {
+        return_sequences: true,
+        ..Default::default()
+    };
+    let mut rnn = RNN::with_options(1, 1, options, backend.clone()).unwrap();
+    *rnn.cells_mut()[0].weight_ih.data_mut() = tensor(vec![2.0], &[1, 1]);
+    *rnn.cells_mut()[0].weight_hh.data_mut() = tensor(vec![0.5], &[1, 1]);
+    let (y, state) = rnn
+        .forward_state(&tensor(vec![1.0, -1.0], &[2, 1, 1]), None)
+        .unwrap();
+    let h1 = 2f32.tanh();
+    let h2 = (-2.0 + 0.5 * h1).tanh();
+    assert_close(y.data(), &[h1, h2], 1e-5);
+    assert_close(state.h[0].data(), &[h2], 1e-5);
+    assert!(state.c.is_empty());
+
+    // Output layouts and parameter names for stacked, bidirectional LSTMs.
+    set_seed(2);
+    let x = seq(&[5, 3, 4]);
+    for (layers, bidirectional, return_sequences, dims) in [
+        (1, false, false, vec![3, 6]),
+        (2, false, true, vec![5, 3, 6]),
+        (2, true, false, vec![3, 12]),
+        (1, true, true, vec![5, 3, 12]),
+    ] {
+        let options = RecurrentOptions {
+            num_layers: layers,
+            bidirectional,
+            return_sequences,
+        };
+        let mut lstm = LSTM::with_options(4, 6, options, backend.clone()).unwrap();
+        lstm.init_uniform().unwrap();
+        let y = lstm.forward(&x).unwrap();
+        assert_eq!(y.shape().dims(), &dims[..]);
+        let mut g = Graph::new();
+        let x_id = g.var(x.clone());
+        let (out, params) = lstm.forward_graph(&mut g, x_id).unwrap();
+        assert_eq!(g.data(out).unwrap().data(), y.data());
+        assert_eq!(params.len(), 4 * layers * if bidirectional { 2 } else { 1 });
+    }
+    let options = RecurrentOptions {
+        num_layers: 2,
+        bidirectional: true,
+        ..Default::default()
+    };
+    let gru = GRU::with_options(4, 6, options, backend.clone()).unwrap();
+    let names: Vec<String> = gru.named_parameters().into_iter().map(|(n, _)| n).collect();
+    assert_eq!(
+        names[..4],
+        ["weight_ih_l0", "weight_hh_l0", "bias_ih_l0", "bias_hh_l0"]
+    );
+    assert_eq!(names[4], "weight_ih_l0_reverse");
+    assert_eq!(names[15], "bias_hh_l1_reverse");
+    assert_eq!(gru.cells()[2].weight_ih.data().shape().dims(), &[12, 18]);
+
+    // A bad initial state or input is an error, not a panic.
+    let bad = RecurrentState {
+        h: vec![tensor(vec![0.0; 6], &[1, 6])],
+        c: vec![],
+    };
+    assert!(gru.forward_state(&x, Some(&bad)).is_err());
+    assert!(gru.forward(&seq(&[5, 3, 2])).is_err());
+}
+
+#[test]
+fn gradients_flow_through_time() {
+    let backend = Arc::new(CpuBackend::new());
+    let ops: Vec<Box<dyn Op>> = vec![
+        Box::new(Narrow::new(1, 1, 2)),
+        Box::new(Concat::new(0)),
+        Box::new(Concat::new(2)),
+        Box::new(Reshape::new(vec![3, 2])),
+    ];
+    for op in &ops {
+        let report = check_op(op.as_ref(), backend.clone(), &HarnessConfig::default()).unwrap();
+        assert!(report.passed(), "{}: {:?}", op.name(), report.failures);
+    }
+
+    set_seed(4);
+    let x = seq(&[4, 2, 3]);
+    let mut modules: Vec<Box<dyn Module>> = Vec::new();
+    let mut rnn = RNN::new(3, 4, backend.clone()).unwrap();
+    rnn.init_uniform().unwrap();
+    modules.push(Box::new(rnn));
+    let mut lstm = LSTM::with_options(
+        3,
+        4,
+        RecurrentOptions {
+            num_layers: 2,
+            return_sequences: true,
+            ..Default::default()
+        },
+        backend.clone(),
+    )
+    .unwrap();
+    lstm.init_uniform().unwrap();
+    modules.push(Box::new(lstm));
+    let mut gru = GRU::with_options(
+        3,
+        4,
+        RecurrentOptions {
+            bidirectional: true,
+            ..Default::default()
+        },
+        backend.clone(),
+    )
+    .unwrap();
+    gru.init_uniform().unwrap();
+    modules.push(Box::new(gru));
+
+    for (i, module) in modules.iter().enumerate() {
+        check_module_gradients(module.as_ref(), &x)
+            .unwrap_or_else(|e| panic!("module {}: {}", i, e));
+    }
+}
+
+#[test]
+fn carried_state_and_truncated_bptt() {
+    let backend = Arc::new(CpuBackend::new());
+    set_seed(6);
+    let options = RecurrentOptions {
+        num_layers: 2,
+        return_sequences: true,
+        ..Default::default()
+    };
+    let mut lstm = LSTM::with_options(2, 3, options, backend.clone()).unwrap();
+    lstm.init_uniform().unwrap();
+
+    // Two chunks with carried state match one pass over the whole sequence.
+    let x = seq(&[6, 2, 2]);
+    let whole = lstm.forward(&x).unwrap();
+    lstm.set_carry_state(true);
+    let first = lstm
+        .forward(&Narrow::new(0, 0, 4).forward(&[&x]).unwrap())
+        .unwrap();
+    let mut g = Graph::new();
+    let rest_id = g.var(Narrow::new(0, 4, 2).forward(&[&x]).unwrap());
+    let (rest, _) = lstm.forward_graph(&mut g, rest_id).unwrap();
+    let mut chunked = first.data().to_vec();
+    chunked.extend(g.data(rest).unwrap().data());
+    assert_close(&chunked, whole.data(), 1e-5);
+    lstm.set_carry_state(false);
+    assert_eq!(lstm.forward(&x).unwrap().data(), whole.data());
+
+    // Bidirectional: the forward half continues across chunks, the reverse half restarts.
+    let mut bi = GRU::with_options(
+        2,
+        3,
+        RecurrentOptions {
+            bidirectional: true,
+            return_sequences: true,
+            ..Default::default()
+        },
+        backend.clone(),
+    )
+    .unwrap();
+    bi.init_uniform().unwrap();
+    let rest_x = Narrow::new(0, 4, 2).forward(&[&x]).unwrap();
+    let whole = bi.forward(&x).unwrap();
+    let alone = bi.forward(&rest_x).unwrap();
+    bi.set_carry_state(true);
+    bi.forward(&Narrow::new(0, 0, 4).forward(&[&x]).unwrap())
+        .unwrap();
+    let rest = bi.forward(&rest_x).unwrap();
+    bi.set_carry_state(false);
+    for (i, &v) in rest.data().iter().enumerate() {
+        let want = if i % 6 < 3 {
+            whole.data()[4 * 2 * 6 + i]
+        } else {
+            alone.data()[i]
+        };
+        assert!((v - want).abs() < 1e-5, "elem {}: {} vs {}", i, v, want);
+    }
+
+    // Next-step forecasting of two phase-shifted sine waves, trained in chunks of 8 steps.
+    let steps = 48;
+    let wave = |t: usize, b: usize| (t as f32 * 0.4 + b as f32).sin();
+    let input = tensor(
+        (0..steps).flat_map(|t| [wave(t, 0), wave(t, 1)]).collect(),
+        &[steps, 2, 1],
+    );
+    let target = tensor(
+        (0..steps)
+            .flat_map(|t| [wave(t + 1, 0), wave(t + 1, 1)])
+            .collect(),
+        &[steps, 2, 1],
+    );
+    // A second, one-unit GRU reads the prediction out of the 8-unit one.
+    let sequences = RecurrentOptions {
+        return_sequences: true,
+        ..Default::default()
+    };
+    let mut gru = GRU::with_options(1, 8, sequences, backend.clone()).unwrap();
+    gru.init_uniform().unwrap();
+    let mut head = GRU::with_options(8, 1, sequences, backend.clone()).unwrap();
+    head.init_uniform().unwrap();
+    let net = Sequential::new().with(gru).with(head);
+
+    let mut trainer = Trainer::new(net, Adam::new(0.02));
+    let initial = trainer
+        .step_tbptt(backend.clone(), &input, &target, 8)
+        .unwrap()
+        .loss;
+    let mut loss = initial;
+    for _ in 0..150 {
+        loss = trainer
+            .step_tbptt(backend.clone(), &input, &target, 8)
+            .unwrap()
+            .loss;
+    }
+    assert!(loss < initial * 0.1, "loss {} -> {}", initial, loss);
+    assert!(trainer.step_tbptt(backend, &input, &target, 0).is_err());
+}
@@ -0,0 +1,129 @@
+//! Sequential chains modules like a hand-written struct; MLPBuilder stacks any depth; Linear
+//! maps the last dim of higher-rank inputs.
+
+use dl_core::autograd::check::{check_gradients, check_op, HarnessConfig};
+use dl_core::backend::Activation;
+use dl_core::nn::Module;
+use dl_core::ops::view::Reshape;
+use dl_core::optimizer::SGD;
+use dl_core::train::Trainer;
+use dl_core::{
+    set_seed, CpuBackend, Graph, Linear, MLPBuilder, ReLU, Sequential, Shape, Tensor, MLP2,
+};
+use std::sync::Arc;
+
+#[test]
+fn sequential_matches_mlp2() {
+    set_seed(3);
+    let backend = Arc::new(CpuBackend::new());
+    let mut mlp = MLP2::new(3, 5, 2, backend.clone()).unwrap();
+    mlp.init_xavier().unwrap();
+    let mut l1 = Linear::new(3, 5, backend.clone()).unwrap();
+    let mut l2 = Linear::new(5, 2, backend.clone()).unwrap();
+    *l1.weight.data_mut() = mlp.linear1.weight.data().clone();
+    *l2.weight.data_mut() = mlp.linear2.weight.data().clone();
+    let seq = Sequential::new().with(l1).with(ReLU::new()).with(l2);
+    assert_eq!(seq.len(), 3);
+    assert_eq!(seq.parameters().len(), 4);
+
+    let x = Tensor::from_vec(
+        (0..12).map(|i| i as f32 * 0.1 - 0.5).collect(),
+        Shape::new(vec![4, 3]),
+        backend.clone(),
+    )
+    .unwrap();
+    let t = Tensor::from_vec(vec![0.5; 8], Shape::new(vec![4, 2]), backend.clone()).unwrap();
+    assert_eq!(
+        seq.forward(&x).unwrap().data(),
+        mlp.forward(&x).unwrap().data()
+    );
+
+    let mut a = Trainer::new(seq, SGD::new(0.1));
+    let mut b = Trainer::new(mlp, SGD::new(0.1));
+    for _ in 0..3 {
+        let la = a.step_batch(backend.clone(), &x, &t).unwrap().loss;
+        let lb = b.step_batch(backend.clone(), &x, &t).unwrap().loss;
+        assert_eq!(la, lb);
+    }
+}
+
+#[test]
+fn builder_stacks_hidden_layers_with_norm_and_dropout_slots() {
+    set_seed(5);
+    let backend = Arc::new(CpuBackend::new());
+    // Identity stand-ins for the norm and dropout slots.
+    let net = MLPBuilder::new(4, 1)
+        .hidden(&[8, 6])
+        .activation(Activation::Sigmoid)
+        .norm(|_, _| Ok(Box::new(Sequential::new())))
+        .dropout(|_, _| Ok(Box::new(Sequential::new())))
+        .xavier()
+        .build(backend.clone())
+        .unwrap();
+    // (Linear, norm, Sigmoid, dropout) x 2 + output Linear.
+    assert_eq!(net.len(), 9);
+    let shapes: Vec<_> = net
+        .parameters()
+        .iter()
+        .map(|p| p.data().shape().dims().to_vec())
+        .collect();
+    assert_eq!(
+        shapes,
+        [
+            vec![4, 8],
+            vec![8],
+            vec![8, 6],
+            vec![6],
+            vec![6, 1],
+            vec![1]
+        ]
+    );
+
+    let x = Tensor::from_vec(
+        (0..32).map(|i| (i % 7) as f32 * 0.2 - 0.6).collect(),
+        Shape::new(vec![8, 4]),
+        backend.clone(),
+    )
+    .unwrap();
+    let t = Tensor::from_vec(vec![0.3; 8], Shape::new(vec![8, 1]), backend.clone()).unwrap();
+    let mut trainer = Trainer::new(net, SGD::new(0.5));
+    let first = trainer.step_batch(backend.clone(), &x, &t).unwrap().loss;
+    let mut last = first;
+    for _ in 0..20 {
+        last = trainer.step_batch(backend.clone(), &x, &t).unwrap().loss;
+    }
+    assert!(last < first, "{} -> {}", first, last);
+}
+
+#[test]
+fn linear_maps_the_last_dim_of_higher_rank_inputs() {
+    set_seed(5);
+    let backend = Arc::new(CpuBackend::new());
+    let mut linear = Linear::new(3, 2, backend.clone()).unwrap();
+    linear.init_xavier().unwrap();
+    let data: Vec<f32> = (0..18).map(|i| i as f32 * 0.1 - 0.8).collect();
+    let rows = Tensor::from_vec(data.clone(), Shape::new(vec![6, 3]), backend.clone()).unwrap();
+    let x = Tensor::from_vec(data, Shape::new(vec![2, 3, 3]), backend.clone()).unwrap();
+
+    // Same values as the [N, in] matrix, with the leading dims kept.
+    let y = linear.forward(&x).unwrap();
+    assert_eq!(y.shape().dims(), &[2, 3, 2]);
+    assert_eq!(y.data(), linear.forward(&rows).unwrap().data());
+    let mut g = Graph::new();
+    let x_id = g.var(x.clone());
+    let (out, _) = linear.forward_graph(&mut g, x_id).unwrap();
+    assert_eq!(g.data(out).unwrap().data(), y.data());
+
+    let report = check_op(
+        &Reshape::new(vec![3, 2]),
+        backend.clone(),
+        &HarnessConfig::default(),
+    )
+    .unwrap();
+    assert!(report.passed(), "{:?}", report.failures);
+    let build = |g: &mut Graph, ids: &[usize]| {
+        let (y, _) = linear.forward_graph(g, ids[0])?;
+        g.sum(y)
+    };
+    check_gradients(&build, std::slice::from_ref(&x), 1e-2, 1e-2, 1e-3).unwrap();
+}
@@ -0,0 +1,326 @@
+//! Static tracing: traced programs match eager forward and round-trip through disk.
+
+use dl_core::autograd::{Graph, NodeId};
+use dl_core::nn::{
+    AdaptiveAvgPool2d, AlphaDropout, AvgPool1d, AvgPool2d, BatchNorm1d, BatchNorm2d, Conv1d,
+    Conv1dOptions, Conv2d, Conv2dOptions, DropPath, Dropout, Embedding, GlobalMaxPool, GroupNorm,
+    LayerNorm, Linear, MaxPool2d, MultiheadAttention, RMSNorm, ReLU, RecurrentOptions, Sequential,
+    GRU, LSTM,
+};
+use dl_core::trace::ProgramState;
+use dl_core::{
+    load_state_dict, save_state_dict, set_seed, trace, CpuBackend, GraphResult, Module, OpRegistry,
+    Parameter, Shape, Tensor, TensorResult, TracedProgram, MLP2,
+};
+use std::sync::Arc;
+
+#[test]
+fn test_trace_run_and_save_load() {
+    set_seed(21);
+    let backend = Arc::new(CpuBackend::new());
+    let mut model = MLP2::new(3, 6, 2, backend.clone()).unwrap();
+    model.init_xavier().unwrap();
+    let example = Tensor::from_vec(vec![0.0; 6], Shape::new(vec![2, 3]), backend.clone()).unwrap();
+    let program = trace(&model, &example).unwrap();
+    assert!(
+        program.plan().len() < 6,
+        "ops: {:?}",
+        program.plan().op_names()
+    );
+
+    let x = Tensor::from_vec(
+        vec![0.1, -0.4, 1.5, 2.0, 0.3, -1.0],
+        Shape::new(vec![2, 3]),
+        backend.clone(),
+    )
+    .unwrap();
+    let eager = model.forward(&x).unwrap();
+    let traced = program.run(&x).unwrap();
+    for (a, b) in eager.data().iter().zip(traced.data().iter()) {
+        assert!((a - b).abs() < 1e-6, "{} vs {}", a, b);
+    }
+
+    let wrong = Tensor::from_vec(vec![0.0; 3], Shape::new(vec![1, 3]), backend.clone()).unwrap();
+    assert!(program.run(&wrong).is_err());
+
+    let dir = std::env::temp_dir();
+    let prog_path = dir.join("dl_core_trace_program.json");
+    let weights_path = dir.join("dl_core_trace_weights.json");
+    program.save(&prog_path).unwrap();
+    save_state_dict(&weights_path, &program.state_dict()).unwrap();
+
+    let weights = load_state_dict(&weights_path).unwrap();
+    let loaded = TracedProgram::load(&prog_path, &weights, backend.clone()).unwrap();
+    let out = loaded.run(&x).unwrap();
+    assert_eq!(out.data(), traced.data());
+
+    let _: Result<(), _> = std::fs::remove_file(&prog_path);
+    let _: Result<(), _> = std::fs::remove_file(&weights_path);
+}
+
+#[test]
+fn test_norm_eps_survives_save_load() {
+    let backend = Arc::new(CpuBackend::new());
+    let x = Tensor::from_vec(
+        vec![0.1, -0.4, 1.5, 2.0, 0.3, -1.0, 0.2, 0.05],
+        Shape::new(vec![2, 4]),
+        backend.clone(),
+    )
+    .unwrap();
+    // A large eps changes the output visibly, so the default (1e-5) would not match.
+    let models: Vec<Box<dyn Module>> = vec![
+        Box::new(LayerNorm::new(&[4], backend.clone()).unwrap().with_eps(0.5)),
+        Box::new(RMSNorm::new(&[4], backend.clone()).unwrap().with_eps(0.5)),
+    ];
+    for model in &models {
+        assert_round_trips(model.as_ref(), &x);
+    }
+
+    // Three groups instead of the registered op's two.
+    let x = ramp(&[2, 6, 5], &backend);
+    let gn = GroupNorm::new(3, 6, backend.clone()).unwrap().with_eps(0.5);
+    assert_round_trips(&gn, &x);
+    let op = OpRegistry::new().get_by_name("GroupNorm").unwrap();
+    assert!(op.with_attrs(&[0.0, 1e-5]).is_none());
+    assert!(op.with_attrs(&[1.5, 1e-5]).is_none());
+}
+
+/// Trace `model` on `x`, round-trip the program through JSON and the default registry, and
+/// check the reloaded program against eager forward.
+fn assert_round_trips(model: &dyn Module, x: &Tensor) {
+    let eager = model.forward(x).unwrap();
+    let program = trace(model, x).unwrap();
+    let json = serde_json::to_string(&program.to_state().unwrap()).unwrap();
+    let state: ProgramState = serde_json::from_str(&json).unwrap();
+    let loaded = TracedProgram::from_state(
+        state,
+        &program.state_dict(),
+        &OpRegistry::new(),
+        x.backend(),
+    )
+    .unwrap();
+    let out = loaded.run(x).unwrap();
+    assert_eq!(out.shape().dims(), eager.shape().dims());
+    for (a, b) in eager.data().iter().zip(out.data().iter()) {
+        assert!((a - b).abs() < 1e-5, "{} vs {}", a, b);
+    }
+}
+
+fn ramp(dims: &[usize], backend: &Arc<CpuBackend>) -> Tensor {
+    let n: usize = dims.iter().product();
+    let data = (0..n).map(|i| ((i * 7 % 11) as f32 - 5.0) * 0.2).collect();
+    Tensor::from_vec(data, Shape::new(dims.to_vec()), backend.clone()).unwrap()
+}
+
+#[test]
+fn test_conv_geometry_survives_save_load() {
+    set_seed(5);
+    let backend = Arc::new(CpuBackend::new());
+    let options = Conv2dOptions {
+        stride: (2, 1),
+        padding: (1, 1),
+        groups: 2,
+        ..Conv2dOptions::default()
+    };
+    let mut conv = Conv2d::with_options(4, 6, (3, 2), options, backend.clone()).unwrap();
+    conv.init_he().unwrap();
+    let cnn = Sequential::new().with(conv).with(ReLU);
+    assert_round_trips(&cnn, &ramp(&[2, 4, 5, 5], &backend));
+
+    let options = Conv1dOptions {
+        dilation: 2,
+        ..Conv1dOptions::default()
+    };
+    let mut conv = Conv1d::with_options(2, 3, 3, options, backend.clone()).unwrap();
+    conv.init_he().unwrap();
+    assert_round_trips(&conv, &ramp(&[2, 2, 9], &backend));
+}
+
+/// Creates its weight with [Graph::var] and returns the node instead of binding it by id.
+struct VarBound {
+    w: Parameter,
+}
+
+impl Module for VarBound {
+    fn parameters(&self) -> Vec<&Parameter> {
+        vec![&self.w]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter> {
+        vec![&mut self.w]
+    }
+
+    fn forward(&self, x: &Tensor) -> TensorResult<Tensor> {
+        x.matmul(self.w.data())
+    }
+
+    fn forward_graph(&self, g: &mut Graph, x_id: NodeId) -> GraphResult<(NodeId, Vec<NodeId>)> {
+        let w = g.var(self.w.data().clone());
+        Ok((g.matmul(x_id, w)?, vec![w]))
+    }
+}
+
+#[test]
+fn test_trace_feeds_var_weights_as_inputs() {
+    let backend = Arc::new(CpuBackend::new());
+    let w = Tensor::from_vec(vec![0.5, -0.5], Shape::new(vec![2, 1]), backend.clone()).unwrap();
+    let mut model = VarBound {
+        w: Parameter::new(w),
+    };
+    let x = Tensor::from_vec(vec![1.0, 2.0], Shape::new(vec![1, 2]), backend.clone()).unwrap();
+    let mut program = trace(&model, &x).unwrap();
+    assert_eq!(program.run(&x).unwrap().data(), vec![-0.5]);
+
+    // New weights must reach the program rather than the values seen while tracing.
+    let w = Tensor::from_vec(vec![2.0, 1.0], Shape::new(vec![2, 1]), backend.clone()).unwrap();
+    *model.w.data_mut() = w;
+    let states: Vec<_> = model.parameters().iter().map(|p| p.to_state()).collect();
+    program.load_state_dict(&states, backend.clone()).unwrap();
+    assert_eq!(
+        program.run(&x).unwrap().data(),
+        model.forward(&x).unwrap().data()
+    );
+}
+
+#[test]
+fn test_trace_rejects_training_mode_and_row_bound_params() {
+    let backend = Arc::new(CpuBackend::new());
+    let x = Tensor::from_vec(vec![0.5, -1.0], Shape::new(vec![1, 2]), backend.clone()).unwrap();
+    let mut model = Sequential::new()
+        .with(Linear::new(2, 2, backend.clone()).unwrap())
+        .with(Dropout::new(0.5).unwrap());
+    match trace(&model, &x) {
+        Err(e) => assert!(e.to_string().contains("eval mode"), "{}", e),
+        Ok(_) => panic!("training-mode dropout traced"),
+    }
+    model.eval();
+    let program = trace(&model, &x).unwrap();
+    assert_eq!(
+        program.run(&x).unwrap().data(),
+        model.forward(&x).unwrap().data()
+    );
+
+    let emb = Embedding::new(5, 2, backend.clone()).unwrap();
+    let ids = Tensor::from_vec(vec![0.0, 3.0], Shape::new(vec![2]), backend.clone()).unwrap();
+    match trace(&emb, &ids) {
+        Err(e) => assert!(e.to_string().contains("bound by rows"), "{}", e),
+        Ok(_) => panic!("embedding traced"),
+    }
+}
+
+#[test]
+fn test_pool_windows_survive_save_load() {
+    set_seed(6);
+    let backend = Arc::new(CpuBackend::new());
+    let mut conv = Conv2d::new(2, 3, (2, 2), backend.clone()).unwrap();
+    conv.init_he().unwrap();
+    let cnn = Sequential::new()
+        .with(conv)
+        .with(
+            MaxPool2d::new((3, 3))
+                .with_stride((2, 1))
+                .with_padding((1, 1)),
+        )
+        .with(AvgPool2d::new((2, 2)))
+        .with(AdaptiveAvgPool2d::new((2, 1)))
+        .with(GlobalMaxPool::new());
+    assert_round_trips(&cnn, &ramp(&[2, 2, 9, 7], &backend));
+    let pool = AvgPool1d::new(3).with_stride(2).with_padding(1);
+    assert_round_trips(&pool, &ramp(&[2, 3, 8], &backend));
+
+    // Integer settings must be whole and non-negative.
+    let max = OpRegistry::new().get_by_name("MaxPool").unwrap();
+    assert!(max.with_attrs(&[1.0, 2.0, 1.0]).is_some());
+    assert!(max.with_attrs(&[1.0, 2.5, 1.0]).is_none());
+    assert!(max.with_attrs(&[1.0, -2.0, 1.0]).is_none());
+}
+
+#[test]
+fn test_batch_norm_running_stats_survive_save_load() {
+    set_seed(7);
+    let backend = Arc::new(CpuBackend::new());
+    let mut conv = Conv2d::new(2, 3, (2, 2), backend.clone()).unwrap();
+    conv.init_he().unwrap();
+    let mut cnn = Sequential::new()
+        .with(conv)
+        .with(BatchNorm2d::new(3, backend.clone()).unwrap().with_eps(0.5))
+        .with(ReLU);
+    let x = ramp(&[2, 2, 4, 4], &backend);
+    // Training steps move the running statistics away from the registered op's.
+    for _ in 0..3 {
+        cnn.forward(&x).unwrap();
+    }
+    cnn.eval();
+    assert_round_trips(&cnn, &x);
+
+    let mut bn = BatchNorm1d::new(4, backend.clone()).unwrap();
+    let x = ramp(&[5, 4], &backend);
+    bn.forward(&x).unwrap();
+    bn.eval();
+    assert_round_trips(&bn, &x);
+
+    // Fixed statistics come in (mean, var) pairs of equal length.
+    let op = OpRegistry::new().get_by_name("BatchNorm").unwrap();
+    assert!(op.with_attrs(&[1e-5, 0.0, 1.0]).is_some());
+    assert!(op.with_attrs(&[1e-5, 0.0, 1.0, 2.0]).is_none());
+}
+
+#[test]
+fn test_eval_mode_dropout_traces_and_saves() {
+    set_seed(8);
+    let backend = Arc::new(CpuBackend::new());
+    let mut model = Sequential::new()
+        .with(Linear::new(3, 4, backend.clone()).unwrap())
+        .with(Dropout::new(0.5).unwrap())
+        .with(ReLU)
+        .with(AlphaDropout::new(0.2).unwrap())
+        .with(DropPath::new(0.3).unwrap());
+    let x = ramp(&[2, 3], &backend);
+    assert!(trace(&model, &x).is_err());
+    // In eval mode each dropout layer is the identity and adds no instruction to save.
+    model.eval();
+    assert_round_trips(&model, &x);
+}
+
+#[test]
+fn test_recurrent_layers_trace_and_save() {
+    set_seed(9);
+    let backend = Arc::new(CpuBackend::new());
+    // Gates are split with Narrow and directions joined with Concat.
+    let options = RecurrentOptions {
+        num_layers: 2,
+        bidirectional: true,
+        return_sequences: true,
+    };
+    let mut lstm = LSTM::with_options(3, 4, options, backend.clone()).unwrap();
+    lstm.init_uniform().unwrap();
+    let x = ramp(&[5, 2, 3], &backend);
+    assert_round_trips(&lstm, &x);
+
+    let mut gru = GRU::new(3, 4, backend.clone()).unwrap();
+    gru.init_uniform().unwrap();
+    assert_round_trips(&gru, &x);
+
+    // Layout settings must be whole and non-negative.
+    let reshape = OpRegistry::new().get_by_name("Reshape").unwrap();
+    assert!(reshape.with_attrs(&[3.0, 2.0, 1.0]).is_some());
+    assert!(reshape.with_attrs(&[3.0, -2.0]).is_none());
+    let narrow = OpRegistry::new().get_by_name("Narrow").unwrap();
+    assert!(narrow.with_attrs(&[1.0, 0.5, 2.0]).is_none());
+}
+
+#[test]
+fn test_attention_traces_and_saves() {
+    set_seed(10);
+    let backend = Arc::new(CpuBackend::new());
+    // Scores use q @ k^T scaled by 1 / sqrt(head dim), unlike the registered BatchMatMul.
+    let mut mha = MultiheadAttention::new(4, 2, backend.clone())
+        .unwrap()
+        .with_causal(true);
+    mha.init_xavier().unwrap();
+    assert_round_trips(&mha, &ramp(&[2, 3, 4], &backend));
+
+    let op = OpRegistry::new().get_by_name("BatchMatMul").unwrap();
+    assert!(op.with_attrs(&[1.0, 0.5]).is_some());
+    assert!(op.with_attrs(&[0.5, 0.5]).is_none());
+}